@@ -0,0 +1,130 @@
+//! Fuzzy matcher benchmarks - `cargo bench --bench fuzzy`
+//!
+//! A few thousand made-up app names, searched the ways people actually type:
+//! prefixes, acronyms, typos and the empty query (which lists everything).
+
+use std::hint::black_box;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use vibewm::command_center::{fuzzy_match, keep_top_scores, AppEntry, CommandCenter, DEFAULT_MAX_RESULTS};
+
+/// How many synthetic apps to search
+const APP_COUNT: usize = 5000;
+
+const QUERIES: &[(&str, &str)] = &[
+    ("empty", ""),
+    ("prefix", "fire"),
+    ("acronym", "gsm"),
+    ("typo", "thunderbrid"),
+    ("long", "system monitor settings"),
+];
+
+const VENDORS: &[&str] = &[
+    "GNOME", "KDE", "Firefox", "Thunderbird", "LibreOffice", "Visual", "Steam", "Signal", "Blender",
+    "Inkscape", "Krita", "Audacity", "OBS", "Wine", "Zed", "Alacritty",
+];
+
+const WORDS: &[&str] = &[
+    "System", "Monitor", "Settings", "Text", "Editor", "Files", "Terminal", "Music", "Player",
+    "Calendar", "Calculator", "Disks", "Software", "Manager", "Viewer", "Studio", "Writer", "Mail",
+];
+
+/// Deterministic app list - same names every run, so numbers compare
+fn synthetic_apps(count: usize) -> Vec<AppEntry> {
+    (0..count)
+        .map(|i| {
+            let vendor = VENDORS[i % VENDORS.len()];
+            let first = WORDS[(i / VENDORS.len()) % WORDS.len()];
+            let second = WORDS[(i / 7 + 3) % WORDS.len()];
+            let name = format!("{} {} {} {}", vendor, first, second, i);
+            let exec = name.to_lowercase().replace(' ', "-");
+
+            AppEntry {
+                name,
+                argv: vec![exec.clone()],
+                desktop_file: PathBuf::from(format!("/usr/share/applications/{}.desktop", exec)),
+                exec,
+                keywords: vec![first.to_lowercase(), second.to_lowercase()],
+                icon: None,
+                score: 0,
+            }
+        })
+        .collect()
+}
+
+fn bench_fuzzy_match(c: &mut Criterion) {
+    let apps = synthetic_apps(APP_COUNT);
+    let mut group = c.benchmark_group("fuzzy_match");
+
+    for (label, query) in QUERIES {
+        group.bench_with_input(BenchmarkId::from_parameter(label), query, |b, query| {
+            b.iter(|| {
+                apps.iter()
+                    .map(|app| fuzzy_match(black_box(query), black_box(&app.name)))
+                    .sum::<i32>()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_update_filter(c: &mut Criterion) {
+    let mut center = CommandCenter::with_apps(synthetic_apps(APP_COUNT));
+    let mut group = c.benchmark_group("update_filter");
+
+    for (label, query) in QUERIES {
+        group.bench_with_input(BenchmarkId::from_parameter(label), query, |b, query| {
+            center.search_query = query.to_string();
+            b.iter(|| {
+                center.update_filter();
+                black_box(center.filtered_apps.len())
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Ranking every match for a broad query - sorting the lot versus picking
+/// the top max_results first
+fn bench_top_scores(c: &mut Criterion) {
+    let scored: Vec<AppEntry> = synthetic_apps(APP_COUNT)
+        .into_iter()
+        .map(|app| AppEntry { score: fuzzy_match("s", &app.name), ..app })
+        .collect();
+    let mut group = c.benchmark_group("rank");
+
+    group.bench_function("full_sort", |b| {
+        b.iter(|| {
+            let mut apps = scored.clone();
+            keep_top_scores(&mut apps, None, |app| app.score);
+            black_box(apps.len())
+        })
+    });
+    group.bench_function("top_k", |b| {
+        b.iter(|| {
+            let mut apps = scored.clone();
+            keep_top_scores(&mut apps, Some(DEFAULT_MAX_RESULTS), |app| app.score);
+            black_box(apps.len())
+        })
+    });
+    group.finish();
+}
+
+/// Typing a query a character at a time - what actually happens per keystroke
+fn bench_typing(c: &mut Criterion) {
+    let mut center = CommandCenter::with_apps(synthetic_apps(APP_COUNT));
+
+    c.bench_function("typing/thunderbird", |b| {
+        b.iter(|| {
+            center.search_query.clear();
+            for ch in "thunderbird".chars() {
+                center.handle_char(ch);
+            }
+            black_box(center.filtered_apps.len())
+        })
+    });
+}
+
+criterion_group!(benches, bench_fuzzy_match, bench_update_filter, bench_top_scores, bench_typing);
+criterion_main!(benches);
@@ -0,0 +1,359 @@
+//! Compositor actions
+//!
+//! Everything vibeWM can do to a window (or to itself) lives here as a named
+//! action, so keybinds and the command center palette speak the same language.
+
+#[cfg(feature = "compositor")]
+use std::time::Duration;
+
+use crate::config::SnapPosition;
+#[cfg(feature = "compositor")]
+use crate::launch;
+#[cfg(feature = "compositor")]
+use crate::state::VibeWM;
+use crate::window::Direction;
+
+/// Something the compositor can do
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Move(Direction),
+    Resize(Direction),
+    Snap(SnapPosition),
+    FocusNext,
+    FocusPrev,
+    PromoteFocused,
+    BalanceWindows,
+    /// Switch to a workspace (0-based index)
+    SwitchWorkspace(usize),
+    /// Send the focused window to a workspace (0-based index)
+    SendToWorkspace(usize),
+    MoveWorkspaceToOutput(Direction),
+    CloseWindow,
+    /// Disconnect the focused window's client - for frozen apps
+    ForceCloseWindow,
+    /// Dim everything but the focused window (screen sharing)
+    ToggleSpotlight,
+    /// Keep the focused window on every workspace
+    ToggleSticky,
+    /// Unsnap the focused window back to where it was, or snap a floating one
+    ToggleFloating,
+    /// Move/resize/snap/close menu for the focused window
+    WindowMenu,
+    /// Magnify the output around the cursor
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    ToggleCommandCenter,
+    /// Keybinding cheat sheet
+    ToggleCheatSheet,
+    /// Drag out a region of the frozen screen and save it
+    ScreenshotRegion,
+    /// Re-read config.toml
+    ReloadConfig,
+    /// Spawn a command (argv, no shell)
+    Exec(Vec<String>),
+    Quit,
+}
+
+/// How an action is built from a palette query
+#[derive(Clone)]
+pub enum ActionKind {
+    /// Runs as-is
+    Fixed(Action),
+
+    /// Needs a number parsed from the end of the query ("ws 3")
+    Targeted(fn(u32) -> Action),
+}
+
+/// A named entry in the action registry
+pub struct ActionEntry {
+    /// Display name, also what the fuzzy matcher scores against
+    pub name: &'static str,
+
+    /// Extra spellings people actually type
+    pub aliases: &'static [&'static str],
+
+    pub kind: ActionKind,
+}
+
+/// A registry entry that matched the current query, ready to run
+#[derive(Debug, Clone)]
+pub struct ActionMatch {
+    pub name: String,
+    pub action: Action,
+    /// Fuzzy match score (higher = better match)
+    pub score: i32,
+}
+
+/// The action registry - every named action the palette can find
+pub const ACTIONS: &[ActionEntry] = &[
+    ActionEntry { name: "Snap left", aliases: &["left half"], kind: ActionKind::Fixed(Action::Snap(SnapPosition::Left)) },
+    ActionEntry { name: "Snap right", aliases: &["right half"], kind: ActionKind::Fixed(Action::Snap(SnapPosition::Right)) },
+    ActionEntry { name: "Snap top", aliases: &["top half"], kind: ActionKind::Fixed(Action::Snap(SnapPosition::Top)) },
+    ActionEntry { name: "Snap bottom", aliases: &["bottom half"], kind: ActionKind::Fixed(Action::Snap(SnapPosition::Bottom)) },
+    ActionEntry { name: "Snap top left", aliases: &["quarter top left"], kind: ActionKind::Fixed(Action::Snap(SnapPosition::TopLeft)) },
+    ActionEntry { name: "Snap top right", aliases: &["quarter top right"], kind: ActionKind::Fixed(Action::Snap(SnapPosition::TopRight)) },
+    ActionEntry { name: "Snap bottom left", aliases: &["quarter bottom left"], kind: ActionKind::Fixed(Action::Snap(SnapPosition::BottomLeft)) },
+    ActionEntry { name: "Snap bottom right", aliases: &["quarter bottom right"], kind: ActionKind::Fixed(Action::Snap(SnapPosition::BottomRight)) },
+    ActionEntry { name: "Maximize", aliases: &["snap maximize", "fullsize"], kind: ActionKind::Fixed(Action::Snap(SnapPosition::Maximize)) },
+    ActionEntry { name: "Center window", aliases: &["snap center"], kind: ActionKind::Fixed(Action::Snap(SnapPosition::Center)) },
+    ActionEntry { name: "Focus next window", aliases: &["next window"], kind: ActionKind::Fixed(Action::FocusNext) },
+    ActionEntry { name: "Focus previous window", aliases: &["prev window"], kind: ActionKind::Fixed(Action::FocusPrev) },
+    ActionEntry { name: "Swap with largest", aliases: &["promote window", "make master"], kind: ActionKind::Fixed(Action::PromoteFocused) },
+    ActionEntry { name: "Balance windows", aliases: &["grid", "tile all"], kind: ActionKind::Fixed(Action::BalanceWindows) },
+    ActionEntry { name: "Workspace", aliases: &["ws", "go to workspace"], kind: ActionKind::Targeted(|n| Action::SwitchWorkspace(n.saturating_sub(1) as usize)) },
+    ActionEntry { name: "Send to workspace", aliases: &["move to workspace"], kind: ActionKind::Targeted(|n| Action::SendToWorkspace(n.saturating_sub(1) as usize)) },
+    ActionEntry { name: "Move workspace to left output", aliases: &["workspace left"], kind: ActionKind::Fixed(Action::MoveWorkspaceToOutput(Direction::Left)) },
+    ActionEntry { name: "Move workspace to right output", aliases: &["workspace right"], kind: ActionKind::Fixed(Action::MoveWorkspaceToOutput(Direction::Right)) },
+    ActionEntry { name: "Close window", aliases: &["kill window"], kind: ActionKind::Fixed(Action::CloseWindow) },
+    ActionEntry { name: "Force close window", aliases: &["force quit", "not responding"], kind: ActionKind::Fixed(Action::ForceCloseWindow) },
+    ActionEntry { name: "Spotlight window", aliases: &["solo", "screen share"], kind: ActionKind::Fixed(Action::ToggleSpotlight) },
+    ActionEntry { name: "Sticky window", aliases: &["pin window", "all workspaces"], kind: ActionKind::Fixed(Action::ToggleSticky) },
+    ActionEntry { name: "Toggle floating", aliases: &["float window", "unsnap"], kind: ActionKind::Fixed(Action::ToggleFloating) },
+    ActionEntry { name: "Window menu", aliases: &["window options"], kind: ActionKind::Fixed(Action::WindowMenu) },
+    ActionEntry { name: "Zoom in", aliases: &["magnifier", "magnify"], kind: ActionKind::Fixed(Action::ZoomIn) },
+    ActionEntry { name: "Zoom out", aliases: &[], kind: ActionKind::Fixed(Action::ZoomOut) },
+    ActionEntry { name: "Reset zoom", aliases: &["unzoom"], kind: ActionKind::Fixed(Action::ZoomReset) },
+    ActionEntry { name: "Keybinding cheat sheet", aliases: &["help", "shortcuts", "keys"], kind: ActionKind::Fixed(Action::ToggleCheatSheet) },
+    ActionEntry { name: "Screenshot region", aliases: &["screenshot", "capture"], kind: ActionKind::Fixed(Action::ScreenshotRegion) },
+    ActionEntry { name: "Reload config", aliases: &["reload", "refresh config"], kind: ActionKind::Fixed(Action::ReloadConfig) },
+    ActionEntry { name: "Quit vibeWM", aliases: &["exit", "logout"], kind: ActionKind::Fixed(Action::Quit) },
+];
+
+/// Names for `[keybinds]` in config.toml (workspace_1..9 are parsed, not listed)
+pub const CONFIG_NAMES: &[(&str, Action)] = &[
+    ("move_up", Action::Move(Direction::Up)),
+    ("move_down", Action::Move(Direction::Down)),
+    ("move_left", Action::Move(Direction::Left)),
+    ("move_right", Action::Move(Direction::Right)),
+    ("resize_up", Action::Resize(Direction::Up)),
+    ("resize_down", Action::Resize(Direction::Down)),
+    ("resize_left", Action::Resize(Direction::Left)),
+    ("resize_right", Action::Resize(Direction::Right)),
+    ("snap_left", Action::Snap(SnapPosition::Left)),
+    ("snap_right", Action::Snap(SnapPosition::Right)),
+    ("snap_top", Action::Snap(SnapPosition::Top)),
+    ("snap_bottom", Action::Snap(SnapPosition::Bottom)),
+    ("snap_top_left", Action::Snap(SnapPosition::TopLeft)),
+    ("snap_top_right", Action::Snap(SnapPosition::TopRight)),
+    ("snap_bottom_left", Action::Snap(SnapPosition::BottomLeft)),
+    ("snap_bottom_right", Action::Snap(SnapPosition::BottomRight)),
+    ("maximize", Action::Snap(SnapPosition::Maximize)),
+    ("center", Action::Snap(SnapPosition::Center)),
+    ("focus_next", Action::FocusNext),
+    ("focus_prev", Action::FocusPrev),
+    ("promote_focused", Action::PromoteFocused),
+    ("balance_windows", Action::BalanceWindows),
+    ("move_workspace_left", Action::MoveWorkspaceToOutput(Direction::Left)),
+    ("move_workspace_right", Action::MoveWorkspaceToOutput(Direction::Right)),
+    ("close_window", Action::CloseWindow),
+    ("force_close_window", Action::ForceCloseWindow),
+    ("toggle_spotlight", Action::ToggleSpotlight),
+    ("toggle_sticky", Action::ToggleSticky),
+    ("toggle_floating", Action::ToggleFloating),
+    ("window_menu", Action::WindowMenu),
+    ("zoom_in", Action::ZoomIn),
+    ("zoom_out", Action::ZoomOut),
+    ("zoom_reset", Action::ZoomReset),
+    ("toggle_command_center", Action::ToggleCommandCenter),
+    ("toggle_cheat_sheet", Action::ToggleCheatSheet),
+    ("screenshot_region", Action::ScreenshotRegion),
+    ("reload_config", Action::ReloadConfig),
+    ("quit", Action::Quit),
+];
+
+impl Action {
+    /// Look up a `[keybinds]` name - "close_window", "workspace_3", "send_to_workspace_3"
+    pub fn from_config_name(name: &str) -> Option<Action> {
+        let workspace = |n: &str| n.parse::<usize>().ok().filter(|n| (1..=9).contains(n)).map(|n| n - 1);
+        if let Some(n) = name.strip_prefix("workspace_") {
+            return workspace(n).map(Action::SwitchWorkspace);
+        }
+        if let Some(n) = name.strip_prefix("send_to_workspace_") {
+            return workspace(n).map(Action::SendToWorkspace);
+        }
+
+        CONFIG_NAMES
+            .iter()
+            .find(|(config_name, _)| *config_name == name)
+            .map(|(_, action)| action.clone())
+    }
+
+    /// Cheat sheet group
+    pub fn category(&self) -> &'static str {
+        match self {
+            Action::Move(_)
+            | Action::Resize(_)
+            | Action::Snap(_)
+            | Action::PromoteFocused
+            | Action::BalanceWindows
+            | Action::CloseWindow
+            | Action::ForceCloseWindow
+            | Action::ToggleSpotlight
+            | Action::ToggleSticky
+            | Action::ToggleFloating
+            | Action::WindowMenu => "Windows",
+            Action::FocusNext | Action::FocusPrev => "Focus",
+            Action::SwitchWorkspace(_) | Action::SendToWorkspace(_) | Action::MoveWorkspaceToOutput(_) => "Workspaces",
+            Action::ZoomIn | Action::ZoomOut | Action::ZoomReset => "Zoom",
+            Action::ToggleCommandCenter
+            | Action::ToggleCheatSheet
+            | Action::ScreenshotRegion
+            | Action::ReloadConfig
+            | Action::Exec(_)
+            | Action::Quit => "General",
+        }
+    }
+
+    /// Human name - the palette's when it has one
+    pub fn describe(&self) -> String {
+        let registered = ACTIONS.iter().find_map(|entry| match &entry.kind {
+            ActionKind::Fixed(action) if action == self => Some(entry.name),
+            _ => None,
+        });
+        if let Some(name) = registered {
+            return name.to_string();
+        }
+
+        match self {
+            Action::Move(direction) => format!("Move window {}", format!("{:?}", direction).to_lowercase()),
+            Action::Resize(direction) => format!("Resize window {}", format!("{:?}", direction).to_lowercase()),
+            Action::SwitchWorkspace(index) => format!("Workspace {}", index + 1),
+            Action::SendToWorkspace(index) => format!("Send to workspace {}", index + 1),
+            Action::ToggleCommandCenter => "Command Center".to_string(),
+            Action::Exec(argv) => format!("Run {}", argv.join(" ")),
+            action => format!("{:?}", action),
+        }
+    }
+}
+
+/// Split a trailing number off the query: "ws 3" -> ("ws", Some(3))
+pub fn split_target(query: &str) -> (&str, Option<u32>) {
+    let query = query.trim();
+
+    if let Some((head, tail)) = query.rsplit_once(' ') {
+        if let Ok(n) = tail.parse() {
+            return (head.trim_end(), Some(n));
+        }
+    }
+
+    (query, None)
+}
+
+/// Find registry actions matching the query, best first
+pub fn match_actions(query: &str, score_fn: impl Fn(&str, &str) -> i32) -> Vec<ActionMatch> {
+    let (text, target) = split_target(query);
+    if text.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches: Vec<ActionMatch> = ACTIONS
+        .iter()
+        .filter_map(|entry| {
+            // Best score across the name and all aliases
+            let score = std::iter::once(entry.name)
+                .chain(entry.aliases.iter().copied())
+                .map(|candidate| score_fn(text, candidate))
+                .max()
+                .unwrap_or(0);
+
+            if score <= 0 {
+                return None;
+            }
+
+            match &entry.kind {
+                ActionKind::Fixed(action) => Some(ActionMatch {
+                    name: entry.name.to_string(),
+                    action: action.clone(),
+                    score,
+                }),
+                ActionKind::Targeted(build) => target.map(|n| ActionMatch {
+                    name: format!("{} {}", entry.name, n),
+                    action: build(n),
+                    score,
+                }),
+            }
+        })
+        .collect();
+
+    matches.sort_by(|a, b| b.score.cmp(&a.score));
+    matches
+}
+
+#[cfg(feature = "compositor")]
+impl VibeWM {
+    /// Run an action against the compositor
+    pub fn dispatch_action(&mut self, action: Action) {
+        tracing::debug!("Action: {:?}", action);
+
+        match action {
+            Action::Move(direction) => self.move_focused(direction),
+            Action::Resize(direction) => self.resize_focused(direction),
+            Action::Snap(position) => self.snap_focused(position),
+            // Focus is pinned while a window is spotlighted
+            Action::FocusNext | Action::FocusPrev if self.spotlight.is_some() => {}
+            Action::FocusNext => {
+                self.windows.focus_next();
+                self.sync_keyboard_focus();
+            }
+            Action::FocusPrev => {
+                self.windows.focus_prev();
+                self.sync_keyboard_focus();
+            }
+            Action::PromoteFocused => self.windows.promote_focused(&mut self.space),
+            Action::BalanceWindows => {
+                if let Some(work_area) = self.work_area() {
+                    let gaps = self.config.tile_gap();
+                    self.windows.balance(&mut self.space, work_area, gaps);
+                }
+            }
+            Action::SwitchWorkspace(index) => self.switch_workspace_animated(index),
+            Action::SendToWorkspace(index) => {
+                if let Some(window) = self.windows.focused().cloned() {
+                    self.send_to_workspace(&window, index);
+                }
+            }
+            Action::MoveWorkspaceToOutput(direction) => self.move_workspace_to_output(direction),
+            Action::CloseWindow => {
+                if let Some(window) = self.windows.focused() {
+                    if let Some(toplevel) = window.toplevel() {
+                        toplevel.send_close();
+                    }
+                }
+            }
+            Action::ForceCloseWindow => self.force_close_focused(),
+            Action::ToggleSpotlight => self.toggle_spotlight(),
+            Action::ToggleSticky => self.windows.toggle_sticky_focused(),
+            Action::ToggleFloating => {
+                if let Some(window) = self.windows.focused().cloned() {
+                    self.toggle_float(&window);
+                }
+            }
+            Action::WindowMenu => self.open_focused_window_menu(),
+            Action::ZoomIn => self.zoom_by(self.config.zoom_step),
+            Action::ZoomOut => self.zoom_by(1.0 / self.config.zoom_step),
+            Action::ZoomReset => self.zoom_reset(),
+            Action::ToggleCommandCenter => match self.command_center {
+                Some(_) => self.toggle_command_center(),
+                // Turned off in config - the external launcher stands in
+                None => self.dispatch_action(Action::Exec(self.config.command_center.launcher.clone())),
+            },
+            Action::ToggleCheatSheet => self.cheat_sheet.toggle(),
+            Action::ScreenshotRegion => self.start_region_screenshot(),
+            Action::ReloadConfig => self.reload_config(),
+            Action::Exec(argv) => {
+                if let Err(e) = launch::spawn(&argv) {
+                    tracing::warn!("Failed to run {:?}: {}", argv, e);
+                }
+            }
+            Action::Quit => {
+                if !self.config.confirm_quit || self.quit_confirm.is_armed() {
+                    self.quit();
+                } else {
+                    // First press - show the card and wait for the second one
+                    self.quit_confirm.arm(Duration::from_millis(self.config.quit_confirm_timeout_ms));
+                }
+            }
+        }
+    }
+}
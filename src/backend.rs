@@ -2,31 +2,53 @@
 //!
 //! Winit backend for development/testing (runs in a window)
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use smithay::{
     backend::{
-        renderer::{
-            element::surface::WaylandSurfaceRenderElement,
-            glow::GlowRenderer,
-            Frame, Renderer,
-        },
+        allocator::Fourcc,
+        renderer::{damage::OutputDamageTracker, glow::GlowRenderer, ExportMem, TextureMapping},
         winit::{self, WinitEvent},
     },
-    desktop::space::SpaceRenderElements,
     output::{Mode, Output, PhysicalProperties, Subpixel},
     reexports::calloop::EventLoop,
-    utils::{Physical, Rectangle, Transform},
+    utils::{Buffer, Rectangle, Transform},
 };
 
+use crate::gpu_reset::{handle_gpu_reset, GpuResetTracker, ResetRecovery};
+use crate::schedule::{Phase, PhaseTimer};
+use crate::screenshot::FrozenFrame;
 use crate::state::VibeWM;
 
+/// Create the winit backend
+///
+/// EGL display/context or GlowRenderer creation fails on some GPUs and in
+/// some VMs. Mesa has read its environment by then, so there's no retrying
+/// on llvmpipe from here - software rendering is decided up front
+/// (`software_rendering = true` or LIBGL_ALWAYS_SOFTWARE=1, see init_env).
+fn init_renderer() -> Result<(winit::WinitGraphicsBackend<GlowRenderer>, winit::WinitEventLoop)> {
+    winit::init::<GlowRenderer>().map_err(|e| {
+        if std::env::var_os("LIBGL_ALWAYS_SOFTWARE").is_some() {
+            anyhow::anyhow!(
+                "Renderer creation failed: {:?} (already on software rendering) - no working GL. \
+                 Check that Mesa (libEGL/libGLES) is installed",
+                e
+            )
+        } else {
+            anyhow::anyhow!(
+                "Renderer creation failed: {:?} - set `software_rendering = true` in config.toml \
+                 (or LIBGL_ALWAYS_SOFTWARE=1) to use llvmpipe, or enable 3D acceleration in your VM",
+                e
+            )
+        }
+    })
+}
+
 /// Run vibeWM with the winit backend (windowed mode)
 pub fn run_winit(event_loop: &mut EventLoop<'static, VibeWM>, state: &mut VibeWM) -> Result<()> {
     // Create winit backend
-    let (mut backend, mut winit_event_loop) = winit::init::<GlowRenderer>()
-        .map_err(|e| anyhow::anyhow!("Failed to init winit backend: {:?}", e))?;
+    let (mut backend, mut winit_event_loop) = init_renderer()?;
 
     // Get output size from the window
     let size = backend.window_size();
@@ -56,16 +78,30 @@ pub fn run_winit(event_loop: &mut EventLoop<'static, VibeWM>, state: &mut VibeWM
     );
     output.set_preferred(mode);
 
-    state.output = Some(output.clone());
-    state.space.map_output(&output, (0, 0));
+    state.add_output(&output, (0, 0).into());
 
     tracing::info!("Winit backend initialized: {}x{}", size.w, size.h);
 
+    // Socket and output are up - clients can connect now
+    if let Some(notify) = &state.sd_notify {
+        notify.ready();
+    }
+
     // Insert winit event source into the event loop
     let mut running = true;
+    let mut reset_tracker = GpuResetTracker::new();
+
+    // What changed since each of the window's buffers was last drawn into
+    let mut damage_tracker = OutputDamageTracker::from_output(&output);
+
+    // A reset was handled and no frame has made it through since
+    let mut recovering = false;
 
     while running {
-        // Process winit events
+        crate::watchdog::ping();
+
+        // Input first - a client flooding us with commits can't hold up the pointer
+        let input = PhaseTimer::start(Phase::Input);
         let pump_status = winit_event_loop.dispatch_new_events(|event| {
             match event {
                 WinitEvent::Resized { size, .. } => {
@@ -74,12 +110,15 @@ pub fn run_winit(event_loop: &mut EventLoop<'static, VibeWM>, state: &mut VibeWM
                         refresh: 60_000,
                     };
                     output.change_current_state(Some(mode), None, None, None);
+                    state.reflow_for_output(&output);
                 }
                 WinitEvent::Input(event) => {
                     state.process_input_event(event);
                 }
                 WinitEvent::Focus(_) => {}
-                WinitEvent::Redraw => {}
+                // The host wants the next frame - as close to "the last one is
+                // on screen" as winit tells us
+                WinitEvent::Redraw => state.frames.presented(Instant::now()),
                 WinitEvent::CloseRequested => {
                     running = false;
                 }
@@ -91,51 +130,131 @@ pub fn run_winit(event_loop: &mut EventLoop<'static, VibeWM>, state: &mut VibeWM
             running = false;
         }
 
-        // Render frame
-        let size = backend.window_size();
-        let bg = state.config.colors.background;
+        // Coalesced motion goes out before the frame that shows it
+        state.flush_pointer_motion();
+        state.frames.timings.finish(input);
 
-        // Bind the backend - returns renderer and framebuffer target
-        let (renderer, mut target) = backend.bind()
-            .map_err(|e| anyhow::anyhow!("Bind error: {:?}", e))?;
+        // Idle/typing cursor hiding - winit shows the host cursor over our window
+        backend.window().set_cursor_visible(state.cursor_visible());
 
-        // Get render elements from the space
-        let output_ref = state.output.as_ref().unwrap();
-        let _elements: Vec<SpaceRenderElements<GlowRenderer, WaylandSurfaceRenderElement<GlowRenderer>>> =
-            state.space.render_elements_for_output(renderer, output_ref, 1.0)
-                .map_err(|e| anyhow::anyhow!("Render elements error: {:?}", e))?;
+        // Only render once the previous frame is on screen - a skipped frame
+        // is better than a queue of stale ones
+        if state.frames.should_render() {
+            let render = PhaseTimer::start(Phase::Render);
+            let drawn = draw_frame(&mut backend, &mut damage_tracker, state);
+            state.frames.timings.finish(render);
 
-        // Render frame
-        let frame_size = (size.w as i32, size.h as i32).into();
-        let damage = Rectangle::<i32, Physical>::from_size(frame_size);
+            match drawn {
+                Ok(()) => {
+                    if recovering {
+                        recovering = false;
+                        tracing::info!("Renderer recovered - resuming");
+                    }
 
-        // Start a render pass with the target
-        let mut frame = renderer.render(&mut target, frame_size, Transform::Normal)
-            .map_err(|e| anyhow::anyhow!("Render start error: {:?}", e))?;
+                    // Presented once the host asks for the next one
+                    state.frames.submitted();
+                    backend.window().request_redraw();
+                    state.send_frame_callbacks(&output, None);
+                }
+                // A failed frame usually means the context went away (suspend/resume,
+                // driver reset) - skip it and try again instead of dying
+                Err(e) => {
+                    tracing::warn!("Render failed: {:#} - treating it as a lost GPU context", e);
+                    state.gpu_reset_pending = true;
+                }
+            }
+        }
 
-        // Clear with background color
-        frame.clear(bg.into(), &[damage])
-            .map_err(|e| anyhow::anyhow!("Clear error: {:?}", e))?;
+        if state.gpu_reset_pending {
+            state.gpu_reset_pending = false;
+            // The window's EGL context belongs to winit and winit won't give us
+            // a second event loop, so there's no swapping the renderer here -
+            // the host recovers the context and we pick it up on the next frame
+            if handle_gpu_reset(&mut reset_tracker) == ResetRecovery::FallBackToSoftware {
+                anyhow::bail!(
+                    "GPU keeps resetting - restart with `software_rendering = true` (or LIBGL_ALWAYS_SOFTWARE=1)"
+                );
+            }
+            recovering = true;
+        }
 
-        // TODO: Actually render elements to the frame
-        // This requires iterating elements and calling draw on each
+        // Handle pending compositor work
+        state.handle_pending();
 
-        // Finish the frame (ignore SyncPoint - we don't need fence synchronization for basic rendering)
-        let _ = frame.finish()
-            .map_err(|e| anyhow::anyhow!("Frame finish error: {:?}", e))?;
+        // Clients get what's ready now, then we wait out the rest of the
+        // frame for more - only the first part counts as dispatch time
+        let dispatch = PhaseTimer::start(Phase::Dispatch);
+        let dispatched = event_loop.dispatch(Duration::ZERO, state);
+        state.frames.timings.finish(dispatch);
+        dispatched?;
 
-        // Drop target before submit
-        drop(target);
+        event_loop.dispatch(state.frames.dispatch_timeout(), state)?;
+    }
 
-        // Submit the frame
-        backend.submit(None)
-            .map_err(|e| anyhow::anyhow!("Submit error: {:?}", e))?;
+    Ok(())
+}
 
-        // Handle pending compositor work
-        state.handle_pending();
+/// Draw what changed into the winit window
+fn draw_frame(
+    backend: &mut winit::WinitGraphicsBackend<GlowRenderer>,
+    damage_tracker: &mut OutputDamageTracker,
+    state: &mut VibeWM,
+) -> Result<()> {
+    let size = backend.window_size();
+    let bg = state.config.colors.background;
 
-        // Dispatch event loop
-        event_loop.dispatch(Duration::from_millis(16), state)?;
+    // A region screenshot reads this frame back, so all of it has to be
+    // drawn - buffer age 0 means no history to skip over
+    let readback = state.region_select.as_ref().is_some_and(|select| select.frozen.is_none());
+    let age = if readback { 0 } else { backend.buffer_age().unwrap_or(0) };
+
+    // Bind the backend - returns renderer and framebuffer target
+    let (renderer, mut target) = backend.bind()
+        .map_err(|e| anyhow::anyhow!("Bind error: {:?}", e))?;
+
+    let output_ref = state.output.as_ref().unwrap();
+    let scale = output_ref.current_scale().fractional_scale();
+
+    // The host draws the cursor over the window.
+    // TODO: One cursor per extra seat, at cursor_positions()
+    let elements = state.output_elements(renderer, output_ref, false);
+    let rendered = damage_tracker
+        .render_output(renderer, &mut target, age, &elements, bg)
+        .map_err(|e| anyhow::anyhow!("Render error: {:?}", e))?;
+    let damage = rendered.damage.cloned();
+
+    // Region screenshot waiting for its frame - read this one back before
+    // it's shown. A failed readback only costs the screenshot, not the frame
+    if readback {
+        let frame_size = (size.w as i32, size.h as i32).into();
+        let region = Rectangle::<i32, Buffer>::from_size((size.w as i32, size.h as i32).into());
+        let pixels = renderer
+            .copy_framebuffer(&target, region, Fourcc::Abgr8888)
+            .and_then(|mapping| {
+                let flipped = mapping.flipped();
+                renderer.map_texture(&mapping).map(|bytes| (bytes.to_vec(), flipped))
+            });
+
+        match pixels {
+            Ok((pixels, flipped)) => {
+                if let Some(select) = state.region_select.as_mut() {
+                    select.freeze(FrozenFrame::from_readback(frame_size, scale, pixels, flipped));
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Couldn't read back the frame for a screenshot: {:?}", e);
+                state.cancel_region_screenshot();
+            }
+        }
+    }
+
+    // Drop target before submit
+    drop(target);
+
+    // Nothing drawn means this buffer is stale - keep showing the last one
+    if let Some(damage) = damage {
+        backend.submit(Some(&damage[..]))
+            .map_err(|e| anyhow::anyhow!("Submit error: {:?}", e))?;
     }
 
     Ok(())
@@ -3,12 +3,28 @@
 //! This backend runs directly on hardware - no window, owns the whole display.
 //! Used for bare metal or VM without a desktop environment.
 
-use std::time::Duration;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use smithay::{
     backend::{
+        allocator::{
+            gbm::{GbmAllocator, GbmBufferFlags, GbmDevice},
+            Format, Fourcc,
+        },
+        drm::{
+            compositor::{DrmCompositor, FrameFlags},
+            exporter::gbm::GbmFramebufferExporter,
+            DrmDevice, DrmDeviceFd, DrmDeviceNotifier, DrmEvent, DrmEventMetadata, DrmEventTime, DrmNode,
+            GbmBufferedSurface, NodeType,
+        },
+        egl::{EGLContext, EGLDisplay},
         libinput::{LibinputInputBackend, LibinputSessionInterface},
+        renderer::{damage::OutputDamageTracker, glow::GlowRenderer, Bind},
         session::{libseat::LibSeatSession, Session, Event as SessionEvent},
         udev::{self, UdevBackend, UdevEvent},
     },
@@ -18,47 +34,639 @@ use smithay::{
             timer::{TimeoutAction, Timer},
             EventLoop,
         },
+        drm::control::{connector, crtc, property, Device as ControlDevice, Mode as DrmMode, ModeTypeFlags},
         input::Libinput,
+        rustix::fs::OFlags,
     },
-    utils::Transform,
+    utils::{Clock, DeviceFd, Monotonic, Transform},
 };
 
+use crate::gpu_reset::{handle_gpu_reset, GpuResetTracker, ResetRecovery};
+use crate::render::OutputRenderElement;
+use crate::schedule::{Phase, PhaseTimer, FRAME_INTERVAL};
 use crate::state::VibeWM;
 
+/// 8-bit formats we can scan out, best first
+const FORMATS_8BIT: &[Fourcc] = &[Fourcc::Xrgb8888, Fourcc::Argb8888, Fourcc::Xbgr8888, Fourcc::Abgr8888];
+
+/// 10-bit formats, tried ahead of the 8-bit ones with prefer_10bit
+const FORMATS_10BIT: &[Fourcc] = &[Fourcc::Xrgb2101010, Fourcc::Xbgr2101010];
+
+/// What a primary plane and the renderer agree on
+#[derive(Debug, Clone)]
+pub struct NegotiatedFormats {
+    /// Formats to create the GBM surface with, best first - walk the list
+    /// until one works
+    pub candidates: Vec<Fourcc>,
+
+    /// Format/modifier pairs both the plane and the renderer take - what
+    /// dmabuf feedback should advertise as its scanout tranche
+    pub scanout: Vec<Format>,
+}
+
+/// Intersect a plane's formats with the renderer's and order them
+///
+/// 10-bit only goes first when asked for and the connector's max bpc allows
+/// it; otherwise it's still there as a fallback behind the 8-bit formats.
+/// Anything else both sides support goes at the very end, for odd GPUs.
+pub fn negotiate_formats(
+    plane: &HashSet<Format>,
+    renderer: &HashSet<Format>,
+    prefer_10bit: bool,
+    max_bpc: Option<u64>,
+) -> NegotiatedFormats {
+    let mut scanout: Vec<Format> = plane.intersection(renderer).copied().collect();
+    scanout.sort_by_key(|format| (format.code as u32, u64::from(format.modifier)));
+
+    let supported: HashSet<Fourcc> = scanout.iter().map(|format| format.code).collect();
+    let ten_bit_ok = prefer_10bit && max_bpc.map_or(true, |bpc| bpc >= 10);
+
+    let preferred: Vec<Fourcc> = if ten_bit_ok {
+        FORMATS_10BIT.iter().chain(FORMATS_8BIT).copied().collect()
+    } else {
+        FORMATS_8BIT.iter().chain(FORMATS_10BIT).copied().collect()
+    };
+
+    let mut candidates: Vec<Fourcc> = preferred.iter().copied().filter(|code| supported.contains(code)).collect();
+    let mut others: Vec<Fourcc> = supported.iter().copied().filter(|code| !preferred.contains(code)).collect();
+    others.sort_by_key(|code| *code as u32);
+    candidates.extend(others);
+
+    match candidates.first() {
+        Some(first) if prefer_10bit && !FORMATS_10BIT.contains(first) => {
+            let reason = match max_bpc {
+                Some(bpc) if bpc < 10 => format!("the connector's max bpc is {}", bpc),
+                _ => "the plane and renderer have no 10-bit format in common".to_string(),
+            };
+            tracing::info!("prefer_10bit is set but {} - using {:?}", reason, first);
+        }
+        Some(first) => tracing::info!("Scanout format: {:?} ({} candidates)", first, candidates.len()),
+        None => tracing::warn!("The plane and the renderer have no format in common"),
+    }
+
+    NegotiatedFormats { candidates, scanout }
+}
+
+/// Highest value the connector's "max bpc" property can be set to
+///
+/// None when the driver doesn't expose it - most only do for HDMI/DP.
+pub fn connector_max_bpc(device: &impl ControlDevice, connector: connector::Handle) -> Option<u64> {
+    let properties = device.get_properties(connector).ok()?;
+    let (handles, _) = properties.as_props_and_values();
+
+    handles.iter().find_map(|&handle| {
+        let info = device.get_property(handle).ok()?;
+        if info.name().to_str() != Ok("max bpc") {
+            return None;
+        }
+        match info.value_type() {
+            property::ValueType::UnsignedRange(_, max) => Some(max),
+            _ => None,
+        }
+    })
+}
+
+/// When the flip actually landed, from the kernel's vblank timestamp
+///
+/// The timestamp is CLOCK_MONOTONIC; Instant has no constructor for that, so
+/// it's shifted back from now by however long ago the flip was. No metadata
+/// (the driver didn't say) means now.
+fn presented_at(metadata: &Option<DrmEventMetadata>) -> Instant {
+    let now = Instant::now();
+    match metadata {
+        Some(DrmEventMetadata { time: DrmEventTime::Monotonic(at), .. }) => {
+            let ago = Duration::from(Clock::<Monotonic>::new().now()).saturating_sub(*at);
+            now.checked_sub(ago).unwrap_or(now)
+        }
+        _ => now,
+    }
+}
+
+type GbmDrmCompositor = DrmCompositor<GbmAllocator<DrmDeviceFd>, GbmFramebufferExporter<DrmDeviceFd>, (), DrmDeviceFd>;
+
+/// How an output's frames get to the screen
+enum Composition {
+    /// Atomic commits - the cursor and client buffers (video, mostly) go
+    /// straight onto the cursor/overlay planes when a test-commit says the
+    /// hardware takes them, everything else is composited with GL
+    Planes(GbmDrmCompositor),
+
+    /// Everything composited with GL into a swapchain that gets flipped -
+    /// drivers without atomic modesetting, or when DrmCompositor wouldn't
+    /// come up
+    Swapchain {
+        surface: GbmBufferedSurface<GbmAllocator<DrmDeviceFd>, ()>,
+
+        /// What changed since each buffer was last drawn into
+        damage_tracker: OutputDamageTracker,
+    },
+}
+
+impl Composition {
+    /// The queued frame is on screen
+    fn frame_submitted(&mut self) -> Result<()> {
+        match self {
+            Composition::Planes(compositor) => compositor.frame_submitted().map(drop).map_err(|e| anyhow::anyhow!("{:?}", e)),
+            Composition::Swapchain { surface, .. } => surface.frame_submitted().map(drop).map_err(|e| anyhow::anyhow!("{:?}", e)),
+        }
+    }
+
+    /// Back from a VT switch - whatever was queued never flipped, and the
+    /// hardware state may have been changed under us
+    fn reset(&mut self) {
+        match self {
+            Composition::Planes(compositor) => {
+                if let Err(e) = compositor.reset_state() {
+                    tracing::warn!("Couldn't reset the output state: {}", e);
+                }
+            }
+            Composition::Swapchain { surface, .. } => surface.reset_buffers(),
+        }
+    }
+}
+
+/// One lit connector - its output and how frames get onto it
+struct OutputSurface {
+    output: Output,
+    connector: connector::Handle,
+    mode: DrmMode,
+    composition: Composition,
+
+    /// A page flip is queued - the next frame waits for its VBlank
+    flip_pending: bool,
+}
+
+/// The GPU we drive - just the primary one for now
+struct DrmBackend {
+    drm: DrmDevice,
+    gbm: GbmDevice<DrmDeviceFd>,
+
+    /// Where client dmabufs get imported for direct scanout
+    render_node: DrmNode,
+
+    renderer: GlowRenderer,
+    surfaces: HashMap<crtc::Handle, OutputSurface>,
+    reset_tracker: GpuResetTracker,
+
+    /// Rebuilt after a GPU reset, and no frame has made it through since
+    recovering: bool,
+
+    /// The GPU can't be used anymore - run_drm returns this
+    fatal: Option<anyhow::Error>,
+
+    /// Switched to another VT, the device isn't ours - nothing renders
+    /// until ActivateSession
+    session: LibSeatSession,
+}
+
+impl DrmBackend {
+    /// Open the GPU through the session and bring up GBM and a GL renderer on it
+    fn open(session: &mut LibSeatSession, path: &Path) -> Result<(Self, DrmDeviceNotifier)> {
+        let fd = session
+            .open(path, OFlags::RDWR | OFlags::CLOEXEC | OFlags::NOCTTY | OFlags::NONBLOCK)
+            .with_context(|| format!("Failed to open {:?}", path))?;
+        let fd = DrmDeviceFd::new(DeviceFd::from(fd));
+
+        let node = DrmNode::from_file(&fd).context("Failed to find the DRM node")?;
+        let render_node = node.node_with_type(NodeType::Render).and_then(Result::ok).unwrap_or(node);
+
+        let (drm, notifier) = DrmDevice::new(fd.clone(), true).context("Failed to open the DRM device")?;
+        let gbm = GbmDevice::new(fd).context("Failed to create a GBM device")?;
+        let renderer = Self::create_renderer(&gbm)?;
+
+        let backend = Self {
+            drm,
+            gbm,
+            render_node,
+            renderer,
+            surfaces: HashMap::new(),
+            reset_tracker: GpuResetTracker::new(),
+            recovering: false,
+            fatal: None,
+            session: session.clone(),
+        };
+        Ok((backend, notifier))
+    }
+
+    /// A fresh EGL display and context on the GPU, and a renderer on them
+    fn create_renderer(gbm: &GbmDevice<DrmDeviceFd>) -> Result<GlowRenderer> {
+        let egl_display = unsafe { EGLDisplay::new(gbm.clone()) }.context("Failed to create an EGL display")?;
+        let context = EGLContext::new(&egl_display).context("Failed to create an EGL context")?;
+        unsafe { GlowRenderer::new(context) }.context("Failed to create the renderer")
+    }
+
+    /// Start over after a GPU reset - a new renderer, and new surfaces for
+    /// every lit output
+    ///
+    /// The outputs stay up, so clients don't see them go and come back.
+    /// Client buffers get imported again by the new renderer, and the new
+    /// surfaces have no history to be partial against - the next frame on
+    /// each output is a full redraw.
+    fn rebuild(&mut self, state: &mut VibeWM) -> Result<()> {
+        let _watchdog = crate::watchdog::disarm();
+
+        // Their buffers belong to the dead context - and their CRTCs have
+        // to be free for the new surfaces
+        let lit: Vec<(crtc::Handle, OutputSurface)> = self.surfaces.drain().collect();
+
+        self.renderer = Self::create_renderer(&self.gbm)?;
+
+        for (crtc, old) in lit {
+            let OutputSurface { output, connector, mode, composition, .. } = old;
+            drop(composition);
+
+            match self.compose(crtc, mode, connector, &output, state.config.prefer_10bit) {
+                Ok(composition) => {
+                    self.surfaces.insert(crtc, OutputSurface { output, connector, mode, composition, flip_pending: false });
+                }
+                // Dark until the next hotplug scan picks the connector up again
+                Err(e) => {
+                    tracing::warn!("Couldn't bring {} back after the GPU reset: {:#}", output.name(), e);
+                    state.remove_output(&output);
+                }
+            }
+        }
+
+        self.recovering = true;
+        Ok(())
+    }
+
+    /// Light up every connected connector we don't drive yet, and drop the
+    /// ones that went away - outputs are laid out left to right
+    fn scan_connectors(&mut self, state: &mut VibeWM) {
+        // Mode-setting blocks, sometimes for a second or more
+        let _watchdog = crate::watchdog::disarm();
+
+        let resources = match self.drm.resource_handles() {
+            Ok(resources) => resources,
+            Err(e) => {
+                tracing::warn!("Couldn't read DRM resources: {}", e);
+                return;
+            }
+        };
+
+        let connected: Vec<connector::Info> = resources
+            .connectors()
+            .iter()
+            .filter_map(|&handle| self.drm.get_connector(handle, false).ok())
+            .filter(|info| info.state() == connector::State::Connected)
+            .collect();
+
+        // Unplugged
+        let gone: Vec<crtc::Handle> = self.surfaces
+            .iter()
+            .filter(|(_, target)| !connected.iter().any(|info| info.handle() == target.connector))
+            .map(|(&crtc, _)| crtc)
+            .collect();
+        for crtc in gone {
+            if let Some(target) = self.surfaces.remove(&crtc) {
+                state.remove_output(&target.output);
+            }
+        }
+
+        // Plugged in
+        for info in connected {
+            if self.surfaces.values().any(|target| target.connector == info.handle()) {
+                continue;
+            }
+
+            let crtc = info.encoders()
+                .iter()
+                .filter_map(|&encoder| self.drm.get_encoder(encoder).ok())
+                .flat_map(|encoder| resources.filter_crtcs(encoder.possible_crtcs()))
+                .find(|crtc| !self.surfaces.contains_key(crtc));
+            let Some(crtc) = crtc else {
+                tracing::warn!("No free CRTC for {:?} - leaving it dark", info.interface());
+                continue;
+            };
+
+            if let Err(e) = self.connect(crtc, &info, state) {
+                tracing::warn!("Couldn't light up {:?}: {:#}", info.interface(), e);
+            }
+        }
+    }
+
+    /// Set up a surface on `crtc` for a connector and announce its output
+    fn connect(&mut self, crtc: crtc::Handle, info: &connector::Info, state: &mut VibeWM) -> Result<()> {
+        let modes = info.modes();
+        let drm_mode = modes
+            .iter()
+            .find(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
+            .or(modes.first())
+            .copied()
+            .context("Connector has no modes")?;
+
+        let name = format!("{}-{}", info.interface().as_str(), info.interface_id());
+        let (width_mm, height_mm) = info.size().unwrap_or((0, 0));
+        let output = Output::new(
+            name,
+            PhysicalProperties {
+                size: (width_mm as i32, height_mm as i32).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Unknown".into(),
+                model: "Unknown".into(),
+            },
+        );
+
+        // Right of everything already up
+        let x = state.space
+            .outputs()
+            .filter_map(|o| state.space.output_geometry(o))
+            .map(|geometry| geometry.loc.x + geometry.size.w)
+            .max()
+            .unwrap_or(0);
+
+        // DrmCompositor sizes its buffers from the output, so its mode goes
+        // in before the surface is set up
+        let mode = Mode::from(drm_mode);
+        output.change_current_state(Some(mode), Some(Transform::Normal), None, Some((x, 0).into()));
+        output.set_preferred(mode);
+
+        let composition = self.compose(crtc, drm_mode, info.handle(), &output, state.config.prefer_10bit)?;
+
+        output.create_global::<VibeWM>(&state.display_handle);
+        state.add_output(&output, (x, 0).into());
+
+        tracing::info!("Output {} up: {}x{}@{}", output.name(), mode.size.w, mode.size.h, mode.refresh / 1000);
+
+        self.surfaces.insert(
+            crtc,
+            OutputSurface {
+                output,
+                connector: info.handle(),
+                mode: drm_mode,
+                composition,
+                flip_pending: false,
+            },
+        );
+        Ok(())
+    }
+
+    /// Set up how frames reach `crtc` - a DrmCompositor where atomic
+    /// modesetting works, the GL swapchain where it doesn't or the
+    /// compositor won't come up
+    fn compose(
+        &self,
+        crtc: crtc::Handle,
+        drm_mode: DrmMode,
+        connector: connector::Handle,
+        output: &Output,
+        prefer_10bit: bool,
+    ) -> Result<Composition> {
+        let drm_surface = self.drm
+            .create_surface(crtc, drm_mode, &[connector])
+            .context("Failed to create a DRM surface")?;
+
+        let plane: HashSet<Format> = drm_surface.plane_info().formats.iter().copied().collect();
+        let renderer: HashSet<Format> = self.renderer.egl_context().dmabuf_render_formats().iter().copied().collect();
+        let max_bpc = connector_max_bpc(self.drm.device_fd(), connector);
+        let formats = negotiate_formats(&plane, &renderer, prefer_10bit, max_bpc);
+        let flags = GbmBufferFlags::RENDERING | GbmBufferFlags::SCANOUT;
+
+        let drm_surface = if self.drm.is_atomic() {
+            // Every frame's plane assignment is test-committed first - what
+            // the hardware refuses gets composited on the primary plane
+            let planes = drm_surface.planes().clone();
+            let compositor = DrmCompositor::new(
+                output,
+                drm_surface,
+                Some(planes),
+                GbmAllocator::new(self.gbm.clone(), flags),
+                GbmFramebufferExporter::new(self.gbm.clone(), Some(self.render_node).into()),
+                formats.candidates.iter().copied(),
+                renderer.iter().copied(),
+                self.drm.cursor_size(),
+                Some(self.gbm.clone()),
+            );
+            match compositor {
+                Ok(compositor) => return Ok(Composition::Planes(compositor)),
+                Err(e) => tracing::warn!("No plane offloading on {}: {:?} - compositing with GL", output.name(), e),
+            }
+
+            // The failed compositor took the surface with it
+            self.drm
+                .create_surface(crtc, drm_mode, &[connector])
+                .context("Failed to create a DRM surface")?
+        } else {
+            tracing::info!("No atomic modesetting - compositing {} with GL", output.name());
+            drm_surface
+        };
+
+        let allocator = GbmAllocator::new(self.gbm.clone(), flags);
+        let surface = GbmBufferedSurface::new(drm_surface, allocator, &formats.candidates, formats.scanout)
+            .context("Failed to create the swapchain")?;
+        Ok(Composition::Swapchain { surface, damage_tracker: OutputDamageTracker::from_output(output) })
+    }
+
+    /// Page flip landed - the buffer is on screen, draw the next one
+    fn vblank(&mut self, crtc: crtc::Handle, presented_at: Instant, state: &mut VibeWM) {
+        let Some(target) = self.surfaces.get_mut(&crtc) else { return };
+        if let Err(e) = target.composition.frame_submitted() {
+            tracing::warn!("Page flip on {} failed: {}", target.output.name(), e);
+        }
+        target.flip_pending = false;
+        state.frames.presented(presented_at);
+
+        self.render(crtc, state);
+    }
+
+    /// Draw a frame for one CRTC and queue it for the next page flip
+    fn render(&mut self, crtc: crtc::Handle, state: &mut VibeWM) {
+        if self.fatal.is_some() || !self.session.is_active() {
+            return;
+        }
+        if self.surfaces.get(&crtc).map_or(true, |target| target.flip_pending) {
+            return;
+        }
+
+        // Coalesced pointer motion goes out before the frame that shows it
+        let input = PhaseTimer::start(Phase::Input);
+        state.flush_pointer_motion();
+        state.frames.timings.finish(input);
+
+        let render = PhaseTimer::start(Phase::Render);
+        let drawn = self.draw(crtc, state);
+        state.frames.timings.finish(render);
+
+        if drawn.is_ok() && self.recovering {
+            self.recovering = false;
+            tracing::info!("Renderer recovered - resuming");
+        }
+
+        match drawn {
+            Ok(true) => {
+                state.frames.submitted();
+                if let Some(target) = self.surfaces.get_mut(&crtc) {
+                    target.flip_pending = true;
+                }
+            }
+            // Nothing changed - no flip, the idle timer looks again next frame
+            Ok(false) => {}
+            // Paused mid-frame (VT switch) - the flip can't work, but the
+            // GPU is fine. ActivateSession starts drawing again
+            Err(e) if !self.session.is_active() => {
+                tracing::debug!("Frame dropped, session paused: {:#}", e);
+            }
+            // A failed frame usually means the context went away - rebuild
+            // and carry on instead of dying
+            Err(e) => {
+                tracing::warn!("Render failed: {:#} - treating it as a lost GPU context", e);
+                state.gpu_reset_pending = true;
+            }
+        }
+
+        if state.gpu_reset_pending {
+            state.gpu_reset_pending = false;
+            match handle_gpu_reset(&mut self.reset_tracker) {
+                ResetRecovery::Reinitialize => {
+                    if let Err(e) = self.rebuild(state) {
+                        self.fatal = Some(e.context("Couldn't rebuild the renderer after a GPU reset"));
+                    }
+                }
+                ResetRecovery::FallBackToSoftware => {
+                    self.fatal = Some(anyhow::anyhow!(
+                        "GPU keeps resetting - restart with `software_rendering = true` (or LIBGL_ALWAYS_SOFTWARE=1)"
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Draw what changed and queue it - false when nothing did, so
+    /// there's nothing to flip
+    fn draw(&mut self, crtc: crtc::Handle, state: &mut VibeWM) -> Result<bool> {
+        let Some(target) = self.surfaces.get_mut(&crtc) else { return Ok(false) };
+        let output = target.output.clone();
+
+        // Overlays for this output
+        state.render_frame(&output);
+
+        // TODO: A region_select without a frozen frame wants this frame
+        // read back (see draw_frame in backend.rs)
+        let elements: Vec<OutputRenderElement> = state.output_elements(&mut self.renderer, &output, true);
+        let background = state.config.colors.background;
+
+        match &mut target.composition {
+            Composition::Planes(compositor) => {
+                let frame = compositor
+                    .render_frame(&mut self.renderer, &elements, background, FrameFlags::DEFAULT)
+                    .map_err(|e| anyhow::anyhow!("Render error: {:?}", e))?;
+
+                // Clients pace themselves on these - they go out even when
+                // nothing changed, or a client waiting on one before drawing
+                // never would
+                state.send_frame_callbacks(&output, Some(&frame.states));
+
+                if frame.is_empty {
+                    return Ok(false);
+                }
+                compositor
+                    .queue_frame(())
+                    .map_err(|e| anyhow::anyhow!("Page flip error: {:?}", e))?;
+            }
+            Composition::Swapchain { surface, damage_tracker } => {
+                // Buffer age tells the damage tracker how far back this
+                // buffer's contents are, so only what changed since gets redrawn
+                let (mut dmabuf, age) = surface
+                    .next_buffer()
+                    .map_err(|e| anyhow::anyhow!("No free buffer: {:?}", e))?;
+                let mut framebuffer = self.renderer
+                    .bind(&mut dmabuf)
+                    .map_err(|e| anyhow::anyhow!("Bind error: {:?}", e))?;
+
+                let rendered = damage_tracker
+                    .render_output(&mut self.renderer, &mut framebuffer, age as usize, &elements, background)
+                    .map_err(|e| anyhow::anyhow!("Render error: {:?}", e))?;
+                drop(framebuffer);
+
+                state.send_frame_callbacks(&output, Some(&rendered.states));
+
+                let Some(damage) = rendered.damage.cloned() else {
+                    return Ok(false);
+                };
+                surface
+                    .queue_buffer(Some(rendered.sync), Some(damage), ())
+                    .map_err(|e| anyhow::anyhow!("Page flip error: {:?}", e))?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Render every output not waiting on a page flip - kicks off the VBlank
+    /// cycle, and picks outputs back up after frames with nothing to draw
+    fn render_all(&mut self, state: &mut VibeWM) {
+        let crtcs: Vec<crtc::Handle> = self.surfaces.keys().copied().collect();
+        for crtc in crtcs {
+            self.render(crtc, state);
+        }
+    }
+}
+
 /// Run vibeWM with the DRM backend (bare metal mode)
 pub fn run_drm(event_loop: &mut EventLoop<'static, VibeWM>, state: &mut VibeWM) -> Result<()> {
     tracing::info!("Initializing DRM backend...");
 
     // Initialize session (libseat handles permissions)
-    let (session, notifier) = LibSeatSession::new()
+    let (mut session, notifier) = LibSeatSession::new()
         .context("Failed to create libseat session - are you running from a TTY?")?;
 
     tracing::info!("Session opened on seat: {}", session.seat());
 
-    // Add session to event loop
+    // Initialize udev for device discovery
+    let udev_backend = UdevBackend::new(session.seat())
+        .context("Failed to create udev backend")?;
+
+    // Find primary GPU
+    let primary_gpu = udev::primary_gpu(session.seat())
+        .context("Failed to query udev for the primary GPU")?
+        .context("No GPU found - is a DRM driver loaded? (check `ls /dev/dri`)")?;
+
+    tracing::info!("Primary GPU: {:?}", primary_gpu);
+
+    // TODO: Multi-GPU - outputs on the primary GPU only for now. Dmabuf
+    // feedback should advertise each output's negotiate_formats(..).scanout
+    // set, so clients pick buffers the planes can take
+    let (backend, drm_notifier) = DrmBackend::open(&mut session, &primary_gpu)?;
+    let backend = Rc::new(RefCell::new(backend));
+
+    // Page flips drive rendering - each output draws its next frame when
+    // the last one hits the screen
+    let vblank_backend = backend.clone();
+    event_loop
+        .handle()
+        .insert_source(drm_notifier, move |event, metadata, state| match event {
+            DrmEvent::VBlank(crtc) => vblank_backend.borrow_mut().vblank(crtc, presented_at(metadata), state),
+            DrmEvent::Error(e) => tracing::error!("DRM error: {}", e),
+        })
+        .map_err(|e| anyhow::anyhow!("Failed to insert DRM source: {:?}", e))?;
+
+    // Add session to event loop - VT switches pause and resume the device
+    let session_backend = backend.clone();
     event_loop
         .handle()
-        .insert_source(notifier, |event, _, _state| match event {
+        .insert_source(notifier, move |event, _, state| match event {
             SessionEvent::ActivateSession => {
                 tracing::info!("Session activated");
+                let _watchdog = crate::watchdog::disarm();
+                let mut backend = session_backend.borrow_mut();
+                if let Err(e) = backend.drm.activate(false) {
+                    tracing::warn!("Couldn't reactivate the DRM device: {}", e);
+                }
+                // Whatever was in flight when we left never flips
+                for target in backend.surfaces.values_mut() {
+                    target.flip_pending = false;
+                    target.composition.reset();
+                }
+                backend.scan_connectors(state);
+                backend.render_all(state);
             }
             SessionEvent::PauseSession => {
                 tracing::info!("Session paused");
+                session_backend.borrow_mut().drm.pause();
             }
         })
         .map_err(|e| anyhow::anyhow!("Failed to insert session source: {:?}", e))?;
 
-    // Initialize udev for device discovery
-    let udev_backend = UdevBackend::new(session.seat())
-        .context("Failed to create udev backend")?;
-
-    // Find primary GPU
-    let primary_gpu = udev::primary_gpu(session.seat())
-        .context("Failed to find primary GPU")?
-        .context("No GPU found")?;
-
-    tracing::info!("Primary GPU: {:?}", primary_gpu);
-
     // Initialize libinput for input handling
     let mut libinput_context = Libinput::new_with_udev::<LibinputSessionInterface<LibSeatSession>>(
         session.clone().into(),
@@ -82,15 +690,24 @@ pub fn run_drm(event_loop: &mut EventLoop<'static, VibeWM>, state: &mut VibeWM)
         tracing::info!("Found GPU: {:?} at {:?}", device_id, path);
     }
 
-    // Add udev to event loop for hotplug
+    // Add udev to event loop for hotplug - a changed GPU usually means a
+    // monitor came or went
+    let udev_drm_backend = backend.clone();
     event_loop
         .handle()
-        .insert_source(udev_backend, move |event, _, _state| match event {
+        .insert_source(udev_backend, move |event, _, state| match event {
             UdevEvent::Added { device_id, path } => {
                 tracing::info!("GPU added: {:?} at {:?}", device_id, path);
             }
             UdevEvent::Changed { device_id } => {
                 tracing::info!("GPU changed: {:?}", device_id);
+                let mut backend = udev_drm_backend.borrow_mut();
+                // Not ours while paused - ActivateSession rescans anyway
+                if !backend.session.is_active() {
+                    return;
+                }
+                backend.scan_connectors(state);
+                backend.render_all(state);
             }
             UdevEvent::Removed { device_id } => {
                 tracing::info!("GPU removed: {:?}", device_id);
@@ -98,57 +715,58 @@ pub fn run_drm(event_loop: &mut EventLoop<'static, VibeWM>, state: &mut VibeWM)
         })
         .map_err(|e| anyhow::anyhow!("Failed to insert udev source: {:?}", e))?;
 
-    // Create a dummy output for now
-    // TODO: Actually enumerate DRM outputs and create real ones
-    let output = Output::new(
-        "DRM-1".to_string(),
-        PhysicalProperties {
-            size: (0, 0).into(),
-            subpixel: Subpixel::Unknown,
-            make: "vibeWM".into(),
-            model: "Virtual Output".into(),
-        },
-    );
-
-    let mode = Mode {
-        size: (1920, 1080).into(),
-        refresh: 60_000,
-    };
-
-    output.create_global::<VibeWM>(&state.display_handle);
-    output.change_current_state(
-        Some(mode),
-        Some(Transform::Normal),
-        None,
-        Some((0, 0).into()),
-    );
-    output.set_preferred(mode);
-
-    state.space.map_output(&output, (0, 0));
-    state.output = Some(output);
-
-    // Set up render timer (60 FPS)
-    let timer = Timer::immediate();
+    // Outputs whose last frame had no damage aren't flipping, so no VBlank
+    // comes to wake them - look at them again every frame interval (render
+    // does nothing while the session is paused)
+    let idle_backend = backend.clone();
     event_loop
         .handle()
-        .insert_source(timer, |_, _, state| {
-            // TODO: Actually render to DRM output
-            state.handle_pending();
-            TimeoutAction::ToDuration(Duration::from_millis(16))
+        .insert_source(Timer::from_duration(FRAME_INTERVAL), move |_, _, state| {
+            idle_backend.borrow_mut().render_all(state);
+            TimeoutAction::ToDuration(FRAME_INTERVAL)
         })
-        .map_err(|e| anyhow::anyhow!("Failed to insert render timer: {:?}", e))?;
+        .map_err(|e| anyhow::anyhow!("Failed to insert idle redraw timer: {:?}", e))?;
 
-    tracing::info!("DRM backend ready (stub mode - no actual rendering yet)");
-    tracing::warn!("DRM rendering not yet implemented - you'll see a blank screen");
+    {
+        let mut backend = backend.borrow_mut();
+        backend.scan_connectors(state);
+        if backend.surfaces.is_empty() {
+            tracing::warn!("No connected outputs - windows wait until a monitor is plugged in");
+        }
+        backend.render_all(state);
+    }
+
+    tracing::info!("DRM backend ready");
+
+    // Socket and outputs are up - clients can connect now
+    if let Some(notify) = &state.sd_notify {
+        notify.ready();
+    }
     tracing::info!("Press mod+Q to quit");
 
-    // Main loop
+    // Main loop - rendering happens on VBlank (and the idle timer)
     let mut running = true;
     while running {
+        crate::watchdog::ping();
+
+        let dispatch = PhaseTimer::start(Phase::Dispatch);
+        let dispatched = event_loop.dispatch(Duration::ZERO, state);
+        state.frames.timings.finish(dispatch);
+        dispatched.context("Event loop error")?;
+
         event_loop
-            .dispatch(Duration::from_millis(16), state)
+            .dispatch(state.frames.dispatch_timeout(), state)
             .context("Event loop error")?;
 
+        // Housekeeping once per iteration, whatever rendered (or didn't) -
+        // with no outputs at all clients still get flushed
+        state.handle_pending();
+
+        // Out of ways to keep the GPU going
+        if let Some(e) = backend.borrow_mut().fatal.take() {
+            return Err(e);
+        }
+
         // Check for quit
         if state.input.quit_requested {
             running = false;
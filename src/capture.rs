@@ -0,0 +1,88 @@
+//! Screen capture sessions - who is recording which output
+//!
+//! The screencopy handler registers a session when a client starts copying
+//! an output and drops it when the client stops (or goes away). Sessions that
+//! copy with damage are recordings; one-shot copies are screenshots and don't
+//! turn the recording indicator on.
+
+use std::time::Instant;
+
+/// One client capturing one output
+#[derive(Debug, Clone)]
+pub struct CaptureSession {
+    pub id: u64,
+
+    /// Output (connector name) being captured
+    pub output: String,
+
+    /// Who's capturing - app_id or process name, whatever the handler can find
+    pub client: String,
+
+    /// Copying with damage, i.e. a video stream rather than a screenshot
+    pub with_damage: bool,
+
+    pub started: Instant,
+}
+
+/// Every active capture session
+pub struct Captures {
+    sessions: Vec<CaptureSession>,
+    next_id: u64,
+}
+
+impl Default for Captures {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Captures {
+    pub fn new() -> Self {
+        Self {
+            sessions: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    /// A client started capturing an output - returns the id to stop it with
+    pub fn start(&mut self, output: impl Into<String>, client: impl Into<String>, with_damage: bool) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let session = CaptureSession {
+            id,
+            output: output.into(),
+            client: client.into(),
+            with_damage,
+            started: Instant::now(),
+        };
+        if with_damage {
+            tracing::info!("{} started recording {}", session.client, session.output);
+        }
+        self.sessions.push(session);
+        id
+    }
+
+    pub fn stop(&mut self, id: u64) {
+        if let Some(index) = self.sessions.iter().position(|s| s.id == id) {
+            let session = self.sessions.remove(index);
+            if session.with_damage {
+                tracing::info!("{} stopped recording {}", session.client, session.output);
+            }
+        }
+    }
+
+    /// Is anyone recording this output?
+    pub fn is_recording(&self, output: &str) -> bool {
+        self.sessions.iter().any(|s| s.with_damage && s.output == output)
+    }
+
+    /// Is anyone recording anything?
+    pub fn any_recording(&self) -> bool {
+        self.sessions.iter().any(|s| s.with_damage)
+    }
+
+    pub fn sessions(&self) -> &[CaptureSession] {
+        &self.sessions
+    }
+}
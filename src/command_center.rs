@@ -3,8 +3,20 @@
 //! No status bars. No minimalism. Just vibes.
 //! Press mod+S and bask in the glow.
 
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::action::{self, Action, ActionMatch};
+use crate::config::{AnimationLevel, CommandCenterAnchor, CommandCenterLayoutConfig, Dimension};
+use crate::icons::IconCache;
+use crate::sysinfo::SystemInfo;
+use crate::usage::UsageSummary;
 
 /// The Command Center state
 pub struct CommandCenter {
@@ -23,6 +35,15 @@ pub struct CommandCenter {
     /// Filtered app list
     pub filtered_apps: Vec<AppEntry>,
 
+    /// Most apps listed at once (config `max_results`), None = all of them
+    pub max_results: Option<usize>,
+
+    /// Matching apps past max_results - the footer says how many
+    pub more_results: usize,
+
+    /// Compositor actions matching the query (listed before apps)
+    pub filtered_actions: Vec<ActionMatch>,
+
     /// All available apps
     pub all_apps: Vec<AppEntry>,
 
@@ -35,22 +56,274 @@ pub struct CommandCenter {
     /// Glow pulse phase (for that sweet sweet animation)
     pub glow_phase: f32,
 
+    /// Hold the glow still (reduced motion, or an output is being recorded)
+    pub glow_paused: bool,
+
+    /// Config `animations` - scales the open/close and scroll easing
+    pub animations: AnimationLevel,
+
     /// Last frame time for animations
     pub last_frame: Instant,
+
+    /// Active workspace name for the system bar
+    pub workspace_name: String,
+
+    /// Output (connector name) the center is anchored to - only that one draws it
+    pub output: Option<String>,
+
+    /// Layout worked out when it opened - config changes apply on the next open
+    pub layout: Option<CommandCenterLayout>,
+
+    /// Result grid scroll state
+    pub scroll: ScrollState,
+
+    /// What the app dirs looked like at the last scan
+    app_dir_stamps: Vec<DirStamp>,
+
+    /// Last time the app dirs were checked for changes
+    last_app_check: Instant,
+
+    /// Apps come from desktop files (false = fixed list, or nothing loaded yet)
+    scans_apps: bool,
+
+    /// Background scan that hasn't reported back yet
+    pending_scan: Option<Receiver<AppScan>>,
+
+    /// App dirs from config, scanned after the XDG ones
+    pub extra_app_dirs: Vec<PathBuf>,
+
+    /// Reopening this soon after closing picks up where you left off (None = always fresh)
+    pub remember_query: Option<Duration>,
+
+    /// What each section looked like when the center last closed
+    memory: QueryMemory,
+
+    /// Recent files section on (config - it reads your file history)
+    pub files_enabled: bool,
+
+    /// Recently used files, most recent first, and the ones matching the query
+    pub all_files: Vec<RecentFile>,
+    pub filtered_files: Vec<RecentFile>,
+
+    /// mtime of recently-used.xbel when it was last read
+    files_modified: Option<SystemTime>,
+
+    /// Open windows, most recently raised first (set when it opens), and the
+    /// ones matching the query
+    pub windows: Vec<WindowInfo>,
+    pub filtered_windows: Vec<WindowInfo>,
+
+    /// Power card that got one Enter and is waiting for the second
+    pub power_armed: Option<PowerAction>,
+
+    /// This week's launches, for the "stats" query (None = tracking off)
+    pub usage: Option<UsageSummary>,
+
+    /// Filled by the sysinfo sampler thread
+    pub system_info: Arc<RwLock<SystemInfo>>,
+
+    /// App icons decoded so far, by Icon= value
+    pub icons: IconCache,
+
+    /// Launches per app name, from the usage history - the app list opens
+    /// most used first
+    pub launch_counts: HashMap<String, u32>,
+}
+
+/// Query and selection from the last time the center was open
+#[derive(Debug, Clone, Default)]
+struct QueryMemory {
+    closed_at: Option<Instant>,
+
+    /// Section that had focus
+    section: Option<CommandCenterSection>,
+
+    /// (query, selected index) per section
+    sections: HashMap<CommandCenterSection, (String, usize)>,
+}
+
+/// Result of scanning the app dirs - built off-thread, applied with apply_scan
+pub struct AppScan {
+    pub apps: Vec<AppEntry>,
+    stamps: Vec<DirStamp>,
+}
+
+/// Snapshot of an app dir, to notice changes inotify would miss
+///
+/// Flatpak swaps whole trees in with a symlink rename, so the dir we scanned
+/// can be replaced without a single event on it. The resolved path, inode
+/// and mtimes of the dir and its parent catch that.
+#[derive(Debug, Clone, PartialEq)]
+struct DirStamp {
+    resolved: Option<PathBuf>,
+    inode: Option<u64>,
+    modified: Option<SystemTime>,
+    parent_modified: Option<SystemTime>,
+}
+
+impl DirStamp {
+    fn of(dir: &Path) -> Self {
+        let resolved = std::fs::canonicalize(dir).ok();
+        let metadata = resolved.as_ref().and_then(|p| std::fs::metadata(p).ok());
+        let parent_modified = dir.parent()
+            .and_then(|p| std::fs::metadata(p).ok())
+            .and_then(|m| m.modified().ok());
+
+        Self {
+            inode: metadata.as_ref().map(|m| m.ino()),
+            modified: metadata.and_then(|m| m.modified().ok()),
+            resolved,
+            parent_modified,
+        }
+    }
+}
+
+/// Scroll state for the result grid - shared by rendering and hit-testing
+#[derive(Debug, Clone, Default)]
+pub struct ScrollState {
+    /// How far the grid is scrolled (px, 0 = top, can overshoot while rubber-banding)
+    pub offset: f32,
+
+    /// Kinetic velocity (px/s)
+    pub velocity: f32,
+
+    /// Fingers on the touchpad - no physics while they are
+    pub touching: bool,
+
+    /// Auto-scroll destination (keeping the selection visible)
+    pub target: Option<f32>,
+
+    /// Grid metrics, from the layout at open time
+    pub columns: usize,
+    pub row_height: f32,
+    pub viewport_height: f32,
+
+    /// When the last axis event arrived, for velocity tracking
+    pub last_event: Option<Instant>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CommandCenterSection {
     Search,
     Apps,
     Windows,
     System,
+    /// Recently used files (Tab from search, when enabled)
+    Files,
+}
+
+/// What Enter on the selected result should do
+#[derive(Debug, Clone)]
+pub enum Selection {
+    /// Spawn this command (argv, no shell)
+    Launch {
+        argv: Vec<String>,
+
+        /// Desktop file name - what the app's window usually calls itself
+        app_id: Option<String>,
+
+        /// Display name, for config rules that go by name
+        name: String,
+    },
+
+    /// Run a compositor action
+    Action(Action),
+
+    /// Open a file with its default app (xdg-open)
+    Open(PathBuf),
+
+    /// Switch to an open window (by id)
+    Window(u64),
+
+    /// Suspend, log out, reboot or shut down
+    Power(PowerAction),
+}
+
+/// The cards in the system section, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerAction {
+    Suspend,
+    Logout,
+    Reboot,
+    Shutdown,
+}
+
+impl PowerAction {
+    pub const ALL: [PowerAction; 4] = [Self::Suspend, Self::Logout, Self::Reboot, Self::Shutdown];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Suspend => "Suspend",
+            Self::Logout => "Log out",
+            Self::Reboot => "Reboot",
+            Self::Shutdown => "Shut down",
+        }
+    }
+
+    /// Card text while it waits for the second Enter
+    pub fn confirm_label(self) -> &'static str {
+        match self {
+            Self::Suspend => "Suspend?",
+            Self::Logout => "Log out? Enter again",
+            Self::Reboot => "Reboot? Enter again",
+            Self::Shutdown => "Shut down? Enter again",
+        }
+    }
+
+    /// Everything but suspend loses your open windows - those take a second Enter
+    pub fn needs_confirm(self) -> bool {
+        self != Self::Suspend
+    }
+
+    /// What to run - None for logout outside a logind session (vibeWM just quits then)
+    pub fn argv(self) -> Option<Vec<String>> {
+        let argv = match self {
+            Self::Suspend => vec!["systemctl".to_string(), "suspend".to_string()],
+            Self::Reboot => vec!["systemctl".to_string(), "reboot".to_string()],
+            Self::Shutdown => vec!["systemctl".to_string(), "poweroff".to_string()],
+            Self::Logout => {
+                let session = std::env::var("XDG_SESSION_ID").ok().filter(|id| !id.is_empty())?;
+                vec!["loginctl".to_string(), "terminate-session".to_string(), session]
+            }
+        };
+        Some(argv)
+    }
+}
+
+/// A recently used file, from recently-used.xbel
+#[derive(Debug, Clone)]
+pub struct RecentFile {
+    /// File name - what the card shows
+    pub name: String,
+    pub path: PathBuf,
+    pub mime_type: Option<String>,
+    /// ISO 8601 stamp from the bookmark - sorts as text
+    pub modified: String,
+    /// Fuzzy match score (higher = better match)
+    pub score: i32,
+}
+
+/// An open window, for the windows section
+#[derive(Debug, Clone)]
+pub struct WindowInfo {
+    /// Window id - the same one IPC uses
+    pub id: u64,
+    /// Title, or the app_id for windows that haven't set one
+    pub title: String,
+    pub app_id: Option<String>,
+    /// Fuzzy match score (higher = better match)
+    pub score: i32,
 }
 
 #[derive(Debug, Clone)]
 pub struct AppEntry {
     pub name: String,
+    /// Exec line as shown to humans
     pub exec: String,
+    /// What actually gets spawned - no shell involved
+    pub argv: Vec<String>,
+    /// Extra search terms from Keywords= (localized when available)
+    pub keywords: Vec<String>,
     pub icon: Option<String>,
     pub desktop_file: PathBuf,
     /// Fuzzy match score (higher = better match)
@@ -86,6 +359,7 @@ pub struct CommandCenterTheme {
     pub card_bg: [f32; 4],
     pub card_hover: [f32; 4],
     pub card_selected: [f32; 4],
+    pub card_action: [f32; 4],
     pub card_border_radius: f32,
 
     // Animation
@@ -124,6 +398,7 @@ impl Default for CommandCenterTheme {
             card_bg: [1.0, 1.0, 1.0, 0.05],
             card_hover: [1.0, 1.0, 1.0, 0.1],
             card_selected: [0.0, 1.0, 0.9, 0.2],
+            card_action: [0.6, 0.2, 1.0, 0.12],     // Purple-tinted glass for actions
             card_border_radius: 12.0,
 
             // Smooth animations
@@ -135,38 +410,98 @@ impl Default for CommandCenterTheme {
 }
 
 impl CommandCenter {
+    /// Empty command center - no apps until load_apps/set_apps
     pub fn new() -> Self {
-        let mut center = Self {
+        Self {
             visible: false,
             animation_t: 0.0,
             animation_start: None,
             search_query: String::new(),
             filtered_apps: Vec::new(),
+            max_results: Some(DEFAULT_MAX_RESULTS),
+            more_results: 0,
+            filtered_actions: Vec::new(),
             all_apps: Vec::new(),
             selected_index: 0,
             section: CommandCenterSection::Search,
             glow_phase: 0.0,
+            glow_paused: false,
+            animations: AnimationLevel::Full,
             last_frame: Instant::now(),
-        };
-
-        // Load apps on creation
-        center.load_apps();
-        center.filtered_apps = center.all_apps.clone();
+            workspace_name: String::new(),
+            output: None,
+            layout: None,
+            scroll: ScrollState::default(),
+            app_dir_stamps: Vec::new(),
+            last_app_check: Instant::now(),
+            scans_apps: false,
+            pending_scan: None,
+            extra_app_dirs: Vec::new(),
+            remember_query: None,
+            memory: QueryMemory::default(),
+            files_enabled: false,
+            all_files: Vec::new(),
+            filtered_files: Vec::new(),
+            files_modified: None,
+            windows: Vec::new(),
+            filtered_windows: Vec::new(),
+            power_armed: None,
+            usage: None,
+            system_info: Arc::new(RwLock::new(SystemInfo::default())),
+            icons: IconCache::new(),
+            launch_counts: HashMap::new(),
+        }
+    }
 
+    /// Command center over a fixed app list - never touches the filesystem
+    pub fn with_apps(apps: Vec<AppEntry>) -> Self {
+        let mut center = Self::new();
+        center.set_apps(apps);
         center
     }
 
     /// Toggle visibility with animation
     pub fn toggle(&mut self) {
+        self.toggle_at(Instant::now());
+    }
+
+    /// Close with no animation - for when its output is gone
+    pub fn close_now(&mut self) {
+        if self.visible {
+            self.toggle();
+        }
+        self.animation_t = 0.0;
+        self.animation_start = None;
+        self.output = None;
+    }
+
+    /// toggle() with the clock passed in
+    pub fn toggle_at(&mut self, now: Instant) {
         self.visible = !self.visible;
-        self.animation_start = Some(Instant::now());
+        self.animation_start = Some(now);
+        self.power_armed = None;
 
         if self.visible {
+            // Pick up installs/removals since the last scan
+            self.refresh_apps_if_stale();
+            self.refresh_recent_files();
+
             // Reset state when opening
             self.search_query.clear();
-            self.filtered_apps = self.all_apps.clone();
-            self.selected_index = 0;
             self.section = CommandCenterSection::Search;
+            self.update_filter();
+
+            // Back soon enough - carry on with the last search
+            if self.remembers_at(now) {
+                self.restore_memory();
+            }
+            self.memory = QueryMemory::default();
+        } else {
+            self.memory.closed_at = Some(now);
+            self.memory.section = Some(self.section);
+            self.memory
+                .sections
+                .insert(self.section, (self.search_query.clone(), self.selected_index));
         }
 
         tracing::info!(
@@ -175,6 +510,45 @@ impl CommandCenter {
         );
     }
 
+    /// Would opening right now restore the last query?
+    fn remembers_at(&self, now: Instant) -> bool {
+        match (self.remember_query, self.memory.closed_at) {
+            (Some(window), Some(closed_at)) => now.saturating_duration_since(closed_at) <= window,
+            _ => false,
+        }
+    }
+
+    fn restore_memory(&mut self) {
+        let Some(section) = self.memory.section else { return };
+        let Some((query, selected)) = self.memory.sections.get(&section).cloned() else {
+            return;
+        };
+
+        self.section = section;
+        self.search_query = query;
+        self.update_filter();
+
+        // The list may have changed under it
+        self.selected_index = selected.min(self.result_count().saturating_sub(1));
+        self.scroll_to_selection();
+    }
+
+    /// Don't bring this query back next time (it did its job)
+    pub fn forget_query(&mut self) {
+        self.memory = QueryMemory::default();
+    }
+
+    /// Escape: clear the query if there is one - returns false when there was nothing to clear
+    pub fn clear_query(&mut self) -> bool {
+        if self.search_query.is_empty() {
+            return false;
+        }
+
+        self.search_query.clear();
+        self.update_filter();
+        true
+    }
+
     /// Update animations - call every frame
     pub fn update(&mut self) {
         let now = Instant::now();
@@ -182,21 +556,37 @@ impl CommandCenter {
         self.last_frame = now;
 
         // Update glow pulse
-        self.glow_phase += dt * (2.0 * std::f32::consts::PI / 2.0); // 2 second cycle
-        if self.glow_phase > 2.0 * std::f32::consts::PI {
-            self.glow_phase -= 2.0 * std::f32::consts::PI;
+        if !self.glow_paused {
+            self.glow_phase += dt * (2.0 * std::f32::consts::PI / 2.0); // 2 second cycle
+            if self.glow_phase > 2.0 * std::f32::consts::PI {
+                self.glow_phase -= 2.0 * std::f32::consts::PI;
+            }
+        }
+
+        self.update_scroll(dt);
+
+        // Background scan finished?
+        if let Some(scan) = self.pending_scan.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.pending_scan = None;
+            self.apply_scan(scan);
+        }
+
+        // Slow background check so the list is fresh before it opens
+        if !self.visible && now.duration_since(self.last_app_check) >= APP_RESCAN_INTERVAL {
+            self.refresh_apps_if_stale();
         }
 
         // Update open/close animation
         if let Some(start) = self.animation_start {
-            let elapsed = now.duration_since(start).as_millis() as f32;
-            let duration = 200.0; // ms
-
-            if self.visible {
-                self.animation_t = (elapsed / duration).min(1.0);
+            let elapsed = now.duration_since(start);
+            let duration = self.animations.duration(OPEN_DURATION);
+            let progress = if duration.is_zero() {
+                1.0
             } else {
-                self.animation_t = 1.0 - (elapsed / duration).min(1.0);
-            }
+                (elapsed.as_secs_f32() / duration.as_secs_f32()).min(1.0)
+            };
+
+            self.animation_t = if self.visible { progress } else { 1.0 - progress };
 
             // Animation complete
             if elapsed >= duration {
@@ -212,9 +602,33 @@ impl CommandCenter {
         base + pulse
     }
 
+    /// Sections with a query to type into
+    fn takes_text(&self) -> bool {
+        matches!(
+            self.section,
+            CommandCenterSection::Search | CommandCenterSection::Files | CommandCenterSection::Windows
+        )
+    }
+
+    /// Tab: search, recent files (when they're on), open windows, power, and
+    /// round again
+    pub fn next_section(&mut self) {
+        let next = match self.section {
+            CommandCenterSection::Search if self.files_enabled => CommandCenterSection::Files,
+            CommandCenterSection::Search | CommandCenterSection::Files => CommandCenterSection::Windows,
+            CommandCenterSection::Windows => CommandCenterSection::System,
+            _ => CommandCenterSection::Search,
+        };
+
+        self.section = next;
+        self.selected_index = 0;
+        self.power_armed = None;
+        self.reset_scroll();
+    }
+
     /// Handle text input for search
     pub fn handle_char(&mut self, c: char) {
-        if self.section == CommandCenterSection::Search {
+        if self.takes_text() {
             self.search_query.push(c);
             self.update_filter();
         }
@@ -222,8 +636,8 @@ impl CommandCenter {
 
     /// Handle backspace
     pub fn handle_backspace(&mut self) {
-        if self.section == CommandCenterSection::Search {
-            self.search_query.pop();
+        // Nothing to delete - don't throw away the selection
+        if self.takes_text() && self.search_query.pop().is_some() {
             self.update_filter();
         }
     }
@@ -233,39 +647,242 @@ impl CommandCenter {
         if self.selected_index > 0 {
             self.selected_index -= 1;
         }
+        self.power_armed = None;
+        self.scroll_to_selection();
     }
 
     /// Move selection down
     pub fn select_next(&mut self) {
-        if self.selected_index < self.filtered_apps.len().saturating_sub(1) {
+        if self.selected_index < self.result_count().saturating_sub(1) {
             self.selected_index += 1;
         }
+        self.power_armed = None;
+        self.scroll_to_selection();
     }
 
-    /// Launch selected app
-    pub fn launch_selected(&mut self) -> Option<String> {
-        if let Some(app) = self.filtered_apps.get(self.selected_index) {
-            let exec = app.exec.clone();
-            tracing::info!("Launching: {}", app.name);
+    /// Take grid metrics from the layout (call when opening)
+    pub fn set_grid_metrics(&mut self, layout: &CommandCenterLayout) {
+        self.scroll.columns = layout.app_columns.max(1) as usize;
+        self.scroll.row_height = layout.app_card_height as f32 + CARD_GAP;
+        self.scroll.viewport_height = layout.apps_height as f32;
+    }
 
-            // Close command center after launch
-            self.toggle();
+    /// Furthest the grid can scroll without overshooting
+    pub fn max_scroll(&self) -> f32 {
+        let columns = self.scroll.columns.max(1);
+        let rows = self.result_count().div_ceil(columns) + self.shows_more_footer() as usize;
+        let content = rows as f32 * self.scroll.row_height - CARD_GAP;
+        (content - self.scroll.viewport_height).max(0.0)
+    }
+
+    /// Feed a scroll delta (px) from the pointer axis
+    pub fn scroll_by(&mut self, delta: f32, finger: bool) {
+        let now = Instant::now();
+        let max = self.max_scroll();
+
+        // Resist when dragged past either end
+        let delta = if self.scroll.offset < 0.0 || self.scroll.offset > max {
+            delta * RUBBER_BAND_RESISTANCE
+        } else {
+            delta
+        };
+
+        self.scroll.offset += delta;
+        self.scroll.target = None;
+        self.scroll.touching = finger;
+
+        // Smoothed velocity from the gap between events
+        if let Some(last) = self.scroll.last_event {
+            let dt = now.duration_since(last).as_secs_f32().max(0.001);
+            self.scroll.velocity = 0.7 * self.scroll.velocity + 0.3 * (delta / dt);
+        }
+        self.scroll.last_event = Some(now);
+
+        if !finger {
+            // Wheels don't fling
+            self.scroll.velocity = 0.0;
+        }
+    }
+
+    /// Fingers lifted - let it glide
+    pub fn scroll_release(&mut self) {
+        self.scroll.touching = false;
+        self.scroll.last_event = None;
+    }
+
+    fn reset_scroll(&mut self) {
+        self.scroll.offset = 0.0;
+        self.scroll.velocity = 0.0;
+        self.scroll.target = None;
+        self.scroll.touching = false;
+        self.scroll.last_event = None;
+    }
+
+    /// Auto-scroll so the selected row is fully on screen
+    fn scroll_to_selection(&mut self) {
+        let columns = self.scroll.columns.max(1);
+        let row = (self.selected_index / columns) as f32;
+
+        let top = row * self.scroll.row_height;
+        let bottom = top + self.scroll.row_height - CARD_GAP;
+        let current = self.scroll.target.unwrap_or(self.scroll.offset);
 
-            Some(exec)
+        let target = if top < current {
+            top
+        } else if bottom > current + self.scroll.viewport_height {
+            bottom - self.scroll.viewport_height
         } else {
-            None
+            return;
+        };
+
+        self.scroll.velocity = 0.0;
+        self.scroll.target = Some(target.clamp(0.0, self.max_scroll()));
+    }
+
+    /// Kinetic glide, auto-scroll easing, and rubber-band spring-back
+    fn update_scroll(&mut self, dt: f32) {
+        if self.scroll.touching {
+            return;
+        }
+
+        let max = self.max_scroll();
+
+        // No animations - eases land right away and flings don't glide
+        let instant = self.animations == AnimationLevel::None;
+        let ease = |rate: f32| if instant { 1.0 } else { (dt * rate).min(1.0) };
+        if instant {
+            self.scroll.velocity = 0.0;
+        }
+
+        if let Some(target) = self.scroll.target {
+            self.scroll.offset += (target - self.scroll.offset) * ease(SCROLL_EASE);
+            if (target - self.scroll.offset).abs() < 0.5 {
+                self.scroll.offset = target;
+                self.scroll.target = None;
+            }
+            return;
+        }
+
+        if self.scroll.velocity != 0.0 {
+            self.scroll.offset += self.scroll.velocity * dt;
+            self.scroll.velocity *= (-SCROLL_FRICTION * dt).exp();
+
+            // Ran off the end or basically stopped
+            if self.scroll.velocity.abs() < 5.0 || self.scroll.offset < 0.0 || self.scroll.offset > max {
+                self.scroll.velocity = 0.0;
+            }
+        }
+
+        // Spring back into range
+        let clamped = self.scroll.offset.clamp(0.0, max);
+        if clamped != self.scroll.offset {
+            self.scroll.offset += (clamped - self.scroll.offset) * ease(RUBBER_BAND_SPRING);
+            if (clamped - self.scroll.offset).abs() < 0.5 {
+                self.scroll.offset = clamped;
+            }
+        }
+    }
+
+    /// Query is "stats" - the grid shows usage instead of results
+    pub fn showing_stats(&self) -> bool {
+        self.usage.is_some()
+            && self.section == CommandCenterSection::Search
+            && self.search_query.trim().eq_ignore_ascii_case(STATS_QUERY)
+    }
+
+    /// "...and N more" row under the grid - it's not a result, so nothing
+    /// selects it
+    pub fn shows_more_footer(&self) -> bool {
+        self.more_results > 0
+            && matches!(self.section, CommandCenterSection::Search | CommandCenterSection::Apps)
+            && !self.showing_stats()
+    }
+
+    /// Total selectable results - actions first, then apps (or just files,
+    /// windows or power cards)
+    pub fn result_count(&self) -> usize {
+        if self.showing_stats() {
+            return 0;
         }
+        match self.section {
+            CommandCenterSection::Files => self.filtered_files.len(),
+            CommandCenterSection::Windows => self.filtered_windows.len(),
+            CommandCenterSection::System => PowerAction::ALL.len(),
+            _ => self.filtered_actions.len() + self.filtered_apps.len(),
+        }
+    }
+
+    /// Launch selected app (or hand back the selected action)
+    pub fn launch_selected(&mut self) -> Option<Selection> {
+        if self.showing_stats() {
+            return None;
+        }
+        let action_count = self.filtered_actions.len();
+
+        let selection = if self.section == CommandCenterSection::Files {
+            let file = self.filtered_files.get(self.selected_index)?;
+            tracing::info!("Opening: {}", file.path.display());
+            Selection::Open(file.path.clone())
+        } else if self.section == CommandCenterSection::Windows {
+            let window = self.filtered_windows.get(self.selected_index)?;
+            tracing::info!("Switching to: {}", window.title);
+            Selection::Window(window.id)
+        } else if self.section == CommandCenterSection::System {
+            let action = *PowerAction::ALL.get(self.selected_index)?;
+
+            // First Enter only arms it - the card asks for another
+            if action.needs_confirm() && self.power_armed != Some(action) {
+                self.power_armed = Some(action);
+                return None;
+            }
+            tracing::info!("Power: {}", action.label());
+            Selection::Power(action)
+        } else if let Some(matched) = self.filtered_actions.get(self.selected_index) {
+            tracing::info!("Running action: {}", matched.name);
+            Selection::Action(matched.action.clone())
+        } else if let Some(app) = self.filtered_apps.get(self.selected_index - action_count).cloned() {
+            tracing::info!("Launching: {}", app.name);
+            self.record_launch(&app);
+            Selection::Launch {
+                argv: app.argv,
+                app_id: app.desktop_file
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned()),
+                name: app.name,
+            }
+        } else {
+            return None;
+        };
+
+        // Close command center before anything runs - a fresh start next time
+        self.toggle();
+        self.forget_query();
+
+        Some(selection)
     }
 
-    /// Update filtered apps based on search query
-    fn update_filter(&mut self) {
+    /// Re-run the search query against every app, action and file
+    ///
+    /// Public for the benches - everything else goes through handle_char.
+    pub fn update_filter(&mut self) {
         if self.search_query.is_empty() {
-            self.filtered_apps = self.all_apps.clone();
+            // Most launched first - the stable sort leaves ties alphabetical
+            let mut apps: Vec<&AppEntry> = self.all_apps.iter().collect();
+            let counts = &self.launch_counts;
+            self.more_results = keep_top_scores(&mut apps, self.max_results, |app| {
+                counts.get(&app.name).map_or(0, |&count| count.min(i32::MAX as u32) as i32)
+            });
+            self.filtered_apps = apps.into_iter().cloned().collect();
+            self.filtered_actions.clear();
         } else {
+            // A few actions up top, apps still visible below
+            self.filtered_actions = action::match_actions(&self.search_query, fuzzy_match);
+            self.filtered_actions.truncate(MAX_ACTION_RESULTS);
+
             self.filtered_apps = self.all_apps
                 .iter()
                 .filter_map(|app| {
-                    let score = fuzzy_match(&self.search_query, &app.name);
+                    let score = score_app(&self.search_query, app);
                     if score > 0 {
                         let mut app = app.clone();
                         app.score = score;
@@ -276,51 +893,180 @@ impl CommandCenter {
                 })
                 .collect();
 
-            // Sort by score descending
-            self.filtered_apps.sort_by(|a, b| b.score.cmp(&a.score));
+            self.more_results = keep_top_scores(&mut self.filtered_apps, self.max_results, |app| app.score);
         }
+        self.filter_files();
+        self.filter_windows();
 
         // Reset selection
         self.selected_index = 0;
+        self.reset_scroll();
     }
 
-    /// Load apps from .desktop files
-    fn load_apps(&mut self) {
-        let app_dirs = [
-            "/usr/share/applications",
-            "/usr/local/share/applications",
-            "~/.local/share/applications",
-        ];
+    /// Recent files matching the query - recency order while it's empty
+    fn filter_files(&mut self) {
+        if self.search_query.is_empty() {
+            self.filtered_files = self.all_files.clone();
+            return;
+        }
 
-        for dir in &app_dirs {
-            let path = if dir.starts_with("~") {
-                if let Ok(home) = std::env::var("HOME") {
-                    PathBuf::from(dir.replace("~", &home))
-                } else {
-                    continue;
-                }
-            } else {
-                PathBuf::from(dir)
-            };
+        self.filtered_files = self.all_files
+            .iter()
+            .filter_map(|file| {
+                let score = fuzzy_match(&self.search_query, &file.name)
+                    .max(fuzzy_match(&self.search_query, &file.path.to_string_lossy()) / 2);
+                (score > 0).then(|| RecentFile { score, ..file.clone() })
+            })
+            .collect();
+
+        // Stable - equal scores stay most recent first
+        self.filtered_files.sort_by(|a, b| b.score.cmp(&a.score));
+    }
 
-            if let Ok(entries) = std::fs::read_dir(&path) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.extension().map(|e| e == "desktop").unwrap_or(false) {
-                        if let Some(app) = parse_desktop_file(&path) {
-                            self.all_apps.push(app);
-                        }
-                    }
-                }
+    /// Open windows matching the query, by title or app_id - most recently
+    /// raised first while it's empty
+    fn filter_windows(&mut self) {
+        if self.search_query.is_empty() {
+            self.filtered_windows = self.windows.clone();
+            return;
+        }
+
+        self.filtered_windows = self.windows
+            .iter()
+            .filter_map(|window| {
+                let app_id = window.app_id.as_deref().unwrap_or_default();
+                let score = fuzzy_match(&self.search_query, &window.title).max(fuzzy_match(&self.search_query, app_id));
+                (score > 0).then(|| WindowInfo { score, ..window.clone() })
+            })
+            .collect();
+
+        self.filtered_windows.sort_by(|a, b| b.score.cmp(&a.score));
+    }
+
+    /// Count a launch towards the most-used order - the compositor keeps
+    /// the history on disk (usage.rs) and hands it back on every open. With
+    /// usage_stats off these counts are the only ones, kept for the session
+    pub fn record_launch(&mut self, app: &AppEntry) {
+        *self.launch_counts.entry(app.name.clone()).or_default() += 1;
+    }
+
+    /// Replace the open windows - the current query is re-run against them
+    pub fn set_windows(&mut self, windows: Vec<WindowInfo>) {
+        self.windows = windows;
+        self.filter_windows();
+    }
+
+    /// Re-read recently-used.xbel if it changed since last time
+    ///
+    /// Files that have since been deleted or moved are left out.
+    pub fn refresh_recent_files(&mut self) {
+        if !self.files_enabled {
+            if !self.all_files.is_empty() {
+                self.set_files(Vec::new());
+            }
+            self.files_modified = None;
+            return;
+        }
+
+        let Some(path) = recent_files_path() else { return };
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified == self.files_modified {
+            return;
+        }
+        self.files_modified = modified;
+
+        let files = match std::fs::read_to_string(&path) {
+            Ok(content) => parse_xbel(&content)
+                .into_iter()
+                .filter(|file| file.path.exists())
+                .collect(),
+            Err(e) => {
+                tracing::debug!("No recent files from {}: {}", path.display(), e);
+                Vec::new()
             }
+        };
+        tracing::info!("Loaded {} recent files", files.len());
+        self.set_files(files);
+    }
+
+    /// Replace the recent files - the current query is re-run against them
+    pub fn set_files(&mut self, files: Vec<RecentFile>) {
+        self.all_files = files;
+        self.filter_files();
+    }
+
+    /// Rescan if an app dir changed since the last scan - true if it did
+    ///
+    /// Apps that went away (uninstalled flatpaks) drop out of the results.
+    pub fn refresh_apps_if_stale(&mut self) -> bool {
+        self.last_app_check = Instant::now();
+        if !self.scans_apps || self.pending_scan.is_some() {
+            return false;
         }
 
-        // Sort alphabetically by default
-        self.all_apps.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+        let stamps: Vec<DirStamp> = app_dirs(&self.extra_app_dirs).iter().map(|dir| DirStamp::of(dir)).collect();
+        if stamps == self.app_dir_stamps {
+            return false;
+        }
+
+        tracing::info!("App directories changed - rescanning");
+        self.load_apps();
+        true
+    }
+
+    /// Config app_dirs changed (reload) - rescan with the new ones
+    pub fn set_app_dirs(&mut self, dirs: &[PathBuf]) {
+        if self.extra_app_dirs == dirs {
+            return;
+        }
+        self.extra_app_dirs = dirs.to_vec();
+        if self.scans_apps {
+            self.load_apps_in_background();
+        }
+    }
+
+    /// Load apps from .desktop files, right now
+    pub fn load_apps(&mut self) {
+        self.apply_scan(scan_apps(&self.extra_app_dirs));
+    }
+
+    /// Load apps from .desktop files on a worker thread - they show up once it's done
+    pub fn load_apps_in_background(&mut self) {
+        self.scans_apps = true;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let extra_dirs = self.extra_app_dirs.clone();
+        let spawned = std::thread::Builder::new()
+            .name("vibewm-app-scan".to_string())
+            .spawn(move || {
+                tx.send(scan_apps(&extra_dirs)).ok();
+            });
+
+        match spawned {
+            Ok(_) => self.pending_scan = Some(rx),
+            Err(e) => {
+                tracing::warn!("Couldn't start the app scan thread ({}) - scanning inline", e);
+                self.load_apps();
+            }
+        }
+    }
 
+    /// Take the results of a scan
+    pub fn apply_scan(&mut self, scan: AppScan) {
+        self.scans_apps = true;
+        self.app_dir_stamps = scan.stamps;
+        self.set_apps(scan.apps);
         tracing::info!("Loaded {} apps", self.all_apps.len());
     }
 
+    /// Replace the app list - the current query is re-run against it
+    pub fn set_apps(&mut self, apps: Vec<AppEntry>) {
+        // A rescan may have brought new icons along
+        self.icons.clear();
+        self.all_apps = apps;
+        self.update_filter();
+    }
+
     /// Get formatted time string
     pub fn get_time_string(&self) -> String {
         let now = SystemTime::now()
@@ -335,112 +1081,351 @@ impl CommandCenter {
         format!("{:02}:{:02}", hours, minutes)
     }
 
-    /// Get system info for display
+    /// Latest system info snapshot - sampled off-thread, never read here
     pub fn get_system_info(&self) -> SystemInfo {
-        SystemInfo {
-            // These would be populated from actual system calls
-            battery_percent: read_battery_percent().unwrap_or(100),
-            battery_charging: read_battery_charging().unwrap_or(false),
-            cpu_usage: 0.0,  // TODO: implement
-            memory_used_gb: 0.0,
-            memory_total_gb: 0.0,
-        }
+        self.system_info
+            .read()
+            .map(|info| info.clone())
+            .unwrap_or_default()
     }
 }
 
-pub struct SystemInfo {
-    pub battery_percent: u8,
-    pub battery_charging: bool,
-    pub cpu_usage: f32,
-    pub memory_used_gb: f32,
-    pub memory_total_gb: f32,
-}
+/// How many action cards the palette shows above the apps
+const MAX_ACTION_RESULTS: usize = 3;
+
+/// Default for config `command_center.max_results`
+pub const DEFAULT_MAX_RESULTS: usize = 50;
+
+/// Sort results best score first and keep the top `limit`, returning how
+/// many were dropped. Equal scores keep their order
+///
+/// Past the limit only the survivors get sorted - select_nth_unstable splits
+/// them off first, so 900 matches cost one partial pass and a sort of 50.
+pub fn keep_top_scores<T>(results: &mut Vec<T>, limit: Option<usize>, score: impl Fn(&T) -> i32) -> usize {
+    let total = results.len();
+    let limit = match limit {
+        Some(limit) if limit < total => limit,
+        _ => {
+            results.sort_by(|a, b| score(b).cmp(&score(a)));
+            return 0;
+        }
+    };
+
+    // List position breaks ties, so the unstable sorts come out stable
+    let by_score = |a: &(usize, T), b: &(usize, T)| score(&b.1).cmp(&score(&a.1)).then(a.0.cmp(&b.0));
+    let mut indexed: Vec<(usize, T)> = results.drain(..).enumerate().collect();
+    if limit > 0 {
+        indexed.select_nth_unstable_by(limit - 1, by_score);
+    }
+    indexed.truncate(limit);
+    indexed.sort_unstable_by(by_score);
 
-/// Fuzzy matching - returns score (0 = no match)
-fn fuzzy_match(query: &str, target: &str) -> i32 {
-    let query = query.to_lowercase();
-    let target_lower = target.to_lowercase();
+    results.extend(indexed.into_iter().map(|(_, result)| result));
+    total - limit
+}
 
-    // Exact prefix match is best
-    if target_lower.starts_with(&query) {
-        return 1000 + (100 - target.len() as i32).max(0);
+/// Typing this swaps the results for the usage stats view
+const STATS_QUERY: &str = "stats";
+
+/// How often the app dirs get re-checked while the center is closed
+///
+/// Catches flatpak installs/updates/removals without a file watcher.
+const APP_RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Open/close animation length at full animations
+const OPEN_DURATION: Duration = Duration::from_millis(200);
+
+/// Gap between cards in the result grid (px)
+pub const CARD_GAP: f32 = 12.0;
+
+/// How far the container glow spreads past its edge (px)
+pub const GLOW_SPREAD: f32 = 20.0;
+
+/// Overscroll drag multiplier past either end of the grid
+const RUBBER_BAND_RESISTANCE: f32 = 0.3;
+
+/// How fast overscroll springs back (1/s)
+const RUBBER_BAND_SPRING: f32 = 12.0;
+
+/// Kinetic decay rate (1/s) - higher stops sooner
+const SCROLL_FRICTION: f32 = 4.0;
+
+/// How fast auto-scroll reaches its target (1/s)
+const SCROLL_EASE: f32 = 15.0;
+
+/// Fuzzy matcher scores, fzf's: every matched char is worth SCORE_MATCH,
+/// plus a bonus where a word starts, minus gaps in between
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = 3;
+const SCORE_GAP_EXTENSION: i32 = 1;
+
+/// Start of the target, or after a space, `-`, `_`, `.` or `/`
+const BONUS_BOUNDARY: i32 = 8;
+/// `fooBar`, `mp3`
+const BONUS_CAMEL: i32 = 7;
+/// Least a char straight after the previous match gets - a run keeps the
+/// bonus of the char it started on, so "fox" in "Fox" is a word start
+/// three times over
+const BONUS_CONSECUTIVE: i32 = 4;
+/// The first query char's bonus counts double - where a match starts says
+/// the most about it
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+/// Each target char left unmatched - shorter names win ties
+const PENALTY_UNMATCHED: i32 = 1;
+
+/// Bonus for a match at `index`, from the char before it
+fn boundary_bonus(target: &[char], index: usize) -> i32 {
+    let Some(&prev) = index.checked_sub(1).and_then(|i| target.get(i)) else {
+        return BONUS_BOUNDARY;
+    };
+    let c = target[index];
+
+    if matches!(prev, ' ' | '-' | '_' | '.' | '/') {
+        BONUS_BOUNDARY
+    } else if (prev.is_lowercase() && c.is_uppercase()) || (!prev.is_numeric() && c.is_numeric()) {
+        BONUS_CAMEL
+    } else {
+        0
     }
+}
 
-    // Contains match
-    if target_lower.contains(&query) {
-        return 500 + (100 - target.len() as i32).max(0);
+/// Fuzzy matching - every query char in order somewhere in the target, case
+/// ignored. Returns score (0 = no match)
+///
+/// Scores the best way of placing the query: word starts (the first one
+/// most), camelCase humps and runs of consecutive chars count for it, gaps
+/// between matched chars and unmatched length against it.
+pub fn fuzzy_match(query: &str, target: &str) -> i32 {
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let target: Vec<char> = target.chars().collect();
+    let folded: Vec<char> = target.iter().copied().map(fold).collect();
+
+    if query.is_empty() {
+        return 1;
+    }
+    if query.len() > target.len() {
+        return 0;
     }
 
-    // Fuzzy character match
-    let mut score = 0;
-    let mut query_chars = query.chars().peekable();
-    let mut consecutive = 0;
+    // best[j]: best score with the query so far placed and its last char on
+    // target[j] (None = can't end there), and the bonus of the char the run
+    // ending there started on
+    let mut best: Vec<Option<(i32, i32)>> = folded
+        .iter()
+        .enumerate()
+        .map(|(j, &c)| {
+            let bonus = boundary_bonus(&target, j);
+            (c == query[0]).then_some((SCORE_MATCH + bonus * BONUS_FIRST_CHAR_MULTIPLIER, bonus))
+        })
+        .collect();
+
+    for &q in &query[1..] {
+        let mut next = vec![None; target.len()];
+        // Best earlier placement to jump from, gap penalty included
+        let mut gapped: Option<i32> = None;
+
+        for j in 1..target.len() {
+            if j >= 2 {
+                let extended = gapped.map(|s| s - SCORE_GAP_EXTENSION);
+                let started = best[j - 2].map(|(s, _)| s - SCORE_GAP_START);
+                gapped = extended.max(started);
+            }
+            if folded[j] != q {
+                continue;
+            }
 
-    for c in target_lower.chars() {
-        if query_chars.peek() == Some(&c) {
-            query_chars.next();
-            consecutive += 1;
-            score += 10 + consecutive * 5;  // Bonus for consecutive matches
-        } else {
-            consecutive = 0;
+            // A word start mid-run starts a new run
+            let bonus = boundary_bonus(&target, j);
+            let consecutive = best[j - 1].map(|(s, run)| {
+                let run = if bonus >= BONUS_BOUNDARY { bonus } else { run };
+                (s + SCORE_MATCH + bonus.max(run).max(BONUS_CONSECUTIVE), run)
+            });
+            let gapped = gapped.map(|s| (s + SCORE_MATCH + bonus, bonus));
+            next[j] = match (consecutive, gapped) {
+                (Some(c), Some(g)) => Some(if g.0 > c.0 { g } else { c }),
+                (c, g) => c.or(g),
+            };
         }
+        best = next;
     }
 
-    // All query chars must match
-    if query_chars.peek().is_some() {
+    let Some(score) = best.iter().filter_map(|best| best.map(|(s, _)| s)).max() else {
         return 0;
-    }
+    };
 
-    score
+    // A match is a match, however far it's spread
+    let unmatched = (target.len() - query.len()).min(i32::MAX as usize) as i32;
+    score.saturating_sub(unmatched.saturating_mul(PENALTY_UNMATCHED)).max(1)
 }
 
-/// Parse a .desktop file
-fn parse_desktop_file(path: &PathBuf) -> Option<AppEntry> {
-    let content = std::fs::read_to_string(path).ok()?;
-
-    let mut name = None;
-    let mut exec = None;
-    let mut icon = None;
-    let mut no_display = false;
-    let mut in_desktop_entry = false;
+/// Score an app against the query - name first, keywords at half weight
+fn score_app(query: &str, app: &AppEntry) -> i32 {
+    let name_score = fuzzy_match(query, &app.name);
 
-    for line in content.lines() {
-        let line = line.trim();
+    let keyword_score = app.keywords
+        .iter()
+        .map(|k| fuzzy_match(query, k) / 2)
+        .max()
+        .unwrap_or(0);
 
-        if line == "[Desktop Entry]" {
-            in_desktop_entry = true;
-            continue;
-        }
+    name_score.max(keyword_score)
+}
+
+/// Most keywords we keep per app, and longest keyword (chars)
+const MAX_KEYWORDS: usize = 32;
+const MAX_KEYWORD_LEN: usize = 64;
+
+/// Locale suffixes to try for Name[xx]/Keywords[xx], best first
+///
+/// From `LC_ALL`/`LC_MESSAGES`/`LANG` per the desktop entry spec:
+/// lang_COUNTRY@MODIFIER, lang_COUNTRY, lang@MODIFIER, lang.
+pub fn locale_candidates() -> Vec<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|v| !v.is_empty());
+
+    match locale {
+        Some(locale) => expand_locale(&locale),
+        None => Vec::new(),
+    }
+}
+
+fn expand_locale(locale: &str) -> Vec<String> {
+    // Encoding is irrelevant for matching: de_DE.UTF-8@euro -> de_DE@euro
+    let (rest, modifier) = match locale.split_once('@') {
+        Some((rest, modifier)) => (rest, Some(modifier)),
+        None => (locale, None),
+    };
+    let rest = rest.split('.').next().unwrap_or(rest);
+    let (lang, country) = match rest.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (rest, None),
+    };
+
+    if lang.is_empty() || lang == "C" || lang == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut candidates = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        candidates.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = country {
+        candidates.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        candidates.push(format!("{}@{}", lang, modifier));
+    }
+    candidates.push(lang.to_string());
+    candidates
+}
+
+/// Best-so-far value for a localizable key: (rank, value), lower rank wins
+type Localized = Option<(usize, String)>;
+
+/// Keep `value` if its locale ranks better than what we have
+fn offer_localized(slot: &mut Localized, locale: Option<&str>, locales: &[String], value: &str) {
+    let rank = match locale {
+        // Unlocalized is the fallback, worse than any match
+        None => locales.len(),
+        Some(l) => match locales.iter().position(|c| c == l) {
+            Some(rank) => rank,
+            None => return,
+        },
+    };
+
+    if slot.as_ref().map(|(best, _)| rank < *best).unwrap_or(true) {
+        *slot = Some((rank, value.to_string()));
+    }
+}
+
+/// Longest Name= we keep (chars) - anything past this is truncated
+const MAX_NAME_LEN: usize = 256;
+
+/// Longest Exec=/Icon= we accept (bytes) - longer means the entry is junk
+const MAX_VALUE_LEN: usize = 4096;
+
+/// Biggest .desktop file we bother reading
+const MAX_DESKTOP_FILE_SIZE: u64 = 256 * 1024;
+
+/// Parse a .desktop file
+pub fn parse_desktop_file(path: &PathBuf, locales: &[String]) -> Option<AppEntry> {
+    // Don't slurp something enormous just because it ends in .desktop
+    if std::fs::metadata(path).ok()?.len() > MAX_DESKTOP_FILE_SIZE {
+        tracing::warn!("Skipping oversized desktop file: {:?}", path);
+        return None;
+    }
+
+    let content = std::fs::read(path).ok()?;
+    parse_desktop_entry(&content, path, locales)
+}
+
+/// Parse desktop file contents - never trusts the bytes
+///
+/// Only keys inside the first `[Desktop Entry]` group count, `[Desktop Action]`
+/// groups and repeats are ignored. CRLF and a UTF-8 BOM are fine. Name and
+/// Keywords use the best `[locale]` variant from `locales`.
+pub fn parse_desktop_entry(content: &[u8], path: &PathBuf, locales: &[String]) -> Option<AppEntry> {
+    let content = std::str::from_utf8(content).ok()?;
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+
+    let mut name: Localized = None;
+    let mut keywords: Localized = None;
+    let mut exec: Option<String> = None;
+    let mut icon = None;
+    let mut no_display = false;
+    let mut in_desktop_entry = false;
+    let mut seen_desktop_entry = false;
+
+    for line in content.lines() {
+        let line = line.trim();
 
         if line.starts_with('[') {
-            in_desktop_entry = false;
+            // Spec says group names are unique - a repeat is not a second entry
+            in_desktop_entry = line == "[Desktop Entry]" && !seen_desktop_entry;
+            seen_desktop_entry |= in_desktop_entry;
             continue;
         }
 
-        if !in_desktop_entry {
+        if !in_desktop_entry || line.starts_with('#') {
             continue;
         }
 
-        if let Some(value) = line.strip_prefix("Name=") {
-            if name.is_none() {  // Use first Name= only
-                name = Some(value.to_string());
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+
+        // Name[de_DE] -> ("Name", Some("de_DE"))
+        let (key, locale) = match key.split_once('[') {
+            Some((base, rest)) => match rest.strip_suffix(']') {
+                Some(locale) => (base, Some(locale)),
+                None => continue,
+            },
+            None => (key, None),
+        };
+
+        match (key, locale) {
+            ("Name", _) => {
+                // Not a novel, thanks
+                let capped: String = value.chars().take(MAX_NAME_LEN).collect();
+                offer_localized(&mut name, locale, locales, &capped);
             }
-        } else if let Some(value) = line.strip_prefix("Exec=") {
-            // Remove field codes like %f, %u, etc.
-            let cleaned = value
-                .replace("%f", "")
-                .replace("%F", "")
-                .replace("%u", "")
-                .replace("%U", "")
-                .replace("%c", "")
-                .replace("%k", "")
-                .trim()
-                .to_string();
-            exec = Some(cleaned);
-        } else if let Some(value) = line.strip_prefix("Icon=") {
-            icon = Some(value.to_string());
-        } else if line == "NoDisplay=true" {
-            no_display = true;
+            ("Keywords", _) if value.len() <= MAX_VALUE_LEN => {
+                offer_localized(&mut keywords, locale, locales, value);
+            }
+            ("Exec", None) => {
+                if value.len() > MAX_VALUE_LEN {
+                    return None;
+                }
+                exec = Some(value.to_string());
+            }
+            ("Icon", None) if value.len() <= MAX_VALUE_LEN => icon = Some(value.to_string()),
+            ("NoDisplay", None) => no_display = value == "true",
+            _ => {}
         }
     }
 
@@ -448,50 +1433,96 @@ fn parse_desktop_file(path: &PathBuf) -> Option<AppEntry> {
         return None;
     }
 
+    let name = name.map(|(_, n)| n).filter(|n| !n.is_empty())?;
+    let exec = exec?;
+
+    // Unbalanced quotes and friends make the whole entry invalid
+    let argv = parse_exec(&exec)?;
+
+    // "browser;internet;www;" -> ["browser", "internet", "www"]
+    let keywords = keywords
+        .map(|(_, k)| {
+            k.split(';')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .take(MAX_KEYWORDS)
+                .map(|k| k.chars().take(MAX_KEYWORD_LEN).collect())
+                .collect()
+        })
+        .unwrap_or_default();
+
     Some(AppEntry {
-        name: name?,
-        exec: exec?,
+        name,
+        exec: argv.join(" "),
+        argv,
+        keywords,
         icon,
         desktop_file: path.clone(),
         score: 0,
     })
 }
 
-/// Read battery percentage from sysfs
-fn read_battery_percent() -> Option<u8> {
-    let paths = [
-        "/sys/class/power_supply/BAT0/capacity",
-        "/sys/class/power_supply/BAT1/capacity",
-    ];
-
-    for path in &paths {
-        if let Ok(content) = std::fs::read_to_string(path) {
-            if let Ok(percent) = content.trim().parse() {
-                return Some(percent);
+/// Split an Exec= value into argv per the desktop entry spec
+///
+/// Handles double quotes and their backslash escapes, and drops field codes
+/// (%f, %U, ...). Returns None for anything malformed - unterminated quotes,
+/// dangling escapes, or an empty command - since it never goes near a shell.
+pub fn parse_exec(exec: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '`' | '$' | '\\') => current.push(c),
+                            _ => return None,
+                        },
+                        c => current.push(c),
+                    }
+                }
+            }
+            '%' => match chars.next()? {
+                '%' => {
+                    current.push('%');
+                    in_arg = true;
+                }
+                // Field codes - we never pass files or URLs
+                'f' | 'F' | 'u' | 'U' | 'd' | 'D' | 'n' | 'N' | 'i' | 'c' | 'k' | 'v' | 'm' => {}
+                _ => return None,
+            },
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
             }
         }
     }
 
-    None
-}
-
-/// Check if battery is charging
-fn read_battery_charging() -> Option<bool> {
-    let paths = [
-        "/sys/class/power_supply/BAT0/status",
-        "/sys/class/power_supply/BAT1/status",
-    ];
-
-    for path in &paths {
-        if let Ok(content) = std::fs::read_to_string(path) {
-            return Some(content.trim() == "Charging");
-        }
+    if in_arg {
+        args.push(current);
     }
 
-    None
+    if args.is_empty() || args[0].is_empty() {
+        None
+    } else {
+        Some(args)
+    }
 }
 
 /// Layout calculations for rendering
+#[derive(Debug, Clone)]
 pub struct CommandCenterLayout {
     pub total_width: i32,
     pub total_height: i32,
@@ -524,17 +1555,255 @@ pub struct CommandCenterLayout {
     pub system_height: i32,
 }
 
+/// Smallest the container gets, however it's configured
+const MIN_CONTAINER_WIDTH: i32 = 320;
+const MIN_CONTAINER_HEIGHT: i32 = 240;
+
+/// Scan every app dir for .desktop files - no state, safe to run on any thread
+pub fn scan_apps(extra_dirs: &[PathBuf]) -> AppScan {
+    let locales = locale_candidates();
+    let dirs = app_dirs(extra_dirs);
+    let stamps = dirs.iter().map(|dir| DirStamp::of(dir)).collect();
+
+    // Re-resolve symlinked roots every scan - the same tree can hide behind two paths
+    let mut apps = Vec::new();
+    let mut scanned = Vec::new();
+
+    // Desktop file IDs already seen - an earlier dir overrides a later one (XDG)
+    let mut seen_ids = HashSet::new();
+    for dir in &dirs {
+        let path = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.clone());
+        if scanned.contains(&path) {
+            continue;
+        }
+        scanned.push(path.clone());
+
+        if let Ok(entries) = std::fs::read_dir(&path) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().map(|e| e == "desktop").unwrap_or(false) {
+                    if !seen_ids.insert(entry.file_name()) {
+                        continue;
+                    }
+                    if let Some(app) = parse_desktop_file(&path, &locales) {
+                        apps.push(app);
+                    }
+                }
+            }
+        }
+    }
+
+    // Sort alphabetically by default
+    apps.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+
+    AppScan { apps, stamps }
+}
+
+/// Most files kept from the bookmarks file
+const MAX_RECENT_FILES: usize = 200;
+
+/// Where GTK (and most everything else) records recently used files
+pub fn recent_files_path() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let data_home = var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local/share")))?;
+    Some(data_home.join("recently-used.xbel"))
+}
+
+/// Local files out of an XBEL bookmarks file, most recent first
+///
+/// Not a real XML parser - just enough for what GLib writes: one
+/// `<bookmark href=... modified=...>` per file, a mime-type inside.
+/// Anything that isn't a file:// URI is skipped.
+pub fn parse_xbel(content: &str) -> Vec<RecentFile> {
+    let mut files = Vec::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find("<bookmark ") {
+        let after = &rest[start..];
+        let Some(tag_end) = after.find('>') else { break };
+        let tag = &after[..tag_end];
+
+        // <bookmark .../> has no body
+        let (body, next) = if tag.ends_with('/') {
+            ("", tag_end + 1)
+        } else {
+            match after.find("</bookmark>") {
+                Some(end) => (&after[tag_end..end], end),
+                None => ("", tag_end + 1),
+            }
+        };
+        rest = &after[next..];
+
+        let Some(path) = xml_attr(tag, "href").and_then(|href| file_uri_to_path(&href)) else {
+            continue;
+        };
+        let modified = xml_attr(tag, "modified")
+            .or_else(|| xml_attr(tag, "visited"))
+            .unwrap_or_default();
+        let mime_type = body.find("mime-type").and_then(|i| xml_attr(&body[i..], "type"));
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        files.push(RecentFile { name, path, mime_type, modified, score: 0 });
+    }
+
+    files.sort_by(|a, b| b.modified.cmp(&a.modified));
+    files.truncate(MAX_RECENT_FILES);
+    files
+}
+
+/// Value of `name="..."` in a tag, unescaped
+fn xml_attr(tag: &str, name: &str) -> Option<String> {
+    let needle = format!("{}=\"", name);
+    let mut from = 0;
+
+    while let Some(i) = tag[from..].find(&needle) {
+        let at = from + i;
+        from = at + needle.len();
+
+        // Whole attribute names only - "href" shouldn't match "xhref"
+        if tag[..at].ends_with(char::is_whitespace) {
+            let len = tag[from..].find('"')?;
+            return Some(xml_unescape(&tag[from..from + len]));
+        }
+    }
+    None
+}
+
+fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// file:///home/me/a%20b.pdf -> /home/me/a b.pdf
+fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // Drops a host part ("file://localhost/...")
+    let path = &rest[rest.find('/')?..];
+
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    Some(PathBuf::from(OsString::from_vec(decoded)))
+}
+
+/// Where .desktop files live, best first - from the XDG environment, plus config extras
+pub fn app_dirs(extra: &[PathBuf]) -> Vec<PathBuf> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    app_dirs_from(
+        var("XDG_DATA_HOME").map(PathBuf::from),
+        var("HOME").map(PathBuf::from),
+        var("XDG_DATA_DIRS"),
+        extra,
+    )
+}
+
+/// app_dirs() with the environment passed in
+///
+/// data_roots_from() each + /applications, then the config extras.
+fn app_dirs_from(
+    data_home: Option<PathBuf>,
+    home: Option<PathBuf>,
+    data_dirs: Option<String>,
+    extra: &[PathBuf],
+) -> Vec<PathBuf> {
+    let roots = data_roots_from(data_home, home, data_dirs);
+
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for dir in roots.into_iter().map(|root| root.join("applications")).chain(extra.iter().cloned()) {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+
+    dirs
+}
+
+/// XDG data dirs, most important first - for desktop files and icons
+pub fn data_roots() -> Vec<PathBuf> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    data_roots_from(
+        var("XDG_DATA_HOME").map(PathBuf::from),
+        var("HOME").map(PathBuf::from),
+        var("XDG_DATA_DIRS"),
+    )
+}
+
+/// $XDG_DATA_HOME (default ~/.local/share), then each of $XDG_DATA_DIRS
+/// (default /usr/local/share:/usr/share). Both flatpak exports are tacked on
+/// in case the session never put them in XDG_DATA_DIRS (started from a TTY).
+fn data_roots_from(data_home: Option<PathBuf>, home: Option<PathBuf>, data_dirs: Option<String>) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Some(data_home) = data_home.or_else(|| home.as_ref().map(|h| h.join(".local/share"))) {
+        roots.push(data_home);
+    }
+
+    let data_dirs = data_dirs.unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    roots.extend(data_dirs.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+
+    if let Some(home) = &home {
+        roots.push(home.join(".local/share/flatpak/exports/share"));
+    }
+    roots.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+
+    roots
+}
+
+/// Fixed bits inside the container
+const SEARCH_HEIGHT: i32 = 56;
+const SYSTEM_HEIGHT: i32 = 48;
+const SECTION_GAP: i32 = 16;
+
 impl CommandCenterLayout {
-    pub fn calculate(screen_width: i32, screen_height: i32) -> Self {
-        let container_width = (screen_width as f32 * 0.6).min(800.0) as i32;
-        let container_height = (screen_height as f32 * 0.7).min(600.0) as i32;
+    pub fn calculate(screen_width: i32, screen_height: i32, config: &CommandCenterLayoutConfig) -> Self {
+        // Configured size, capped, then squeezed onto the output
+        let fit = |dim: Dimension, cap: Option<i32>, min: i32, total: i32| {
+            let size = dim.resolve(total).min(cap.unwrap_or(i32::MAX));
+            size.max(min).min(total)
+        };
+        let container_width = fit(config.width, config.max_width, MIN_CONTAINER_WIDTH, screen_width);
+        let container_height = fit(config.height, config.max_height, MIN_CONTAINER_HEIGHT, screen_height);
 
         let container_x = (screen_width - container_width) / 2;
-        let container_y = (screen_height - container_height) / 2;
+        let free_y = screen_height - container_height;
+        let anchored_y = match config.anchor {
+            CommandCenterAnchor::Center => free_y / 2,
+            CommandCenterAnchor::Top => 0,
+            CommandCenterAnchor::Bottom => free_y,
+        };
+        let container_y = (anchored_y + config.offset_y).clamp(0, free_y.max(0));
 
-        let padding = 24;
-        let search_height = 56;
-        let system_height = 48;
+        // Padding can't eat the whole container
+        let padding = config.padding.clamp(0, container_width.min(container_height) / 4);
+        let inner_width = container_width - padding * 2;
+
+        let apps_height = (container_height - padding * 2 - SEARCH_HEIGHT - SYSTEM_HEIGHT - SECTION_GAP * 2).max(0);
+        let app_card_width = config.card_width.clamp(1, inner_width.max(1));
+        let app_card_height = config.card_height.clamp(1, apps_height.max(1));
+        let gap = CARD_GAP as i32;
+        let app_columns = ((inner_width + gap) / (app_card_width + gap)).max(1);
 
         Self {
             total_width: screen_width,
@@ -547,21 +1816,796 @@ impl CommandCenterLayout {
 
             search_x: container_x + padding,
             search_y: container_y + padding,
-            search_width: container_width - padding * 2,
-            search_height,
+            search_width: inner_width,
+            search_height: SEARCH_HEIGHT,
 
             apps_x: container_x + padding,
-            apps_y: container_y + padding + search_height + 16,
-            apps_width: container_width - padding * 2,
-            apps_height: container_height - padding * 2 - search_height - system_height - 32,
-            app_card_width: 180,
-            app_card_height: 64,
-            app_columns: 3,
+            apps_y: container_y + padding + SEARCH_HEIGHT + SECTION_GAP,
+            apps_width: inner_width,
+            apps_height,
+            app_card_width,
+            app_card_height,
+            app_columns,
 
             system_x: container_x + padding,
-            system_y: container_y + container_height - padding - system_height,
-            system_width: container_width - padding * 2,
-            system_height,
+            system_y: container_y + container_height - padding - SYSTEM_HEIGHT,
+            system_width: inner_width,
+            system_height: SYSTEM_HEIGHT,
+        }
+    }
+
+    /// Is (x, y) over the app grid's viewport?
+    pub fn grid_contains(&self, x: f64, y: f64) -> bool {
+        let left = self.apps_x as f64;
+        let top = self.apps_y as f64;
+
+        x >= left && x < left + self.apps_width as f64 && y >= top && y < top + self.apps_height as f64
+    }
+
+    /// Does a click at (x, y) land on the command center?
+    ///
+    /// The hit rectangle is the container rect, grown by GLOW_SPREAD on every
+    /// side when `include_glow` is set:
+    /// `[container_x - spread, container_x + container_width + spread)` by
+    /// `[container_y - spread, container_y + container_height + spread)`.
+    /// The glow's rounded falloff is ignored - the corners count too.
+    pub fn contains(&self, x: f64, y: f64, include_glow: bool) -> bool {
+        let spread = if include_glow { GLOW_SPREAD as f64 } else { 0.0 };
+
+        let left = self.container_x as f64 - spread;
+        let top = self.container_y as f64 - spread;
+        let right = (self.container_x + self.container_width) as f64 + spread;
+        let bottom = (self.container_y + self.container_height) as f64 + spread;
+
+        x >= left && x < right && y >= top && y < bottom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Option<AppEntry> {
+        parse_desktop_entry(content.as_bytes(), &PathBuf::from("test.desktop"), &[])
+    }
+
+    /// Tiny xorshift so the fuzz runs are reproducible without extra crates
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    #[test]
+    fn parses_crlf_and_bom() {
+        let app = parse("\u{feff}[Desktop Entry]\r\nName=Firefox\r\nExec=firefox %u\r\n").unwrap();
+        assert_eq!(app.name, "Firefox");
+        assert_eq!(app.argv, vec!["firefox"]);
+    }
+
+    #[test]
+    fn ignores_desktop_action_and_repeated_groups() {
+        let app = parse(
+            "[Desktop Entry]\nName=Real\nExec=real\n\
+             [Desktop Action new-window]\nName=Fake\nExec=fake\n\
+             [Desktop Entry]\nExec=evil\n",
+        )
+        .unwrap();
+        assert_eq!(app.name, "Real");
+        assert_eq!(app.argv, vec!["real"]);
+    }
+
+    #[test]
+    fn caps_giant_names_and_rejects_giant_execs() {
+        let long = "x".repeat(500_000);
+        let app = parse(&format!("[Desktop Entry]\nName={}\nExec=app\n", long)).unwrap();
+        assert_eq!(app.name.chars().count(), MAX_NAME_LEN);
+
+        assert!(parse(&format!("[Desktop Entry]\nName=App\nExec={}\n", long)).is_none());
+    }
+
+    #[test]
+    fn exec_quoting() {
+        assert_eq!(
+            parse_exec(r#"sh -c "echo \"hi\" \$HOME" %F"#).unwrap(),
+            vec!["sh", "-c", r#"echo "hi" $HOME"#]
+        );
+        assert_eq!(parse_exec(r#""/opt/My App/bin" --flag"#).unwrap(), vec!["/opt/My App/bin", "--flag"]);
+        assert_eq!(parse_exec("app 100%%").unwrap(), vec!["app", "100%"]);
+        assert_eq!(parse_exec(r#"app """#).unwrap(), vec!["app", ""]);
+    }
+
+    #[test]
+    fn exec_rejects_malformed() {
+        assert!(parse_exec(r#"app "unterminated"#).is_none());
+        assert!(parse_exec(r#"app "bad \q escape""#).is_none());
+        assert!(parse_exec("app %").is_none());
+        assert!(parse_exec("app %z").is_none());
+        assert!(parse_exec("   ").is_none());
+        assert!(parse_exec("%U").is_none());
+        assert!(parse("[Desktop Entry]\nName=App\nExec=app \"oops\n").is_none());
+    }
+
+    #[test]
+    fn keywords_match_at_lower_weight() {
+        let app = parse("[Desktop Entry]\nName=Firefox\nExec=firefox\nKeywords=browser;internet;www;\n").unwrap();
+        assert_eq!(app.keywords, vec!["browser", "internet", "www"]);
+
+        assert!(score_app("browser", &app) > 0);
+        assert!(score_app("fire", &app) > score_app("brow", &app));
+    }
+
+    #[test]
+    fn localized_name_and_keywords() {
+        let content = "[Desktop Entry]\nName=Files\nName[de]=Dateien\nName[de_AT]=Dateien (AT)\n\
+                       Exec=nautilus\nKeywords=folder;\nKeywords[de]=Ordner;\n";
+        let locales = expand_locale("de_DE.UTF-8");
+        assert_eq!(locales, vec!["de_DE", "de"]);
+
+        let app = parse_desktop_entry(content.as_bytes(), &PathBuf::from("t.desktop"), &locales).unwrap();
+        assert_eq!(app.name, "Dateien");
+        assert_eq!(app.keywords, vec!["Ordner"]);
+
+        // No locale match falls back to the plain keys
+        let app = parse(content).unwrap();
+        assert_eq!(app.name, "Files");
+        assert_eq!(app.keywords, vec!["folder"]);
+    }
+
+    #[test]
+    fn fuzz_parser_random_bytes() {
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+
+        for _ in 0..5_000 {
+            let len = rng.below(512);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+            let _ = parse_desktop_entry(&bytes, &PathBuf::from("fuzz.desktop"), &expand_locale("de_DE"));
+        }
+    }
+
+    #[test]
+    fn fuzz_parser_structured() {
+        // Plausible fragments glued together at random hit the interesting paths
+        const FRAGMENTS: &[&str] = &[
+            "[Desktop Entry]", "[Desktop Action x]", "[", "]", "\n", "\r\n", "\u{feff}",
+            "Name=", "Name[de]=", "Exec=", "Icon=", "NoDisplay=true", "=", " ", "\"", "\\",
+            "%", "%f", "%%", "$", "`", ";", "#", "ü", "日本", "app", "--flag",
+        ];
+        let mut rng = Rng(0xdeadbeefcafef00d);
+
+        for _ in 0..20_000 {
+            let content: String = (0..rng.below(40))
+                .map(|_| FRAGMENTS[rng.below(FRAGMENTS.len())])
+                .collect();
+
+            if let Some(app) = parse(&content) {
+                assert!(!app.name.is_empty());
+                assert!(app.name.chars().count() <= MAX_NAME_LEN);
+                assert!(!app.argv.is_empty() && !app.argv[0].is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn fuzz_matcher() {
+        const CHARS: &[char] = &['a', 'b', 'A', ' ', '-', 'ü', 'ß', 'İ', '日', '\0'];
+        let mut rng = Rng(0x0123456789abcdef);
+
+        for _ in 0..20_000 {
+            let query: String = (0..rng.below(8)).map(|_| CHARS[rng.below(CHARS.len())]).collect();
+            let target: String = (0..rng.below(300)).map(|_| CHARS[rng.below(CHARS.len())]).collect();
+            assert!(fuzzy_match(&query, &target) >= 0);
+        }
+    }
+
+    #[test]
+    fn app_dirs_follow_xdg_order() {
+        let dirs = app_dirs_from(
+            None,
+            Some(PathBuf::from("/home/me")),
+            Some("/nix/profile/share::/usr/share".to_string()),
+            &[PathBuf::from("/opt/apps")],
+        );
+
+        assert_eq!(
+            dirs,
+            [
+                "/home/me/.local/share/applications",
+                "/nix/profile/share/applications",
+                "/usr/share/applications",
+                "/home/me/.local/share/flatpak/exports/share/applications",
+                "/var/lib/flatpak/exports/share/applications",
+                "/opt/apps",
+            ]
+            .map(PathBuf::from)
+        );
+
+        // Defaults, and flatpak already in XDG_DATA_DIRS isn't listed twice
+        let dirs = app_dirs_from(Some(PathBuf::from("/data")), None, None, &[]);
+        assert_eq!(
+            dirs[..3],
+            ["/data/applications", "/usr/local/share/applications", "/usr/share/applications"].map(PathBuf::from)
+        );
+        let dirs = app_dirs_from(None, None, Some("/var/lib/flatpak/exports/share".to_string()), &[]);
+        assert_eq!(dirs, vec![PathBuf::from("/var/lib/flatpak/exports/share/applications")]);
+    }
+
+    #[test]
+    fn default_layout_is_capped_and_centered() {
+        let layout = CommandCenterLayout::calculate(1920, 1080, &CommandCenterLayoutConfig::default());
+
+        assert_eq!((layout.container_width, layout.container_height), (800, 600));
+        assert_eq!((layout.container_x, layout.container_y), (560, 240));
+        assert_eq!(layout.app_columns, 3);
+    }
+
+    #[test]
+    fn layout_fits_small_outputs() {
+        let config = CommandCenterLayoutConfig {
+            width: Dimension::Pixels(2000),
+            height: Dimension::Pixels(2000),
+            max_width: None,
+            max_height: None,
+            anchor: CommandCenterAnchor::Bottom,
+            offset_y: 500,
+            padding: 1000,
+            ..CommandCenterLayoutConfig::default()
+        };
+        let layout = CommandCenterLayout::calculate(1366, 768, &config);
+
+        assert_eq!((layout.container_width, layout.container_height), (1366, 768));
+        assert_eq!((layout.container_x, layout.container_y), (0, 0));
+        assert!(layout.apps_height >= 0);
+        assert!(layout.app_columns >= 1);
+        assert!(layout.search_width > 0);
+    }
+
+    #[test]
+    fn top_anchor_with_offset() {
+        let config = CommandCenterLayoutConfig {
+            anchor: CommandCenterAnchor::Top,
+            offset_y: 100,
+            ..CommandCenterLayoutConfig::default()
+        };
+        let layout = CommandCenterLayout::calculate(1920, 1080, &config);
+
+        assert_eq!(layout.container_y, 100);
+        assert_eq!(layout.search_y, 100 + config.padding);
+    }
+
+    #[test]
+    fn grid_hit_excludes_search_and_system_bars() {
+        let layout = CommandCenterLayout::calculate(1920, 1080, &CommandCenterLayoutConfig::default());
+        let x = (layout.apps_x + layout.apps_width / 2) as f64;
+
+        assert!(layout.grid_contains(x, layout.apps_y as f64));
+        assert!(layout.grid_contains(x, (layout.apps_y + layout.apps_height - 1) as f64));
+        assert!(!layout.grid_contains(x, (layout.search_y + 1) as f64));
+        assert!(!layout.grid_contains(x, (layout.system_y + 1) as f64));
+        assert!(!layout.grid_contains((layout.container_x - 1) as f64, layout.apps_y as f64));
+    }
+
+    fn app(name: &str) -> AppEntry {
+        let exec = name.to_lowercase();
+        AppEntry {
+            name: name.to_string(),
+            argv: vec![exec.clone()],
+            desktop_file: PathBuf::from(format!("/apps/{}.desktop", exec)),
+            exec,
+            keywords: Vec::new(),
+            icon: None,
+            score: 0,
+        }
+    }
+
+    fn opened() -> CommandCenter {
+        let mut center = CommandCenter::with_apps(
+            ["Firefox", "Files", "Terminal", "Thunderbird"].into_iter().map(app).collect(),
+        );
+        center.toggle();
+        center
+    }
+
+    fn app_names(center: &CommandCenter) -> Vec<&str> {
+        center.filtered_apps.iter().map(|a| a.name.as_str()).collect()
+    }
+
+    fn type_str(center: &mut CommandCenter, text: &str) {
+        text.chars().for_each(|c| center.handle_char(c));
+    }
+
+    #[test]
+    fn typing_filters_and_backspace_restores() {
+        let mut center = opened();
+        assert_eq!(app_names(&center).len(), 4);
+
+        type_str(&mut center, "fir");
+        assert_eq!(app_names(&center), vec!["Firefox"]);
+
+        (0..3).for_each(|_| center.handle_backspace());
+        assert!(center.search_query.is_empty());
+        assert_eq!(app_names(&center).len(), 4);
+        assert!(center.filtered_actions.is_empty());
+    }
+
+    #[test]
+    fn best_match_sorts_first() {
+        let mut center = opened();
+        type_str(&mut center, "th");
+
+        assert_eq!(app_names(&center)[0], "Thunderbird");
+    }
+
+    #[test]
+    fn fuzzy_ranks_by_where_chars_land() {
+        assert!(fuzzy_match("fox", "Fox Proxy") > fuzzy_match("fox", "Firefox"));
+        assert!(fuzzy_match("fox", "Firefox") > fuzzy_match("fox", "Flatpak Box"));
+        assert!(fuzzy_match("st", "Steam") > fuzzy_match("st", "Settings"));
+        assert!(fuzzy_match("gsm", "GNOME System Monitor") > 0);
+    }
+
+    #[test]
+    fn fuzzy_ignores_case_and_needs_every_char() {
+        assert!(fuzzy_match("gim", "GIMP") > 0);
+        assert_eq!(fuzzy_match("gimx", "GIMP"), 0);
+        assert_eq!(fuzzy_match("pg", "GIMP"), 0);
+    }
+
+    #[test]
+    fn selection_stays_in_range() {
+        let mut center = opened();
+
+        center.select_prev();
+        assert_eq!(center.selected_index, 0);
+
+        (0..10).for_each(|_| center.select_next());
+        assert_eq!(center.selected_index, center.result_count() - 1);
+
+        // Nothing matches - nothing to select
+        type_str(&mut center, "zzzz");
+        assert_eq!(center.result_count(), 0);
+        center.select_next();
+        assert_eq!(center.selected_index, 0);
+        assert!(center.launch_selected().is_none());
+    }
+
+    #[test]
+    fn filtering_resets_selection() {
+        let mut center = opened();
+        center.select_next();
+        center.select_next();
+
+        center.handle_char('t');
+        assert_eq!(center.selected_index, 0);
+    }
+
+    #[test]
+    fn backspace_on_empty_query_keeps_selection() {
+        let mut center = opened();
+        center.select_next();
+        center.select_next();
+
+        center.handle_backspace();
+        assert_eq!(center.selected_index, 2);
+    }
+
+    #[test]
+    fn typing_ignored_outside_search() {
+        let mut center = opened();
+        center.section = CommandCenterSection::Apps;
+
+        center.handle_char('f');
+        center.handle_backspace();
+        assert!(center.search_query.is_empty());
+        assert_eq!(app_names(&center).len(), 4);
+    }
+
+    #[test]
+    fn reopening_resets_state() {
+        let mut center = opened();
+        type_str(&mut center, "fi");
+        center.select_next();
+        center.section = CommandCenterSection::System;
+
+        center.toggle();
+        assert!(!center.visible);
+        center.toggle();
+
+        assert!(center.visible);
+        assert!(center.search_query.is_empty());
+        assert_eq!(center.section, CommandCenterSection::Search);
+        assert_eq!(center.selected_index, 0);
+        assert_eq!(app_names(&center).len(), 4);
+        assert!(center.filtered_actions.is_empty());
+    }
+
+    #[test]
+    fn new_starts_empty_and_set_apps_refilters() {
+        let mut center = CommandCenter::new();
+        center.toggle();
+        assert_eq!(center.result_count(), 0);
+
+        type_str(&mut center, "fir");
+        center.set_apps(vec![app("Firefox"), app("Files")]);
+        assert_eq!(app_names(&center), vec!["Firefox"]);
+    }
+
+    /// Open center with remembering on, closed at `t0` with "fi" typed and the second app selected
+    fn closed_mid_search(t0: Instant) -> CommandCenter {
+        let mut center = opened();
+        center.remember_query = Some(Duration::from_secs(30));
+        type_str(&mut center, "fi");
+        center.select_next();
+        center.toggle_at(t0);
+        center
+    }
+
+    #[test]
+    fn reopening_soon_restores_query_and_selection() {
+        let t0 = Instant::now();
+        let mut center = closed_mid_search(t0);
+
+        center.toggle_at(t0 + Duration::from_secs(29));
+        assert_eq!(center.search_query, "fi");
+        assert_eq!(center.selected_index, 1);
+        assert!(app_names(&center).contains(&"Firefox"));
+    }
+
+    #[test]
+    fn reopening_late_starts_fresh() {
+        let t0 = Instant::now();
+        let mut center = closed_mid_search(t0);
+
+        center.toggle_at(t0 + Duration::from_secs(31));
+        assert!(center.search_query.is_empty());
+        assert_eq!(center.selected_index, 0);
+        assert_eq!(app_names(&center).len(), 4);
+    }
+
+    #[test]
+    fn remembering_off_starts_fresh() {
+        let t0 = Instant::now();
+        let mut center = closed_mid_search(t0);
+        center.remember_query = None;
+
+        center.toggle_at(t0 + Duration::from_secs(1));
+        assert!(center.search_query.is_empty());
+    }
+
+    #[test]
+    fn restored_selection_clamps_to_shrunk_list() {
+        let t0 = Instant::now();
+        let mut center = closed_mid_search(t0);
+
+        // Pretend it was way down a list that has since shrunk
+        center.memory.sections.insert(CommandCenterSection::Search, ("fi".to_string(), 50));
+        center.toggle_at(t0 + Duration::from_secs(1));
+        assert_eq!(center.selected_index, center.result_count() - 1);
+    }
+
+    #[test]
+    fn launching_forgets_the_query() {
+        let mut center = opened();
+        center.remember_query = Some(Duration::from_secs(30));
+        type_str(&mut center, "thunder");
+        center.selected_index = center.filtered_actions.len();
+
+        assert!(center.launch_selected().is_some());
+        center.toggle();
+        assert!(center.search_query.is_empty());
+    }
+
+    #[test]
+    fn no_animations_opens_and_closes_in_one_tick() {
+        let mut center = opened();
+        center.animations = AnimationLevel::None;
+
+        center.update();
+        assert_eq!(center.animation_t, 1.0);
+        assert!(center.animation_start.is_none());
+
+        center.toggle();
+        center.update();
+        assert_eq!(center.animation_t, 0.0);
+        assert!(center.animation_start.is_none());
+    }
+
+    #[test]
+    fn results_stop_at_max_results() {
+        let apps = (0..30).map(|i| app(&format!("Terminal {}", i))).collect();
+        let mut center = CommandCenter::with_apps(apps);
+        center.max_results = Some(10);
+        center.update_filter();
+        assert_eq!(center.filtered_apps.len(), 10);
+        assert_eq!(center.more_results, 20);
+        assert!(center.shows_more_footer());
+
+        // The footer isn't a result - selection stops at the last app
+        for _ in 0..15 {
+            center.select_next();
         }
+        assert_eq!(center.selected_index, 9);
+
+        center.max_results = None;
+        center.update_filter();
+        assert_eq!(center.filtered_apps.len(), 30);
+        assert!(!center.shows_more_footer());
+    }
+
+    #[test]
+    fn top_scores_match_a_full_sort() {
+        let scores: Vec<i32> = (0..200).map(|i| (i * 37 % 23) as i32).collect();
+
+        let mut full = scores.clone();
+        full.sort_by(|a, b| b.cmp(a));
+
+        let mut top = scores.clone();
+        assert_eq!(keep_top_scores(&mut top, Some(15), |&s| s), 185);
+        assert_eq!(top, full[..15]);
+    }
+
+    #[test]
+    fn equal_scores_keep_list_order() {
+        let mut items: Vec<(i32, usize)> = (0..100).map(|i| ((i % 3) as i32, i)).collect();
+        keep_top_scores(&mut items, Some(5), |&(score, _)| score);
+        assert_eq!(items, vec![(2, 2), (2, 5), (2, 8), (2, 11), (2, 14)]);
+    }
+
+    #[test]
+    fn closing_now_skips_the_animation() {
+        let mut center = opened();
+        center.output = Some("HDMI-A-1".to_string());
+        center.update();
+
+        center.close_now();
+        assert!(!center.visible);
+        assert_eq!(center.animation_t, 0.0);
+        assert!(center.animation_start.is_none());
+        assert!(center.output.is_none());
+
+        // Remembers the query like a normal close
+        assert!(center.memory.closed_at.is_some());
+    }
+
+    #[test]
+    fn no_animations_lands_scrolls_in_one_tick() {
+        let mut center = opened();
+        center.animations = AnimationLevel::None;
+        center.scroll.offset = -40.0;
+        center.scroll.velocity = 900.0;
+
+        center.update();
+        assert_eq!(center.scroll.offset, 0.0);
+        assert_eq!(center.scroll.velocity, 0.0);
+    }
+
+    #[test]
+    fn reduced_animations_open_quicker() {
+        let t0 = Instant::now();
+        let mut center = opened();
+        center.animations = AnimationLevel::Reduced;
+        center.animation_start = Some(t0 - OPEN_DURATION / 4);
+
+        center.update();
+        assert_eq!(center.animation_t, 1.0);
+        assert!(center.animation_start.is_none());
+    }
+
+    #[test]
+    fn escape_clears_before_closing() {
+        let mut center = opened();
+        type_str(&mut center, "fi");
+
+        assert!(center.clear_query());
+        assert!(center.search_query.is_empty());
+        assert_eq!(app_names(&center).len(), 4);
+        assert!(!center.clear_query());
+    }
+
+    const XBEL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xbel version="1.0" xmlns:mime="http://www.freedesktop.org/standards/shared-mime-info">
+  <bookmark href="file:///home/me/old%20notes.txt" added="2024-01-01T10:00:00Z" modified="2024-01-01T10:00:00Z" visited="2024-01-01T10:00:00Z">
+    <info><metadata owner="http://freedesktop.org"><mime:mime-type type="text/plain"/></metadata></info>
+  </bookmark>
+  <bookmark href="https://example.com/page" modified="2024-03-01T10:00:00Z"/>
+  <bookmark href="file://localhost/home/me/Tom&amp;Jerry.pdf" modified="2024-02-01T10:00:00Z">
+    <info><metadata owner="http://freedesktop.org"><mime:mime-type type="application/pdf"/></metadata></info>
+  </bookmark>
+</xbel>"#;
+
+    fn file(name: &str) -> RecentFile {
+        RecentFile {
+            name: name.to_string(),
+            path: PathBuf::from(format!("/home/me/{}", name)),
+            mime_type: None,
+            modified: String::new(),
+            score: 0,
+        }
+    }
+
+    #[test]
+    fn xbel_keeps_local_files_newest_first() {
+        let files = parse_xbel(XBEL);
+
+        let paths: Vec<_> = files.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("/home/me/Tom&Jerry.pdf"), PathBuf::from("/home/me/old notes.txt")]
+        );
+        assert_eq!(files[0].name, "Tom&Jerry.pdf");
+        assert_eq!(files[0].mime_type.as_deref(), Some("application/pdf"));
+        assert_eq!(files[1].mime_type.as_deref(), Some("text/plain"));
+    }
+
+    #[test]
+    fn xbel_garbage_is_empty() {
+        assert!(parse_xbel("").is_empty());
+        assert!(parse_xbel("<bookmark href=\"file:///a").is_empty());
+        assert!(parse_xbel("<bookmark xhref=\"file:///a\"></bookmark>").is_empty());
+    }
+
+    #[test]
+    fn files_section_searches_and_opens() {
+        let mut center = opened();
+        center.files_enabled = true;
+        center.set_files(vec![file("report.pdf"), file("firefox-notes.txt")]);
+
+        center.next_section();
+        assert_eq!(center.section, CommandCenterSection::Files);
+        assert_eq!(center.result_count(), 2);
+
+        type_str(&mut center, "report");
+        assert_eq!(center.result_count(), 1);
+        match center.launch_selected() {
+            Some(Selection::Open(path)) => assert_eq!(path, PathBuf::from("/home/me/report.pdf")),
+            other => panic!("expected an open, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn files_section_needs_enabling() {
+        let mut center = opened();
+        center.next_section();
+        assert_eq!(center.section, CommandCenterSection::Windows);
+    }
+
+    #[test]
+    fn tab_cycles_back_to_search() {
+        let mut center = opened();
+        center.files_enabled = true;
+
+        let mut sections = Vec::new();
+        for _ in 0..4 {
+            center.next_section();
+            sections.push(center.section);
+        }
+        assert_eq!(
+            sections,
+            vec![
+                CommandCenterSection::Files,
+                CommandCenterSection::Windows,
+                CommandCenterSection::System,
+                CommandCenterSection::Search,
+            ]
+        );
+    }
+
+    #[test]
+    fn power_actions_take_a_second_enter() {
+        let mut center = opened();
+        center.section = CommandCenterSection::System;
+        assert_eq!(center.result_count(), PowerAction::ALL.len());
+
+        // Shutdown arms on the first Enter, runs on the second
+        center.selected_index = 3;
+        assert!(center.launch_selected().is_none());
+        assert_eq!(center.power_armed, Some(PowerAction::Shutdown));
+        assert!(center.visible);
+        assert!(matches!(center.launch_selected(), Some(Selection::Power(PowerAction::Shutdown))));
+
+        // That closed it - reopen, arm it again, and move off and back
+        center.toggle();
+        center.section = CommandCenterSection::System;
+        center.selected_index = 3;
+        center.launch_selected();
+        center.select_prev();
+        center.select_next();
+        assert!(center.launch_selected().is_none());
+
+        // Suspend loses nothing, so it goes right away
+        center.selected_index = 0;
+        assert!(matches!(center.launch_selected(), Some(Selection::Power(PowerAction::Suspend))));
+    }
+
+    #[test]
+    fn windows_section_searches_and_switches() {
+        let window = |id, title: &str, app_id: &str| WindowInfo {
+            id,
+            title: title.to_string(),
+            app_id: Some(app_id.to_string()),
+            score: 0,
+        };
+        let mut center = opened();
+        center.set_windows(vec![
+            window(3, "~/src - nvim", "foot"),
+            window(1, "Inbox - Thunderbird", "thunderbird"),
+        ]);
+
+        center.next_section();
+        assert_eq!(center.result_count(), 2);
+
+        type_str(&mut center, "thunder");
+        assert_eq!(center.result_count(), 1);
+        match center.launch_selected() {
+            Some(Selection::Window(id)) => assert_eq!(id, 1),
+            other => panic!("expected a window, got {:?}", other),
+        }
+        assert!(!center.visible);
+    }
+
+    #[test]
+    fn launch_selected_skips_past_actions() {
+        let mut center = opened();
+        type_str(&mut center, "thunder");
+
+        // Apps come after whatever actions matched
+        center.selected_index = center.filtered_actions.len();
+        match center.launch_selected() {
+            Some(Selection::Launch { argv, app_id, name }) => {
+                assert_eq!(argv, vec!["thunderbird"]);
+                assert_eq!(app_id.as_deref(), Some("thunderbird"));
+                assert_eq!(name, "Thunderbird");
+            }
+            other => panic!("expected a launch, got {:?}", other),
+        }
+        assert!(!center.visible);
+    }
+
+    #[test]
+    fn empty_query_lists_most_launched_first() {
+        let mut center = opened();
+        type_str(&mut center, "thunder");
+        center.selected_index = center.filtered_actions.len();
+        center.launch_selected();
+
+        center.record_launch(&app("Terminal"));
+        center.record_launch(&app("Terminal"));
+
+        // Ties keep the list order
+        center.toggle();
+        assert_eq!(app_names(&center), vec!["Terminal", "Thunderbird", "Firefox", "Files"]);
+    }
+
+    #[test]
+    fn stats_query_shows_usage_instead_of_results() {
+        let mut center = opened();
+        center.usage = Some(UsageSummary {
+            top_apps: vec![("Firefox".to_string(), 3)],
+            windows_opened: 5,
+            started: Instant::now(),
+        });
+        type_str(&mut center, "Stats");
+
+        assert!(center.showing_stats());
+        assert_eq!(center.result_count(), 0);
+        assert!(center.launch_selected().is_none());
+        assert!(center.visible);
+    }
+
+    #[test]
+    fn stats_query_is_a_search_without_tracking() {
+        let mut center = opened();
+        type_str(&mut center, "stats");
+
+        assert!(!center.showing_stats());
     }
 }
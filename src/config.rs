@@ -1,3 +1,13 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use smithay::input::keyboard::Keysym;
+
+use crate::action::Action;
+use crate::command_center::DEFAULT_MAX_RESULTS;
+use crate::keybind::{self, Keybind};
+use crate::launch;
+
 /// vibeWM configuration
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -7,19 +17,392 @@ pub struct Config {
     /// Gap between snapped windows (pixels)
     pub inner_gap: i32,
 
+    /// Gap between snapped halves/quarters (None = inner_gap)
+    pub snap_inner_gap: Option<i32>,
+
+    /// Share of the output the left/top side of a snap gets (0.5 = even halves)
+    pub snap_ratio: f64,
+
+    /// Gap between tiled windows, e.g. mod+B balance (None = inner_gap)
+    pub tile_inner_gap: Option<i32>,
+
+    /// Size new windows are asked to open at (width, height), unless the
+    /// client's min/max pins it (None = let the client pick)
+    pub default_window_size: Option<(Dimension, Dimension)>,
+
     /// Window move step size (pixels)
     pub move_step: i32,
 
     /// Window resize step size (pixels)
     pub resize_step: i32,
 
+    /// How far (pixels) a window has to be pushed past an output edge before
+    /// it crosses onto the next output - 0 turns it off
+    pub edge_resistance: i32,
+
+    /// Pointer leaving the rightmost output comes back in on the leftmost (and back)
+    pub pointer_wrap: bool,
+
     /// Border width (pixels)
     pub border_width: i32,
 
+    /// Scan out at 10 bits per channel when the connector and GPU can (DRM backend)
+    pub prefer_10bit: bool,
+
+    /// Render with llvmpipe instead of the GPU - for VMs and GPUs whose GL
+    /// doesn't work. Read once at startup, like LIBGL_ALWAYS_SOFTWARE=1
+    pub software_rendering: bool,
+
+    /// Named workspaces, in mod+1..9 order (empty = just numbers)
+    pub workspaces: Vec<WorkspaceConfig>,
+
+    /// Most workspaces there can be, counting the named ones - switching or
+    /// sending past it (palette, scripts) is an error
+    pub max_workspaces: usize,
+
+    /// Background image - or a directory of them to cycle through (None = just the color)
+    pub wallpaper: Option<PathBuf>,
+
+    /// How long each image of a wallpaper directory stays up (seconds)
+    pub wallpaper_interval_secs: u64,
+
+    /// What to do when the last window on screen closes
+    pub on_last_window_closed: EmptyDesktopAction,
+
+    /// How much moves - full, reduced (quicker, no decorative effects) or
+    /// none (everything happens instantly)
+    pub animations: AnimationLevel,
+
+    /// Corner card with per-phase loop timings (input/dispatch/render)
+    pub debug_overlay: bool,
+
+    /// Serve Prometheus metrics on 127.0.0.1 at this port (needs the
+    /// `metrics` feature; None = only the `metrics <file>` IPC command)
+    pub metrics_port: Option<u16>,
+
+    /// Dump diagnostics to $XDG_STATE_HOME/vibewm when the event loop hasn't
+    /// come round for this long (ms, None = no watchdog)
+    pub watchdog_timeout_ms: Option<u64>,
+
+    /// Abort after the dump, so systemd restarts a wedged session
+    pub watchdog_abort: bool,
+
+    /// Ask for a second mod+Q before quitting
+    pub confirm_quit: bool,
+
+    /// How long the second mod+Q has to arrive (ms)
+    pub quit_confirm_timeout_ms: u64,
+
+    /// Command (argv, no shell) run on exit, after clients are closed
+    pub on_exit: Option<Vec<String>>,
+
+    /// Absolute pointer devices (tablets, VM pointers) pinned to an output:
+    /// (device name, output connector). Unlisted devices span all outputs
+    pub absolute_device_outputs: Vec<(String, String)>,
+
+    /// Keybinds handed to specific apps instead of run (VMs, remote desktops):
+    /// (app_id, chords like "super+Tab")
+    pub passthrough: Vec<(String, Vec<String>)>,
+
+    /// Hide the cursor after this long without pointer activity (ms, None = never)
+    pub cursor_hide_timeout_ms: Option<u64>,
+
+    /// Hide the cursor as soon as you type into a window
+    pub cursor_hide_while_typing: bool,
+
+    /// A client that doesn't answer a ping this fast is flagged "Not responding" (ms)
+    pub ping_timeout_ms: u64,
+
+    /// How long one init.rhai callback may run before it's cut off (ms)
+    pub script_budget_ms: u64,
+
+    /// Zoom range (1.0 = unzoomed) and how much each step multiplies by
+    pub zoom_min: f64,
+    pub zoom_max: f64,
+    pub zoom_step: f64,
+
+    /// What enters resize mode (ijkl resize instead of move)
+    pub resize_mode_key: Keybind,
+
+    /// Hold the key for resize mode, or press once to toggle it
+    pub resize_mode_style: ResizeModeStyle,
+
+    /// Extra seats, each claiming keyboards/mice by name (empty = one seat for everything)
+    pub seats: Vec<SeatConfig>,
+
+    /// Key combo -> action (resize mode is handled separately)
+    pub keybinds: Vec<(Keybind, Action)>,
+
+    /// Wrapper every app launch goes through (argv, `%exec` = the app's argv)
+    /// e.g. systemd-run --user --scope -- %exec
+    pub launch_command_template: Vec<String>,
+
+    /// Where screenshots are saved (None = $XDG_PICTURES_DIR/Screenshots)
+    pub screenshot_dir: Option<PathBuf>,
+
+    /// Also put screenshots on the clipboard (needs wl-copy)
+    pub screenshot_copy: bool,
+
+    /// Env vars for everything vibeWM starts, e.g. ("QT_QPA_PLATFORM", "wayland")
+    pub env: Vec<(String, String)>,
+
+    /// Extra env per app, on top of `env`: (desktop file id or app name, vars)
+    /// e.g. ("firefox", [("MOZ_ENABLE_WAYLAND", "1")])
+    pub app_env: Vec<(String, Vec<(String, String)>)>,
+
+    /// Command center on/off, and what mod+S runs instead
+    pub command_center: CommandCenterConfig,
+
+    /// On-screen keyboard (argv, e.g. ["squeekboard"]) spawned while the command
+    /// center is open on a seat with a touchscreen and no keyboard
+    pub osk: Option<Vec<String>>,
+
+    /// Glow that flashes around a window when it gains focus
+    pub focus_ring: FocusRingConfig,
+
+    /// Indicator and quieter visuals while an output is being recorded
+    pub recording: RecordingConfig,
+
+    /// Unfocused windows fade to a lower opacity
+    pub focus_fade: FocusFadeConfig,
+
+    /// Compositor-drawn titlebars, for apps with no decorations of their own
+    pub titlebars: TitlebarConfig,
+
     /// Colors - vibecode af
     pub colors: Colors,
 }
 
+/// Command center settings
+#[derive(Debug, Clone)]
+pub struct CommandCenterConfig {
+    /// false = never build the command center (no .desktop scan either)
+    pub enabled: bool,
+
+    /// External launcher toggle_command_center (mod+S) spawns when disabled
+    /// (argv, no shell)
+    pub launcher: Vec<String>,
+
+    /// Clicks on the glow around the container count as inside (don't dismiss)
+    pub glow_is_inside: bool,
+
+    /// Size and position of the container
+    pub layout: CommandCenterLayoutConfig,
+
+    /// Output it opens on - picked when it opens, it stays there until closed
+    pub placement: CommandCenterPlacement,
+
+    /// Extra dirs of .desktop files, on top of $XDG_DATA_HOME/$XDG_DATA_DIRS
+    pub app_dirs: Vec<PathBuf>,
+
+    /// Reopening within remember_query_ms restores the last query and selection
+    pub remember_query: bool,
+    pub remember_query_ms: u64,
+
+    /// Scrolling anywhere on the container scrolls the app grid, not just over it
+    pub scroll_anywhere: bool,
+
+    /// Most apps listed at once, best matches first - None lists them all
+    pub max_results: Option<usize>,
+
+    /// Recently used files section (Tab), from recently-used.xbel - off by
+    /// default since it reads your file history
+    pub recent_files: bool,
+
+    /// Keep a launch history for the "stats" view (90 days, on disk) - turning
+    /// it off also deletes what's there
+    pub usage_stats: bool,
+}
+
+/// A length that's either a share of the output or fixed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dimension {
+    /// Percent of the output (0..100)
+    Percent(f32),
+    Pixels(i32),
+}
+
+impl Dimension {
+    pub fn resolve(self, total: i32) -> i32 {
+        match self {
+            Self::Percent(p) => (total as f32 * p / 100.0) as i32,
+            Self::Pixels(px) => px,
+        }
+    }
+}
+
+/// Where the command center sits vertically
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommandCenterAnchor {
+    Center,
+    /// Top-center, like Spotlight
+    Top,
+    Bottom,
+}
+
+/// Which output the command center opens on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommandCenterPlacement {
+    /// The one under the pointer
+    Pointer,
+    /// The focused window's - the active workspace's when nothing's focused
+    Focused,
+    Primary,
+}
+
+/// Command center geometry - everything gets clamped to fit the output
+#[derive(Debug, Clone)]
+pub struct CommandCenterLayoutConfig {
+    pub width: Dimension,
+    pub height: Dimension,
+
+    /// Caps so it doesn't sprawl across an ultrawide (None = no cap)
+    pub max_width: Option<i32>,
+    pub max_height: Option<i32>,
+
+    pub anchor: CommandCenterAnchor,
+
+    /// Pushes the container down (or up, negative) from its anchor (px)
+    pub offset_y: i32,
+
+    /// Space between the container edge and its contents (px)
+    pub padding: i32,
+
+    pub card_width: i32,
+    pub card_height: i32,
+}
+
+/// Focus ring flash settings
+#[derive(Debug, Clone)]
+pub struct FocusRingConfig {
+    pub enabled: bool,
+
+    /// How long the ring takes to expand and fade out (ms)
+    pub duration_ms: u64,
+
+    pub color: [f32; 4],
+}
+
+/// Opacity fades on focus changes
+#[derive(Debug, Clone)]
+pub struct FocusFadeConfig {
+    pub enabled: bool,
+
+    /// Opacity of unfocused windows (0..1)
+    pub unfocused_dim: f32,
+
+    /// How long gaining focus takes to fade up, and losing it to fade down (ms)
+    pub focus_ms: u64,
+    pub blur_ms: u64,
+}
+
+/// Server-side titlebars
+#[derive(Debug, Clone)]
+pub struct TitlebarConfig {
+    /// Off by default - when on, windows that ask for server-side decorations get a titlebar
+    pub enabled: bool,
+
+    /// Bar height (px) - taken off the top of snapped/tiled slots
+    pub height: i32,
+
+    /// app_ids that always get one, asked or not (mpv, SDL games)
+    pub apps: Vec<String>,
+}
+
+/// What changes while a screencopy client records an output
+#[derive(Debug, Clone)]
+pub struct RecordingConfig {
+    /// Small red dot on every output being recorded
+    pub indicator: bool,
+    pub indicator_corner: ScreenCorner,
+
+    /// Keep notices and snap previews off recorded outputs
+    pub hide_popups: bool,
+
+    /// Stop the glow pulse and focus ring flash while anything is recorded,
+    /// so they don't end up in the video
+    pub calm_animations: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScreenCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// An extra seat with its own pointer and keyboard focus
+#[derive(Debug, Clone)]
+pub struct SeatConfig {
+    pub name: String,
+
+    /// Devices whose name contains any of these belong to this seat
+    pub devices: Vec<String>,
+}
+
+/// How the resize mode key behaves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResizeModeStyle {
+    /// Resize mode while the key is down
+    Hold,
+    /// Press to turn it on, again (or Escape) to turn it off
+    Toggle,
+}
+
+/// What an empty desktop does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmptyDesktopAction {
+    /// Pop the command center open
+    CommandCenter,
+
+    /// Show the keybind hints card
+    HelpOverlay,
+
+    /// Just the background
+    Nothing,
+}
+
+/// Global animation setting - `reduced` is for prefers-reduced-motion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationLevel {
+    Full,
+
+    /// 4x quicker, and no purely decorative effects (glow pulse, focus ring)
+    Reduced,
+
+    /// Everything jumps straight to where it ends up
+    None,
+}
+
+impl AnimationLevel {
+    /// How long an animation that takes `full` at full level takes at this one
+    pub fn duration(self, full: Duration) -> Duration {
+        match self {
+            Self::Full => full,
+            Self::Reduced => full / 4,
+            Self::None => Duration::ZERO,
+        }
+    }
+
+    /// Effects that are just for looks - only at full
+    pub fn decorative(self) -> bool {
+        self == Self::Full
+    }
+}
+
+/// A declared workspace
+#[derive(Debug, Clone)]
+pub struct WorkspaceConfig {
+    pub name: String,
+
+    /// Output (connector name) it should open on in multi-monitor setups
+    pub output: Option<String>,
+
+    /// Wallpaper (file or directory) for just this workspace, instead of the global one
+    pub wallpaper: Option<PathBuf>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Colors {
     /// Background color
@@ -38,20 +421,173 @@ pub struct Colors {
     pub accent: [f32; 4],
 }
 
+impl Config {
+    pub fn snap_gap(&self) -> i32 {
+        self.snap_inner_gap.unwrap_or(self.inner_gap)
+    }
+
+    pub fn tile_gap(&self) -> i32 {
+        self.tile_inner_gap.unwrap_or(self.inner_gap)
+    }
+
+    /// Per-app env for a launch - every app_env rule matching the desktop
+    /// file id or the name (case-insensitive), in config order
+    pub fn env_for_app(&self, app_id: Option<&str>, name: Option<&str>) -> Vec<(String, String)> {
+        let matches = |rule: &str| {
+            app_id.is_some_and(|id| id.eq_ignore_ascii_case(rule))
+                || name.is_some_and(|name| name.eq_ignore_ascii_case(rule))
+        };
+
+        self.app_env
+            .iter()
+            .filter(|(rule, _)| matches(rule))
+            .flat_map(|(_, vars)| vars.iter().cloned())
+            .collect()
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             // Modifier key is always Super/Logo - checked via modifiers.logo in input.rs
             outer_gap: 10,
             inner_gap: 10,
+            snap_inner_gap: None,
+            snap_ratio: 0.5,
+            default_window_size: None,
+            tile_inner_gap: None,
             move_step: 50,
             resize_step: 50,
+            edge_resistance: 0,
+            pointer_wrap: false,
             border_width: 2,
+            prefer_10bit: false,
+            software_rendering: false,
+            workspaces: Vec::new(),
+            max_workspaces: 32,
+            wallpaper: None,
+            wallpaper_interval_secs: 300,
+            on_last_window_closed: EmptyDesktopAction::HelpOverlay,
+            animations: AnimationLevel::Full,
+            debug_overlay: false,
+            metrics_port: None,
+            watchdog_timeout_ms: Some(5000),
+            watchdog_abort: false,
+            confirm_quit: true,
+            quit_confirm_timeout_ms: 2000,
+            on_exit: None,
+            absolute_device_outputs: Vec::new(),
+            passthrough: Vec::new(),
+            cursor_hide_timeout_ms: Some(5000),
+            cursor_hide_while_typing: true,
+            ping_timeout_ms: 5000,
+            script_budget_ms: 20,
+            zoom_min: 1.0,
+            zoom_max: 8.0,
+            zoom_step: 1.25,
+            resize_mode_key: Keybind::logo(Keysym::r),
+            resize_mode_style: ResizeModeStyle::Hold,
+            seats: Vec::new(),
+            keybinds: keybind::default_bindings(),
+            launch_command_template: default_launch_template(),
+            screenshot_dir: None,
+            screenshot_copy: true,
+            env: Vec::new(),
+            app_env: Vec::new(),
+            command_center: CommandCenterConfig::default(),
+            osk: None,
+            focus_ring: FocusRingConfig::default(),
+            recording: RecordingConfig::default(),
+            focus_fade: FocusFadeConfig::default(),
+            titlebars: TitlebarConfig::default(),
             colors: Colors::default(),
         }
     }
 }
 
+/// No wrapper - just the app
+pub fn default_launch_template() -> Vec<String> {
+    vec![launch::EXEC_PLACEHOLDER.to_string()]
+}
+
+impl Default for CommandCenterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            launcher: vec!["fuzzel".to_string()],
+            glow_is_inside: true,
+            layout: CommandCenterLayoutConfig::default(),
+            placement: CommandCenterPlacement::Pointer,
+            app_dirs: Vec::new(),
+            remember_query: true,
+            remember_query_ms: 30_000,
+            scroll_anywhere: false,
+            max_results: Some(DEFAULT_MAX_RESULTS),
+            recent_files: false,
+            usage_stats: true,
+        }
+    }
+}
+
+impl Default for CommandCenterLayoutConfig {
+    fn default() -> Self {
+        Self {
+            width: Dimension::Percent(60.0),
+            height: Dimension::Percent(70.0),
+            max_width: Some(800),
+            max_height: Some(600),
+            anchor: CommandCenterAnchor::Center,
+            offset_y: 0,
+            padding: 24,
+            card_width: 180,
+            card_height: 64,
+        }
+    }
+}
+
+impl Default for TitlebarConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            height: 28,
+            apps: Vec::new(),
+        }
+    }
+}
+
+impl Default for FocusFadeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            unfocused_dim: 0.85,
+            focus_ms: 120,
+            blur_ms: 250,
+        }
+    }
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            indicator: true,
+            indicator_corner: ScreenCorner::TopRight,
+            hide_popups: false,
+            calm_animations: false,
+        }
+    }
+}
+
+impl Default for FocusRingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            duration_ms: 350,
+            // Same neon cyan as the focused border
+            color: [0.0, 0.9, 0.9, 1.0],
+        }
+    }
+}
+
 impl Default for Colors {
     fn default() -> Self {
         // Vibecode aesthetic - dark with neon accents
@@ -88,3 +624,22 @@ pub enum SnapPosition {
     Maximize,
     Center,
 }
+
+impl SnapPosition {
+    /// Parse a name like "left" or "top-right" (for IPC)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().replace('_', "-").as_str() {
+            "left" => Self::Left,
+            "right" => Self::Right,
+            "top" => Self::Top,
+            "bottom" => Self::Bottom,
+            "top-left" => Self::TopLeft,
+            "top-right" => Self::TopRight,
+            "bottom-left" => Self::BottomLeft,
+            "bottom-right" => Self::BottomRight,
+            "maximize" => Self::Maximize,
+            "center" => Self::Center,
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,862 @@
+//! config.toml - overrides on top of Config::default()
+//!
+//! Lives at $XDG_CONFIG_HOME/vibewm/config.toml (~/.config/vibewm/config.toml).
+//! Every key is optional; anything missing keeps its default. Keys mirror the
+//! Config field names, nested structs are tables (`[command_center.layout]`).
+//!
+//! Conventions:
+//! - optional settings take `false` to turn them off (`max_width = false`)
+//! - colors are `[r, g, b, a]` (0..1) or `"#rrggbb"` / `"#rrggbbaa"`
+//! - sizes are pixels (`800`) or a share of the output (`"60%"`)
+//! - lists of pairs (env, passthrough, ...) are tables, kept in file order
+//!
+//! Unknown keys and wrong types are errors naming the key - a typo shouldn't
+//! silently leave a setting at its default.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use toml::{Table, Value};
+
+use crate::action::Action;
+use crate::config::{
+    AnimationLevel, Colors, CommandCenterAnchor, CommandCenterConfig, CommandCenterLayoutConfig,
+    CommandCenterPlacement, Config, Dimension, EmptyDesktopAction, FocusFadeConfig, FocusRingConfig, RecordingConfig, ResizeModeStyle,
+    ScreenCorner, SeatConfig, TitlebarConfig, WorkspaceConfig,
+};
+use crate::keybind::Keybind;
+
+impl Config {
+    /// $XDG_CONFIG_HOME/vibewm/config.toml, falling back to ~/.config
+    pub fn path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("vibewm").join("config.toml"))
+    }
+
+    /// Read a config file - defaults for whatever it leaves out
+    pub fn load(path: &Path) -> Result<Config> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        Self::from_toml_str(&content).with_context(|| format!("Bad config in {}", path.display()))
+    }
+
+    /// Swap in the config at `path` - if it doesn't load (unreadable, bad
+    /// TOML, one bad key) nothing changes
+    pub fn reload_from(&mut self, path: &Path) -> Result<()> {
+        *self = Self::load(path)?;
+        Ok(())
+    }
+
+    /// Parse config.toml contents on top of the defaults
+    pub fn from_toml_str(content: &str) -> Result<Config> {
+        let table: Table = content.parse().map_err(|e: toml::de::Error| anyhow!("{}", e))?;
+
+        let mut config = Config::default();
+        let mut root = Section::new(table, "");
+        read_config(&mut root, &mut config)?;
+        root.finish()?;
+        Ok(config)
+    }
+}
+
+fn read_config(s: &mut Section, c: &mut Config) -> Result<()> {
+    s.set("outer_gap", &mut c.outer_gap)?;
+    s.set("inner_gap", &mut c.inner_gap)?;
+    s.set("snap_inner_gap", &mut c.snap_inner_gap)?;
+    s.set("snap_ratio", &mut c.snap_ratio)?;
+    s.set("tile_inner_gap", &mut c.tile_inner_gap)?;
+    s.set("default_window_size", &mut c.default_window_size)?;
+    s.set("move_step", &mut c.move_step)?;
+    s.set("resize_step", &mut c.resize_step)?;
+    s.set("edge_resistance", &mut c.edge_resistance)?;
+    s.set("pointer_wrap", &mut c.pointer_wrap)?;
+    s.set("border_width", &mut c.border_width)?;
+    s.set("prefer_10bit", &mut c.prefer_10bit)?;
+    s.set("software_rendering", &mut c.software_rendering)?;
+    s.set("max_workspaces", &mut c.max_workspaces)?;
+    s.set("wallpaper", &mut c.wallpaper)?;
+    s.set("wallpaper_interval_secs", &mut c.wallpaper_interval_secs)?;
+    s.set("on_last_window_closed", &mut c.on_last_window_closed)?;
+    s.set("animations", &mut c.animations)?;
+    s.set("debug_overlay", &mut c.debug_overlay)?;
+    s.set("metrics_port", &mut c.metrics_port)?;
+    s.set("watchdog_timeout_ms", &mut c.watchdog_timeout_ms)?;
+    s.set("watchdog_abort", &mut c.watchdog_abort)?;
+    s.set("confirm_quit", &mut c.confirm_quit)?;
+    s.set("quit_confirm_timeout_ms", &mut c.quit_confirm_timeout_ms)?;
+    s.set("on_exit", &mut c.on_exit)?;
+    s.set_map("absolute_device_outputs", &mut c.absolute_device_outputs)?;
+    s.set_map("passthrough", &mut c.passthrough)?;
+    s.set("cursor_hide_timeout_ms", &mut c.cursor_hide_timeout_ms)?;
+    s.set("cursor_hide_while_typing", &mut c.cursor_hide_while_typing)?;
+    s.set("ping_timeout_ms", &mut c.ping_timeout_ms)?;
+    s.set("script_budget_ms", &mut c.script_budget_ms)?;
+    s.set("zoom_min", &mut c.zoom_min)?;
+    s.set("zoom_max", &mut c.zoom_max)?;
+    s.set("zoom_step", &mut c.zoom_step)?;
+    check_zoom(c);
+    s.set("resize_mode_key", &mut c.resize_mode_key)?;
+    s.set("resize_mode_style", &mut c.resize_mode_style)?;
+    s.set("launch_command_template", &mut c.launch_command_template)?;
+    s.set("screenshot_dir", &mut c.screenshot_dir)?;
+    s.set("screenshot_copy", &mut c.screenshot_copy)?;
+    s.set_map("env", &mut c.env)?;
+    s.set("osk", &mut c.osk)?;
+
+    // [[workspaces]] name = "web", output = "DP-1", wallpaper = "..."
+    if let Some(list) = s.tables("workspaces")? {
+        c.workspaces = list
+            .into_iter()
+            .map(|mut ws| {
+                let mut workspace = WorkspaceConfig { name: String::new(), output: None, wallpaper: None };
+                ws.require("name", &mut workspace.name)?;
+                ws.set("output", &mut workspace.output)?;
+                ws.set("wallpaper", &mut workspace.wallpaper)?;
+                ws.finish()?;
+                Ok(workspace)
+            })
+            .collect::<Result<_>>()?;
+    }
+
+    // [[seats]] name = "second", devices = ["Logitech"]
+    if let Some(list) = s.tables("seats")? {
+        c.seats = list
+            .into_iter()
+            .map(|mut seat| {
+                let mut config = SeatConfig { name: String::new(), devices: Vec::new() };
+                seat.require("name", &mut config.name)?;
+                seat.set("devices", &mut config.devices)?;
+                seat.finish()?;
+                Ok(config)
+            })
+            .collect::<Result<_>>()?;
+    }
+
+    // [app_env.firefox] MOZ_ENABLE_WAYLAND = "1"
+    if let Some(mut apps) = s.section("app_env")? {
+        let names: Vec<String> = apps.table.keys().cloned().collect();
+        c.app_env = names
+            .into_iter()
+            .map(|name| {
+                let mut vars = Vec::new();
+                apps.set_map(&name, &mut vars)?;
+                Ok((name, vars))
+            })
+            .collect::<Result<_>>()?;
+    }
+
+    // [keybinds] close_window = "super+x" - replaces that action's stock combos,
+    // "super+shift+q" = "quit" - rebinds just that combo
+    if let Some(mut binds) = s.section("keybinds")? {
+        read_keybinds(&mut binds, &mut c.keybinds)?;
+        binds.finish()?;
+    }
+
+    if let Some(mut cc) = s.section("command_center")? {
+        read_command_center(&mut cc, &mut c.command_center)?;
+        cc.finish()?;
+    }
+    if let Some(mut ring) = s.section("focus_ring")? {
+        read_focus_ring(&mut ring, &mut c.focus_ring)?;
+        ring.finish()?;
+    }
+    if let Some(mut recording) = s.section("recording")? {
+        read_recording(&mut recording, &mut c.recording)?;
+        recording.finish()?;
+    }
+    if let Some(mut fade) = s.section("focus_fade")? {
+        read_focus_fade(&mut fade, &mut c.focus_fade)?;
+        fade.finish()?;
+    }
+    if let Some(mut titlebars) = s.section("titlebars")? {
+        read_titlebars(&mut titlebars, &mut c.titlebars)?;
+        titlebars.finish()?;
+    }
+    if let Some(mut colors) = s.section("colors")? {
+        read_colors(&mut colors, &mut c.colors)?;
+        colors.finish()?;
+    }
+
+    Ok(())
+}
+
+/// A zoom range zoom_by can clamp to - anything else (inf, nan, 0, min
+/// above max) goes back to the defaults with a warning
+fn check_zoom(c: &mut Config) {
+    let valid = |f: f64| f.is_finite() && f > 0.0;
+    if valid(c.zoom_min) && valid(c.zoom_max) && c.zoom_min <= c.zoom_max && valid(c.zoom_step) {
+        return;
+    }
+
+    let default = Config::default();
+    tracing::warn!(
+        "zoom_min = {}, zoom_max = {}, zoom_step = {} isn't a usable zoom range - using {}, {}, {}",
+        c.zoom_min,
+        c.zoom_max,
+        c.zoom_step,
+        default.zoom_min,
+        default.zoom_max,
+        default.zoom_step
+    );
+    c.zoom_min = default.zoom_min;
+    c.zoom_max = default.zoom_max;
+    c.zoom_step = default.zoom_step;
+}
+
+/// Either way round:
+/// - action name = combo, a list of combos, or false to unbind the action
+/// - combo = action name, or false to unbind the combo
+fn read_keybinds(s: &mut Section, bindings: &mut Vec<(Keybind, Action)>) -> Result<()> {
+    let names: Vec<String> = s.table.keys().cloned().collect();
+
+    for name in names {
+        if let Some(action) = Action::from_config_name(&name) {
+            let mut combos = Combos(Vec::new());
+            s.set(&name, &mut combos)?;
+
+            bindings.retain(|(_, bound)| *bound != action);
+            bindings.extend(combos.0.into_iter().map(|combo| (combo, action.clone())));
+            continue;
+        }
+
+        let Some(combo) = Keybind::parse(&name) else {
+            bail!("{}: unknown action or key combo", s.key(&name));
+        };
+        let mut action: Option<Action> = None;
+        s.set(&name, &mut action)?;
+
+        bindings.retain(|(bound, _)| *bound != combo);
+        bindings.extend(action.map(|action| (combo, action)));
+    }
+    Ok(())
+}
+
+fn read_command_center(s: &mut Section, c: &mut CommandCenterConfig) -> Result<()> {
+    s.set("enabled", &mut c.enabled)?;
+    s.set("launcher", &mut c.launcher)?;
+    s.set("glow_is_inside", &mut c.glow_is_inside)?;
+    s.set("placement", &mut c.placement)?;
+    s.set("app_dirs", &mut c.app_dirs)?;
+    s.set("remember_query", &mut c.remember_query)?;
+    s.set("remember_query_ms", &mut c.remember_query_ms)?;
+    s.set("scroll_anywhere", &mut c.scroll_anywhere)?;
+    s.set("max_results", &mut c.max_results)?;
+    s.set("recent_files", &mut c.recent_files)?;
+    s.set("usage_stats", &mut c.usage_stats)?;
+
+    if let Some(mut layout) = s.section("layout")? {
+        read_command_center_layout(&mut layout, &mut c.layout)?;
+        layout.finish()?;
+    }
+    Ok(())
+}
+
+fn read_command_center_layout(s: &mut Section, c: &mut CommandCenterLayoutConfig) -> Result<()> {
+    s.set("width", &mut c.width)?;
+    s.set("height", &mut c.height)?;
+    s.set("max_width", &mut c.max_width)?;
+    s.set("max_height", &mut c.max_height)?;
+    s.set("anchor", &mut c.anchor)?;
+    s.set("offset_y", &mut c.offset_y)?;
+    s.set("padding", &mut c.padding)?;
+    s.set("card_width", &mut c.card_width)?;
+    s.set("card_height", &mut c.card_height)
+}
+
+fn read_focus_ring(s: &mut Section, c: &mut FocusRingConfig) -> Result<()> {
+    s.set("enabled", &mut c.enabled)?;
+    s.set("duration_ms", &mut c.duration_ms)?;
+    s.set("color", &mut c.color)
+}
+
+fn read_recording(s: &mut Section, c: &mut RecordingConfig) -> Result<()> {
+    s.set("indicator", &mut c.indicator)?;
+    s.set("indicator_corner", &mut c.indicator_corner)?;
+    s.set("hide_popups", &mut c.hide_popups)?;
+    s.set("calm_animations", &mut c.calm_animations)
+}
+
+fn read_focus_fade(s: &mut Section, c: &mut FocusFadeConfig) -> Result<()> {
+    s.set("enabled", &mut c.enabled)?;
+    s.set("unfocused_dim", &mut c.unfocused_dim)?;
+    s.set("focus_ms", &mut c.focus_ms)?;
+    s.set("blur_ms", &mut c.blur_ms)
+}
+
+fn read_titlebars(s: &mut Section, c: &mut TitlebarConfig) -> Result<()> {
+    s.set("enabled", &mut c.enabled)?;
+    s.set("height", &mut c.height)?;
+    s.set("apps", &mut c.apps)
+}
+
+fn read_colors(s: &mut Section, c: &mut Colors) -> Result<()> {
+    s.set("background", &mut c.background)?;
+    s.set("border_focused", &mut c.border_focused)?;
+    s.set("border_unfocused", &mut c.border_unfocused)?;
+    s.set("command_center_bg", &mut c.command_center_bg)?;
+    s.set("accent", &mut c.accent)
+}
+
+/// A table being read - keys are taken out as they're used, so whatever's
+/// left at the end is unknown
+struct Section {
+    table: Table,
+    path: String,
+}
+
+impl Section {
+    fn new(table: Table, path: &str) -> Self {
+        Self { table, path: path.to_string() }
+    }
+
+    /// Full dotted name of a key, for errors
+    fn key(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", self.path, key)
+        }
+    }
+
+    /// Overwrite `target` if the key is there
+    fn set<T: FromToml>(&mut self, key: &str, target: &mut T) -> Result<()> {
+        if let Some(value) = self.table.remove(key) {
+            *target = T::from_toml(&value).map_err(|e| anyhow!("{}: {}", self.key(key), e))?;
+        }
+        Ok(())
+    }
+
+    /// Like set, but the key has to be there
+    fn require<T: FromToml>(&mut self, key: &str, target: &mut T) -> Result<()> {
+        if !self.table.contains_key(key) {
+            bail!("{}: missing", self.key(key));
+        }
+        self.set(key, target)
+    }
+
+    /// A table of name = value, as pairs in file order
+    fn set_map<T: FromToml>(&mut self, key: &str, target: &mut Vec<(String, T)>) -> Result<()> {
+        let Some(mut section) = self.section(key)? else {
+            return Ok(());
+        };
+
+        let table = std::mem::take(&mut section.table);
+        *target = table
+            .into_iter()
+            .map(|(name, value)| {
+                let parsed = T::from_toml(&value).map_err(|e| anyhow!("{}: {}", section.key(&name), e))?;
+                Ok((name, parsed))
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+
+    /// A nested table
+    fn section(&mut self, key: &str) -> Result<Option<Section>> {
+        match self.table.remove(key) {
+            None => Ok(None),
+            Some(Value::Table(table)) => Ok(Some(Section::new(table, &self.key(key)))),
+            Some(other) => bail!("{}: expected a table, got {}", self.key(key), other.type_str()),
+        }
+    }
+
+    /// An array of tables ([[name]])
+    fn tables(&mut self, key: &str) -> Result<Option<Vec<Section>>> {
+        let items = match self.table.remove(key) {
+            None => return Ok(None),
+            Some(Value::Array(items)) => items,
+            Some(other) => bail!("{}: expected an array of tables, got {}", self.key(key), other.type_str()),
+        };
+
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| match item {
+                Value::Table(table) => Ok(Section::new(table, &format!("{}[{}]", self.key(key), i))),
+                other => bail!("{}[{}]: expected a table, got {}", self.key(key), i, other.type_str()),
+            })
+            .collect::<Result<_>>()
+            .map(Some)
+    }
+
+    /// Error on anything that wasn't read
+    fn finish(self) -> Result<()> {
+        match self.table.keys().next() {
+            Some(key) => bail!("{}: unknown key", self.key(key)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A config value that can come out of TOML
+trait FromToml: Sized {
+    fn from_toml(value: &Value) -> Result<Self, String>;
+}
+
+fn expected<T>(what: &str, value: &Value) -> Result<T, String> {
+    Err(format!("expected {}, got {}", what, value.type_str()))
+}
+
+impl FromToml for bool {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        value.as_bool().map_or_else(|| expected("true or false", value), Ok)
+    }
+}
+
+impl FromToml for String {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        value.as_str().map_or_else(|| expected("a string", value), |s| Ok(s.to_string()))
+    }
+}
+
+impl FromToml for PathBuf {
+    /// `~/` expands to $HOME
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        let path = String::from_toml(value)?;
+        match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+            (Some(rest), Some(home)) => Ok(PathBuf::from(home).join(rest)),
+            _ => Ok(PathBuf::from(path)),
+        }
+    }
+}
+
+macro_rules! integer_from_toml {
+    ($($ty:ty),*) => {$(
+        impl FromToml for $ty {
+            fn from_toml(value: &Value) -> Result<Self, String> {
+                let Some(n) = value.as_integer() else {
+                    return expected("an integer", value);
+                };
+                <$ty>::try_from(n).map_err(|_| format!("{} is out of range", n))
+            }
+        }
+    )*};
+}
+integer_from_toml!(i32, u16, u64, usize);
+
+impl FromToml for f64 {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Float(f) => Ok(*f),
+            Value::Integer(n) => Ok(*n as f64),
+            _ => expected("a number", value),
+        }
+    }
+}
+
+impl FromToml for f32 {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        f64::from_toml(value).map(|f| f as f32)
+    }
+}
+
+/// `false` turns an optional setting off
+impl<T: FromToml> FromToml for Option<T> {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Boolean(false) => Ok(None),
+            _ => T::from_toml(value).map(Some),
+        }
+    }
+}
+
+impl<T: FromToml> FromToml for Vec<T> {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        let Some(items) = value.as_array() else {
+            return expected("an array", value);
+        };
+        items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| T::from_toml(item).map_err(|e| format!("[{}]: {}", i, e)))
+            .collect()
+    }
+}
+
+impl<A: FromToml, B: FromToml> FromToml for (A, B) {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        match value.as_array().map(Vec::as_slice) {
+            Some([a, b]) => Ok((A::from_toml(a)?, B::from_toml(b)?)),
+            _ => expected("a pair [a, b]", value),
+        }
+    }
+}
+
+/// A color: [r, g, b, a] in 0..1, or "#rrggbb" / "#rrggbbaa"
+impl FromToml for [f32; 4] {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        if let Some(hex) = value.as_str() {
+            return parse_hex_color(hex).ok_or_else(|| format!("\"{}\" isn't #rrggbb or #rrggbbaa", hex));
+        }
+
+        let channels: Vec<f32> = Vec::from_toml(value)
+            .map_err(|_| "expected [r, g, b, a] or \"#rrggbbaa\"".to_string())?;
+        match channels[..] {
+            [r, g, b, a] if channels.iter().all(|c| (0.0..=1.0).contains(c)) => Ok([r, g, b, a]),
+            [_, _, _, _] => Err("color channels go from 0.0 to 1.0".to_string()),
+            _ => Err(format!("expected 4 channels [r, g, b, a], got {}", channels.len())),
+        }
+    }
+}
+
+/// "#rrggbb" or "#rrggbbaa" to 0..1 channels
+pub fn parse_hex_color(hex: &str) -> Option<[f32; 4]> {
+    let digits = hex.strip_prefix('#')?;
+    if !matches!(digits.len(), 6 | 8) || !digits.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok().map(|c| c as f32 / 255.0);
+    let alpha = if digits.len() == 8 { channel(6)? } else { 1.0 };
+    Some([channel(0)?, channel(2)?, channel(4)?, alpha])
+}
+
+/// 800 (pixels) or "60%" (of the output)
+impl FromToml for Dimension {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        if let Some(px) = value.as_integer() {
+            return i32::try_from(px)
+                .map(Dimension::Pixels)
+                .map_err(|_| format!("{} is out of range", px));
+        }
+
+        let percent = value
+            .as_str()
+            .and_then(|s| s.strip_suffix('%'))
+            .and_then(|n| n.trim().parse::<f32>().ok());
+        match percent {
+            Some(p) if (0.0..=100.0).contains(&p) => Ok(Dimension::Percent(p)),
+            Some(_) => Err("percentages go from 0% to 100%".to_string()),
+            None => expected("pixels (800) or a percentage (\"60%\")", value),
+        }
+    }
+}
+
+impl FromToml for Keybind {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        let chord = String::from_toml(value)?;
+        Keybind::parse(&chord).ok_or_else(|| format!("\"{}\" isn't a key combo like \"super+r\"", chord))
+    }
+}
+
+impl FromToml for Action {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        let name = String::from_toml(value)?;
+        Action::from_config_name(&name).ok_or_else(|| format!("unknown action \"{}\"", name))
+    }
+}
+
+/// One combo, a list of them, or false for none
+struct Combos(Vec<Keybind>);
+
+impl FromToml for Combos {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Boolean(false) => Ok(Combos(Vec::new())),
+            Value::Array(_) => Vec::from_toml(value).map(Combos),
+            _ => Keybind::from_toml(value).map(|combo| Combos(vec![combo])),
+        }
+    }
+}
+
+/// Enums are lowercase names with dashes
+fn one_of<T: Copy>(value: &Value, names: &[(&str, T)]) -> Result<T, String> {
+    let name = String::from_toml(value)?;
+    names
+        .iter()
+        .find(|(n, _)| *n == name.to_lowercase().replace('_', "-"))
+        .map(|(_, v)| *v)
+        .ok_or_else(|| {
+            let options: Vec<&str> = names.iter().map(|(n, _)| *n).collect();
+            format!("\"{}\" isn't one of {}", name, options.join(", "))
+        })
+}
+
+impl FromToml for EmptyDesktopAction {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        one_of(value, &[
+            ("command-center", Self::CommandCenter),
+            ("help-overlay", Self::HelpOverlay),
+            ("nothing", Self::Nothing),
+        ])
+    }
+}
+
+/// "full", "reduced" or "none" - true/false still work for full/none
+impl FromToml for AnimationLevel {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Boolean(true) => Ok(Self::Full),
+            Value::Boolean(false) => Ok(Self::None),
+            _ => one_of(value, &[("full", Self::Full), ("reduced", Self::Reduced), ("none", Self::None)]),
+        }
+    }
+}
+
+impl FromToml for ResizeModeStyle {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        one_of(value, &[("hold", Self::Hold), ("toggle", Self::Toggle)])
+    }
+}
+
+impl FromToml for CommandCenterAnchor {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        one_of(value, &[("center", Self::Center), ("top", Self::Top), ("bottom", Self::Bottom)])
+    }
+}
+
+impl FromToml for CommandCenterPlacement {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        one_of(value, &[("pointer", Self::Pointer), ("focused", Self::Focused), ("primary", Self::Primary)])
+    }
+}
+
+impl FromToml for ScreenCorner {
+    fn from_toml(value: &Value) -> Result<Self, String> {
+        one_of(value, &[
+            ("top-left", Self::TopLeft),
+            ("top-right", Self::TopRight),
+            ("bottom-left", Self::BottomLeft),
+            ("bottom-right", Self::BottomRight),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config file under the temp dir, unique to this test process
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("vibewm-{}-{}.toml", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn reload_swaps_in_the_new_config() {
+        let path = write_config(
+            "reload-ok",
+            "outer_gap = 30\n[env]\nQT_QPA_PLATFORM = \"wayland\"\n[command_center]\napp_dirs = [\"/opt/apps\"]",
+        );
+        let mut config = Config { inner_gap: 99, ..Config::default() };
+        config.env = vec![("GDK_BACKEND".to_string(), "x11".to_string())];
+
+        config.reload_from(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(config.outer_gap, 30);
+        // Keys the file leaves out go back to the defaults, not the old values
+        assert_eq!(config.inner_gap, Config::default().inner_gap);
+
+        // apply_config hands these to launch and the command center
+        assert_eq!(config.env, vec![("QT_QPA_PLATFORM".to_string(), "wayland".to_string())]);
+        assert_eq!(config.command_center.app_dirs, vec![PathBuf::from("/opt/apps")]);
+    }
+
+    #[test]
+    fn reload_with_one_bad_key_keeps_the_old_config() {
+        // The first key is fine - it mustn't be applied on its own either
+        let path = write_config("reload-bad-key", "outer_gap = 30\ninner_gap = \"wide\"");
+        let mut config = Config { outer_gap: 17, ..Config::default() };
+
+        let err = config.reload_from(&path).unwrap_err();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(err.root_cause().to_string(), "inner_gap: expected an integer, got string");
+        assert_eq!(config.outer_gap, 17);
+    }
+
+    #[test]
+    fn reload_of_broken_or_missing_file_keeps_the_old_config() {
+        let path = write_config("reload-bad-toml", "outer_gap = ");
+        let mut config = Config { outer_gap: 17, ..Config::default() };
+
+        assert!(config.reload_from(&path).is_err());
+        std::fs::remove_file(&path).ok();
+        assert!(config.reload_from(&path).is_err());
+
+        assert_eq!(config.outer_gap, 17);
+    }
+
+    #[test]
+    fn keybinds_replace_the_stock_combos_for_their_action() {
+        let config = Config::from_toml_str(
+            r#"
+            [keybinds]
+            close_window = "super+x"
+            quit = ["super+shift+q", "ctrl+alt+Delete"]
+            zoom_in = false
+            workspace_2 = "super+F2"
+            "#,
+        )
+        .unwrap();
+
+        let combos = |action: &Action| -> Vec<String> {
+            config.keybinds.iter().filter(|(_, a)| a == action).map(|(k, _)| k.label()).collect()
+        };
+        assert_eq!(combos(&Action::CloseWindow), ["mod+x"]);
+        assert_eq!(combos(&Action::Quit), ["mod+shift+q", "ctrl+alt+Delete"]);
+        assert!(combos(&Action::ZoomIn).is_empty());
+        assert_eq!(combos(&Action::SwitchWorkspace(1)), ["mod+F2"]);
+        // Untouched actions keep theirs
+        assert_eq!(combos(&Action::ToggleSticky), ["mod+shift+S"]);
+    }
+
+    #[test]
+    fn keybinds_by_combo_rebind_just_that_combo() {
+        let config = Config::from_toml_str(
+            r#"
+            [keybinds]
+            "Super+Shift+q" = "quit"
+            "super+w" = "toggle_sticky"
+            "super+b" = false
+            "#,
+        )
+        .unwrap();
+
+        let lookup = |chord: &str| {
+            let combo = Keybind::parse(chord).unwrap();
+            config.keybinds.iter().rev().find(|(k, _)| *k == combo).map(|(_, a)| a.clone())
+        };
+        assert_eq!(lookup("super+shift+q"), Some(Action::Quit));
+        // The stock combo stays - it's the combo that's rebound, not the action
+        assert_eq!(lookup("super+q"), Some(Action::Quit));
+        assert_eq!(lookup("super+w"), Some(Action::ToggleSticky));
+        assert_eq!(lookup("super+b"), None);
+        assert!(!config.keybinds.iter().any(|(_, a)| *a == Action::CloseWindow));
+    }
+
+    #[test]
+    fn keybind_errors_name_the_action() {
+        let err = Config::from_toml_str("[keybinds]\nclose_windw = \"super+x\"").unwrap_err();
+        assert_eq!(err.to_string(), "keybinds.close_windw: unknown action or key combo");
+
+        let err = Config::from_toml_str("[keybinds]\n\"super+x\" = \"close_windw\"").unwrap_err();
+        assert_eq!(err.to_string(), "keybinds.super+x: unknown action \"close_windw\"");
+
+        let err = Config::from_toml_str("[keybinds]\nquit = \"hyper+q\"").unwrap_err();
+        assert_eq!(err.to_string(), "keybinds.quit: \"hyper+q\" isn't a key combo like \"super+r\"");
+    }
+
+    #[test]
+    fn empty_file_is_the_defaults() {
+        let config = Config::from_toml_str("").unwrap();
+        let default = Config::default();
+        assert_eq!(config.outer_gap, default.outer_gap);
+        assert_eq!(config.colors.accent, default.colors.accent);
+    }
+
+    #[test]
+    fn overrides_nested_keys_and_keeps_the_rest() {
+        let config = Config::from_toml_str(
+            r##"
+            outer_gap = 4
+            default_window_size = ["50%", 600]
+
+            [command_center.layout]
+            max_width = false
+            anchor = "top"
+
+            [colors]
+            accent = "#ff000080"
+            background = [0.1, 0.2, 0.3, 1.0]
+
+            [env]
+            QT_QPA_PLATFORM = "wayland"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(config.outer_gap, 4);
+        assert_eq!(config.inner_gap, Config::default().inner_gap);
+        assert_eq!(config.default_window_size, Some((Dimension::Percent(50.0), Dimension::Pixels(600))));
+        assert_eq!(config.command_center.layout.max_width, None);
+        assert_eq!(config.command_center.layout.anchor, CommandCenterAnchor::Top);
+        assert_eq!(config.colors.accent, [1.0, 0.0, 0.0, 128.0 / 255.0]);
+        assert_eq!(config.colors.background, [0.1, 0.2, 0.3, 1.0]);
+        assert_eq!(config.env, vec![("QT_QPA_PLATFORM".to_string(), "wayland".to_string())]);
+    }
+
+    #[test]
+    fn errors_name_the_key() {
+        let err = Config::from_toml_str("[colors]\naccent = \"pink\"").unwrap_err();
+        assert!(err.to_string().starts_with("colors.accent:"), "{}", err);
+
+        let err = Config::from_toml_str("[focus_ring]\nduraton_ms = 10").unwrap_err();
+        assert_eq!(err.to_string(), "focus_ring.duraton_ms: unknown key");
+
+        let err = Config::from_toml_str("outer_gap = \"wide\"").unwrap_err();
+        assert_eq!(err.to_string(), "outer_gap: expected an integer, got string");
+    }
+
+    #[test]
+    fn animation_levels_and_the_old_bool() {
+        let level = |toml: &str| Config::from_toml_str(toml).map(|c| c.animations);
+        assert_eq!(level("animations = \"reduced\"").unwrap(), AnimationLevel::Reduced);
+        assert_eq!(level("animations = \"none\"").unwrap(), AnimationLevel::None);
+        assert_eq!(level("animations = true").unwrap(), AnimationLevel::Full);
+        assert_eq!(level("animations = false").unwrap(), AnimationLevel::None);
+
+        let err = level("animations = \"some\"").unwrap_err();
+        assert_eq!(err.to_string(), "animations: \"some\" isn't one of full, reduced, none");
+    }
+
+    #[test]
+    fn command_center_placement() {
+        let placement = |toml: &str| Config::from_toml_str(toml).map(|c| c.command_center.placement);
+        assert_eq!(placement("").unwrap(), CommandCenterPlacement::Pointer);
+        assert_eq!(placement("[command_center]\nplacement = \"focused\"").unwrap(), CommandCenterPlacement::Focused);
+
+        let err = placement("[command_center]\nplacement = \"mouse\"").unwrap_err();
+        assert_eq!(err.to_string(), "command_center.placement: \"mouse\" isn't one of pointer, focused, primary");
+    }
+
+    #[test]
+    fn bad_zoom_ranges_fall_back_to_the_defaults() {
+        let zoom = |toml: &str| Config::from_toml_str(toml).map(|c| (c.zoom_min, c.zoom_max, c.zoom_step)).unwrap();
+        let default = zoom("");
+
+        assert_eq!(zoom("zoom_min = 2.0\nzoom_max = 4"), (2.0, 4.0, default.2));
+        assert_eq!(zoom("zoom_min = 3.0\nzoom_max = 3.0"), (3.0, 3.0, default.2));
+
+        assert_eq!(zoom("zoom_min = 8.0\nzoom_max = 2.0"), default);
+        assert_eq!(zoom("zoom_max = nan"), default);
+        assert_eq!(zoom("zoom_max = inf"), default);
+        assert_eq!(zoom("zoom_min = -inf"), default);
+        assert_eq!(zoom("zoom_min = 0"), default);
+        assert_eq!(zoom("zoom_step = nan"), default);
+    }
+
+    #[test]
+    fn app_env_matches_desktop_id_or_name() {
+        let config = Config::from_toml_str(
+            "[app_env.firefox]\nMOZ_ENABLE_WAYLAND = \"1\"\n[app_env.\"org.gnome.Maps\"]\nGSK_RENDERER = \"gl\"",
+        )
+        .unwrap();
+        let vars = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+        };
+
+        let firefox = vars(&[("MOZ_ENABLE_WAYLAND", "1")]);
+        assert_eq!(config.env_for_app(Some("firefox"), None), firefox);
+        assert_eq!(config.env_for_app(None, Some("Firefox")), firefox);
+        assert_eq!(config.env_for_app(Some("org.mozilla.firefox"), Some("FIREFOX")), firefox);
+
+        assert_eq!(config.env_for_app(Some("org.gnome.Maps"), Some("Maps")), vars(&[("GSK_RENDERER", "gl")]));
+        assert!(config.env_for_app(Some("foot"), Some("Foot")).is_empty());
+        assert!(config.env_for_app(None, None).is_empty());
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(parse_hex_color("#ffffff"), Some([1.0, 1.0, 1.0, 1.0]));
+        assert_eq!(parse_hex_color("#00000000"), Some([0.0, 0.0, 0.0, 0.0]));
+        assert_eq!(parse_hex_color("ffffff"), None);
+        assert_eq!(parse_hex_color("#fff"), None);
+        assert_eq!(parse_hex_color("#gggggg"), None);
+    }
+}
@@ -0,0 +1,321 @@
+//! Window lists for taskbars - ext-foreign-toplevel-list-v1 and
+//! wlr-foreign-toplevel-management-unstable-v1
+//!
+//! The ext list is read-only (smithay implements it). The wlr one also lets
+//! bars activate, close and minimize windows - that one's done by hand here.
+//! Both are kept in sync once a frame from WindowManager, the same way the
+//! script hooks pick up title changes.
+
+use smithay::{
+    desktop::Window,
+    output::Output,
+    reexports::{
+        wayland_protocols_wlr::foreign_toplevel::v1::server::{
+            zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+            zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+        },
+        wayland_server::{
+            backend::ClientId, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+        },
+    },
+    wayland::foreign_toplevel_list::{
+        ForeignToplevelHandle, ForeignToplevelListHandler, ForeignToplevelListState,
+    },
+};
+
+use crate::config::SnapPosition;
+use crate::state::VibeWM;
+use crate::window::{window_app_id, window_title};
+
+type ToplevelState = zwlr_foreign_toplevel_handle_v1::State;
+
+/// Highest zwlr_foreign_toplevel_manager_v1 version we speak
+const WLR_VERSION: u32 = 3;
+
+/// What a bar gets told about one window
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    title: String,
+    app_id: String,
+    states: Vec<ToplevelState>,
+    outputs: Vec<Output>,
+}
+
+/// One window as published to bars
+struct Published {
+    /// WindowMeta id - also the user data on every wlr handle
+    id: u64,
+    ext: ForeignToplevelHandle,
+    handles: Vec<ZwlrForeignToplevelHandleV1>,
+    snapshot: Snapshot,
+}
+
+/// Both foreign toplevel globals and what's been sent on them
+pub struct ForeignToplevels {
+    pub list_state: ForeignToplevelListState,
+    managers: Vec<ZwlrForeignToplevelManagerV1>,
+    published: Vec<Published>,
+}
+
+impl ForeignToplevels {
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<VibeWM, ZwlrForeignToplevelManagerV1, ()>(WLR_VERSION, ());
+
+        Self {
+            list_state: ForeignToplevelListState::new::<VibeWM>(display_handle),
+            managers: Vec::new(),
+            published: Vec::new(),
+        }
+    }
+
+    /// Publish a new window, or send whatever changed about a known one
+    fn sync(&mut self, display_handle: &DisplayHandle, id: u64, snapshot: Snapshot) {
+        let Some(published) = self.published.iter_mut().find(|p| p.id == id) else {
+            let ext = self.list_state.new_toplevel::<VibeWM>(&snapshot.title, &snapshot.app_id);
+            let handles = self.managers
+                .iter()
+                .filter_map(|manager| announce(display_handle, manager, id, &snapshot))
+                .collect();
+            self.published.push(Published { id, ext, handles, snapshot });
+            return;
+        };
+
+        if published.snapshot == snapshot {
+            return;
+        }
+        let old = std::mem::replace(&mut published.snapshot, snapshot);
+        let new = &published.snapshot;
+
+        if old.title != new.title || old.app_id != new.app_id {
+            if old.title != new.title {
+                published.ext.send_title(&new.title);
+            }
+            if old.app_id != new.app_id {
+                published.ext.send_app_id(&new.app_id);
+            }
+            published.ext.send_done();
+        }
+
+        for handle in &published.handles {
+            if old.title != new.title {
+                handle.title(new.title.clone());
+            }
+            if old.app_id != new.app_id {
+                handle.app_id(new.app_id.clone());
+            }
+            if old.states != new.states {
+                handle.state(encode_states(&new.states));
+            }
+            if old.outputs != new.outputs {
+                send_outputs(handle, &old.outputs, &new.outputs);
+            }
+            handle.done();
+        }
+    }
+
+    /// Tell bars about windows that are gone
+    fn retain(&mut self, live: &[u64]) {
+        let (gone, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.published)
+            .into_iter()
+            .partition(|p| !live.contains(&p.id));
+        self.published = kept;
+
+        for published in gone {
+            self.list_state.remove_toplevel(&published.ext);
+            for handle in published.handles {
+                handle.closed();
+            }
+        }
+    }
+}
+
+/// Create a handle for one window on one manager and send it everything
+fn announce(
+    display_handle: &DisplayHandle,
+    manager: &ZwlrForeignToplevelManagerV1,
+    id: u64,
+    snapshot: &Snapshot,
+) -> Option<ZwlrForeignToplevelHandleV1> {
+    let client = manager.client()?;
+    let handle = client
+        .create_resource::<ZwlrForeignToplevelHandleV1, u64, VibeWM>(display_handle, manager.version(), id)
+        .ok()?;
+
+    manager.toplevel(&handle);
+    handle.title(snapshot.title.clone());
+    handle.app_id(snapshot.app_id.clone());
+    handle.state(encode_states(&snapshot.states));
+    send_outputs(&handle, &[], &snapshot.outputs);
+    handle.done();
+
+    Some(handle)
+}
+
+/// output_enter/output_leave for the outputs that changed
+fn send_outputs(handle: &ZwlrForeignToplevelHandleV1, old: &[Output], new: &[Output]) {
+    let Some(client) = handle.client() else { return };
+
+    for output in old.iter().filter(|o| !new.contains(o)) {
+        for wl_output in output.client_outputs(&client) {
+            handle.output_leave(&wl_output);
+        }
+    }
+    for output in new.iter().filter(|o| !old.contains(o)) {
+        for wl_output in output.client_outputs(&client) {
+            handle.output_enter(&wl_output);
+        }
+    }
+}
+
+/// The state event wants an array of native-endian u32s
+fn encode_states(states: &[ToplevelState]) -> Vec<u8> {
+    states
+        .iter()
+        .flat_map(|state| (*state as u32).to_ne_bytes())
+        .collect()
+}
+
+impl VibeWM {
+    /// Send bars this frame's window list - new windows, title/app_id/state
+    /// changes and closed windows
+    pub fn update_foreign_toplevels(&mut self) {
+        let focused = self.windows.focused().cloned();
+        let mut live = Vec::new();
+
+        for window in self.windows.all().to_vec() {
+            let Some(meta) = self.windows.meta(&window) else { continue };
+            live.push(meta.id);
+
+            let mut states = Vec::new();
+            if meta.minimized {
+                states.push(ToplevelState::Minimized);
+            } else if focused.as_ref() == Some(&window) {
+                states.push(ToplevelState::Activated);
+            }
+            if meta.snap_state == Some(SnapPosition::Maximize) {
+                states.push(ToplevelState::Maximized);
+            }
+
+            // Parked and minimized windows stay listed on the output they left
+            let outputs = if self.space.element_location(&window).is_some() {
+                self.space.outputs_for_element(&window)
+            } else {
+                self.foreign_toplevels
+                    .published
+                    .iter()
+                    .find(|p| p.id == meta.id)
+                    .map(|p| p.snapshot.outputs.clone())
+                    .unwrap_or_default()
+            };
+
+            let snapshot = Snapshot {
+                title: window_title(&window),
+                app_id: window_app_id(&window).unwrap_or_default(),
+                states,
+                outputs,
+            };
+            self.foreign_toplevels.sync(&self.display_handle, meta.id, snapshot);
+        }
+
+        self.foreign_toplevels.retain(&live);
+    }
+
+    /// A bar clicked a window - bring up its workspace, restore and focus it
+    pub fn activate_window(&mut self, window: &Window) {
+        let Some(workspace) = self.windows.meta(window).map(|m| m.workspace) else {
+            return;
+        };
+
+        self.switch_workspace(workspace);
+        self.windows.unminimize(window, &mut self.space);
+        self.focus_and_raise(window);
+    }
+}
+
+impl ForeignToplevelListHandler for VibeWM {
+    fn foreign_toplevel_list_state(&mut self) -> &mut ForeignToplevelListState {
+        &mut self.foreign_toplevels.list_state
+    }
+}
+
+impl GlobalDispatch<ZwlrForeignToplevelManagerV1, ()> for VibeWM {
+    fn bind(
+        state: &mut Self,
+        display_handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrForeignToplevelManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let manager = data_init.init(resource, ());
+
+        // Everything that's already open
+        let toplevels = &mut state.foreign_toplevels;
+        for published in &mut toplevels.published {
+            if let Some(handle) = announce(display_handle, &manager, published.id, &published.snapshot) {
+                published.handles.push(handle);
+            }
+        }
+        toplevels.managers.push(manager);
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for VibeWM {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        manager: &ZwlrForeignToplevelManagerV1,
+        request: zwlr_foreign_toplevel_manager_v1::Request,
+        _data: &(),
+        _display_handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Request::Stop = request {
+            state.foreign_toplevels.managers.retain(|m| m != manager);
+            manager.finished();
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, manager: &ZwlrForeignToplevelManagerV1, _data: &()) {
+        state.foreign_toplevels.managers.retain(|m| m != manager);
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, u64> for VibeWM {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _handle: &ZwlrForeignToplevelHandleV1,
+        request: zwlr_foreign_toplevel_handle_v1::Request,
+        id: &u64,
+        _display_handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        use zwlr_foreign_toplevel_handle_v1::Request;
+
+        let Some(window) = state.windows.by_id(*id).cloned() else {
+            return;
+        };
+
+        match request {
+            Request::Activate { .. } | Request::UnsetMinimized => state.activate_window(&window),
+            Request::Close => {
+                if let Some(toplevel) = window.toplevel() {
+                    toplevel.send_close();
+                }
+            }
+            Request::SetMinimized => state.windows.minimize(&window, &mut state.space),
+            Request::SetMaximized => {
+                state.snap_window(&window, SnapPosition::Maximize);
+            }
+            // No fullscreen yet, and no minimize animation to aim with set_rectangle
+            _ => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, handle: &ZwlrForeignToplevelHandleV1, _id: &u64) {
+        for published in &mut state.foreign_toplevels.published {
+            published.handles.retain(|h| h != handle);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! GPU reset recovery - shared by both backends
+//!
+//! Suspend/resume and driver resets can take the EGL context with them.
+//! A render error gets treated as a lost context: it's counted, the frame is
+//! dropped, and the backend recovers - DRM rebuilds its renderer and output
+//! surfaces, winit waits for the host to bring its context back. A GPU that
+//! keeps doing it stops the compositor instead of being retried forever.
+
+use std::time::{Duration, Instant};
+
+/// How many GPU resets we tolerate per minute before giving up on the hardware renderer
+const MAX_GPU_RESETS_PER_MINUTE: usize = 3;
+
+/// What to do after a GPU reset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResetRecovery {
+    /// Tear down and rebuild the renderer on the same GPU
+    Reinitialize,
+
+    /// The GPU keeps dying - stop spinning, software rendering is the way out
+    FallBackToSoftware,
+}
+
+/// Tracks GPU resets so a flapping GPU doesn't loop forever
+#[derive(Default)]
+pub struct GpuResetTracker {
+    resets: Vec<Instant>,
+}
+
+impl GpuResetTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a reset and decide how to recover
+    pub fn record_reset(&mut self) -> ResetRecovery {
+        let now = Instant::now();
+        self.resets.retain(|t| now.duration_since(*t) < Duration::from_secs(60));
+        self.resets.push(now);
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::gpu_reset();
+
+        tracing::error!("!!! GPU RESET DETECTED ({} in the last minute) !!!", self.resets.len());
+
+        if self.resets.len() > MAX_GPU_RESETS_PER_MINUTE {
+            ResetRecovery::FallBackToSoftware
+        } else {
+            ResetRecovery::Reinitialize
+        }
+    }
+}
+
+/// Count a lost GPU context and decide what the backend does about it
+///
+/// The backend does the recovery itself. Client textures belong to the
+/// renderer and get imported again by a new one; vibeWM uploads nothing
+/// of its own yet. Switching to llvmpipe needs a restart - software
+/// rendering is only picked up before the first EGL display, see init_env.
+pub fn handle_gpu_reset(tracker: &mut GpuResetTracker) -> ResetRecovery {
+    let recovery = tracker.record_reset();
+    match recovery {
+        ResetRecovery::Reinitialize => {
+            tracing::warn!("Recovering the renderer after a GPU reset");
+        }
+        ResetRecovery::FallBackToSoftware => {
+            tracing::error!(
+                "GPU reset more than {} times in a minute - restart with `software_rendering = true` to use llvmpipe",
+                MAX_GPU_RESETS_PER_MINUTE
+            );
+        }
+    }
+
+    recovery
+}
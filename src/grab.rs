@@ -0,0 +1,411 @@
+//! mod+drag on a window moves it, mod+right-drag resizes it
+//!
+//! Grabs of ours - while one's on, motion and buttons stop at the compositor
+//! and the client sees none of it. Dragging a titlebar is the same move grab.
+//! Releasing the button drops the window where it is, Escape puts a moved
+//! one back.
+//!
+//! Resizing drags the edges nearest where the press landed. The client gets
+//! a new size once it has acked the last one rather than on every motion
+//! event, with the Resizing state set so it can skip the expensive bits.
+
+use smithay::{
+    desktop::Window,
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    utils::{Logical, Point, Rectangle, Serial, Size},
+    wayland::shell::xdg::Configure,
+};
+
+use crate::config::SnapPosition;
+use crate::input::resist_edge;
+use crate::state::VibeWM;
+use crate::layout::clamp_resize;
+use crate::window::{set_layout_state, size_limits, TiledEdges};
+
+/// linux/input-event-codes.h
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+
+/// Window following the pointer
+pub struct MoveGrab {
+    pub window: Window,
+
+    /// Pointer minus window location at the press
+    pub grab_offset: Point<i32, Logical>,
+
+    /// Where it was and how it was snapped - Escape goes back to that
+    pub start: Point<i32, Logical>,
+    pub snap_state: Option<SnapPosition>,
+}
+
+/// Edges a resize drags
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResizeEdges {
+    pub left: bool,
+    pub right: bool,
+    pub top: bool,
+    pub bottom: bool,
+}
+
+impl ResizeEdges {
+    const NONE: Self = Self { left: false, right: false, top: false, bottom: false };
+
+    /// From where in the window the press landed - the outer thirds drag
+    /// that side (a corner where two meet), the middle the nearest corner
+    pub fn at(rect: Rectangle<i32, Logical>, pointer: Point<f64, Logical>) -> Self {
+        let x = (pointer.x - rect.loc.x as f64) / rect.size.w.max(1) as f64;
+        let y = (pointer.y - rect.loc.y as f64) / rect.size.h.max(1) as f64;
+
+        let mut edges = Self { left: x < 1.0 / 3.0, right: x > 2.0 / 3.0, top: y < 1.0 / 3.0, bottom: y > 2.0 / 3.0 };
+        if edges == Self::NONE {
+            edges = Self { left: x < 0.5, right: x >= 0.5, top: y < 0.5, bottom: y >= 0.5 };
+        }
+        edges
+    }
+}
+
+/// What a button does to a grab in progress
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MidGrab {
+    /// Goes nowhere - not to the client, not to a new grab
+    Swallow,
+    End,
+}
+
+/// Any release ends a grab, whichever button started it - other presses
+/// mid-drag are swallowed
+fn mid_grab_button(pressed: bool) -> MidGrab {
+    if pressed {
+        MidGrab::Swallow
+    } else {
+        MidGrab::End
+    }
+}
+
+/// Window being resized
+pub struct ResizeGrab {
+    pub window: Window,
+    pub edges: ResizeEdges,
+
+    /// Pointer position and window geometry at the press
+    pub start_pointer: Point<f64, Logical>,
+    pub start: Rectangle<i32, Logical>,
+
+    /// Size the pointer asks for, and the last one sent
+    pub wanted: Size<i32, Logical>,
+    pub sent: Size<i32, Logical>,
+
+    /// Configure the client hasn't acked yet - nothing more goes out until it does
+    pub awaiting: Option<Serial>,
+
+    /// Button's up - only waiting for the client to draw the last size, so
+    /// the left/top edges land where they should
+    pub released: bool,
+}
+
+impl ResizeGrab {
+    /// Where the window goes at a size - the edges not being dragged stay put
+    fn location_for(&self, size: Size<i32, Logical>) -> Point<i32, Logical> {
+        let mut location = self.start.loc;
+        if self.edges.left {
+            location.x += self.start.size.w - size.w;
+        }
+        if self.edges.top {
+            location.y += self.start.size.h - size.h;
+        }
+        location
+    }
+}
+
+/// How far a dragged window follows the pointer - edge resistance holds it
+/// flush until the pointer is `resistance` px past the edge. The pointer
+/// carries the whole overshoot, so nothing piles up between motion events
+fn drag_delta(
+    outputs: &[Rectangle<i32, Logical>],
+    window: Rectangle<i32, Logical>,
+    delta: Point<i32, Logical>,
+    resistance: i32,
+) -> Point<i32, Logical> {
+    resist_edge(outputs, window, delta, &mut Point::from((0, 0)), resistance)
+}
+
+impl VibeWM {
+    /// Pointer button - starts a move on mod+left press over a window, ends
+    /// it on release. Returns whether the button was ours
+    pub fn move_grab_button(&mut self, button: u32, pressed: bool) -> bool {
+        if self.move_grab.is_some() {
+            if mid_grab_button(pressed) == MidGrab::End {
+                self.move_grab = None;
+            }
+            return true;
+        }
+
+        let mod_held = self.seat.get_keyboard().is_some_and(|kb| kb.modifier_state().logo);
+        if !pressed || !mod_held || button != BTN_LEFT {
+            return false;
+        }
+
+        // Only the spotlighted window in spotlight mode
+        let pointer = self.input.pointer_pos;
+        let under = self.space.element_under(pointer).map(|(window, _)| window.clone());
+        let Some(window) = under.filter(|w| self.spotlight.as_ref().map_or(true, |s| s == w)) else {
+            return false;
+        };
+
+        self.focus_and_raise(&window);
+        self.start_move_grab(window);
+        true
+    }
+
+    /// The window follows the pointer until a button comes up
+    pub fn start_move_grab(&mut self, window: Window) {
+        let Some(start) = self.space.element_location(&window) else { return };
+
+        let snap_state = self.windows.meta(&window).and_then(|m| m.snap_state);
+        self.move_grab = Some(MoveGrab {
+            grab_offset: self.input.pointer_pos.to_i32_round() - start,
+            window,
+            start,
+            snap_state,
+        });
+    }
+
+    /// Pointer moved mid-drag - move_window un-snaps it on the way
+    pub fn move_grab_motion(&mut self) {
+        let Some(grab) = &self.move_grab else { return };
+        let window = grab.window.clone();
+        let wanted = self.input.pointer_pos.to_i32_round() - grab.grab_offset;
+        let (Some(location), Some(geometry)) =
+            (self.space.element_location(&window), self.space.element_geometry(&window))
+        else {
+            return;
+        };
+
+        let delta = drag_delta(&self.output_rects(), geometry, wanted - location, self.config.edge_resistance);
+        self.move_window(&window, location + delta);
+    }
+
+    /// Pointer button - starts a resize on mod+right press over a window,
+    /// ends it on release. Returns whether the button was ours
+    pub fn resize_grab_button(&mut self, button: u32, pressed: bool) -> bool {
+        if self.resize_grab.as_ref().is_some_and(|grab| !grab.released) {
+            if mid_grab_button(pressed) == MidGrab::End {
+                self.finish_resize_grab();
+            }
+            return true;
+        }
+
+        // Mid-move the move grab has the buttons
+        let mod_held = self.seat.get_keyboard().is_some_and(|kb| kb.modifier_state().logo);
+        if self.move_grab.is_some() || !pressed || !mod_held || button != BTN_RIGHT {
+            return false;
+        }
+
+        let pointer = self.input.pointer_pos;
+        let under = self.space.element_under(pointer).map(|(window, _)| window.clone());
+        let Some(window) = under.filter(|w| self.spotlight.as_ref().map_or(true, |s| s == w)) else {
+            return false;
+        };
+        let Some(start) = self.space.element_geometry(&window) else {
+            return false;
+        };
+
+        self.focus_and_raise(&window);
+
+        // Dragged by hand - no longer snapped or tiled
+        if let Some(meta) = self.windows.meta_mut(&window) {
+            meta.snap_state = None;
+        }
+        let bounds = self.work_area().map(|area| area.size);
+        set_layout_state(&window, TiledEdges::NONE, false, bounds);
+
+        self.resize_grab = Some(ResizeGrab {
+            edges: ResizeEdges::at(start, pointer),
+            start_pointer: pointer,
+            start,
+            wanted: start.size,
+            sent: start.size,
+            awaiting: None,
+            released: false,
+            window,
+        });
+        self.send_resize(true);
+        true
+    }
+
+    /// Pointer moved mid-resize
+    pub fn resize_grab_motion(&mut self) {
+        let pointer = self.input.pointer_pos;
+        let Some(grab) = self.resize_grab.as_mut().filter(|grab| !grab.released) else { return };
+
+        let delta = (pointer - grab.start_pointer).to_i32_round();
+        let mut size = grab.start.size;
+        if grab.edges.left {
+            size.w -= delta.x;
+        } else if grab.edges.right {
+            size.w += delta.x;
+        }
+        if grab.edges.top {
+            size.h -= delta.y;
+        } else if grab.edges.bottom {
+            size.h += delta.y;
+        }
+
+        let (min, max) = size_limits(&grab.window);
+        grab.wanted = clamp_resize(size, min, max);
+
+        if grab.awaiting.is_none() {
+            self.send_resize(true);
+        }
+    }
+
+    /// Button up - one last configure without Resizing, then the grab hangs
+    /// on until that size is drawn
+    fn finish_resize_grab(&mut self) {
+        let Some(grab) = self.resize_grab.as_mut() else { return };
+        grab.released = true;
+        self.send_resize(false);
+    }
+
+    /// Send the wanted size if it's not out already (or the Resizing state changed)
+    fn send_resize(&mut self, resizing: bool) {
+        let Some(grab) = self.resize_grab.as_mut() else { return };
+        let Some(toplevel) = grab.window.toplevel() else { return };
+
+        let size = grab.wanted;
+        toplevel.with_pending_state(|state| {
+            state.size = Some(size);
+            if resizing {
+                state.states.set(xdg_toplevel::State::Resizing);
+            } else {
+                state.states.unset(xdg_toplevel::State::Resizing);
+            }
+        });
+        grab.sent = size;
+        grab.awaiting = toplevel.send_pending_configure();
+
+        let location = grab.location_for(size);
+        if let Some(meta) = self.windows.meta_mut(&grab.window) {
+            meta.applied_geometry = Some(Rectangle::new(location, size));
+        }
+    }
+
+    /// The client acked a configure - the next size can go out
+    pub fn resize_grab_ack(&mut self, window: &Window, configure: &Configure) {
+        let Configure::Toplevel(configure) = configure else { return };
+        let Some(grab) = self.resize_grab.as_mut().filter(|grab| &grab.window == window) else { return };
+        if grab.awaiting != Some(configure.serial) {
+            return;
+        }
+
+        grab.awaiting = None;
+        if !grab.released && grab.wanted != grab.sent {
+            self.send_resize(true);
+        }
+    }
+
+    /// The window committed - dragging the left or top edge moves it, by
+    /// however much the size it actually took changed
+    pub fn resize_grab_commit(&mut self, window: &Window) {
+        let Some(grab) = self.resize_grab.as_ref().filter(|grab| &grab.window == window) else { return };
+
+        if grab.edges.left || grab.edges.top {
+            let location = grab.location_for(window.geometry().size);
+            if self.space.element_geometry(window).map(|geometry| geometry.loc) != Some(location) {
+                self.space.map_element(window.clone(), location, false);
+            }
+        }
+
+        if grab.released && grab.awaiting.is_none() {
+            self.resize_grab = None;
+        }
+    }
+
+    /// Escape - back where it started, snapped again if it was
+    pub fn cancel_move_grab(&mut self) {
+        let Some(grab) = self.move_grab.take() else { return };
+        match grab.snap_state {
+            Some(position) => {
+                self.snap_window(&grab.window, position);
+            }
+            None => self.move_window(&grab.window, grab.start),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs() -> [Rectangle<i32, Logical>; 2] {
+        [
+            Rectangle::new((0, 0).into(), (1920, 1080).into()),
+            Rectangle::new((1920, 0).into(), (1920, 1080).into()),
+        ]
+    }
+
+    fn edges(left: bool, right: bool, top: bool, bottom: bool) -> ResizeEdges {
+        ResizeEdges { left, right, top, bottom }
+    }
+
+    #[test]
+    fn corners_drag_both_edges() {
+        let rect = Rectangle::new((100, 100).into(), (300, 300).into());
+        let at = |x: f64, y: f64| ResizeEdges::at(rect, (x, y).into());
+
+        assert_eq!(at(110.0, 110.0), edges(true, false, true, false));
+        assert_eq!(at(390.0, 110.0), edges(false, true, true, false));
+        assert_eq!(at(110.0, 390.0), edges(true, false, false, true));
+        assert_eq!(at(390.0, 390.0), edges(false, true, false, true));
+    }
+
+    #[test]
+    fn outer_thirds_drag_one_edge() {
+        let rect = Rectangle::new((100, 100).into(), (300, 300).into());
+        let at = |x: f64, y: f64| ResizeEdges::at(rect, (x, y).into());
+
+        assert_eq!(at(110.0, 250.0), edges(true, false, false, false));
+        assert_eq!(at(390.0, 250.0), edges(false, true, false, false));
+        assert_eq!(at(250.0, 110.0), edges(false, false, true, false));
+        assert_eq!(at(250.0, 390.0), edges(false, false, false, true));
+    }
+
+    #[test]
+    fn middle_drags_the_nearest_corner() {
+        let rect = Rectangle::new((100, 100).into(), (300, 300).into());
+        let at = |x: f64, y: f64| ResizeEdges::at(rect, (x, y).into());
+
+        assert_eq!(at(240.0, 240.0), edges(true, false, true, false));
+        assert_eq!(at(260.0, 240.0), edges(false, true, true, false));
+        assert_eq!(at(240.0, 260.0), edges(true, false, false, true));
+        assert_eq!(at(250.0, 250.0), edges(false, true, false, true));
+    }
+
+    #[test]
+    fn any_release_ends_a_grab() {
+        assert_eq!(mid_grab_button(false), MidGrab::End);
+        assert_eq!(mid_grab_button(true), MidGrab::Swallow);
+    }
+
+    #[test]
+    fn drags_stop_at_the_edge_until_pushed_through() {
+        let outputs = outputs();
+        let window = Rectangle::new((1000, 100).into(), (800, 600).into());
+
+        // Short of the edge: follows
+        assert_eq!(drag_delta(&outputs, window, (100, 0).into(), 100), Point::from((100, 0)));
+
+        // Past it: flush, however many motion events it takes
+        assert_eq!(drag_delta(&outputs, window, (170, 0).into(), 100), Point::from((120, 0)));
+        let flush = Rectangle::new((1120, 100).into(), (800, 600).into());
+        for _ in 0..3 {
+            assert_eq!(drag_delta(&outputs, flush, (99, 0).into(), 100), Point::from((0, 0)));
+        }
+
+        // Pointer far enough past: through, all the way to it
+        assert_eq!(drag_delta(&outputs, flush, (100, 40).into(), 100), Point::from((100, 40)));
+
+        // Off
+        assert_eq!(drag_delta(&outputs, window, (170, 0).into(), 0), Point::from((170, 0)));
+    }
+}
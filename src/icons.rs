@@ -0,0 +1,127 @@
+//! App icons for the command center cards
+//!
+//! A desktop file's Icon= is a path or a name to look up in the icon theme
+//! dirs: ~/.icons, then each XDG data dir + /icons, in hicolor (the theme
+//! every app installs into), nearest to the size we draw at first. Then
+//! /usr/share/pixmaps and friends. Only PNG - an app that ships nothing but
+//! an SVG gets its monogram.
+//!
+//! Decoded icons are kept by name, misses too, so scrolling through the
+//! list doesn't hit the disk again.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::command_center::data_roots;
+
+/// Theme sizes to try, best first - 48 for a 24px card at scale 2, then
+/// bigger (scaled down looks better than up)
+const SIZES: &[u32] = &[48, 64, 96, 128, 256, 512, 32, 24, 22, 16];
+
+/// Extensions a legacy Icon= may carry - the lookup wants the bare name
+const STRIP_EXTENSIONS: &[&str] = &[".png", ".svg", ".xpm"];
+
+/// A decoded icon, RGBA8
+pub struct IconImage {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Icons by Icon= value - None for ones that couldn't be found or decoded
+#[derive(Default)]
+pub struct IconCache {
+    loaded: RefCell<HashMap<String, Option<Arc<IconImage>>>>,
+
+    /// Icon dirs, looked up the first time they're needed
+    dirs: RefCell<Option<Vec<PathBuf>>>,
+}
+
+impl IconCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The icon for an Icon= value, loading it the first time
+    pub fn get(&self, icon: &str) -> Option<Arc<IconImage>> {
+        if let Some(loaded) = self.loaded.borrow().get(icon) {
+            return loaded.clone();
+        }
+
+        let dirs = self.dirs.borrow_mut().get_or_insert_with(icon_dirs).clone();
+        let image = find_icon(&dirs, icon).and_then(|path| match decode(&path) {
+            Ok(image) => Some(Arc::new(image)),
+            Err(e) => {
+                tracing::debug!("Couldn't decode icon {}: {}", path.display(), e);
+                None
+            }
+        });
+
+        self.loaded.borrow_mut().insert(icon.to_string(), image.clone());
+        image
+    }
+
+    /// Forget everything - apps were installed or removed
+    pub fn clear(&self) {
+        self.loaded.borrow_mut().clear();
+        *self.dirs.borrow_mut() = None;
+    }
+}
+
+/// Icon theme roots, most important first
+fn icon_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    home.map(|home| home.join(".icons"))
+        .into_iter()
+        .chain(data_roots().into_iter().map(|root| root.join("icons")))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// The file for an Icon= value - the path itself if it's absolute
+fn find_icon(dirs: &[PathBuf], icon: &str) -> Option<PathBuf> {
+    let icon = icon.trim();
+    if icon.is_empty() {
+        return None;
+    }
+    if icon.starts_with('/') {
+        let path = PathBuf::from(icon);
+        return path.is_file().then_some(path);
+    }
+
+    let name = STRIP_EXTENSIONS.iter().find_map(|ext| icon.strip_suffix(ext)).unwrap_or(icon);
+    if name.contains('/') {
+        return None;
+    }
+    let file = format!("{}.png", name);
+
+    let themed = SIZES.iter().find_map(|size| {
+        dirs.iter()
+            .map(|dir| dir.join(format!("hicolor/{size}x{size}/apps")).join(&file))
+            .find(|path| path.is_file())
+    });
+
+    // pixmaps sits next to icons in each data dir
+    themed.or_else(|| {
+        dirs.iter()
+            .filter(|dir| dir.file_name().is_some_and(|name| name == "icons"))
+            .filter_map(|dir| dir.parent())
+            .map(|root| root.join("pixmaps").join(&file))
+            .find(|path| path.is_file())
+    })
+}
+
+fn decode(path: &Path) -> Result<IconImage, String> {
+    let image = image::open(path).map_err(|e| e.to_string())?.into_rgba8();
+    let (width, height) = image.dimensions();
+
+    Ok(IconImage {
+        path: path.to_path_buf(),
+        width,
+        height,
+        pixels: image.into_raw(),
+    })
+}
@@ -1,23 +1,42 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
 use smithay::{
     backend::input::{
-        AbsolutePositionEvent, Axis, AxisSource, ButtonState, Event, InputBackend, InputEvent,
-        KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionEvent,
+        AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, Event, GestureBeginEvent,
+        GestureEndEvent, GestureSwipeUpdateEvent, InputBackend, InputEvent, KeyState,
+        KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionEvent,
     },
+    desktop::{Window, WindowSurfaceType},
     input::{
-        keyboard::{FilterResult, Keysym, ModifiersState},
+        keyboard::{FilterResult, Keycode, Keysym, ModifiersState},
         pointer::{AxisFrame, ButtonEvent, MotionEvent},
     },
-    utils::{Logical, Point, SERIAL_COUNTER},
+    output::Output,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, Rectangle, Size, SERIAL_COUNTER},
     wayland::seat::WaylandFocus,
 };
 
-use crate::config::SnapPosition;
+use crate::action::Action;
+use crate::command_center::{CommandCenterLayout, PowerAction, Selection};
+use crate::config::{ResizeModeStyle, SnapPosition};
+use crate::keybind::{Keybind, Mods};
+use crate::launch;
+use crate::layout::{clamp_resize, compute_snap_rect, fit_inside, SnapGaps};
+use crate::overlay::SNAP_PREVIEW;
 use crate::state::VibeWM;
-use crate::window::Direction;
+use crate::window::{initial_configure_sent, place_window, set_layout_state, size_limits, Direction, TiledEdges};
+
+/// Pixels scrolled per wheel notch
+const WHEEL_STEP: f64 = 40.0;
+
+/// Fingers for the workspace swipe
+const SWIPE_FINGERS: u32 = 3;
 
 /// Input handling state
 pub struct InputState {
-    /// Is resize mode active (mod+R held)?
+    /// Is resize mode active (mod+R held, or toggled on)?
     pub resize_mode: bool,
 
     /// Current pointer position
@@ -25,6 +44,77 @@ pub struct InputState {
 
     /// Has quit been requested?
     pub quit_requested: bool,
+
+    /// Timestamp of pointer motion not yet sent to clients (coalesced per frame)
+    pub pending_motion: Option<u32>,
+
+    /// Keys held down on the default seat's keyboards
+    pub held_keys: HeldKeys,
+
+    /// Cursor hidden (idle or typing) - only affects drawing, never hit-testing
+    pub cursor_hidden: bool,
+
+    /// Last pointer motion/button, for the idle hide
+    pub last_pointer_activity: Instant,
+
+    /// Chords the focused window gets instead of us, and which window that was worked out for
+    pub passthrough: Vec<Keybind>,
+    pub passthrough_window: Option<u64>,
+
+    /// How far the focused window has been pushed into an output edge so far,
+    /// and which window that was (edge resistance)
+    pub edge_push: Point<i32, Logical>,
+    pub edge_push_window: Option<u64>,
+}
+
+/// Keys held down, and which keyboards (device ids) are holding them
+///
+/// All of a seat's keyboards share one seat keyboard, so a key only goes
+/// down with the first of them and up once every one has let go of it.
+#[derive(Debug, Default)]
+pub struct HeldKeys(HashMap<Keycode, HashSet<String>>);
+
+impl HeldKeys {
+    /// Note a key edge - returns whether the seat keyboard should see it
+    pub fn track(&mut self, keycode: Keycode, device: String, key_state: KeyState) -> bool {
+        match key_state {
+            KeyState::Pressed => {
+                let holders = self.0.entry(keycode).or_default();
+                let first = holders.is_empty();
+                holders.insert(device);
+                first
+            }
+            KeyState::Released => {
+                let Some(holders) = self.0.get_mut(&keycode) else {
+                    // Never saw it go down - let it through
+                    return true;
+                };
+
+                holders.remove(&device);
+                if holders.is_empty() {
+                    self.0.remove(&keycode);
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// Unplugged keyboard - the keys only it was holding, now up
+    pub fn release_device(&mut self, device: &str) -> Vec<Keycode> {
+        let mut released = Vec::new();
+        self.0.retain(|keycode, holders| {
+            holders.remove(device);
+            if holders.is_empty() {
+                released.push(*keycode);
+                false
+            } else {
+                true
+            }
+        });
+        released
+    }
 }
 
 impl InputState {
@@ -33,6 +123,14 @@ impl InputState {
             resize_mode: false,
             pointer_pos: Point::from((0.0, 0.0)),
             quit_requested: false,
+            pending_motion: None,
+            held_keys: HeldKeys::default(),
+            cursor_hidden: false,
+            last_pointer_activity: Instant::now(),
+            passthrough: Vec::new(),
+            passthrough_window: None,
+            edge_push: Point::from((0, 0)),
+            edge_push_window: None,
         }
     }
 }
@@ -40,176 +138,332 @@ impl InputState {
 impl VibeWM {
     /// Process input events
     pub fn process_input_event<I: InputBackend>(&mut self, event: InputEvent<I>) {
+        crate::watchdog::input_seen();
+        #[cfg(feature = "metrics")]
+        crate::metrics::input_seen();
+
+        // Devices claimed by an extra seat drive that seat instead
+        let Some(event) = self.route_to_extra_seat(event) else {
+            return;
+        };
+
         match event {
             InputEvent::Keyboard { event } => self.handle_keyboard(event),
+            InputEvent::DeviceAdded { device } => self.osk.device_added(&device),
+            InputEvent::DeviceRemoved { device } => {
+                self.osk.device_removed(&device.id());
+                self.release_device_keys(&device.id());
+            }
             InputEvent::PointerMotion { event } => self.handle_pointer_motion(event),
             InputEvent::PointerMotionAbsolute { event } => self.handle_pointer_motion_absolute(event),
             InputEvent::PointerButton { event } => self.handle_pointer_button(event),
             InputEvent::PointerAxis { event } => self.handle_pointer_axis(event),
+
+            // Three-finger horizontal swipe drags between workspaces
+            InputEvent::GestureSwipeBegin { event } if event.fingers() == SWIPE_FINGERS => {
+                self.begin_workspace_swipe();
+            }
+            InputEvent::GestureSwipeUpdate { event } => self.update_workspace_swipe(event.delta_x()),
+            InputEvent::GestureSwipeEnd { event } => self.end_workspace_swipe(event.cancelled()),
             _ => {}
         }
     }
 
     fn handle_keyboard<I: InputBackend>(&mut self, event: impl KeyboardKeyEvent<I>) {
-        let serial = SERIAL_COUNTER.next_serial();
-        let time = Event::time_msec(&event);
         let keycode = event.key_code();
-        let pressed = event.state() == KeyState::Pressed;
+        let device = event.device().id();
+
+        // Second keyboard pressing a held key, or one of two letting go - the
+        // seat's xkb state must not see it, or mods get released too early
+        if !self.input.held_keys.track(keycode, device, event.state()) {
+            return;
+        }
+
+        self.send_key(keycode, event.state(), Event::time_msec(&event));
+    }
+
+    /// Unplugged keyboard - release whatever only it was holding
+    fn release_device_keys(&mut self, device: &str) {
+        let released = self.input.held_keys.release_device(device);
+        let time = self.start_time.elapsed().as_millis() as u32;
+        for keycode in released {
+            self.send_key(keycode, KeyState::Released, time);
+        }
+    }
+
+    /// Feed a key edge to the seat keyboard, through the keybind filter
+    fn send_key(&mut self, keycode: Keycode, key_state: KeyState, time: u32) {
+        let serial = SERIAL_COUNTER.next_serial();
+        let pressed = key_state == KeyState::Pressed;
 
         let keyboard = self.seat.get_keyboard().unwrap();
 
-        keyboard.input::<(), _>(
+        let intercepted = keyboard.input::<(), _>(
             self,
             keycode,
-            event.state(),
+            key_state,
             serial,
             time,
             |state, modifiers, keysym_handle| {
-                // Get the keysym from the handle
+                // Get the keysym from the handle - the raw one is for
+                // shifted chords like mod+shift+1
                 let keysym = keysym_handle.modified_sym();
+                let raw = keysym_handle.raw_latin_sym_or_raw_current_sym();
 
-                if state.handle_keybind(modifiers, keysym, pressed) {
+                if state.handle_keybind(modifiers, keysym, raw, pressed) {
                     FilterResult::Intercept(())
                 } else {
                     FilterResult::Forward
                 }
             },
-        );
+        ).is_some();
+
+        // Typing into a window - get the cursor out of the way
+        if pressed && !intercepted && self.config.cursor_hide_while_typing {
+            self.input.cursor_hidden = true;
+        }
     }
 
-    /// Handle vibeWM keybinds - returns true if handled
-    fn handle_keybind(&mut self, modifiers: &ModifiersState, keysym: Keysym, pressed: bool) -> bool {
-        let mod_held = modifiers.logo;
+    /// Pointer moved or clicked - bring the cursor back
+    fn pointer_activity(&mut self) {
+        self.input.cursor_hidden = false;
+        self.input.last_pointer_activity = Instant::now();
+    }
 
-        // Track resize mode (mod+R)
-        if mod_held && keysym == Keysym::r {
-            self.input.resize_mode = pressed;
-            return true;
-        }
+    /// Hide the cursor once the pointer has been idle long enough
+    pub fn update_cursor_idle(&mut self) {
+        let Some(timeout) = self.config.cursor_hide_timeout_ms else {
+            return;
+        };
 
-        // Only handle on press, not release
-        if !pressed {
-            return false;
+        if self.input.last_pointer_activity.elapsed() >= Duration::from_millis(timeout) {
+            self.input.cursor_hidden = true;
         }
+    }
 
-        // Command center toggle always works
-        if mod_held && keysym == Keysym::s {
-            self.toggle_command_center();
-            return true;
+    /// Should the cursor be drawn this frame?
+    pub fn cursor_visible(&self) -> bool {
+        !self.input.cursor_hidden
+    }
+
+    /// Handle vibeWM keybinds - returns true if handled
+    fn handle_keybind(
+        &mut self,
+        modifiers: &ModifiersState,
+        keysym: Keysym,
+        raw: Option<Keysym>,
+        pressed: bool,
+    ) -> bool {
+        // Region screenshot is a grab - every key press is ours
+        if self.region_select.is_some() {
+            if pressed {
+                self.region_screenshot_key(keysym, modifiers);
+            }
+            return pressed;
         }
 
-        // When command center is open, route input there
-        if self.command_center.visible {
-            return self.handle_command_center_input(keysym, modifiers);
+        // So is the window menu
+        if self.window_menu.is_some() {
+            if pressed {
+                self.window_menu_key(keysym);
+            }
+            return pressed;
         }
 
-        // Global quit
-        if mod_held && keysym == Keysym::q {
-            tracing::info!("Quit requested");
-            self.input.quit_requested = true;
+        // Escape drops a mod+drag back where it started
+        if self.move_grab.is_some() && pressed && keysym == Keysym::Escape {
+            self.cancel_move_grab();
             return true;
         }
 
-        if mod_held {
-            match keysym {
-                // Focus cycling: mod+Tab
-                Keysym::Tab => {
-                    if modifiers.shift {
-                        self.windows.focus_prev();
-                    } else {
-                        self.windows.focus_next();
-                    }
-                    return true;
-                }
+        // Chords the focused app asked for (VMs, remote desktops) - the
+        // command center still gets its keys while it's open
+        let command_center_open = self.command_center.as_ref().is_some_and(|cc| cc.visible);
+        if !command_center_open && self.is_passthrough(modifiers, keysym) {
+            return false;
+        }
 
-                // Vim motions for move/resize: ijkl
-                Keysym::i => {
-                    self.handle_vim_motion(Direction::Up);
-                    return true;
-                }
-                Keysym::k => {
-                    self.handle_vim_motion(Direction::Down);
+        // Resize mode trigger (mod+R by default) - held or sticky
+        let trigger = self.config.resize_mode_key;
+        if keysym == trigger.keysym {
+            let matches = Mods::from(modifiers) == trigger.mods;
+            match (self.config.resize_mode_style, pressed) {
+                (ResizeModeStyle::Hold, true) if matches => {
+                    self.input.resize_mode = true;
                     return true;
                 }
-                Keysym::j => {
-                    self.handle_vim_motion(Direction::Left);
+                // Let go of the key - mods may already be up by now
+                (ResizeModeStyle::Hold, false) if self.input.resize_mode => {
+                    self.input.resize_mode = false;
                     return true;
                 }
-                Keysym::l => {
-                    self.handle_vim_motion(Direction::Right);
+                (ResizeModeStyle::Toggle, true) if matches => {
+                    self.input.resize_mode = !self.input.resize_mode;
+                    tracing::info!("Resize mode {}", if self.input.resize_mode { "on" } else { "off" });
                     return true;
                 }
+                (ResizeModeStyle::Toggle, false) if matches => return true,
+                _ => {}
+            }
+        }
 
-                // Arrow keys for snap
-                Keysym::Left => {
-                    self.snap_focused(SnapPosition::Left);
-                    return true;
-                }
-                Keysym::Right => {
-                    self.snap_focused(SnapPosition::Right);
-                    return true;
-                }
-                Keysym::Up => {
-                    self.snap_focused(SnapPosition::Top);
-                    return true;
-                }
-                Keysym::Down => {
-                    self.snap_focused(SnapPosition::Bottom);
-                    return true;
-                }
+        // Only handle on press, not release
+        if !pressed {
+            return false;
+        }
 
-                // Close window: mod+W
-                Keysym::w => {
-                    if let Some(window) = self.windows.focused() {
-                        if let Some(toplevel) = window.toplevel() {
-                            toplevel.send_close();
-                        }
-                    }
-                    return true;
-                }
+        // Escape backs out of a pending quit
+        if self.quit_confirm.is_armed() && keysym == Keysym::Escape {
+            self.quit_confirm.cancel();
+            return true;
+        }
 
-                _ => {}
+        // Escape leaves sticky resize mode
+        if self.input.resize_mode
+            && self.config.resize_mode_style == ResizeModeStyle::Toggle
+            && keysym == Keysym::Escape
+        {
+            self.input.resize_mode = false;
+            return true;
+        }
+
+        // Escape dismisses the cheat sheet
+        if self.cheat_sheet.visible && keysym == Keysym::Escape {
+            self.cheat_sheet.hide();
+            return true;
+        }
+
+        // Escape dismisses the empty-desktop hints
+        if self.help_overlay.visible && keysym == Keysym::Escape {
+            self.help_overlay.hide();
+            return true;
+        }
+
+        let action = self.keybinds.lookup_key(Mods::from(modifiers), keysym, raw).cloned();
+
+        // When command center is open, route input there - except its own
+        // toggle, which closes it
+        if self.command_center.as_ref().is_some_and(|cc| cc.visible) {
+            if action == Some(Action::ToggleCommandCenter) {
+                self.dispatch_action(Action::ToggleCommandCenter);
+                return true;
             }
+            return self.handle_command_center_input(keysym, modifiers);
+        }
+
+        let action = action.map(|action| match action {
+            // ijkl resize instead of move while in resize mode
+            Action::Move(direction) if self.input.resize_mode => Action::Resize(direction),
+            action => action,
+        });
+
+        if let Some(action) = action {
+            self.dispatch_action(action);
+            return true;
         }
 
         false
     }
 
+    /// Suspend/reboot/shut down through systemd, log out through logind
+    fn run_power_action(&mut self, action: PowerAction) {
+        let Some(argv) = action.argv() else {
+            tracing::info!("No XDG_SESSION_ID - logging out by quitting");
+            self.quit();
+            return;
+        };
+
+        if let Err(e) = launch::spawn_with_env(&argv, &[]) {
+            tracing::warn!("Failed to run {:?}: {}", argv, e);
+            self.notice.show(format!("{} failed: {}", action.label(), e));
+        }
+    }
+
     /// Handle input when command center is open
-    fn handle_command_center_input(&mut self, keysym: Keysym, _modifiers: &ModifiersState) -> bool {
+    fn handle_command_center_input(&mut self, keysym: Keysym, modifiers: &ModifiersState) -> bool {
+        let Some(command_center) = self.command_center.as_mut() else {
+            return false;
+        };
+
+        // Ctrl+1..9 launches the selected app onto that workspace
+        if modifiers.ctrl {
+            if let Some(workspace) = digit_workspace(keysym) {
+                match command_center.launch_selected() {
+                    Some(Selection::Launch { argv, app_id, name }) => {
+                        self.launch_on_workspace(&argv, app_id, &name, workspace);
+                    }
+                    Some(Selection::Open(path)) => open_file(&path),
+                    Some(Selection::Action(action)) => self.dispatch_action(action),
+                    // A window goes to that workspace instead
+                    Some(Selection::Window(id)) => {
+                        if let Some(window) = self.windows.by_id(id).cloned() {
+                            self.send_to_workspace(&window, workspace);
+                        }
+                    }
+                    Some(Selection::Power(action)) => self.run_power_action(action),
+                    None => {}
+                }
+                return true;
+            }
+        }
+
         match keysym {
-            // Close on Escape
+            // Escape clears the search first, closes on the next press
             Keysym::Escape => {
-                self.command_center.toggle();
+                if !command_center.clear_query() {
+                    command_center.toggle();
+                }
+                true
+            }
+
+            // Tab goes round search, recent files and open windows
+            Keysym::Tab => {
+                command_center.next_section();
                 true
             }
 
             // Navigate with arrows
             Keysym::Up => {
-                self.command_center.select_prev();
+                command_center.select_prev();
                 true
             }
             Keysym::Down => {
-                self.command_center.select_next();
+                command_center.select_next();
                 true
             }
 
-            // Launch on Enter
+            // Launch (or run the action) on Enter - power cards but suspend
+            // want it twice
             Keysym::Return => {
-                if let Some(exec) = self.command_center.launch_selected() {
-                    // Spawn the app
-                    std::process::Command::new("sh")
-                        .arg("-c")
-                        .arg(&exec)
-                        .spawn()
-                        .ok();
+                match command_center.launch_selected() {
+                    Some(Selection::Launch { argv, app_id, name }) => {
+                        // Spawn the app, through the user's wrapper if any
+                        let env = self.config.env_for_app(app_id.as_deref(), Some(&name));
+                        match launch::launch_app(&self.config.launch_command_template, &argv, &env) {
+                            Ok(_) => self.usage.record_launch(&name),
+                            Err(e) => tracing::warn!("Failed to launch {:?}: {}", argv, e),
+                        }
+                    }
+                    Some(Selection::Action(action)) => {
+                        // Center is already closed, so this hits the focused window
+                        self.dispatch_action(action);
+                    }
+                    Some(Selection::Open(path)) => open_file(&path),
+                    Some(Selection::Window(id)) => {
+                        if let Some(window) = self.windows.by_id(id).cloned() {
+                            self.activate_window(&window);
+                            self.sync_keyboard_focus();
+                        }
+                    }
+                    Some(Selection::Power(action)) => self.run_power_action(action),
+                    None => {}
                 }
                 true
             }
 
             // Backspace for search
             Keysym::BackSpace => {
-                self.command_center.handle_backspace();
+                command_center.handle_backspace();
                 true
             }
 
@@ -217,7 +471,7 @@ impl VibeWM {
             _ => {
                 // Convert keysym to char if it's a printable character
                 if let Some(c) = keysym_to_char(keysym) {
-                    self.command_center.handle_char(c);
+                    command_center.handle_char(c);
                     true
                 } else {
                     false
@@ -226,187 +480,268 @@ impl VibeWM {
         }
     }
 
-    fn handle_vim_motion(&mut self, direction: Direction) {
-        if self.input.resize_mode {
-            self.resize_focused(direction);
-        } else {
-            self.move_focused(direction);
-        }
-    }
-
-    fn move_focused(&mut self, direction: Direction) {
+    pub fn move_focused(&mut self, direction: Direction) {
         let Some(window) = self.windows.focused().cloned() else {
             return;
         };
 
-        let Some(current_loc) = self.space.element_location(&window) else {
+        let (Some(current_loc), Some(geometry)) =
+            (self.space.element_location(&window), self.space.element_geometry(&window))
+        else {
             return;
         };
 
-        let delta = direction.to_delta(self.config.move_step);
-        let new_loc = current_loc + delta;
+        // Pushing against a different window starts from scratch
+        let id = self.windows.meta(&window).map(|m| m.id);
+        if id != self.input.edge_push_window {
+            self.input.edge_push_window = id;
+            self.input.edge_push = Point::from((0, 0));
+        }
+
+        let delta = resist_edge(
+            &self.output_rects(),
+            geometry,
+            direction.to_delta(self.config.move_step),
+            &mut self.input.edge_push,
+            self.config.edge_resistance,
+        );
+        self.move_window(&window, current_loc + delta);
+    }
+
+    /// Move a window to a position - it floats afterwards
+    pub fn move_window(&mut self, window: &Window, location: Point<i32, Logical>) {
+        // Moved by hand - no longer snapped
+        if let Some(meta) = self.windows.meta_mut(window) {
+            meta.snap_state = None;
+            if let Some(applied) = &mut meta.applied_geometry {
+                applied.loc = location;
+            }
+        }
+
+        let bounds = self.work_area().map(|area| area.size);
+        set_layout_state(window, TiledEdges::NONE, false, bounds);
+        if let Some(toplevel) = window.toplevel() {
+            toplevel.send_pending_configure();
+        }
 
-        self.space.map_element(window, new_loc, false);
+        self.space.map_element(window.clone(), location, false);
     }
 
-    fn resize_focused(&mut self, direction: Direction) {
-        let Some(window) = self.windows.focused() else {
+    pub fn resize_focused(&mut self, direction: Direction) {
+        let Some(window) = self.windows.focused().cloned() else {
             return;
         };
 
         let current_size = window.geometry().size;
         let (dw, dh) = direction.to_size_delta(self.config.resize_step);
 
-        let new_width = (current_size.w + dw).max(100);
-        let new_height = (current_size.h + dh).max(100);
+        self.resize_window(&window, (current_size.w + dw, current_size.h + dh).into());
+    }
+
+    /// Ask a window to take a size (100px minimum, inside its own min/max)
+    /// - it floats afterwards
+    pub fn resize_window(&mut self, window: &Window, size: Size<i32, Logical>) {
+        let (min, max) = size_limits(window);
+        let size = clamp_resize(size, min, max);
+
+        let location = self.space.element_location(window).unwrap_or_default();
+        if let Some(meta) = self.windows.meta_mut(window) {
+            meta.snap_state = None;
+            meta.applied_geometry = Some(Rectangle::new(location, size));
+        }
+
+        let bounds = self.work_area().map(|area| area.size);
+        set_layout_state(window, TiledEdges::NONE, false, bounds);
 
         if let Some(toplevel) = window.toplevel() {
             toplevel.with_pending_state(|state| {
-                state.size = Some((new_width, new_height).into());
+                state.size = Some(size);
             });
             toplevel.send_pending_configure();
         }
     }
 
-    fn snap_focused(&mut self, position: SnapPosition) {
+    pub fn snap_focused(&mut self, position: SnapPosition) {
         let Some(window) = self.windows.focused().cloned() else {
             return;
         };
 
-        let output_size = self.output.as_ref()
-            .and_then(|o| o.current_mode())
-            .map(|m| m.size)
-            .unwrap_or((1920, 1080).into());
-
-        let gap = self.config.outer_gap;
-        let inner = self.config.inner_gap;
-
-        let (x, y, w, h) = match position {
-            SnapPosition::Left => (
-                gap,
-                gap,
-                output_size.w / 2 - gap - inner / 2,
-                output_size.h - gap * 2,
-            ),
-            SnapPosition::Right => (
-                output_size.w / 2 + inner / 2,
-                gap,
-                output_size.w / 2 - gap - inner / 2,
-                output_size.h - gap * 2,
-            ),
-            SnapPosition::Top => (
-                gap,
-                gap,
-                output_size.w - gap * 2,
-                output_size.h / 2 - gap - inner / 2,
-            ),
-            SnapPosition::Bottom => (
-                gap,
-                output_size.h / 2 + inner / 2,
-                output_size.w - gap * 2,
-                output_size.h / 2 - gap - inner / 2,
-            ),
-            SnapPosition::TopLeft => (
-                gap,
-                gap,
-                output_size.w / 2 - gap - inner / 2,
-                output_size.h / 2 - gap - inner / 2,
-            ),
-            SnapPosition::TopRight => (
-                output_size.w / 2 + inner / 2,
-                gap,
-                output_size.w / 2 - gap - inner / 2,
-                output_size.h / 2 - gap - inner / 2,
-            ),
-            SnapPosition::BottomLeft => (
-                gap,
-                output_size.h / 2 + inner / 2,
-                output_size.w / 2 - gap - inner / 2,
-                output_size.h / 2 - gap - inner / 2,
-            ),
-            SnapPosition::BottomRight => (
-                output_size.w / 2 + inner / 2,
-                output_size.h / 2 + inner / 2,
-                output_size.w / 2 - gap - inner / 2,
-                output_size.h / 2 - gap - inner / 2,
-            ),
-            SnapPosition::Maximize => (
-                gap,
-                gap,
-                output_size.w - gap * 2,
-                output_size.h - gap * 2,
-            ),
-            SnapPosition::Center => {
-                let current_size = window.geometry().size;
-                (
-                    (output_size.w - current_size.w) / 2,
-                    (output_size.h - current_size.h) / 2,
-                    current_size.w,
-                    current_size.h,
-                )
-            }
+        let Some(target) = self.snap_window(&window, position) else {
+            return;
+        };
+
+        // Flash the target region - a new snap replaces the old preview
+        self.snap_preview.show(target, self.animation_duration(SNAP_PREVIEW));
+    }
+
+    /// Snap a window into position and remember it, so layout changes can re-snap it
+    ///
+    /// Returns the rectangle it was snapped to - None with no output to snap on.
+    pub fn snap_window(&mut self, window: &Window, position: SnapPosition) -> Option<Rectangle<i32, Logical>> {
+        let Some(output_rect) = self.primary_output_rect() else {
+            tracing::warn!("No output - not snapping");
+            return None;
         };
 
-        // Move window
-        self.space.map_element(window.clone(), (x, y), false);
+        let gaps = SnapGaps {
+            outer: self.config.outer_gap,
+            inner: self.config.snap_gap(),
+        };
+        let rect = compute_snap_rect(position, output_rect, gaps, self.config.snap_ratio, window.geometry().size);
+
+        // Remember where it was before the first snap (for restore) - at the
+        // size we last gave it, which the client may not have drawn yet
+        let current_geometry = self.space.element_geometry(window);
+        if let Some(meta) = self.windows.meta_mut(window) {
+            if meta.snap_state.is_none() {
+                meta.pre_snap_geometry = current_geometry.map(|geometry| match meta.applied_geometry {
+                    Some(applied) => Rectangle::new(geometry.loc, applied.size),
+                    None => geometry,
+                });
+            }
+            meta.snap_state = Some(position);
+        }
 
-        // Resize window
-        if let Some(toplevel) = window.toplevel() {
-            toplevel.with_pending_state(|state| {
-                state.size = Some((w, h).into());
-            });
-            toplevel.send_pending_configure();
+        // Tiled edges + bounds so the client drops shadows that would eat the gaps
+        let bounds = self.work_area().map(|area| area.size);
+        set_layout_state(
+            window,
+            TiledEdges::for_snap(position),
+            position == SnapPosition::Maximize,
+            bounds,
+        );
+
+        // Move and resize window - centered in the slot if its size limits
+        // won't let it fill it
+        let titlebar = self.windows.titlebar_height(window);
+        let applied = place_window(&mut self.space, window, rect, titlebar);
+        if let Some(meta) = self.windows.meta_mut(window) {
+            meta.applied_geometry = Some(applied);
         }
+        Some(rect)
     }
 
-    fn handle_pointer_motion<I: InputBackend>(&mut self, event: impl PointerMotionEvent<I>) {
-        let delta = event.delta();
-        self.input.pointer_pos += delta;
+    /// Re-apply layout after an output changes mode
+    ///
+    /// Snapped windows get re-snapped to the new size, floating ones are
+    /// clamped so they don't end up stranded off-screen.
+    pub fn reflow_for_output(&mut self, output: &Output) {
+        let Some(output_geo) = self.space.output_geometry(output) else {
+            return;
+        };
 
-        let serial = SERIAL_COUNTER.next_serial();
-        let pointer = self.seat.get_pointer().unwrap();
+        // Hidden workspaces catch up when they're switched to
+        for window in self.windows.on_workspace(self.windows.active_workspace()) {
+            let snap_state = self.windows.meta(&window).and_then(|m| m.snap_state);
 
-        // Find surface under pointer and convert to the right types
-        let under = self.space
-            .element_under(self.input.pointer_pos)
-            .and_then(|(window, loc)| {
-                window.wl_surface().map(|surface| {
-                    (surface.into_owned(), loc.to_f64())
-                })
-            });
+            if let Some(position) = snap_state {
+                self.snap_window(&window, position);
+                continue;
+            }
 
-        pointer.motion(
-            self,
-            under,
-            &MotionEvent {
-                location: self.input.pointer_pos,
-                serial,
-                time: event.time_msec(),
-            },
+            let Some(geo) = self.space.element_geometry(&window) else {
+                continue;
+            };
+
+            // Keep the whole window on screen when it fits, its top-left corner otherwise
+            let location = fit_inside(geo, output_geo);
+            if location != geo.loc {
+                self.space.map_element(window, location, false);
+            }
+        }
+
+        tracing::info!("Reflowed windows for {} at {}x{}", output.name(), output_geo.size.w, output_geo.size.h);
+    }
+
+    fn handle_pointer_motion<I: InputBackend>(&mut self, event: impl PointerMotionEvent<I>) {
+        let from = self.input.pointer_pos;
+        self.input.pointer_pos = constrain_pointer(
+            &self.output_rects(),
+            from,
+            from + event.delta(),
+            self.config.pointer_wrap,
         );
+        self.pointer_activity();
+
+        // Dispatched once per frame from handle_pending
+        self.input.pending_motion = Some(event.time_msec());
     }
 
     fn handle_pointer_motion_absolute<I: InputBackend>(&mut self, event: impl AbsolutePositionEvent<I>) {
-        let output_size = self.output.as_ref()
-            .and_then(|o| o.current_mode())
-            .map(|m| m.size)
-            .unwrap_or((1920, 1080).into());
+        let device_name = event.device().name();
+        let Some(target) = self.absolute_target(&device_name) else {
+            return;
+        };
 
-        self.input.pointer_pos = (
-            event.x_transformed(output_size.w) as f64,
-            event.y_transformed(output_size.h) as f64,
-        ).into();
+        // Normalized 0..1 across the device's area
+        let normalized = (event.x_transformed(1), event.y_transformed(1));
+        self.input.pointer_pos = absolute_position(normalized, target);
+        self.pointer_activity();
+
+        self.input.pending_motion = Some(event.time_msec());
+    }
+
+    /// Logical rect an absolute device maps onto
+    ///
+    /// The output assigned to the device in config, or every output at once
+    /// (their bounding box) when it has none or the output isn't connected.
+    pub fn absolute_target(&self, device_name: &str) -> Option<Rectangle<i32, Logical>> {
+        let assigned = self.config.absolute_device_outputs
+            .iter()
+            .find(|(device, _)| device == device_name)
+            .and_then(|(_, output)| self.space.outputs().find(|o| o.name() == *output))
+            .and_then(|output| self.space.output_geometry(output));
+
+        assigned.or_else(|| {
+            outputs_bounding_box(self.output_rects())
+        })
+    }
+
+    /// Logical rects of every mapped output
+    pub fn output_rects(&self) -> Vec<Rectangle<i32, Logical>> {
+        self.space.outputs().filter_map(|o| self.space.output_geometry(o)).collect()
+    }
+
+    /// Send the accumulated pointer motion to clients, if there is any
+    ///
+    /// 1000Hz mice would otherwise do a surface lookup and a motion
+    /// dispatch per raw event - once per frame is plenty.
+    pub fn flush_pointer_motion(&mut self) {
+        // Held back while a swipe owns the pointer
+        if self.swipe_tracking() {
+            return;
+        }
+
+        let Some(time) = self.input.pending_motion.take() else {
+            return;
+        };
+
+        // Region screenshot grab - motion only stretches the selection
+        if let Some(select) = self.region_select.as_mut() {
+            select.drag_to(self.input.pointer_pos);
+            return;
+        }
+
+        // Window menu grab - hover, or drag the window being moved/resized
+        if self.window_menu.is_some() {
+            self.window_menu_motion();
+            return;
+        }
+
+        // mod+drag or a titlebar drag - the window follows, clients don't
+        // see the motion
+        if self.move_grab.is_some() {
+            self.move_grab_motion();
+            return;
+        }
+        if self.resize_grab.as_ref().is_some_and(|grab| !grab.released) {
+            self.resize_grab_motion();
+            return;
+        }
 
         let serial = SERIAL_COUNTER.next_serial();
         let pointer = self.seat.get_pointer().unwrap();
-
-        let under = self.space
-            .element_under(self.input.pointer_pos)
-            .and_then(|(window, loc)| {
-                window.wl_surface().map(|surface| {
-                    (surface.into_owned(), loc.to_f64())
-                })
-            });
+        let under = self.surface_under(self.input.pointer_pos);
 
         pointer.motion(
             self,
@@ -414,12 +749,101 @@ impl VibeWM {
             &MotionEvent {
                 location: self.input.pointer_pos,
                 serial,
-                time: event.time_msec(),
+                time,
             },
         );
     }
 
+    /// Window under a point - in spotlight mode only the spotlighted one
+    pub fn window_under(&self, pos: Point<f64, Logical>) -> Option<(&Window, Point<i32, Logical>)> {
+        self.space
+            .element_under(pos)
+            .filter(|(window, _)| self.spotlight.as_ref().map_or(true, |s| s == *window))
+    }
+
+    /// Surface under a point, for pointer focus - subsurfaces and popups
+    /// included, each with its own origin
+    ///
+    /// In spotlight mode only the spotlighted window gets the pointer.
+    pub fn surface_under(&self, pos: Point<f64, Logical>) -> Option<(WlSurface, Point<f64, Logical>)> {
+        self.window_under(pos).and_then(|(window, loc)| {
+            window
+                .surface_under(pos - loc.to_f64(), WindowSurfaceType::ALL)
+                .map(|(surface, surface_loc)| (surface, (surface_loc + loc).to_f64()))
+        })
+    }
+
     fn handle_pointer_button<I: InputBackend>(&mut self, event: impl PointerButtonEvent<I>) {
+        self.pointer_activity();
+
+        // Clients don't get clicks mid-swipe
+        if self.swipe_tracking() {
+            return;
+        }
+
+        // Clients must see where the pointer is before the click lands
+        self.flush_pointer_motion();
+
+        // Region screenshot grab - clicks drag out the selection
+        if self.region_select.is_some() {
+            self.region_screenshot_button(event.state() == ButtonState::Pressed);
+            return;
+        }
+
+        // Window menu grab - clicks pick entries, outside dismisses
+        if self.window_menu.is_some() {
+            self.window_menu_button(event.state() == ButtonState::Pressed);
+            return;
+        }
+
+        // Command center eats presses while it's open - outside it dismisses
+        if event.state() == ButtonState::Pressed
+            && self.command_center.as_ref().is_some_and(|cc| cc.visible)
+        {
+            if !self.command_center_hit(self.input.pointer_pos) {
+                self.toggle_command_center();
+            }
+            return;
+        }
+
+        // mod+drag moves the window under the pointer, mod+right-drag resizes it
+        if self.resize_grab_button(event.button_code(), event.state() == ButtonState::Pressed) {
+            return;
+        }
+        if self.move_grab_button(event.button_code(), event.state() == ButtonState::Pressed) {
+            return;
+        }
+
+        // Compositor titlebars - buttons, drag to move, right-click menu
+        if self.titlebar_button(event.button_code(), event.state() == ButtonState::Pressed) {
+            return;
+        }
+
+        // Clicking outside the spotlighted window ends spotlight instead
+        if event.state() == ButtonState::Pressed {
+            if let Some(spotlight) = &self.spotlight {
+                let on_spotlight = self.space
+                    .element_under(self.input.pointer_pos)
+                    .is_some_and(|(window, _)| window == spotlight);
+                if !on_spotlight {
+                    self.toggle_spotlight();
+                    return;
+                }
+            }
+        }
+
+        // Click to focus - the window comes up and gets the keyboard before
+        // it sees the click. Empty space takes focus away
+        if event.state() == ButtonState::Pressed {
+            match self.space.element_under(self.input.pointer_pos).map(|(window, _)| window.clone()) {
+                Some(window) => self.focus_and_raise(&window),
+                None => {
+                    self.windows.unfocus();
+                    self.sync_keyboard_focus();
+                }
+            }
+        }
+
         let serial = SERIAL_COUNTER.next_serial();
         let pointer = self.seat.get_pointer().unwrap();
 
@@ -432,18 +856,121 @@ impl VibeWM {
                 time: event.time_msec(),
             },
         );
+    }
 
-        // Focus on click
-        if event.state() == ButtonState::Pressed {
-            if let Some((window, _)) = self.space.element_under(self.input.pointer_pos) {
-                // Find window index and focus it
-                let _pos = self.windows.all().iter().position(|w| w == window);
-                // TODO: proper focus management
+    /// Hand the keyboard to the focused window (wl_keyboard.enter), and set
+    /// or clear Activated on every toplevel so they draw focused/unfocused.
+    /// Does nothing while the keyboard is already there, or while a popup
+    /// menu has it grabbed
+    pub fn sync_keyboard_focus(&mut self) {
+        let focused = self.windows.focused().cloned();
+        let surface = focused.as_ref().and_then(|window| window.wl_surface()).map(|s| s.into_owned());
+        let Some(keyboard) = self.seat.get_keyboard() else { return };
+        if keyboard.current_focus() == surface || keyboard.is_grabbed() {
+            return;
+        }
+
+        // A window still waiting for its initial configure gets Activated
+        // with it - sending now would pre-empt the default size
+        for window in self.windows.all() {
+            let changed = window.set_activated(Some(window) == focused.as_ref());
+            if let Some(toplevel) = window.toplevel().filter(|_| changed && initial_configure_sent(window)) {
+                toplevel.send_pending_configure();
             }
         }
+
+        keyboard.set_focus(self, surface, SERIAL_COUNTER.next_serial());
+    }
+
+    /// Is a point on the command center? (container, plus the glow if configured)
+    fn command_center_hit(&self, pos: Point<f64, Logical>) -> bool {
+        self.command_center_local(pos).is_some_and(|(layout, local)| {
+            layout.contains(local.x, local.y, self.config.command_center.glow_is_inside)
+        })
+    }
+
+    /// Command center layout on its output, and a point in that output's coordinates
+    fn command_center_local(&self, pos: Point<f64, Logical>) -> Option<(CommandCenterLayout, Point<f64, Logical>)> {
+        let output = self.command_center
+            .as_ref()
+            .and_then(|cc| cc.output.as_deref())
+            .and_then(|name| self.space.outputs().find(|o| o.name() == name))
+            .or(self.output.as_ref());
+
+        let output = output?;
+
+        let output_size = output.current_mode()?.size;
+        let origin = self.space.output_geometry(output)
+            .map(|g| g.loc.to_f64())
+            .unwrap_or_default();
+
+        // Layout is output-local
+        let layout = self.command_center_layout(output_size.w, output_size.h);
+        Some((layout, pos - origin))
+    }
+
+    /// Should a scroll at this point go to the command center's grid?
+    ///
+    /// Over the grid it does, and so does the rest of the container with
+    /// scroll_anywhere. A touchpad scroll that started on the grid keeps
+    /// going there until the fingers lift, wherever the pointer wanders.
+    fn command_center_scrolls(&self, pos: Point<f64, Logical>) -> bool {
+        if self.command_center.as_ref().is_some_and(|cc| cc.scroll.touching) {
+            return true;
+        }
+
+        self.command_center_local(pos).is_some_and(|(layout, local)| {
+            layout.grid_contains(local.x, local.y)
+                || (self.config.command_center.scroll_anywhere && layout.contains(local.x, local.y, false))
+        })
     }
 
     fn handle_pointer_axis<I: InputBackend>(&mut self, event: impl PointerAxisEvent<I>) {
+        if self.swipe_tracking() || self.region_select.is_some() || self.window_menu.is_some() {
+            return;
+        }
+
+        self.flush_pointer_motion();
+
+        // mod+scroll zooms
+        let mod_held = self.seat.get_keyboard().is_some_and(|kb| kb.modifier_state().logo);
+        if mod_held {
+            let delta = event.amount(Axis::Vertical)
+                .or_else(|| event.amount_v120(Axis::Vertical).map(|v| v / 120.0 * WHEEL_STEP))
+                .unwrap_or(0.0);
+
+            // Scroll up = zoom in, one step per wheel notch
+            if delta != 0.0 {
+                let steps = -delta / WHEEL_STEP;
+                self.zoom_by(self.config.zoom_step.powf(steps));
+            }
+            return;
+        }
+
+        // Scrolling over the app grid scrolls the grid, not the client under it
+        let command_center_open = self.command_center.as_ref().is_some_and(|cc| cc.visible);
+        if command_center_open && self.command_center_scrolls(self.input.pointer_pos) {
+            let Some(command_center) = self.command_center.as_mut() else {
+                return;
+            };
+            let finger = event.source() == AxisSource::Finger;
+            let delta = event.amount(Axis::Vertical)
+                .or_else(|| event.amount_v120(Axis::Vertical).map(|v| v / 120.0 * WHEEL_STEP));
+
+            match delta {
+                // Touchpads send an explicit zero when the fingers lift
+                Some(d) if finger && d == 0.0 => command_center.scroll_release(),
+                Some(d) => command_center.scroll_by(d as f32, finger),
+                None => {}
+            }
+            return;
+        }
+
+        // The rest of the command center is dead space for scrolling
+        if command_center_open && self.command_center_hit(self.input.pointer_pos) {
+            return;
+        }
+
         let pointer = self.seat.get_pointer().unwrap();
 
         let mut frame = AxisFrame::new(event.time_msec());
@@ -463,6 +990,199 @@ impl VibeWM {
     }
 }
 
+/// Map a normalized (0..1) absolute position into a logical rect
+pub fn absolute_position(normalized: (f64, f64), target: Rectangle<i32, Logical>) -> Point<f64, Logical> {
+    let (x, y) = normalized;
+
+    (
+        target.loc.x as f64 + x.clamp(0.0, 1.0) * target.size.w as f64,
+        target.loc.y as f64 + y.clamp(0.0, 1.0) * target.size.h as f64,
+    ).into()
+}
+
+/// Smallest rect covering all the given output rects
+pub fn outputs_bounding_box(
+    rects: impl IntoIterator<Item = Rectangle<i32, Logical>>,
+) -> Option<Rectangle<i32, Logical>> {
+    rects.into_iter().reduce(|a, b| a.merge(b))
+}
+
+/// Where relative pointer motion from `from` to `to` actually ends up
+///
+/// Off the outputs it stops at the edge of the output it came from instead
+/// of sliding into the gap next to a shorter monitor. With `wrap`, leaving
+/// the far right comes back in on the far left (and the other way round),
+/// landing on the nearest output if the other side doesn't go that high.
+pub fn constrain_pointer(
+    outputs: &[Rectangle<i32, Logical>],
+    from: Point<f64, Logical>,
+    to: Point<f64, Logical>,
+    wrap: bool,
+) -> Point<f64, Logical> {
+    let on_output = |p: Point<f64, Logical>| outputs.iter().any(|o| o.to_f64().contains(p));
+    if on_output(to) {
+        return to;
+    }
+    let Some(bounds) = outputs_bounding_box(outputs.iter().copied()) else {
+        return to;
+    };
+
+    if wrap {
+        let bounds = bounds.to_f64();
+        let right = bounds.loc.x + bounds.size.w;
+        let wrapped_x = if to.x >= right {
+            Some(bounds.loc.x + (to.x - right))
+        } else if to.x < bounds.loc.x {
+            Some(right - (bounds.loc.x - to.x))
+        } else {
+            None
+        };
+
+        if let Some(x) = wrapped_x {
+            let wrapped = Point::from((x, to.y));
+            if let Some(target) = nearest_output(outputs, wrapped) {
+                return clamp_to_output(target, wrapped);
+            }
+        }
+    }
+
+    let home = outputs
+        .iter()
+        .find(|o| o.to_f64().contains(from))
+        .or_else(|| nearest_output(outputs, to));
+    match home {
+        Some(home) => clamp_to_output(home, to),
+        None => to,
+    }
+}
+
+/// Output closest to a point (0 away if it's on one)
+fn nearest_output(
+    outputs: &[Rectangle<i32, Logical>],
+    p: Point<f64, Logical>,
+) -> Option<&Rectangle<i32, Logical>> {
+    let distance = |o: &Rectangle<i32, Logical>| {
+        let o = o.to_f64();
+        let dx = (o.loc.x - p.x).max(p.x - (o.loc.x + o.size.w)).max(0.0);
+        let dy = (o.loc.y - p.y).max(p.y - (o.loc.y + o.size.h)).max(0.0);
+        dx * dx + dy * dy
+    };
+    outputs.iter().min_by(|a, b| distance(a).total_cmp(&distance(b)))
+}
+
+fn clamp_to_output(output: &Rectangle<i32, Logical>, p: Point<f64, Logical>) -> Point<f64, Logical> {
+    let o = output.to_f64();
+    Point::from((
+        p.x.clamp(o.loc.x, o.loc.x + o.size.w - 1.0),
+        p.y.clamp(o.loc.y, o.loc.y + o.size.h - 1.0),
+    ))
+}
+
+/// How much of a move `delta` a window gets, with edge resistance
+///
+/// A window crossing from its output onto a neighbouring one first stops
+/// flush with the edge; the overshoot piles up in `push` and once it reaches
+/// `resistance` pixels the window goes through. Edges with no output behind
+/// them don't resist. Works for keyboard steps and drags alike.
+pub fn resist_edge(
+    outputs: &[Rectangle<i32, Logical>],
+    window: Rectangle<i32, Logical>,
+    delta: Point<i32, Logical>,
+    push: &mut Point<i32, Logical>,
+    resistance: i32,
+) -> Point<i32, Logical> {
+    let center = Point::<i32, Logical>::from((
+        window.loc.x + window.size.w / 2,
+        window.loc.y + window.size.h / 2,
+    ));
+    let home = outputs.iter().find(|o| o.contains(center));
+    let Some(home) = home.filter(|_| resistance > 0) else {
+        *push = Point::from((0, 0));
+        return delta;
+    };
+
+    let overlaps_x = |o: &Rectangle<i32, Logical>| {
+        o.loc.x < window.loc.x + window.size.w && o.loc.x + o.size.w > window.loc.x
+    };
+    let overlaps_y = |o: &Rectangle<i32, Logical>| {
+        o.loc.y < window.loc.y + window.size.h && o.loc.y + o.size.h > window.loc.y
+    };
+    let left = outputs.iter().any(|o| o.loc.x + o.size.w == home.loc.x && overlaps_y(o));
+    let right = outputs.iter().any(|o| o.loc.x == home.loc.x + home.size.w && overlaps_y(o));
+    let above = outputs.iter().any(|o| o.loc.y + o.size.h == home.loc.y && overlaps_x(o));
+    let below = outputs.iter().any(|o| o.loc.y == home.loc.y + home.size.h && overlaps_x(o));
+
+    Point::from((
+        resist_axis(
+            delta.x,
+            (window.loc.x, window.size.w),
+            (home.loc.x, home.size.w),
+            (left, right),
+            &mut push.x,
+            resistance,
+        ),
+        resist_axis(
+            delta.y,
+            (window.loc.y, window.size.h),
+            (home.loc.y, home.size.h),
+            (above, below),
+            &mut push.y,
+            resistance,
+        ),
+    ))
+}
+
+/// One axis of resist_edge - spans are (start, length)
+fn resist_axis(
+    delta: i32,
+    window: (i32, i32),
+    home: (i32, i32),
+    neighbours: (bool, bool),
+    push: &mut i32,
+    resistance: i32,
+) -> i32 {
+    // Leading edge of the window and the output edge it's heading for
+    let (leading, edge, neighbour) = match delta.signum() {
+        1 => (window.0 + window.1, home.0 + home.1, neighbours.1),
+        -1 => (window.0, home.0, neighbours.0),
+        _ => return 0,
+    };
+
+    let crosses = if delta > 0 {
+        leading <= edge && leading + delta > edge
+    } else {
+        leading >= edge && leading + delta < edge
+    };
+    if !neighbour || !crosses {
+        *push = 0;
+        return delta;
+    }
+
+    *push += (leading + delta - edge).abs();
+    if *push >= resistance {
+        *push = 0;
+        delta
+    } else {
+        // Sit flush against the edge until pushed hard enough
+        edge - leading
+    }
+}
+
+/// Open a recent file from the command center
+fn open_file(path: &std::path::Path) {
+    if let Err(e) = launch::open_path(path) {
+        tracing::warn!("Failed to open {}: {}", path.display(), e);
+    }
+}
+
+/// Workspace index for a digit key, 1..9 -> 0..8
+fn digit_workspace(keysym: Keysym) -> Option<usize> {
+    match keysym.raw() {
+        raw @ 0x31..=0x39 => Some((raw - 0x31) as usize),
+        _ => None,
+    }
+}
+
 /// Convert keysym to character for text input
 fn keysym_to_char(keysym: Keysym) -> Option<char> {
     // Handle common ASCII characters
@@ -496,3 +1216,133 @@ fn keysym_to_char(keysym: Keysym) -> Option<char> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1920x1080 at scale 1, with a 2560x1440 at scale 2 to its right
+    fn two_outputs() -> (Rectangle<i32, Logical>, Rectangle<i32, Logical>) {
+        let left = Rectangle::new((0, 0).into(), (1920, 1080).into());
+        let right = Rectangle::new((1920, 0).into(), (2560 / 2, 1440 / 2).into());
+        (left, right)
+    }
+
+    #[test]
+    fn absolute_maps_into_assigned_output() {
+        let (_, right) = two_outputs();
+
+        // Corners and center of the scaled output, in global logical space
+        assert_eq!(absolute_position((0.0, 0.0), right), Point::from((1920.0, 0.0)));
+        assert_eq!(absolute_position((1.0, 1.0), right), Point::from((3200.0, 720.0)));
+        assert_eq!(absolute_position((0.5, 0.5), right), Point::from((2560.0, 360.0)));
+    }
+
+    #[test]
+    fn absolute_falls_back_to_all_outputs() {
+        let (left, right) = two_outputs();
+        let union = outputs_bounding_box([left, right]).unwrap();
+
+        assert_eq!(union, Rectangle::new((0, 0).into(), (3200, 1080).into()));
+
+        // Halfway across lands on the left output, three quarters on the right one
+        let half = absolute_position((0.5, 0.25), union);
+        assert!(left.to_f64().contains(half));
+        let three_quarters = absolute_position((0.75, 0.25), union);
+        assert!(right.to_f64().contains(three_quarters));
+    }
+
+    #[test]
+    fn absolute_clamps_out_of_range() {
+        let (left, _) = two_outputs();
+        assert_eq!(absolute_position((-0.5, 1.5), left), Point::from((0.0, 1080.0)));
+    }
+
+    #[test]
+    fn bounding_box_of_nothing() {
+        assert_eq!(outputs_bounding_box([]), None);
+    }
+
+    #[test]
+    fn pointer_stays_out_of_dead_zones() {
+        let (left, right) = two_outputs();
+        let outputs = [left, right];
+
+        // Crossing over at a height both outputs have is fine
+        let crossed = constrain_pointer(&outputs, (1910.0, 500.0).into(), (1930.0, 500.0).into(), false);
+        assert_eq!(crossed, Point::from((1930.0, 500.0)));
+
+        // Below the shorter right output there's nothing - stop at the left one's edge
+        let stopped = constrain_pointer(&outputs, (1910.0, 900.0).into(), (1930.0, 900.0).into(), false);
+        assert_eq!(stopped, Point::from((1919.0, 900.0)));
+
+        // Off the far right without wrap just stops
+        let edge = constrain_pointer(&outputs, (3190.0, 100.0).into(), (3210.0, 100.0).into(), false);
+        assert_eq!(edge, Point::from((3199.0, 100.0)));
+    }
+
+    #[test]
+    fn pointer_wraps_between_outer_outputs() {
+        let (left, right) = two_outputs();
+        let outputs = [left, right];
+
+        let wrapped = constrain_pointer(&outputs, (3190.0, 100.0).into(), (3210.0, 100.0).into(), true);
+        assert_eq!(wrapped, Point::from((10.0, 100.0)));
+
+        // Wrapping left at a height the right output doesn't have lands on its bottom row
+        let wrapped = constrain_pointer(&outputs, (5.0, 900.0).into(), (-5.0, 900.0).into(), true);
+        assert_eq!(wrapped, Point::from((3195.0, 719.0)));
+    }
+
+    #[test]
+    fn held_keys_go_up_with_the_last_keyboard() {
+        let mut held = HeldKeys::default();
+        let a = Keycode::new(38);
+
+        assert!(held.track(a, "laptop".to_string(), KeyState::Pressed));
+        assert!(!held.track(a, "usb".to_string(), KeyState::Pressed));
+        assert!(!held.track(a, "laptop".to_string(), KeyState::Released));
+        assert!(held.track(a, "usb".to_string(), KeyState::Released));
+
+        // A release we never saw go down still gets through
+        assert!(held.track(a, "usb".to_string(), KeyState::Released));
+    }
+
+    #[test]
+    fn unplugging_releases_only_what_nothing_else_holds() {
+        let mut held = HeldKeys::default();
+        let (a, b) = (Keycode::new(38), Keycode::new(56));
+        held.track(a, "laptop".to_string(), KeyState::Pressed);
+        held.track(b, "laptop".to_string(), KeyState::Pressed);
+        held.track(b, "usb".to_string(), KeyState::Pressed);
+
+        assert_eq!(held.release_device("laptop"), vec![a]);
+        assert!(held.release_device("laptop").is_empty());
+        assert_eq!(held.release_device("usb"), vec![b]);
+    }
+
+    #[test]
+    fn edge_resistance_holds_then_lets_go() {
+        let (left, right) = two_outputs();
+        let outputs = [left, right];
+        let mut push = Point::from((0, 0));
+        let step = Point::from((50, 0));
+
+        // 20px short of the edge: first step stops flush with it
+        let window = Rectangle::new((1100, 100).into(), (800, 600).into());
+        assert_eq!(resist_edge(&outputs, window, step, &mut push, 100), Point::from((20, 0)));
+
+        // Flush: pushes pile up until they beat the resistance
+        let window = Rectangle::new((1120, 100).into(), (800, 600).into());
+        assert_eq!(resist_edge(&outputs, window, step, &mut push, 100), Point::from((0, 0)));
+        assert_eq!(resist_edge(&outputs, window, step, &mut push, 100), step);
+        assert_eq!(push, Point::from((0, 0)));
+
+        // No output past the top edge - nothing to resist
+        let up = Point::from((0, -150));
+        assert_eq!(resist_edge(&outputs, window, up, &mut push, 100), up);
+
+        // Turned off
+        assert_eq!(resist_edge(&outputs, window, step, &mut push, 0), step);
+    }
+}
@@ -0,0 +1,438 @@
+//! IPC for vibeWM
+//!
+//! A unix socket in $XDG_RUNTIME_DIR that takes one line-based command per
+//! connection and answers with "ok", "error: ..." or the command's output.
+//! Meant for scripts, e.g. `echo quit | socat - UNIX-CONNECT:$VIBEWM_SOCK`.
+//!
+//! Each connection is its own non-blocking source, read and answered as it
+//! becomes ready - a client that connects and goes quiet never holds up the
+//! loop, it just gets dropped after CLIENT_TIMEOUT.
+
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use smithay::{
+    desktop::Window,
+    reexports::calloop::{
+        generic::Generic,
+        timer::{TimeoutAction, Timer},
+        EventLoop, Interest, LoopHandle, Mode, PostAction,
+    },
+};
+
+use crate::config::{Config, SnapPosition};
+use crate::rules::{evaluate, Matcher};
+use crate::state::VibeWM;
+use crate::window::{window_app_id, window_title};
+
+/// How long a client gets to send its command line and take the reply
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How far past the watchdog timeout debug-stall may hang the loop
+#[cfg(debug_assertions)]
+const DEBUG_STALL_MARGIN: Duration = Duration::from_secs(5);
+
+/// Longest command line we bother reading
+const MAX_LINE_LEN: usize = 4096;
+
+/// Socket path for a given wayland display name
+pub fn socket_path(wayland_display: &str) -> PathBuf {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+
+    runtime_dir.join(format!("vibewm-{}.sock", wayland_display))
+}
+
+/// Bind the IPC socket and hook it into the event loop
+///
+/// Returns the socket path so it can be cleaned up on exit.
+pub fn init(event_loop: &mut EventLoop<'static, VibeWM>, wayland_display: &str) -> Result<PathBuf> {
+    let path = socket_path(wayland_display);
+
+    // Leftover from a crashed session - nobody is listening on it
+    if path.exists() {
+        std::fs::remove_file(&path).ok();
+    }
+
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to bind IPC socket at {}", path.display()))?;
+    listener.set_nonblocking(true)?;
+
+    std::env::set_var("VIBEWM_SOCK", &path);
+
+    let handle = event_loop.handle();
+    event_loop
+        .handle()
+        .insert_source(
+            Generic::new(listener, Interest::READ, Mode::Level),
+            move |_, listener, _| {
+                while let Ok((stream, _)) = listener.accept() {
+                    if let Err(e) = add_client(&handle, stream) {
+                        tracing::debug!("IPC client error: {:#}", e);
+                    }
+                }
+                Ok(PostAction::Continue)
+            },
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to insert IPC source: {:?}", e))?;
+
+    tracing::info!("IPC socket: {}", path.display());
+    Ok(path)
+}
+
+/// Hook a new connection into the loop, with a deadline to finish by
+fn add_client(handle: &LoopHandle<'static, VibeWM>, stream: UnixStream) -> Result<()> {
+    stream.set_nonblocking(true)?;
+
+    let mut client = Client::default();
+    let token = handle
+        .insert_source(
+            Generic::new(stream, Interest::BOTH, Mode::Edge),
+            move |_, stream, state| match client.ready(stream.get_ref(), state) {
+                Ok(true) => Ok(PostAction::Remove),
+                Ok(false) => Ok(PostAction::Continue),
+                Err(e) => {
+                    tracing::debug!("IPC client error: {}", e);
+                    Ok(PostAction::Remove)
+                }
+            },
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to insert IPC client: {:?}", e))?;
+
+    // Removing one that already finished is a no-op
+    let timeout_handle = handle.clone();
+    handle
+        .insert_source(Timer::from_duration(CLIENT_TIMEOUT), move |_, _, _| {
+            timeout_handle.remove(token);
+            TimeoutAction::Drop
+        })
+        .map_err(|e| anyhow::anyhow!("Failed to insert IPC client timeout: {:?}", e))?;
+    Ok(())
+}
+
+/// One connection: the command line as it comes in, then the reply as it goes out
+#[derive(Default)]
+struct Client {
+    line: Vec<u8>,
+
+    /// None until the command has run
+    reply: Option<Vec<u8>>,
+    written: usize,
+}
+
+impl Client {
+    /// Read what's there, run the command once the line is in, write what
+    /// fits - true once the whole reply is out
+    fn ready(&mut self, mut stream: &UnixStream, state: &mut VibeWM) -> std::io::Result<bool> {
+        if self.reply.is_none() {
+            let mut buf = [0u8; 1024];
+            while !self.line.contains(&b'\n') && self.line.len() < MAX_LINE_LEN {
+                match stream.read(&mut buf) {
+                    // Hung up without a newline - run what we got
+                    Ok(0) => break,
+                    Ok(n) => self.line.extend_from_slice(&buf[..n]),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(false),
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let end = self.line.iter().position(|&b| b == b'\n').unwrap_or(self.line.len()).min(MAX_LINE_LEN);
+            let line = String::from_utf8_lossy(&self.line[..end]);
+            let mut reply = match state.handle_ipc_command(line.trim()) {
+                Ok(output) => output,
+                Err(e) => format!("error: {}", e),
+            };
+            reply.push('\n');
+            self.reply = Some(reply.into_bytes());
+        }
+
+        let reply = self.reply.as_deref().unwrap_or_default();
+        while self.written < reply.len() {
+            match stream.write(&reply[self.written..]) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => self.written += n,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(false),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl VibeWM {
+    /// Run a single IPC command, returning the reply
+    pub fn handle_ipc_command(&mut self, command: &str) -> Result<String, String> {
+        tracing::debug!("IPC: {}", command);
+
+        let mut words = command.split_whitespace();
+        let name = words.next().unwrap_or("");
+        let args: Vec<&str> = words.collect();
+
+        match name {
+            // Scripts don't get asked "are you sure"
+            "quit" => {
+                self.quit();
+                Ok("ok".to_string())
+            }
+
+            // One tab-separated line per window: id, workspace, pid, cpu%, rss kB, status, title
+            "list-windows" => Ok(self.list_windows()),
+
+            // Window commands, by id from list-windows
+            "snap" => {
+                let [id, position] = args[..] else {
+                    return Err("usage: snap <id> <position>".to_string());
+                };
+                let window = self.ipc_window(id)?;
+                let position = SnapPosition::from_name(position)
+                    .ok_or_else(|| format!("unknown snap position '{}'", position))?;
+
+                if self.on_screen(&window) {
+                    self.snap_window(&window, position);
+                } else if let Some(meta) = self.windows.meta_mut(&window) {
+                    // Switching to its workspace re-snaps it
+                    meta.snap_state = Some(position);
+                }
+                Ok("ok".to_string())
+            }
+            "move" => {
+                let [id, x, y] = args[..] else {
+                    return Err("usage: move <id> <x> <y>".to_string());
+                };
+                let window = self.ipc_window(id)?;
+                let location = (parse_int(x)?, parse_int(y)?).into();
+
+                if self.on_screen(&window) {
+                    self.move_window(&window, location);
+                } else if let Some(meta) = self.windows.meta_mut(&window) {
+                    // Hidden - it shows up there when its workspace does
+                    meta.saved_location = Some(location);
+                    meta.snap_state = None;
+                }
+                Ok("ok".to_string())
+            }
+            "resize" => {
+                let [id, w, h] = args[..] else {
+                    return Err("usage: resize <id> <w> <h>".to_string());
+                };
+                let window = self.ipc_window(id)?;
+                self.resize_window(&window, (parse_int(w)?, parse_int(h)?).into());
+                Ok("ok".to_string())
+            }
+            "focus" => {
+                let [id] = args[..] else {
+                    return Err("usage: focus <id>".to_string());
+                };
+                let window = self.ipc_window(id)?;
+
+                // Bring its workspace up first
+                if let Some(workspace) = self.windows.meta(&window).map(|m| m.workspace) {
+                    self.switch_workspace(workspace);
+                }
+                self.focus_and_raise(&window);
+                Ok("ok".to_string())
+            }
+            "close" => {
+                let [id] = args[..] else {
+                    return Err("usage: close <id>".to_string());
+                };
+                let window = self.ipc_window(id)?;
+                if let Some(toplevel) = window.toplevel() {
+                    toplevel.send_close();
+                }
+                Ok("ok".to_string())
+            }
+
+            // Which rules were checked against a window and what they did.
+            // --dry-run checks config.toml as it is on disk now, without
+            // loading or applying it
+            "explain-window" => {
+                let (id, dry_run) = match args[..] {
+                    [id] => (id, false),
+                    ["--dry-run", id] | [id, "--dry-run"] => (id, true),
+                    _ => return Err("usage: explain-window [--dry-run] <id>".to_string()),
+                };
+                let window = self.ipc_window(id)?;
+                self.explain_window(&window, dry_run)
+            }
+
+            // Open windows a matcher would pick: app_id=<id>, title=<text> or a bare app_id
+            "test-rule" => {
+                let matcher = command[name.len()..].trim();
+                let matcher = Matcher::parse(matcher)?;
+                Ok(self.test_rule(&matcher))
+            }
+
+            // "recording" or "idle", then one line per capture: output, client,
+            // seconds, recording/screenshot
+            "capture-status" => Ok(self.capture_status()),
+
+            // Dump Prometheus metrics to a file (node_exporter textfile collector)
+            #[cfg(feature = "metrics")]
+            "metrics" => {
+                let [path] = args[..] else {
+                    return Err("usage: metrics <file>".to_string());
+                };
+                std::fs::write(path, crate::metrics::render())
+                    .map_err(|e| format!("can't write {}: {}", path, e))?;
+                Ok("ok".to_string())
+            }
+
+            // Toggle spotlight on the focused window (screen sharing)
+            "spotlight" => {
+                self.toggle_spotlight();
+                Ok("ok".to_string())
+            }
+
+            // Re-read keybinds from config and swap them in
+            "reload-keybinds" => {
+                self.reload_keybinds();
+                Ok("ok".to_string())
+            }
+
+            // Pretend the GPU went away, to exercise the recovery path
+            "debug-gpu-reset" => {
+                self.gpu_reset_pending = true;
+                Ok("ok".to_string())
+            }
+
+            // Hang the event loop for a while, to check the stall watchdog fires.
+            // Debug builds only, and never much longer than it takes to fire
+            #[cfg(debug_assertions)]
+            "debug-stall" => {
+                let [secs] = args[..] else {
+                    return Err("usage: debug-stall <seconds>".to_string());
+                };
+                let secs: u64 = secs.parse().map_err(|_| format!("not a number of seconds: {}", secs))?;
+                let Some(timeout) = crate::watchdog::timeout() else {
+                    return Err("the watchdog is off - nothing to test".to_string());
+                };
+                let max = timeout + DEBUG_STALL_MARGIN;
+                if Duration::from_secs(secs) > max {
+                    return Err(format!("at most {}s with this watchdog_timeout_ms", max.as_secs()));
+                }
+                std::thread::sleep(Duration::from_secs(secs));
+                Ok("ok".to_string())
+            }
+
+            "" => Err("empty command".to_string()),
+            other => Err(format!("unknown command '{}'", other)),
+        }
+    }
+
+    /// Is the window on the workspace that's showing?
+    fn on_screen(&self, window: &Window) -> bool {
+        self.windows.meta(window).map(|m| m.workspace) == Some(self.windows.active_workspace())
+    }
+
+    /// Resolve a window id argument
+    fn ipc_window(&self, id: &str) -> Result<Window, String> {
+        let id: u64 = id.parse().map_err(|_| format!("bad window id '{}'", id))?;
+        self.windows
+            .by_id(id)
+            .cloned()
+            .ok_or_else(|| format!("no window with id {}", id))
+    }
+
+    /// app_id and title, then one line per rule: rule, matched/applied, effect
+    fn explain_window(&self, window: &Window, dry_run: bool) -> Result<String, String> {
+        let app_id = window_app_id(window);
+        let trace = if dry_run {
+            let path = Config::path().ok_or("no config path ($XDG_CONFIG_HOME or $HOME)")?;
+            let config = Config::load(&path).map_err(|e| format!("config.toml doesn't load: {:#}", e))?;
+            evaluate(&config, app_id.as_deref())
+        } else {
+            self.windows
+                .meta(window)
+                .and_then(|meta| meta.rules.clone())
+                .unwrap_or_else(|| evaluate(&self.config, app_id.as_deref()))
+        };
+
+        let mut lines = vec![
+            format!("app_id\t{}", trace.app_id.as_deref().unwrap_or("-")),
+            format!("title\t{}", window_title(window)),
+        ];
+        if trace.checks.is_empty() {
+            lines.push("no rules configured".to_string());
+        }
+        lines.extend(trace.checks.iter().map(|check| {
+            let outcome = match (check.matched, check.applied) {
+                (false, _) => "no match",
+                (true, false) => "matched, not applied",
+                (true, true) if dry_run => "would apply",
+                (true, true) => "applied",
+            };
+            format!("{}\t{}\t{}", check.rule, outcome, check.effect)
+        }));
+
+        Ok(lines.join("\n"))
+    }
+
+    /// Matching windows in list-windows' id/workspace/title columns
+    fn test_rule(&self, matcher: &Matcher) -> String {
+        let matching: Vec<String> = self.windows
+            .all()
+            .iter()
+            .filter(|window| matcher.matches(window_app_id(window).as_deref(), &window_title(window)))
+            .filter_map(|window| {
+                let meta = self.windows.meta(window)?;
+                Some(format!("{}\t{}\t{}", meta.id, self.workspaces.name(meta.workspace), window_title(window)))
+            })
+            .collect();
+
+        if matching.is_empty() {
+            "no open windows match".to_string()
+        } else {
+            matching.join("\n")
+        }
+    }
+
+    fn capture_status(&self) -> String {
+        let mut lines = vec![if self.captures.any_recording() { "recording" } else { "idle" }.to_string()];
+
+        lines.extend(self.captures.sessions().iter().map(|session| {
+            format!(
+                "{}\t{}\t{}\t{}",
+                session.output,
+                session.client,
+                session.started.elapsed().as_secs(),
+                if session.with_damage { "recording" } else { "screenshot" },
+            )
+        }));
+
+        lines.join("\n")
+    }
+
+    fn list_windows(&self) -> String {
+        self.windows
+            .all()
+            .iter()
+            .filter_map(|window| {
+                let meta = self.windows.meta(window)?;
+                let usage = meta.pid.and_then(|pid| self.proc_sampler.usage(pid));
+
+                Some(format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    meta.id,
+                    self.workspaces.name(meta.workspace),
+                    meta.pid.map(|p| p.to_string()).unwrap_or_else(|| "-".to_string()),
+                    usage.map(|u| format!("{:.1}", u.cpu_percent)).unwrap_or_else(|| "-".to_string()),
+                    usage.map(|u| u.rss_kb.to_string()).unwrap_or_else(|| "-".to_string()),
+                    if self.is_unresponsive(window) { "not-responding" } else { "ok" },
+                    window_title(window),
+                ))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn parse_int(value: &str) -> Result<i32, String> {
+    value.parse().map_err(|_| format!("expected a number, got '{}'", value))
+}
@@ -0,0 +1,461 @@
+//! Keybinds - which key combo runs which action
+//!
+//! The table is built from config and swapped out whole on reload, so a
+//! keypress never sees half an update.
+
+use std::collections::HashMap;
+
+use smithay::input::keyboard::{xkb, Keysym, ModifiersState};
+
+use crate::action::Action;
+use crate::config::SnapPosition;
+use crate::window::Direction;
+#[cfg(feature = "compositor")]
+use crate::{
+    config::ResizeModeStyle,
+    overlay::CheatSheetGroup,
+    rules::passthrough_chords,
+    state::VibeWM,
+    window::window_app_id,
+};
+
+/// Modifiers that take part in a binding (lock keys don't)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Mods {
+    pub logo: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl From<&ModifiersState> for Mods {
+    fn from(state: &ModifiersState) -> Self {
+        Self {
+            logo: state.logo,
+            ctrl: state.ctrl,
+            alt: state.alt,
+            shift: state.shift,
+        }
+    }
+}
+
+/// A key combo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Keybind {
+    pub mods: Mods,
+    pub keysym: Keysym,
+}
+
+impl Keybind {
+    /// mod+key
+    pub fn logo(keysym: Keysym) -> Self {
+        Self {
+            mods: Mods { logo: true, ..Mods::default() },
+            keysym,
+        }
+    }
+
+    /// mod+shift+key
+    pub fn logo_shift(keysym: Keysym) -> Self {
+        Self {
+            mods: Mods { logo: true, shift: true, ..Mods::default() },
+            keysym,
+        }
+    }
+
+    /// mod+ctrl+key
+    pub fn logo_ctrl(keysym: Keysym) -> Self {
+        Self {
+            mods: Mods { logo: true, ctrl: true, ..Mods::default() },
+            keysym,
+        }
+    }
+
+    /// mod+ctrl+shift+key
+    pub fn logo_ctrl_shift(keysym: Keysym) -> Self {
+        Self {
+            mods: Mods { logo: true, ctrl: true, shift: true, ..Mods::default() },
+            keysym,
+        }
+    }
+
+    /// How the cheat sheet shows it: "mod+shift+W"
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        for (held, name) in [
+            (self.mods.logo, "mod+"),
+            (self.mods.ctrl, "ctrl+"),
+            (self.mods.alt, "alt+"),
+            (self.mods.shift, "shift+"),
+        ] {
+            if held {
+                label.push_str(name);
+            }
+        }
+        label.push_str(&xkb::keysym_get_name(self.keysym));
+        label
+    }
+
+    /// "super+Tab", "ctrl+alt+Delete" - modifiers, then an xkb key name
+    ///
+    /// Shifted letters go by their shifted name, like the table: "super+shift+W".
+    pub fn parse(chord: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = chord.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|k| !k.is_empty())?;
+
+        let mut mods = Mods::default();
+        for part in parts {
+            match part.to_lowercase().as_str() {
+                "super" | "logo" | "mod" | "win" => mods.logo = true,
+                "ctrl" | "control" => mods.ctrl = true,
+                "alt" => mods.alt = true,
+                "shift" => mods.shift = true,
+                _ => return None,
+            }
+        }
+
+        // Exact name first so "q" and "Q" stay different keys
+        let mut keysym = xkb::keysym_from_name(key, xkb::KEYSYM_NO_FLAGS);
+        if keysym == Keysym::NoSymbol {
+            keysym = xkb::keysym_from_name(key, xkb::KEYSYM_CASE_INSENSITIVE);
+        }
+        if keysym == Keysym::NoSymbol {
+            return None;
+        }
+
+        Some(Self { mods, keysym })
+    }
+}
+
+/// Cheat sheet group order
+const CHEAT_SHEET_ORDER: &[&str] = &["Windows", "Focus", "Workspaces", "Zoom", "General"];
+
+/// The stock bindings
+pub fn default_bindings() -> Vec<(Keybind, Action)> {
+    let mut bindings = vec![
+        // Global quit
+        (Keybind::logo(Keysym::q), Action::Quit),
+
+        // Command center (or the external launcher when it's off): mod+S
+        (Keybind::logo(Keysym::s), Action::ToggleCommandCenter),
+
+        // Focus cycling: mod+Tab (Shift+Tab usually arrives as ISO_Left_Tab)
+        (Keybind::logo(Keysym::Tab), Action::FocusNext),
+        (Keybind::logo_shift(Keysym::Tab), Action::FocusPrev),
+        (Keybind::logo_shift(Keysym::ISO_Left_Tab), Action::FocusPrev),
+
+        // Vim motions: ijkl (resize while mod+R is held)
+        (Keybind::logo(Keysym::i), Action::Move(Direction::Up)),
+        (Keybind::logo(Keysym::k), Action::Move(Direction::Down)),
+        (Keybind::logo(Keysym::j), Action::Move(Direction::Left)),
+        (Keybind::logo(Keysym::l), Action::Move(Direction::Right)),
+
+        // Arrow keys for snap
+        (Keybind::logo(Keysym::Left), Action::Snap(SnapPosition::Left)),
+        (Keybind::logo(Keysym::Right), Action::Snap(SnapPosition::Right)),
+        (Keybind::logo(Keysym::Up), Action::Snap(SnapPosition::Top)),
+        (Keybind::logo(Keysym::Down), Action::Snap(SnapPosition::Bottom)),
+
+        // mod+ctrl+shift+arrows: move the workspace between outputs
+        (Keybind::logo_ctrl_shift(Keysym::Left), Action::MoveWorkspaceToOutput(Direction::Left)),
+        (Keybind::logo_ctrl_shift(Keysym::Right), Action::MoveWorkspaceToOutput(Direction::Right)),
+
+        // Close window: mod+W
+        (Keybind::logo(Keysym::w), Action::CloseWindow),
+
+        // Frozen window that ignores mod+W: mod+shift+W
+        (Keybind::logo_shift(Keysym::W), Action::ForceCloseWindow),
+
+        // Swap focused into the biggest spot: mod+shift+Return
+        (Keybind::logo_shift(Keysym::Return), Action::PromoteFocused),
+
+        // Even grid of everything: mod+B
+        (Keybind::logo(Keysym::b), Action::BalanceWindows),
+
+        // Spotlight the focused window: mod+ctrl+F
+        (Keybind::logo_ctrl(Keysym::f), Action::ToggleSpotlight),
+
+        // On every workspace: mod+shift+S
+        (Keybind::logo_shift(Keysym::S), Action::ToggleSticky),
+
+        // Window menu without the titlebar: mod+Menu
+        (Keybind::logo(Keysym::Menu), Action::WindowMenu),
+
+        // Region screenshot: mod+shift+Print
+        (Keybind::logo_shift(Keysym::Print), Action::ScreenshotRegion),
+
+        // Re-read config.toml: mod+shift+R
+        (Keybind::logo_shift(Keysym::R), Action::ReloadConfig),
+
+        // Cheat sheet: mod+/
+        (Keybind::logo(Keysym::slash), Action::ToggleCheatSheet),
+
+        // Magnifier: mod+= / mod+- / mod+0 (mod+scroll works too)
+        (Keybind::logo(Keysym::equal), Action::ZoomIn),
+        (Keybind::logo(Keysym::minus), Action::ZoomOut),
+        (Keybind::logo(Keysym::_0), Action::ZoomReset),
+    ];
+
+    // Workspaces: mod+1..9, mod+shift+1..9 sends the focused window there
+    for n in 0..9 {
+        let digit = Keysym::new(0x31 + n);
+        bindings.push((Keybind::logo(digit), Action::SwitchWorkspace(n as usize)));
+        bindings.push((Keybind::logo_shift(digit), Action::SendToWorkspace(n as usize)));
+    }
+
+    bindings
+}
+
+/// Live keybind table
+pub struct Keybinds {
+    map: HashMap<Keybind, Action>,
+}
+
+impl Keybinds {
+    /// Build the table - later entries win over earlier ones, with a warning
+    pub fn from_config(bindings: &[(Keybind, Action)]) -> Self {
+        let mut map = HashMap::new();
+
+        for (bind, action) in bindings {
+            if let Some(previous) = map.insert(*bind, action.clone()) {
+                if previous != *action {
+                    tracing::warn!(
+                        "{} is bound to both \"{}\" and \"{}\" - using \"{}\"",
+                        bind.label(),
+                        previous.describe(),
+                        action.describe(),
+                        action.describe(),
+                    );
+                }
+            }
+        }
+
+        Self { map }
+    }
+
+    pub fn lookup(&self, mods: Mods, keysym: Keysym) -> Option<&Action> {
+        self.map.get(&Keybind { mods, keysym })
+    }
+
+    /// lookup(), falling back to the key's unshifted (latin) symbol - shift+1
+    /// is "exclam" on one layout and something else on the next, the binding
+    /// says shift+1
+    pub fn lookup_key(&self, mods: Mods, keysym: Keysym, raw: Option<Keysym>) -> Option<&Action> {
+        self.lookup(mods, keysym).or_else(|| self.lookup(mods, raw?))
+    }
+}
+
+#[cfg(feature = "compositor")]
+impl VibeWM {
+    /// Rebuild the keybind table from config and swap it in
+    pub fn reload_keybinds(&mut self) {
+        self.keybinds = Keybinds::from_config(&self.config.keybinds);
+
+        // Held keys may not mean the same thing anymore - don't leave them stuck
+        self.input.resize_mode = false;
+        self.quit_confirm.cancel();
+
+        tracing::info!("Keybinds reloaded ({} bindings)", self.keybinds.map.len());
+
+        // Config may have new pass-through rules too
+        self.input.passthrough.clear();
+        self.input.passthrough_window = None;
+        self.update_passthrough();
+    }
+
+    /// What the cheat sheet lists - the live table, grouped, in config order
+    ///
+    /// While resize mode is on it's only the resize mode bindings.
+    pub fn cheat_sheet_groups(&self) -> Vec<CheatSheetGroup> {
+        let mut groups: Vec<CheatSheetGroup> = Vec::new();
+        let mut add = |title: &'static str, keys: String, what: String| {
+            match groups.iter_mut().find(|group| group.title == title) {
+                Some(group) => group.bindings.push((keys, what)),
+                None => groups.push(CheatSheetGroup { title, bindings: vec![(keys, what)] }),
+            }
+        };
+
+        let resize_key = self.config.resize_mode_key.label();
+        if self.input.resize_mode {
+            for (bind, action) in self.effective_bindings() {
+                if let Action::Move(direction) = action {
+                    add("Resize mode", bind.label(), Action::Resize(*direction).describe());
+                }
+            }
+            let leave = match self.config.resize_mode_style {
+                ResizeModeStyle::Hold => format!("release {}", resize_key),
+                ResizeModeStyle::Toggle => format!("Escape / {}", resize_key),
+            };
+            add("Resize mode", leave, "Leave resize mode".to_string());
+            return groups;
+        }
+
+        for (bind, action) in self.effective_bindings() {
+            add(action.category(), bind.label(), action.describe());
+        }
+        add("Windows", resize_key, "Resize mode (ijkl resize)".to_string());
+
+        groups.sort_by_key(|group| {
+            CHEAT_SHEET_ORDER.iter().position(|title| *title == group.title).unwrap_or(usize::MAX)
+        });
+        groups
+    }
+
+    /// Bindings that are actually live, in config order
+    ///
+    /// Entries overridden by a later one are dropped, and so are second
+    /// spellings of the same chord (mod+shift+Tab vs ISO_Left_Tab).
+    fn effective_bindings(&self) -> Vec<(Keybind, &Action)> {
+        let mut live: Vec<(Keybind, &Action)> = Vec::new();
+        for (bind, action) in &self.config.keybinds {
+            let overridden = self.keybinds.lookup(bind.mods, bind.keysym) != Some(action);
+            let duplicate = live.iter().any(|(seen, seen_action)| seen.mods == bind.mods && *seen_action == action);
+            if !overridden && !duplicate {
+                live.push((*bind, action));
+            }
+        }
+        live
+    }
+
+    /// Work out which chords go straight to the focused window (called every frame)
+    ///
+    /// Only does anything when focus moved to a different window.
+    pub fn update_passthrough(&mut self) {
+        let focused = self.windows.focused().cloned();
+        let id = focused.as_ref().and_then(|w| self.windows.meta(w)).map(|m| m.id);
+        if id == self.input.passthrough_window {
+            return;
+        }
+        self.input.passthrough_window = id;
+
+        let app_id = focused.as_ref().and_then(window_app_id);
+        let chords = passthrough_chords(&self.config, app_id.as_deref());
+
+        self.input.passthrough = chords
+            .iter()
+            .filter_map(|chord| {
+                let bind = Keybind::parse(chord);
+                if bind.is_none() {
+                    tracing::warn!("Pass-through: can't parse key chord {:?}", chord);
+                }
+                bind
+            })
+            .collect();
+
+        tracing::debug!(
+            "Pass-through for {:?}: {}",
+            app_id.as_deref().unwrap_or("(no app_id)"),
+            if self.input.passthrough.is_empty() {
+                "none".to_string()
+            } else {
+                chords.join(", ")
+            }
+        );
+    }
+
+    /// Does this chord belong to the focused window instead of us?
+    pub fn is_passthrough(&self, modifiers: &ModifiersState, keysym: Keysym) -> bool {
+        let chord = Keybind { mods: Mods::from(modifiers), keysym };
+        self.input.passthrough.contains(&chord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mods(logo: bool, ctrl: bool, alt: bool, shift: bool) -> Mods {
+        Mods { logo, ctrl, alt, shift }
+    }
+
+    #[test]
+    fn modifier_aliases_and_case() {
+        let expected = Keybind::logo(Keysym::q);
+        for chord in ["super+q", "mod+q", "logo+q", "win+q", "SUPER+q", "Super + q"] {
+            assert_eq!(Keybind::parse(chord), Some(expected), "{}", chord);
+        }
+
+        let bind = Keybind::parse("Control+ALT+Delete").unwrap();
+        assert_eq!(bind.mods, mods(false, true, true, false));
+        assert_eq!(bind.keysym, Keysym::Delete);
+    }
+
+    #[test]
+    fn modifier_order_and_repeats_dont_matter() {
+        let a = Keybind::parse("shift+ctrl+super+Left").unwrap();
+        let b = Keybind::parse("super+ctrl+shift+shift+Left").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a, Keybind::logo_ctrl_shift(Keysym::Left));
+    }
+
+    #[test]
+    fn key_names_keep_case_when_they_can() {
+        // "q" and "Q" are different keysyms, like in the table
+        assert_eq!(Keybind::parse("super+shift+W"), Some(Keybind::logo_shift(Keysym::W)));
+        assert_ne!(Keybind::parse("super+q"), Keybind::parse("super+Q"));
+
+        // ...and fall back to case-insensitive for the named ones
+        assert_eq!(Keybind::parse("super+return"), Some(Keybind::logo(Keysym::Return)));
+        assert_eq!(Keybind::parse("super+plus").map(|b| b.keysym), Some(Keysym::plus));
+    }
+
+    #[test]
+    fn bare_keys_have_no_modifiers() {
+        let bind = Keybind::parse("Print").unwrap();
+        assert_eq!(bind.mods, Mods::default());
+        assert_eq!(bind.keysym, Keysym::Print);
+    }
+
+    #[test]
+    fn rejects_malformed_chords() {
+        for chord in ["", "+", "super+", "super++", "hyper+q", "super+notakey", "super+shift"] {
+            assert_eq!(Keybind::parse(chord), None, "{}", chord);
+        }
+    }
+
+    #[test]
+    fn config_names_resolve() {
+        assert_eq!(Action::from_config_name("close_window"), Some(Action::CloseWindow));
+        assert_eq!(Action::from_config_name("snap_top_left"), Some(Action::Snap(SnapPosition::TopLeft)));
+        assert_eq!(Action::from_config_name("workspace_1"), Some(Action::SwitchWorkspace(0)));
+        assert_eq!(Action::from_config_name("workspace_9"), Some(Action::SwitchWorkspace(8)));
+        assert_eq!(Action::from_config_name("send_to_workspace_2"), Some(Action::SendToWorkspace(1)));
+
+        for name in ["workspace_0", "workspace_10", "workspace_", "send_to_workspace_0", "Close_Window", "exec", ""] {
+            assert_eq!(Action::from_config_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn shifted_digits_fall_back_to_the_raw_key() {
+        let keybinds = Keybinds::from_config(&default_bindings());
+        let logo_shift = mods(true, false, false, true);
+
+        // US layout: shift+3 comes in as numbersign
+        assert_eq!(
+            keybinds.lookup_key(logo_shift, Keysym::numbersign, Some(Keysym::_3)),
+            Some(&Action::SendToWorkspace(2))
+        );
+        assert_eq!(keybinds.lookup_key(logo_shift, Keysym::numbersign, None), None);
+
+        // The shifted symbol still wins when it's bound itself
+        assert_eq!(
+            keybinds.lookup_key(logo_shift, Keysym::W, Some(Keysym::w)),
+            Some(&Action::ForceCloseWindow)
+        );
+    }
+
+    #[test]
+    fn command_center_toggle_is_a_table_entry() {
+        let keybinds = Keybinds::from_config(&default_bindings());
+        let logo = mods(true, false, false, false);
+        assert_eq!(keybinds.lookup(logo, Keysym::s), Some(&Action::ToggleCommandCenter));
+    }
+
+    #[test]
+    fn later_duplicate_wins() {
+        let combo = Keybind::logo(Keysym::x);
+        let keybinds = Keybinds::from_config(&[(combo, Action::CloseWindow), (combo, Action::Quit)]);
+        assert_eq!(keybinds.lookup(combo.mods, combo.keysym), Some(&Action::Quit));
+    }
+}
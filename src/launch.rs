@@ -0,0 +1,253 @@
+//! Launching apps
+//!
+//! Everything the command center starts goes through here. Commands are
+//! spawned as argv directly - desktop file contents never reach a shell.
+
+use std::path::Path;
+use std::process::{Child, Command};
+use std::sync::Mutex;
+
+/// Env vars that are about us, not the apps we start
+const SCRUBBED_ENV: &[&str] = &[
+    // Handed to us by whoever started us - an fd that's ours alone
+    "WAYLAND_SOCKET",
+    // systemd's, meant for vibeWM (sd_notify drops them too, but it may be off)
+    "NOTIFY_SOCKET",
+    "WATCHDOG_USEC",
+    "WATCHDOG_PID",
+];
+
+/// Vars vibeWM set on itself (software rendering fallback etc.)
+static INTERNAL_ENV: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+/// Set an env var for vibeWM only - apps we launch don't inherit it
+pub fn set_internal_var(name: &'static str, value: &str) {
+    std::env::set_var(name, value);
+    if let Ok(mut vars) = INTERNAL_ENV.lock() {
+        if !vars.contains(&name) {
+            vars.push(name);
+        }
+    }
+}
+
+/// Config `env` - applied to everything we spawn
+static LAUNCH_ENV: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Set the env every launch gets (config `env`)
+///
+/// Names that can't be env vars are dropped with a warning instead of
+/// failing every launch later.
+pub fn set_launch_env(vars: &[(String, String)]) {
+    let valid = vars
+        .iter()
+        .filter(|(name, _)| {
+            let ok = valid_var_name(name);
+            if !ok {
+                tracing::warn!("Ignoring env var {:?} - not a valid name", name);
+            }
+            ok
+        })
+        .cloned()
+        .collect();
+
+    if let Ok(mut env) = LAUNCH_ENV.lock() {
+        *env = valid;
+    }
+}
+
+fn valid_var_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['=', '\0'])
+}
+
+/// Apps that bring their own sandbox (and their own cgroup scope)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sandbox {
+    /// `flatpak run [options] <app id>`
+    Flatpak { app_id: String },
+
+    /// `snap run <name>` or /snap/bin/<name>
+    Snap { name: String },
+}
+
+/// Tell sandboxed apps from their Exec line
+pub fn detect_sandbox(argv: &[String]) -> Option<Sandbox> {
+    let program = Path::new(argv.first()?);
+    let name = program.file_name()?.to_str()?;
+
+    // First argument after `run` that isn't an option
+    let run_target = || {
+        let mut rest = argv[1..].iter();
+        rest.next().filter(|arg| *arg == "run")?;
+        rest.find(|arg| !arg.starts_with('-')).cloned()
+    };
+
+    match name {
+        "flatpak" => run_target().map(|app_id| Sandbox::Flatpak { app_id }),
+        "snap" => run_target().map(|name| Sandbox::Snap { name }),
+        _ if program.starts_with("/snap/bin") => Some(Sandbox::Snap { name: name.to_string() }),
+        _ => None,
+    }
+}
+
+/// Spawn a command from its argv
+///
+/// The child gets our WAYLAND_DISPLAY and none of our own env.
+pub fn spawn(argv: &[String]) -> std::io::Result<Child> {
+    spawn_with_env(argv, &[])
+}
+
+/// Spawn with extra env on top of config `env` (per-app vars win)
+pub fn spawn_with_env(argv: &[String], env: &[(String, String)]) -> std::io::Result<Child> {
+    command(argv, env)?.spawn()
+}
+
+/// The Command spawn_with_env would run, for callers that need stdio too
+pub fn command(argv: &[String], env: &[(String, String)]) -> std::io::Result<Command> {
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty command"))?;
+
+    let mut command = Command::new(program);
+    command.args(args);
+
+    for var in SCRUBBED_ENV {
+        command.env_remove(var);
+    }
+    if let Ok(vars) = INTERNAL_ENV.lock() {
+        for var in vars.iter() {
+            command.env_remove(var);
+        }
+    }
+
+    if let Ok(vars) = LAUNCH_ENV.lock() {
+        command.envs(vars.iter().map(|(name, value)| (name, value)));
+    }
+    command.envs(env.iter().filter(|(name, _)| valid_var_name(name)).map(|(name, value)| (name, value)));
+
+    // Set up by VibeWM::new - make sure nothing upstream shadows it
+    if let Some(display) = std::env::var_os("WAYLAND_DISPLAY") {
+        command.env("WAYLAND_DISPLAY", display);
+        command.env("XDG_SESSION_TYPE", "wayland");
+    }
+
+    Ok(command)
+}
+
+/// Open a file with the user's default app for it
+pub fn open_path(path: &Path) -> std::io::Result<Child> {
+    command(&["xdg-open".to_string()], &[])?.arg(path).spawn()
+}
+
+/// Launch an app from the command center
+///
+/// Goes through launch_command_template, except for Flatpak/Snap apps - they
+/// set up their own scope, and wrapping them just nests a second one.
+/// `env` is the app's own vars (Config::env_for_app).
+pub fn launch_app(template: &[String], argv: &[String], env: &[(String, String)]) -> std::io::Result<Child> {
+    match detect_sandbox(argv) {
+        Some(Sandbox::Flatpak { .. }) => {
+            // flatpak run starts the app with a fresh env - hand ours over as --env
+            let mut vars = LAUNCH_ENV.lock().map(|vars| vars.clone()).unwrap_or_default();
+            vars.extend(env.iter().cloned());
+            tracing::debug!("Launching Flatpak app without the wrapper");
+            spawn_with_env(&flatpak_env_args(argv, &vars), env)
+        }
+        Some(sandbox) => {
+            tracing::debug!("Launching sandboxed app {:?} without the wrapper", sandbox);
+            spawn_with_env(argv, env)
+        }
+        None => spawn_with_env(&apply_template(template, argv), env),
+    }
+}
+
+/// `flatpak run org.foo.Bar` -> `flatpak run --env=A=1 org.foo.Bar`
+fn flatpak_env_args(argv: &[String], vars: &[(String, String)]) -> Vec<String> {
+    let Some(run) = argv.iter().position(|arg| arg == "run") else {
+        return argv.to_vec();
+    };
+
+    let mut out = argv[..=run].to_vec();
+    out.extend(
+        vars.iter()
+            .filter(|(name, _)| valid_var_name(name))
+            .map(|(name, value)| format!("--env={}={}", name, value)),
+    );
+    out.extend_from_slice(&argv[run + 1..]);
+    out
+}
+
+/// Argument in launch_command_template that the app's argv gets spliced into
+pub const EXEC_PLACEHOLDER: &str = "%exec";
+
+/// Check a launch template - it needs exactly one `%exec` argument
+pub fn validate_template(template: &[String]) -> Result<(), String> {
+    match template.iter().filter(|arg| *arg == EXEC_PLACEHOLDER).count() {
+        1 => Ok(()),
+        0 => Err(format!("launch_command_template needs a {} argument", EXEC_PLACEHOLDER)),
+        _ => Err(format!("launch_command_template has more than one {}", EXEC_PLACEHOLDER)),
+    }
+}
+
+/// Wrap an app's argv in the launch template
+///
+/// `["systemd-run", "--user", "--scope", "--", "%exec"]` + `["foot"]`
+/// -> `["systemd-run", "--user", "--scope", "--", "foot"]`
+pub fn apply_template(template: &[String], argv: &[String]) -> Vec<String> {
+    template
+        .iter()
+        .flat_map(|arg| {
+            if arg == EXEC_PLACEHOLDER {
+                argv.to_vec()
+            } else {
+                vec![arg.clone()]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn flatpak_env_goes_in_after_run() {
+        let vars = vec![
+            ("MOZ_ENABLE_WAYLAND".to_string(), "1".to_string()),
+            ("GTK_THEME".to_string(), "Adwaita:dark".to_string()),
+        ];
+
+        assert_eq!(
+            flatpak_env_args(&args(&["flatpak", "run", "--branch=stable", "org.mozilla.firefox", "%u"]), &vars),
+            args(&[
+                "flatpak",
+                "run",
+                "--env=MOZ_ENABLE_WAYLAND=1",
+                "--env=GTK_THEME=Adwaita:dark",
+                "--branch=stable",
+                "org.mozilla.firefox",
+                "%u",
+            ]),
+        );
+    }
+
+    #[test]
+    fn flatpak_env_skips_what_it_cant_pass() {
+        let vars = vec![
+            ("".to_string(), "x".to_string()),
+            ("A=B".to_string(), "x".to_string()),
+            ("EMPTY".to_string(), "".to_string()),
+        ];
+        assert_eq!(
+            flatpak_env_args(&args(&["flatpak", "run", "org.foo.Bar"]), &vars),
+            args(&["flatpak", "run", "--env=EMPTY=", "org.foo.Bar"]),
+        );
+
+        // No `run` to put them after - left alone
+        let argv = args(&["flatpak", "info", "org.foo.Bar"]);
+        assert_eq!(flatpak_env_args(&argv, &vars), argv);
+    }
+}
@@ -0,0 +1,376 @@
+//! Snap layout math
+//!
+//! Pure rectangle arithmetic, no compositor state - snap_window asks for a
+//! rect and applies it.
+
+use smithay::utils::{Logical, Point, Rectangle, Size};
+
+use crate::config::SnapPosition;
+
+/// Gaps around snapped windows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnapGaps {
+    /// Between windows and the output edges
+    pub outer: i32,
+
+    /// Between the two halves (or four quarters)
+    pub inner: i32,
+}
+
+/// Where a window snapped to `position` ends up, in the same coordinates as `output_rect`
+///
+/// `ratio` is how much of the usable width/height the left/top side gets
+/// (0.5 = even halves). The two sides always tile the usable area exactly
+/// with `inner` pixels between them, whatever the gap and output size - the
+/// rounding lands on the second side. `current_size` only matters for
+/// Center, which keeps the window's size.
+pub fn compute_snap_rect(
+    position: SnapPosition,
+    output_rect: Rectangle<i32, Logical>,
+    gaps: SnapGaps,
+    ratio: f64,
+    current_size: Size<i32, Logical>,
+) -> Rectangle<i32, Logical> {
+    let usable = usable_area(output_rect, gaps.outer);
+    let (x, y) = (usable.loc.x, usable.loc.y);
+    let (w, h) = (usable.size.w, usable.size.h);
+
+    // (offset, length) of the first and second side along each axis
+    let (left, right) = split(w, gaps.inner, ratio);
+    let (top, bottom) = split(h, gaps.inner, ratio);
+    let full_w = (0, w);
+    let full_h = (0, h);
+
+    let (cols, rows) = match position {
+        SnapPosition::Left => (left, full_h),
+        SnapPosition::Right => (right, full_h),
+        SnapPosition::Top => (full_w, top),
+        SnapPosition::Bottom => (full_w, bottom),
+        SnapPosition::TopLeft => (left, top),
+        SnapPosition::TopRight => (right, top),
+        SnapPosition::BottomLeft => (left, bottom),
+        SnapPosition::BottomRight => (right, bottom),
+        SnapPosition::Maximize => (full_w, full_h),
+        SnapPosition::Center => {
+            // Keep the size, but never bigger than what fits
+            let cw = current_size.w.clamp(0, w);
+            let ch = current_size.h.clamp(0, h);
+            (((w - cw) / 2, cw), ((h - ch) / 2, ch))
+        }
+    };
+
+    Rectangle::new((x + cols.0, y + rows.0).into(), (cols.1, rows.1).into())
+}
+
+/// The output minus the outer gap on every side
+///
+/// A gap too big for the output eats it down to nothing rather than going negative.
+pub fn usable_area(output_rect: Rectangle<i32, Logical>, outer: i32) -> Rectangle<i32, Logical> {
+    let gap_x = outer.clamp(0, output_rect.size.w.max(0) / 2);
+    let gap_y = outer.clamp(0, output_rect.size.h.max(0) / 2);
+
+    Rectangle::new(
+        (output_rect.loc.x + gap_x, output_rect.loc.y + gap_y).into(),
+        (
+            (output_rect.size.w - gap_x * 2).max(0),
+            (output_rect.size.h - gap_y * 2).max(0),
+        )
+            .into(),
+    )
+}
+
+/// Move `rect` as little as possible so it's on `area` - for windows coming
+/// back on an output that isn't the one they left (smaller, or elsewhere)
+///
+/// Too big to fit, it lines up with the top-left corner so the edge with the
+/// titlebar and close button is on screen.
+pub fn fit_inside(rect: Rectangle<i32, Logical>, area: Rectangle<i32, Logical>) -> Point<i32, Logical> {
+    let fit = |pos: i32, len: i32, start: i32, area_len: i32| pos.min(start + area_len - len).max(start);
+
+    (
+        fit(rect.loc.x, rect.size.w, area.loc.x, area.size.w),
+        fit(rect.loc.y, rect.size.h, area.loc.y, area.size.h),
+    )
+        .into()
+}
+
+/// Smallest a resize by hand (keys or pointer) makes a window, unless its
+/// max size is smaller
+pub const MIN_RESIZE: i32 = 100;
+
+/// Fit a size into min/max limits (0 = no limit on that axis)
+///
+/// The min wins when a client sets it above its max.
+pub fn clamp_to_limits(
+    size: Size<i32, Logical>,
+    min: Size<i32, Logical>,
+    max: Size<i32, Logical>,
+) -> Size<i32, Logical> {
+    let clamp = |value: i32, min: i32, max: i32| {
+        let value = if max > 0 { value.min(max) } else { value };
+        value.max(min).max(1)
+    };
+    (clamp(size.w, min.w, max.w), clamp(size.h, min.h, max.h)).into()
+}
+
+/// clamp_to_limits() for a resize by hand - never under MIN_RESIZE, unless
+/// the max says so
+pub fn clamp_resize(
+    size: Size<i32, Logical>,
+    min: Size<i32, Logical>,
+    max: Size<i32, Logical>,
+) -> Size<i32, Logical> {
+    let floor = |min: i32, max: i32| min.max(if max > 0 { MIN_RESIZE.min(max) } else { MIN_RESIZE });
+    clamp_to_limits(size, (floor(min.w, max.w), floor(min.h, max.h)).into(), max)
+}
+
+/// The part of `area` a window with these limits takes - all of it if it
+/// can, otherwise its nearest size centered on it (sticking out when the min
+/// is bigger than the area)
+pub fn fit_to_limits(
+    area: Rectangle<i32, Logical>,
+    min: Size<i32, Logical>,
+    max: Size<i32, Logical>,
+) -> Rectangle<i32, Logical> {
+    let size = clamp_to_limits(area.size, min, max);
+    let loc = (
+        area.loc.x + (area.size.w - size.w) / 2,
+        area.loc.y + (area.size.h - size.h) / 2,
+    );
+    Rectangle::new(loc.into(), size)
+}
+
+/// Split `length` into two sides with `gap` between them
+///
+/// Returns (offset, length) for each side, relative to the start.
+fn split(length: i32, gap: i32, ratio: f64) -> ((i32, i32), (i32, i32)) {
+    let gap = gap.clamp(0, length);
+    let available = length - gap;
+    let ratio = if ratio.is_finite() { ratio.clamp(0.0, 1.0) } else { 0.5 };
+
+    let first = ((available as f64 * ratio).round() as i32).clamp(0, available);
+    let second = available - first;
+
+    ((0, first), (first + gap, second))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const QUARTERS: [SnapPosition; 4] = [
+        SnapPosition::TopLeft,
+        SnapPosition::TopRight,
+        SnapPosition::BottomLeft,
+        SnapPosition::BottomRight,
+    ];
+
+    const ALL: [SnapPosition; 10] = [
+        SnapPosition::Left,
+        SnapPosition::Right,
+        SnapPosition::Top,
+        SnapPosition::Bottom,
+        SnapPosition::TopLeft,
+        SnapPosition::TopRight,
+        SnapPosition::BottomLeft,
+        SnapPosition::BottomRight,
+        SnapPosition::Maximize,
+        SnapPosition::Center,
+    ];
+
+    fn output() -> impl Strategy<Value = Rectangle<i32, Logical>> {
+        (-4000..4000, -4000..4000, 0..8000, 0..8000)
+            .prop_map(|(x, y, w, h)| Rectangle::new((x, y).into(), (w, h).into()))
+    }
+
+    fn gaps() -> impl Strategy<Value = SnapGaps> {
+        (0..200, 0..200).prop_map(|(outer, inner)| SnapGaps { outer, inner })
+    }
+
+    /// Edges included - a zero-sized rect on the border still counts as inside
+    fn inside(outer: Rectangle<i32, Logical>, inner: Rectangle<i32, Logical>) -> bool {
+        inner.loc.x >= outer.loc.x
+            && inner.loc.y >= outer.loc.y
+            && inner.loc.x + inner.size.w <= outer.loc.x + outer.size.w
+            && inner.loc.y + inner.size.h <= outer.loc.y + outer.size.h
+    }
+
+    /// Disjoint when separated on either axis (touching edges is fine)
+    fn disjoint(a: Rectangle<i32, Logical>, b: Rectangle<i32, Logical>) -> bool {
+        a.loc.x + a.size.w <= b.loc.x
+            || b.loc.x + b.size.w <= a.loc.x
+            || a.loc.y + a.size.h <= b.loc.y
+            || b.loc.y + b.size.h <= a.loc.y
+    }
+
+    fn rect(position: SnapPosition, output: Rectangle<i32, Logical>, gaps: SnapGaps, ratio: f64) -> Rectangle<i32, Logical> {
+        compute_snap_rect(position, output, gaps, ratio, (800, 600).into())
+    }
+
+    #[test]
+    fn even_halves_with_odd_gap() {
+        let output = Rectangle::new((0, 0).into(), (1920, 1080).into());
+        let gaps = SnapGaps { outer: 10, inner: 7 };
+
+        let left = rect(SnapPosition::Left, output, gaps, 0.5);
+        let right = rect(SnapPosition::Right, output, gaps, 0.5);
+
+        assert_eq!(left, Rectangle::new((10, 10).into(), (947, 1060).into()));
+        assert_eq!(right.loc.x - (left.loc.x + left.size.w), 7);
+        assert_eq!(right.loc.x + right.size.w, 1910);
+    }
+
+    #[test]
+    fn windows_on_a_returning_output_stay_put() {
+        let output = Rectangle::new((0, 0).into(), (1920, 1080).into());
+        let window = Rectangle::new((100, 200).into(), (800, 600).into());
+        assert_eq!(fit_inside(window, output), Point::from((100, 200)));
+    }
+
+    #[test]
+    fn windows_come_back_onto_a_smaller_output() {
+        // Left a 4K output on the right half, back on 1080p
+        let output = Rectangle::new((0, 0).into(), (1920, 1080).into());
+        let window = Rectangle::new((2600, 1400).into(), (800, 600).into());
+        assert_eq!(fit_inside(window, output), Point::from((1120, 480)));
+
+        // ...or on an output to the right of where it was
+        let output = Rectangle::new((1920, 0).into(), (1920, 1080).into());
+        let window = Rectangle::new((-50, -20).into(), (800, 600).into());
+        assert_eq!(fit_inside(window, output), Point::from((1920, 0)));
+    }
+
+    #[test]
+    fn too_big_windows_pin_to_the_top_left() {
+        let output = Rectangle::new((0, 0).into(), (1280, 720).into());
+        let window = Rectangle::new((300, 300).into(), (2000, 1000).into());
+        assert_eq!(fit_inside(window, output), Point::from((0, 0)));
+    }
+
+    #[test]
+    fn snap_slots_too_small_center_the_min_size() {
+        let slot = Rectangle::new((10, 10).into(), (400, 1060).into());
+        let fitted = fit_to_limits(slot, (600, 300).into(), Size::default());
+        assert_eq!(fitted, Rectangle::new((-90, 10).into(), (600, 1060).into()));
+    }
+
+    #[test]
+    fn snap_slots_too_big_center_the_max_size() {
+        let slot = Rectangle::new((0, 0).into(), (1920, 1080).into());
+        let fitted = fit_to_limits(slot, Size::default(), (800, 0).into());
+        assert_eq!(fitted, Rectangle::new((560, 0).into(), (800, 1080).into()));
+    }
+
+    #[test]
+    fn resizing_by_hand_keeps_a_floor_under_the_max() {
+        let none = Size::default();
+        assert_eq!(clamp_resize((20, 20).into(), none, none), Size::from((100, 100)));
+        assert_eq!(clamp_resize((20, 20).into(), none, (50, 0).into()), Size::from((50, 100)));
+        assert_eq!(clamp_resize((500, 500).into(), (640, 0).into(), none), Size::from((640, 500)));
+
+        // A min above the max wins
+        assert_eq!(clamp_to_limits((500, 500).into(), (700, 0).into(), (600, 0).into()), Size::from((700, 500)));
+    }
+
+    proptest! {
+        #[test]
+        fn fitting_limits_stays_centered(
+            area in output(),
+            min in (0..3000, 0..3000),
+            max in (0..3000, 0..3000),
+        ) {
+            let (min, max): (Size<i32, Logical>, Size<i32, Logical>) = (min.into(), max.into());
+            let fitted = fit_to_limits(area, min, max);
+
+            prop_assert_eq!(fitted.size, clamp_to_limits(area.size, min, max));
+            prop_assert!(fitted.size.w >= min.w && fitted.size.h >= min.h);
+            if max.w >= min.w && max.w > 0 {
+                prop_assert!(fitted.size.w <= max.w);
+            }
+
+            // Same middle, give or take the odd pixel
+            let middle = |loc: i32, len: i32| loc * 2 + len;
+            prop_assert!((middle(fitted.loc.x, fitted.size.w) - middle(area.loc.x, area.size.w)).abs() <= 1);
+            prop_assert!((middle(fitted.loc.y, fitted.size.h) - middle(area.loc.y, area.size.h)).abs() <= 1);
+        }
+
+        #[test]
+        fn fitted_windows_end_up_inside(
+            output in output(),
+            x in -8000..8000,
+            y in -8000..8000,
+            w in 0..4000,
+            h in 0..4000,
+        ) {
+            prop_assume!(w <= output.size.w && h <= output.size.h);
+
+            let loc = fit_inside(Rectangle::new((x, y).into(), (w, h).into()), output);
+            prop_assert!(inside(output, Rectangle::new(loc, (w, h).into())));
+        }
+
+        #[test]
+        fn always_inside_usable_area(
+            output in output(),
+            gaps in gaps(),
+            ratio in 0.0..=1.0f64,
+            size in (0..10000, 0..10000),
+        ) {
+            let usable = usable_area(output, gaps.outer);
+            for position in ALL {
+                let rect = compute_snap_rect(position, output, gaps, ratio, size.into());
+                prop_assert!(rect.size.w >= 0 && rect.size.h >= 0, "{:?}: {:?}", position, rect);
+                prop_assert!(inside(usable, rect), "{:?}: {:?} outside {:?}", position, rect, usable);
+            }
+        }
+
+        #[test]
+        fn left_and_right_tile_exactly(output in output(), gaps in gaps(), ratio in 0.0..=1.0f64) {
+            let usable = usable_area(output, gaps.outer);
+            prop_assume!(usable.size.w >= gaps.inner);
+
+            let left = rect(SnapPosition::Left, output, gaps, ratio);
+            let right = rect(SnapPosition::Right, output, gaps, ratio);
+
+            prop_assert_eq!(left.loc.x, usable.loc.x);
+            prop_assert_eq!(right.loc.x - (left.loc.x + left.size.w), gaps.inner);
+            prop_assert_eq!(right.loc.x + right.size.w, usable.loc.x + usable.size.w);
+            prop_assert_eq!(left.loc.y, right.loc.y);
+            prop_assert_eq!(left.size.h, right.size.h);
+        }
+
+        #[test]
+        fn top_and_bottom_tile_exactly(output in output(), gaps in gaps(), ratio in 0.0..=1.0f64) {
+            let usable = usable_area(output, gaps.outer);
+            prop_assume!(usable.size.h >= gaps.inner);
+
+            let top = rect(SnapPosition::Top, output, gaps, ratio);
+            let bottom = rect(SnapPosition::Bottom, output, gaps, ratio);
+
+            prop_assert_eq!(top.loc.y, usable.loc.y);
+            prop_assert_eq!(bottom.loc.y - (top.loc.y + top.size.h), gaps.inner);
+            prop_assert_eq!(bottom.loc.y + bottom.size.h, usable.loc.y + usable.size.h);
+        }
+
+        #[test]
+        fn quarters_never_overlap(output in output(), gaps in gaps(), ratio in 0.0..=1.0f64) {
+            let rects = QUARTERS.map(|position| rect(position, output, gaps, ratio));
+            for (i, a) in rects.iter().enumerate() {
+                for b in &rects[i + 1..] {
+                    prop_assert!(disjoint(*a, *b), "{:?} overlaps {:?}", a, b);
+                }
+            }
+        }
+
+        #[test]
+        fn maximize_is_the_gapped_output(output in output(), gaps in gaps(), ratio in 0.0..=1.0f64) {
+            prop_assume!(output.size.w >= gaps.outer * 2 && output.size.h >= gaps.outer * 2);
+
+            let expected = Rectangle::new(
+                (output.loc.x + gaps.outer, output.loc.y + gaps.outer).into(),
+                (output.size.w - gaps.outer * 2, output.size.h - gaps.outer * 2).into(),
+            );
+            prop_assert_eq!(rect(SnapPosition::Maximize, output, gaps, ratio), expected);
+        }
+    }
+}
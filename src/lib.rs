@@ -0,0 +1,226 @@
+//! vibeWM as a library
+//!
+//! main.rs is a thin binary over this. The window manager, snap math, fuzzy
+//! matcher and desktop file parsing live here so tests and tools can use them
+//! without going through a backend - build with `default-features = false`
+//! to leave out everything behind the `compositor` feature (state, input,
+//! rendering, the backends).
+
+// Logic - no compositor needed
+pub mod action;
+pub mod keybind;
+pub mod window;
+pub mod rules;
+pub mod layout;
+pub mod config;
+pub mod config_file;
+pub mod launch;
+pub mod procstat;
+pub mod sysinfo;
+pub mod watchdog;
+pub mod usage;
+pub mod gpu_reset;
+pub mod capture;
+pub mod icons;
+pub mod schedule;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod overlay;
+pub mod command_center;
+pub mod render_command_center;
+
+// The compositor
+#[cfg(feature = "compositor")]
+pub mod state;
+#[cfg(feature = "compositor")]
+pub mod input;
+#[cfg(feature = "compositor")]
+pub mod window_menu;
+#[cfg(feature = "compositor")]
+pub mod titlebar;
+#[cfg(feature = "compositor")]
+pub mod grab;
+#[cfg(feature = "compositor")]
+pub mod workspace;
+#[cfg(feature = "compositor")]
+pub mod output;
+#[cfg(feature = "compositor")]
+pub mod popup;
+#[cfg(feature = "compositor")]
+pub mod zoom;
+#[cfg(feature = "compositor")]
+pub mod ipc;
+#[cfg(feature = "compositor")]
+pub mod ping;
+#[cfg(feature = "compositor")]
+pub mod sd_notify;
+#[cfg(feature = "compositor")]
+pub mod seat;
+#[cfg(feature = "compositor")]
+pub mod osk;
+#[cfg(feature = "compositor")]
+pub mod script;
+#[cfg(feature = "compositor")]
+pub mod screenshot;
+#[cfg(feature = "compositor")]
+pub mod foreign_toplevel;
+#[cfg(feature = "compositor")]
+pub mod wallpaper;
+#[cfg(feature = "compositor")]
+pub mod render;
+
+// Backend modules - winit for dev, DRM for bare metal
+#[cfg(all(feature = "compositor", not(feature = "udev")))]
+pub mod backend;
+#[cfg(feature = "udev")]
+pub mod backend_drm;
+
+#[cfg(feature = "compositor")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "compositor")]
+use anyhow::Result;
+#[cfg(feature = "compositor")]
+use tracing::info;
+
+#[cfg(feature = "compositor")]
+use smithay::reexports::calloop::{
+    signals::{Signal, Signals},
+    EventLoop,
+};
+#[cfg(feature = "compositor")]
+use crate::state::VibeWM;
+#[cfg(feature = "compositor")]
+use crate::config::Config;
+
+/// Settle the process environment - call before anything spawns a thread,
+/// the env isn't safe to change once one exists
+#[cfg(feature = "compositor")]
+pub fn init_env(config: &Config) {
+    // Mesa reads this when the first EGL display comes up - deciding
+    // after a failed init is too late
+    if config.software_rendering && std::env::var_os("LIBGL_ALWAYS_SOFTWARE").is_none() {
+        info!("software_rendering is on - using llvmpipe");
+        launch::set_internal_var("LIBGL_ALWAYS_SOFTWARE", "1");
+    }
+
+    sd_notify::take_env();
+}
+
+/// Run the compositor until quit, then shut down cleanly
+#[cfg(feature = "compositor")]
+pub fn run(config: Config) -> Result<()> {
+    // Create event loop with 'static lifetime
+    let mut event_loop: EventLoop<'static, VibeWM> = EventLoop::try_new()?;
+
+    // Before VibeWM::new spawns its worker threads - they inherit the
+    // blocked mask, so SIGUSR1 can't land on one of them and kill us
+    if let Err(e) = init_reload_signal(&mut event_loop) {
+        tracing::warn!("SIGUSR1 config reload disabled: {:#}", e);
+    }
+
+    // Initialize compositor state
+    let mut state = VibeWM::new(&mut event_loop, config)?;
+    watchdog::configure(&state.config);
+
+    if let Some(notify) = &state.sd_notify {
+        notify.status("Starting IPC");
+        if let Some(interval) = notify.watchdog_interval() {
+            sd_notify::init_watchdog(&mut event_loop, interval);
+        }
+    }
+
+    // IPC is nice to have - don't refuse to start without it
+    let wayland_display = std::env::var("WAYLAND_DISPLAY").unwrap_or_default();
+    let ipc_socket = match ipc::init(&mut event_loop, &wayland_display) {
+        Ok(path) => Some(path),
+        Err(e) => {
+            tracing::warn!("IPC disabled: {:#}", e);
+            None
+        }
+    };
+
+    #[cfg(feature = "metrics")]
+    if let Some(port) = state.config.metrics_port {
+        if let Err(e) = metrics::init(port) {
+            tracing::warn!("Metrics disabled: {:#}", e);
+        }
+    }
+
+    if let Some(notify) = &state.sd_notify {
+        notify.status("Starting backend");
+    }
+
+    info!("vibeWM ready - let's go ~");
+
+    // Run with appropriate backend
+    #[cfg(not(feature = "udev"))]
+    {
+        info!("Using winit backend (windowed mode)");
+        backend::run_winit(&mut event_loop, &mut state)?;
+    }
+
+    #[cfg(feature = "udev")]
+    {
+        info!("Using DRM backend (bare metal mode)");
+        backend_drm::run_drm(&mut event_loop, &mut state)?;
+    }
+
+    info!("vibeWM shutting down ~");
+    shutdown(&mut event_loop, &mut state);
+
+    if let Some(path) = ipc_socket {
+        std::fs::remove_file(path).ok();
+    }
+
+    Ok(())
+}
+
+/// `kill -USR1` re-reads config.toml, same as mod+shift+R
+#[cfg(feature = "compositor")]
+fn init_reload_signal(event_loop: &mut EventLoop<'static, VibeWM>) -> Result<()> {
+    let signals = Signals::new(&[Signal::SIGUSR1])?;
+    event_loop
+        .handle()
+        .insert_source(signals, |_, _, state| {
+            info!("SIGUSR1 - reloading config");
+            state.reload_config();
+        })
+        .map_err(|e| anyhow::anyhow!("Failed to insert signal source: {}", e.error))?;
+    Ok(())
+}
+
+/// How long clients get to close before the exit hook runs anyway
+#[cfg(feature = "compositor")]
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Close clients, then run the on_exit hook
+#[cfg(feature = "compositor")]
+fn shutdown(event_loop: &mut EventLoop<'static, VibeWM>, state: &mut VibeWM) {
+    // Closing clients and the exit hook can take a while, and that's fine
+    let _watchdog = watchdog::disarm();
+
+    if let Some(notify) = &state.sd_notify {
+        notify.stopping();
+    }
+
+    state.close_all_windows();
+
+    // Keep dispatching so clients can actually finish closing
+    let start = Instant::now();
+    while !state.windows.all().is_empty() && start.elapsed() < CLOSE_TIMEOUT {
+        if event_loop.dispatch(Duration::from_millis(16), state).is_err() {
+            break;
+        }
+        state.handle_pending();
+    }
+
+    state.usage.flush();
+
+    if let Some(argv) = &state.config.on_exit {
+        info!("Running on_exit: {:?}", argv);
+        if let Err(e) = launch::spawn(argv) {
+            tracing::warn!("on_exit failed: {}", e);
+        }
+    }
+}
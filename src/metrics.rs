@@ -0,0 +1,185 @@
+//! Prometheus metrics (`--features metrics`)
+//!
+//! Counters and histograms are plain atomics bumped from the spots the debug
+//! overlay already times, so collecting costs a few relaxed adds per frame.
+//! The text format only gets built when something asks: a scrape on
+//! `metrics_port` (localhost only) or the `metrics <file>` IPC command.
+//!
+//! Scrapes are answered on their own thread - it only reads the atomics, so
+//! a scraper that connects and never sends anything can't stall the loop.
+
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+/// Histogram bucket upper bounds (ms) - dense around a 16ms frame
+const BUCKETS_MS: [f64; 11] = [1.0, 2.0, 4.0, 8.0, 12.0, 16.0, 24.0, 33.0, 50.0, 100.0, 250.0];
+
+/// How long a scraper gets to send its request and take the reply
+const SCRAPE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Fixed-bucket histogram, in the Prometheus cumulative-on-output sense
+struct Histogram {
+    buckets: [AtomicU64; BUCKETS_MS.len()],
+    sum_us: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; BUCKETS_MS.len()],
+            sum_us: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, elapsed: Duration) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        if let Some(bucket) = BUCKETS_MS.iter().position(|&le| ms <= le) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.sum_us.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn write(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+
+        let mut cumulative = 0;
+        for (le, bucket) in BUCKETS_MS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le / 1000.0, cumulative);
+        }
+
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(out, "{}_sum {}", name, self.sum_us.load(Ordering::Relaxed) as f64 / 1_000_000.0);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+static FRAME_TIME: Histogram = Histogram::new();
+static INPUT_LATENCY: Histogram = Histogram::new();
+static DROPPED_FRAMES: AtomicU64 = AtomicU64::new(0);
+static GPU_RESETS: AtomicU64 = AtomicU64::new(0);
+static CLIENTS: AtomicI64 = AtomicI64::new(0);
+static WINDOWS: AtomicI64 = AtomicI64::new(0);
+
+/// Oldest input not on screen yet (µs since EPOCH + 1, 0 = none)
+static PENDING_INPUT: AtomicU64 = AtomicU64::new(0);
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+fn now_us() -> u64 {
+    us_at(Instant::now())
+}
+
+fn us_at(at: Instant) -> u64 {
+    at.saturating_duration_since(*EPOCH.get_or_init(Instant::now)).as_micros() as u64 + 1
+}
+
+/// A frame took this long to render
+pub fn frame_rendered(elapsed: Duration) {
+    FRAME_TIME.observe(elapsed);
+}
+
+/// A frame was skipped because the last one was still in flight
+pub fn frame_dropped() {
+    DROPPED_FRAMES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// An input event came in - latency runs until the next frame is presented
+pub fn input_seen() {
+    PENDING_INPUT.compare_exchange(0, now_us(), Ordering::Relaxed, Ordering::Relaxed).ok();
+}
+
+/// A frame hit the screen at `at` - closes out any input waiting on it
+pub fn frame_presented(at: Instant) {
+    let since = PENDING_INPUT.swap(0, Ordering::Relaxed);
+    if since != 0 {
+        INPUT_LATENCY.observe(Duration::from_micros(us_at(at).saturating_sub(since)));
+    }
+}
+
+pub fn gpu_reset() {
+    GPU_RESETS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn client_connected() {
+    CLIENTS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn client_disconnected() {
+    CLIENTS.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// A window was added or removed - this many now
+pub fn windows_changed(count: usize) {
+    WINDOWS.store(count as i64, Ordering::Relaxed);
+}
+
+/// Everything, in the Prometheus text exposition format
+pub fn render() -> String {
+    let mut out = String::new();
+
+    FRAME_TIME.write(&mut out, "vibewm_frame_seconds", "Time spent rendering a frame");
+    INPUT_LATENCY.write(&mut out, "vibewm_input_latency_seconds", "Input event to the frame that shows it");
+
+    let counters = [
+        ("vibewm_dropped_frames_total", "counter", "Frames skipped while the previous one was in flight", DROPPED_FRAMES.load(Ordering::Relaxed) as i64),
+        ("vibewm_gpu_resets_total", "counter", "GPU context losses recovered from", GPU_RESETS.load(Ordering::Relaxed) as i64),
+        ("vibewm_clients", "gauge", "Connected Wayland clients", CLIENTS.load(Ordering::Relaxed)),
+        ("vibewm_windows", "gauge", "Mapped toplevel windows", WINDOWS.load(Ordering::Relaxed)),
+    ];
+    for (name, kind, help, value) in counters {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+
+    out
+}
+
+/// Serve metrics on 127.0.0.1:port from a thread of their own
+pub fn init(port: u16) -> Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .with_context(|| format!("Failed to bind the metrics listener on port {}", port))?;
+
+    std::thread::Builder::new()
+        .name("metrics".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let served = stream.and_then(|stream| serve(stream, &render()));
+                if let Err(e) = served {
+                    tracing::debug!("Metrics scrape failed: {}", e);
+                }
+            }
+        })
+        .context("Failed to start the metrics thread")?;
+
+    tracing::info!("Metrics on http://127.0.0.1:{}/metrics", port);
+    Ok(())
+}
+
+/// Answer any request with the metrics - there's only the one page
+fn serve(mut stream: TcpStream, body: &str) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(SCRAPE_TIMEOUT))?;
+    stream.set_write_timeout(Some(SCRAPE_TIMEOUT))?;
+
+    // Only the request line matters, and not even that
+    let mut request = [0u8; 1024];
+    let _request_len = stream.read(&mut request);
+
+    write!(
+        stream,
+        "HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
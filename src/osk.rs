@@ -0,0 +1,120 @@
+//! On-screen keyboard for touch-only setups
+//!
+//! With a touchscreen and no keyboard, the command center search can't be
+//! typed into. When `osk` is configured and the default seat has touch but no
+//! keyboard, the command center spawns it on open and kills it on close.
+
+use std::collections::HashSet;
+use std::process::Child;
+
+use smithay::backend::input::{Device, DeviceCapability};
+
+use crate::launch;
+use crate::state::VibeWM;
+
+/// Input devices on the default seat, and the keyboard process if it's up
+#[derive(Default)]
+pub struct Osk {
+    keyboards: HashSet<String>,
+    touch: HashSet<String>,
+    child: Option<Child>,
+
+    /// Closed by hand while the command center was open - stays closed until it reopens
+    dismissed: bool,
+}
+
+impl Osk {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn device_added(&mut self, device: &impl Device) {
+        if device.has_capability(DeviceCapability::Keyboard) {
+            self.keyboards.insert(device.id());
+        }
+        if device.has_capability(DeviceCapability::Touch) {
+            self.touch.insert(device.id());
+        }
+    }
+
+    pub fn device_removed(&mut self, id: &str) {
+        self.keyboards.remove(id);
+        self.touch.remove(id);
+    }
+
+    /// Touchscreen and nothing to type on
+    pub fn touch_only(&self) -> bool {
+        !self.touch.is_empty() && self.keyboards.is_empty()
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.child.is_some()
+    }
+
+    fn show(&mut self, argv: &[String]) {
+        if self.child.is_some() {
+            return;
+        }
+
+        match launch::spawn(argv) {
+            Ok(child) => {
+                tracing::info!("On-screen keyboard up: {:?}", argv);
+                self.child = Some(child);
+            }
+            Err(e) => tracing::warn!("Couldn't start the on-screen keyboard {:?}: {}", argv, e),
+        }
+    }
+
+    fn hide(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+
+    /// Forget a keyboard that quit on its own - true if it just did
+    fn reap(&mut self) -> bool {
+        let exited = self.child.as_mut().is_some_and(|child| !matches!(child.try_wait(), Ok(None)));
+        if exited {
+            self.child = None;
+        }
+        exited
+    }
+}
+
+impl Drop for Osk {
+    fn drop(&mut self) {
+        self.hide();
+    }
+}
+
+impl VibeWM {
+    /// Keep the on-screen keyboard in step with the command center
+    ///
+    /// Watches visibility rather than hooking every open/close path, so
+    /// launching, Escape and clicking outside all take it down.
+    pub fn update_osk(&mut self) {
+        let exited = self.osk.reap();
+
+        let Some(argv) = self.config.osk.as_ref().filter(|argv| !argv.is_empty()) else {
+            return;
+        };
+
+        let wanted = self.osk.touch_only()
+            && self.command_center.as_ref().is_some_and(|center| center.visible);
+
+        if !wanted {
+            self.osk.dismissed = false;
+            self.osk.hide();
+            return;
+        }
+
+        // Closed it themselves - don't pop it right back up
+        if exited {
+            self.osk.dismissed = true;
+        }
+        if !self.osk.is_shown() && !self.osk.dismissed {
+            self.osk.show(argv);
+        }
+    }
+}
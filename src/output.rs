@@ -0,0 +1,246 @@
+//! Outputs coming and going - and the no-output state in between
+//!
+//! Before the backend has an output up, and after the last one is unplugged,
+//! there's no screen to lay anything out on. Rather than making up a size,
+//! new toplevels wait in a queue, windows on screen get parked (their spot
+//! saved, unmapped) and layout operations are skipped. The first output that
+//! shows up maps the queue and brings the parked windows back.
+//!
+//! Which windows park and where they come back is worked out from the
+//! windows and the outputs alone (`place_for_outputs`), then applied.
+
+use std::time::Duration;
+
+use smithay::{
+    backend::renderer::element::{default_primary_scanout_output_compare, RenderElementStates},
+    desktop::utils::{surface_primary_scanout_output, update_surface_primary_scanout_output},
+    output::Output,
+    desktop::Window,
+    utils::{Logical, Point, Rectangle, Size},
+};
+
+use crate::layout::fit_inside;
+use crate::state::VibeWM;
+use crate::window::has_buffer;
+
+/// How often a surface that isn't on its primary output (hidden, covered,
+/// not drawn yet) still gets a frame callback
+const FRAME_THROTTLE: Duration = Duration::from_secs(1);
+
+/// A window as parking sees it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spot {
+    /// Where it is on screen - None while unmapped
+    pub mapped: Option<Point<i32, Logical>>,
+
+    /// Where it goes when it's mapped again
+    pub saved: Option<Point<i32, Logical>>,
+
+    pub size: Size<i32, Logical>,
+
+    /// On the active workspace and not minimized - belongs on screen
+    pub shown: bool,
+}
+
+/// Where every window is once the outputs are `outputs` - with none,
+/// everything on screen is parked at its spot; with some, parked windows
+/// that belong on screen come back onto the primary (first) one, pulled
+/// inside it if they'd land off it
+pub fn place_for_outputs(spots: &[Spot], outputs: &[Rectangle<i32, Logical>]) -> Vec<Spot> {
+    spots
+        .iter()
+        .map(|spot| match outputs.first() {
+            None => Spot { mapped: None, saved: spot.mapped.or(spot.saved), ..*spot },
+            Some(&area) if spot.shown && spot.mapped.is_none() => {
+                let saved = Rectangle::new(spot.saved.unwrap_or_default(), spot.size);
+                Spot { mapped: Some(fit_inside(saved, area)), ..*spot }
+            }
+            Some(_) => *spot,
+        })
+        .collect()
+}
+
+impl VibeWM {
+    /// Logical rect of the primary output - None while there isn't one
+    pub fn primary_output_rect(&self) -> Option<Rectangle<i32, Logical>> {
+        self.space.output_geometry(self.output.as_ref()?)
+    }
+
+    /// A backend brought up an output - the first one after none maps the
+    /// waiting toplevels and brings parked windows back
+    pub fn add_output(&mut self, output: &Output, location: Point<i32, Logical>) {
+        let was_empty = self.output.is_none();
+
+        self.space.map_output(output, location);
+        if was_empty {
+            self.output = Some(output.clone());
+            let outputs: Vec<_> = self.primary_output_rect().into_iter().collect();
+            self.place_for_outputs(&outputs);
+            self.windows.focus_recent();
+
+            // Only the ones that got as far as a buffer - the rest map on
+            // their own commit
+            let ready: Vec<_> = self.pending_toplevels.iter().filter(|w| has_buffer(w)).cloned().collect();
+            for window in ready {
+                self.map_toplevel(&window);
+            }
+        }
+
+        tracing::info!("Output added: {}", output.name());
+    }
+
+    /// An output went away - losing the last one parks every window until
+    /// another shows up
+    pub fn remove_output(&mut self, output: &Output) {
+        // Park first, while the windows still have somewhere to be measured from
+        let last = self.space.outputs().all(|o| o == output);
+        if last {
+            self.place_for_outputs(&[]);
+            self.spotlight = None;
+            self.move_grab = None;
+            self.resize_grab = None;
+            tracing::warn!("No outputs left - windows parked until one comes back");
+        }
+
+        self.space.unmap_output(output);
+        if self.output.as_ref() == Some(output) {
+            self.output = self.space.outputs().next().cloned();
+        }
+
+        // The command center stays where it opened - it goes with its output
+        let name = output.name();
+        if let Some(command_center) = self.command_center
+            .as_mut()
+            .filter(|cc| last || cc.output.as_deref() == Some(name.as_str()))
+        {
+            command_center.close_now();
+        }
+
+        // Nothing to anchor to any more
+        if last {
+            self.close_window_menu();
+            self.cancel_region_screenshot();
+        }
+
+        tracing::info!("Output removed: {}", output.name());
+    }
+
+    /// Frame callbacks for the windows on an output, once a frame for it is out
+    ///
+    /// Each output sends with its own frame time. A window spanning two only
+    /// hears from its primary one - the output showing most of it, going by
+    /// what was actually drawn - so it isn't paced twice. Without element
+    /// `states` the primary is whatever an earlier frame decided, or this
+    /// output for a surface no frame has picked one for yet. Surfaces that
+    /// aren't drawn anywhere still get one a second, so they don't stall.
+    pub fn send_frame_callbacks(&self, output: &Output, states: Option<&RenderElementStates>) {
+        let time = self.start_time.elapsed();
+
+        for window in self.space.elements_for_output(output) {
+            if let Some(states) = states {
+                window.with_surfaces(|surface, surface_data| {
+                    update_surface_primary_scanout_output(
+                        surface,
+                        output,
+                        surface_data,
+                        states,
+                        default_primary_scanout_output_compare,
+                    );
+                });
+            }
+
+            window.send_frame(output, time, Some(FRAME_THROTTLE), |surface, surface_data| {
+                surface_primary_scanout_output(surface, surface_data).or_else(|| states.is_none().then(|| output.clone()))
+            });
+        }
+    }
+
+    /// Park or bring back windows for a new set of outputs - brought back
+    /// ones snap again if they were
+    fn place_for_outputs(&mut self, outputs: &[Rectangle<i32, Logical>]) {
+        let active = self.windows.active_workspace();
+        let (windows, spots): (Vec<Window>, Vec<Spot>) = self.windows
+            .all()
+            .iter()
+            .filter_map(|window| {
+                let meta = self.windows.meta(window)?;
+                let spot = Spot {
+                    mapped: self.space.element_location(window),
+                    saved: meta.saved_location,
+                    size: window.geometry().size,
+                    shown: meta.workspace == active && !meta.minimized,
+                };
+                Some((window.clone(), spot))
+            })
+            .unzip();
+
+        let placed = place_for_outputs(&spots, outputs);
+        for ((window, before), after) in windows.iter().zip(&spots).zip(placed) {
+            let snap_state = self.windows.meta_mut(window).and_then(|meta| {
+                meta.saved_location = after.saved;
+                meta.snap_state
+            });
+            if after.mapped == before.mapped {
+                continue;
+            }
+
+            match after.mapped {
+                Some(location) => {
+                    self.space.map_element(window.clone(), location, false);
+                    if let Some(position) = snap_state {
+                        self.snap_window(window, position);
+                    }
+                }
+                None => self.space.unmap_elem(window),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spot(x: i32, y: i32, shown: bool) -> Spot {
+        Spot { mapped: Some((x, y).into()), saved: None, size: (800, 600).into(), shown }
+    }
+
+    #[test]
+    fn output_removed_and_added_back() {
+        let output = Rectangle::new((0, 0).into(), (1920, 1080).into());
+        let hidden = Spot { mapped: None, saved: Some((300, 300).into()), ..spot(0, 0, false) };
+        let windows = [spot(100, 100, true), spot(1000, 400, true), hidden];
+
+        // Last output gone: everything parked where it was
+        let parked = place_for_outputs(&windows, &[]);
+        assert!(parked.iter().all(|spot| spot.mapped.is_none()));
+        assert_eq!(parked[0].saved, Some((100, 100).into()));
+        assert_eq!(parked[1].saved, Some((1000, 400).into()));
+        assert_eq!(parked[2], hidden);
+
+        // Parking again changes nothing
+        assert_eq!(place_for_outputs(&parked, &[]), parked);
+
+        // Same output back: same spots, the hidden workspace stays hidden
+        let restored = place_for_outputs(&parked, &[output]);
+        assert_eq!(restored[0].mapped, Some((100, 100).into()));
+        assert_eq!(restored[1].mapped, Some((1000, 400).into()));
+        assert_eq!(restored[2], hidden);
+    }
+
+    #[test]
+    fn smaller_output_pulls_windows_onto_it() {
+        let small = Rectangle::new((0, 0).into(), (1280, 720).into());
+        let parked = place_for_outputs(&[spot(1000, 400, true)], &[]);
+
+        let restored = place_for_outputs(&parked, &[small]);
+        assert_eq!(restored[0].mapped, Some((480, 120).into()));
+    }
+
+    #[test]
+    fn mapped_windows_stay_put_when_outputs_come_and_go() {
+        let windows = [spot(100, 100, true)];
+        let second = Rectangle::new((1920, 0).into(), (1920, 1080).into());
+        assert_eq!(place_for_outputs(&windows, &[second]), windows);
+    }
+}
@@ -0,0 +1,681 @@
+//! Overlays - small cards floating above the windows
+//!
+//! Same deal as the command center: state lives here, rendering produces
+//! plain render data that the GPU side draws.
+
+use std::time::{Duration, Instant};
+
+use smithay::utils::{Logical, Rectangle};
+
+use crate::command_center::CommandCenterTheme;
+use crate::config::{FocusRingConfig, ScreenCorner};
+use crate::render_command_center::{with_alpha, FontWeight, GlowEffect, RenderQuad, TextRender};
+
+/// Fade-in duration at full animations
+pub const FADE: Duration = Duration::from_millis(200);
+
+/// Hints shown on an empty desktop
+const HELP_LINES: &[(&str, &str)] = &[
+    ("mod+S", "Command Center - launch something"),
+    ("mod+1..9", "Switch workspace"),
+    ("mod+Tab", "Cycle focus"),
+    ("mod+ijkl", "Move window"),
+    ("mod+arrows", "Snap window"),
+    ("mod+/", "All keybindings"),
+    ("mod+Q", "Quit"),
+];
+
+/// How far the focus ring expands past the window edge (px)
+const FOCUS_RING_SPREAD: f32 = 24.0;
+
+/// How long a snap preview stays up at full animations
+pub const SNAP_PREVIEW: Duration = Duration::from_millis(250);
+
+/// How long an OSD notice stays up
+const NOTICE_MS: f32 = 4000.0;
+
+/// Cheat sheet metrics (px)
+const CHEAT_LINE_HEIGHT: f32 = 24.0;
+const CHEAT_COLUMN_WIDTH: f32 = 340.0;
+const CHEAT_KEYS_WIDTH: f32 = 140.0;
+const CHEAT_MARGIN: f32 = 48.0;
+
+/// Fade-in progress (0..1) of something shown at `since` - 1 straight away with no fade
+fn fade_in(since: Instant, fade: Duration) -> f32 {
+    if fade.is_zero() {
+        return 1.0;
+    }
+    (since.elapsed().as_secs_f32() / fade.as_secs_f32()).min(1.0)
+}
+
+/// "What now?" card for an empty desktop
+pub struct HelpOverlay {
+    pub visible: bool,
+
+    /// When it was shown, for the fade-in
+    pub shown_at: Option<Instant>,
+}
+
+/// Render data for the help overlay
+pub struct HelpOverlayFrame {
+    pub background: RenderQuad,
+    pub title: TextRender,
+    pub lines: Vec<TextRender>,
+    pub opacity: f32,
+}
+
+impl HelpOverlay {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            shown_at: None,
+        }
+    }
+
+    pub fn show(&mut self) {
+        if !self.visible {
+            self.visible = true;
+            self.shown_at = Some(Instant::now());
+        }
+    }
+
+    pub fn hide(&mut self) {
+        self.visible = false;
+        self.shown_at = None;
+    }
+
+    /// Generate render data, centered on the screen
+    pub fn render(
+        &self,
+        screen_width: i32,
+        screen_height: i32,
+        fade: Duration,
+        theme: &CommandCenterTheme,
+    ) -> HelpOverlayFrame {
+        let opacity = self.shown_at
+            .map(|t| fade_in(t, fade))
+            .unwrap_or(0.0);
+
+        let line_height = 28.0;
+        let padding = 24.0;
+        let width = 420.0;
+        let height = padding * 2.0 + 40.0 + HELP_LINES.len() as f32 * line_height;
+
+        let x = (screen_width as f32 - width) / 2.0;
+        let y = (screen_height as f32 - height) / 2.0;
+
+        let lines = HELP_LINES
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (keys, what))| {
+                let line_y = y + padding + 40.0 + i as f32 * line_height;
+                [
+                    TextRender {
+                        x: x + padding,
+                        y: line_y,
+                        text: keys.to_string(),
+                        color: with_alpha(theme.accent_primary, opacity),
+                        size: 14.0,
+                        font_weight: FontWeight::Bold,
+                    },
+                    TextRender {
+                        x: x + padding + 120.0,
+                        y: line_y,
+                        text: what.to_string(),
+                        color: with_alpha(theme.text_secondary, opacity),
+                        size: 14.0,
+                        font_weight: FontWeight::Regular,
+                    },
+                ]
+            })
+            .collect();
+
+        HelpOverlayFrame {
+            background: RenderQuad {
+                x,
+                y,
+                width,
+                height,
+                color: with_alpha(theme.bg_color, opacity),
+                corner_radius: theme.card_border_radius,
+            },
+            title: TextRender {
+                x: x + padding,
+                y: y + padding + 8.0,
+                text: "Nothing open ~".to_string(),
+                color: with_alpha(theme.text_primary, opacity),
+                size: 18.0,
+                font_weight: FontWeight::Medium,
+            },
+            lines,
+            opacity,
+        }
+    }
+}
+
+/// "Press mod+Q again" card - the first mod+Q arms it, the second one quits
+pub struct QuitConfirm {
+    /// When the first press happened
+    pub armed_at: Option<Instant>,
+
+    /// How long to wait for the second press
+    pub timeout: Duration,
+}
+
+/// Render data for the quit confirmation
+pub struct QuitConfirmFrame {
+    pub background: RenderQuad,
+    pub title: TextRender,
+    pub hint: TextRender,
+    pub opacity: f32,
+}
+
+impl QuitConfirm {
+    pub fn new() -> Self {
+        Self {
+            armed_at: None,
+            timeout: Duration::ZERO,
+        }
+    }
+
+    pub fn arm(&mut self, timeout: Duration) {
+        self.armed_at = Some(Instant::now());
+        self.timeout = timeout;
+    }
+
+    pub fn cancel(&mut self) {
+        self.armed_at = None;
+    }
+
+    /// Armed and still inside the timeout
+    pub fn is_armed(&self) -> bool {
+        self.armed_at
+            .map(|t| t.elapsed() < self.timeout)
+            .unwrap_or(false)
+    }
+
+    /// Drop the card once the timeout runs out
+    pub fn update(&mut self) {
+        if self.armed_at.is_some() && !self.is_armed() {
+            self.cancel();
+        }
+    }
+
+    /// Generate render data, centered on the screen
+    pub fn render(
+        &self,
+        screen_width: i32,
+        screen_height: i32,
+        fade: Duration,
+        theme: &CommandCenterTheme,
+    ) -> QuitConfirmFrame {
+        let opacity = self.armed_at
+            .map(|t| fade_in(t, fade))
+            .unwrap_or(0.0);
+
+        let padding = 24.0;
+        let width = 440.0;
+        let height = padding * 2.0 + 56.0;
+
+        let x = (screen_width as f32 - width) / 2.0;
+        let y = (screen_height as f32 - height) / 2.0;
+
+        // Seconds left, so it's obvious the card will go away on its own
+        let remaining = self.armed_at
+            .map(|t| self.timeout.saturating_sub(t.elapsed()).as_secs() + 1)
+            .unwrap_or(0);
+
+        QuitConfirmFrame {
+            background: RenderQuad {
+                x,
+                y,
+                width,
+                height,
+                color: with_alpha(theme.bg_color, opacity),
+                corner_radius: theme.card_border_radius,
+            },
+            title: TextRender {
+                x: x + padding,
+                y: y + padding + 8.0,
+                text: "Press mod+Q again to quit, Escape to cancel".to_string(),
+                color: with_alpha(theme.text_primary, opacity),
+                size: 16.0,
+                font_weight: FontWeight::Medium,
+            },
+            hint: TextRender {
+                x: x + padding,
+                y: y + padding + 36.0,
+                text: format!("closing in {}s", remaining),
+                color: with_alpha(theme.text_secondary, opacity),
+                size: 13.0,
+                font_weight: FontWeight::Regular,
+            },
+            opacity,
+        }
+    }
+}
+
+/// A titled block of bindings on the cheat sheet
+pub struct CheatSheetGroup {
+    pub title: &'static str,
+
+    /// (keys, what they do)
+    pub bindings: Vec<(String, String)>,
+}
+
+/// mod+/ card listing every keybinding
+pub struct CheatSheet {
+    pub visible: bool,
+
+    /// When it was shown, for the fade-in
+    pub shown_at: Option<Instant>,
+}
+
+/// Render data for the cheat sheet
+pub struct CheatSheetFrame {
+    pub background: RenderQuad,
+    pub title: TextRender,
+    /// Group headers, keys and descriptions, already placed in their columns
+    pub lines: Vec<TextRender>,
+    pub columns: usize,
+    pub opacity: f32,
+}
+
+/// One row of the cheat sheet
+enum CheatRow<'a> {
+    Header(&'a str),
+    Binding(&'a str, &'a str),
+}
+
+impl CheatSheet {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            shown_at: None,
+        }
+    }
+
+    pub fn show(&mut self) {
+        if !self.visible {
+            self.visible = true;
+            self.shown_at = Some(Instant::now());
+        }
+    }
+
+    pub fn hide(&mut self) {
+        self.visible = false;
+        self.shown_at = None;
+    }
+
+    pub fn toggle(&mut self) {
+        if self.visible {
+            self.hide();
+        } else {
+            self.show();
+        }
+    }
+
+    /// Generate render data, centered on the screen
+    ///
+    /// One column while it fits the screen height, more side by side when it
+    /// doesn't - as many as the width allows. A group header never ends up
+    /// alone at the bottom of a column.
+    pub fn render(
+        &self,
+        screen_width: i32,
+        screen_height: i32,
+        groups: &[CheatSheetGroup],
+        fade: Duration,
+        theme: &CommandCenterTheme,
+    ) -> CheatSheetFrame {
+        let opacity = self.shown_at
+            .map(|t| fade_in(t, fade))
+            .unwrap_or(0.0);
+
+        let padding = 24.0;
+        let title_height = 40.0;
+
+        let rows: Vec<CheatRow> = groups
+            .iter()
+            .flat_map(|group| {
+                std::iter::once(CheatRow::Header(group.title)).chain(
+                    group.bindings.iter().map(|(keys, what)| CheatRow::Binding(keys, what)),
+                )
+            })
+            .collect();
+
+        let usable_width = screen_width as f32 - CHEAT_MARGIN * 2.0 - padding * 2.0;
+        let usable_height = screen_height as f32 - CHEAT_MARGIN * 2.0 - padding * 2.0 - title_height;
+        let fit_columns = ((usable_width / CHEAT_COLUMN_WIDTH).floor() as usize).max(1);
+        let max_rows = ((usable_height / CHEAT_LINE_HEIGHT).floor() as usize).max(1);
+        let columns = rows.len().div_ceil(max_rows).clamp(1, fit_columns);
+        let per_column = rows.len().div_ceil(columns).max(1);
+
+        // (column, row) for every row
+        let mut placed = Vec::with_capacity(rows.len());
+        let (mut column, mut row) = (0, 0);
+        for entry in &rows {
+            let orphan_header = matches!(entry, CheatRow::Header(_)) && row + 1 >= per_column;
+            if row > 0 && (row >= per_column || orphan_header) && column + 1 < columns {
+                column += 1;
+                row = 0;
+            }
+            placed.push((column, row));
+            row += 1;
+        }
+        let used_rows = placed.iter().map(|(_, row)| row + 1).max().unwrap_or(0);
+
+        let width = padding * 2.0 + columns as f32 * CHEAT_COLUMN_WIDTH;
+        let height = padding * 2.0 + title_height + used_rows as f32 * CHEAT_LINE_HEIGHT;
+        let x = (screen_width as f32 - width) / 2.0;
+        let y = (screen_height as f32 - height) / 2.0;
+
+        let lines = rows
+            .iter()
+            .zip(&placed)
+            .flat_map(|(entry, (column, row))| {
+                let line_x = x + padding + *column as f32 * CHEAT_COLUMN_WIDTH;
+                let line_y = y + padding + title_height + *row as f32 * CHEAT_LINE_HEIGHT;
+                match entry {
+                    CheatRow::Header(title) => vec![TextRender {
+                        x: line_x,
+                        y: line_y,
+                        text: title.to_string(),
+                        color: with_alpha(theme.text_primary, opacity),
+                        size: 15.0,
+                        font_weight: FontWeight::Medium,
+                    }],
+                    CheatRow::Binding(keys, what) => vec![
+                        TextRender {
+                            x: line_x,
+                            y: line_y,
+                            text: keys.to_string(),
+                            color: with_alpha(theme.accent_primary, opacity),
+                            size: 13.0,
+                            font_weight: FontWeight::Bold,
+                        },
+                        TextRender {
+                            x: line_x + CHEAT_KEYS_WIDTH,
+                            y: line_y,
+                            text: what.to_string(),
+                            color: with_alpha(theme.text_secondary, opacity),
+                            size: 13.0,
+                            font_weight: FontWeight::Regular,
+                        },
+                    ],
+                }
+            })
+            .collect();
+
+        CheatSheetFrame {
+            background: RenderQuad {
+                x,
+                y,
+                width,
+                height,
+                color: with_alpha(theme.bg_color, opacity),
+                corner_radius: theme.card_border_radius,
+            },
+            title: TextRender {
+                x: x + padding,
+                y: y + padding + 8.0,
+                text: "Keybindings".to_string(),
+                color: with_alpha(theme.text_primary, opacity),
+                size: 18.0,
+                font_weight: FontWeight::Medium,
+            },
+            lines,
+            columns,
+            opacity,
+        }
+    }
+}
+
+/// One-line notice at the top of the screen - script errors and the like
+pub struct Notice {
+    /// Message and when it went up
+    pub shown: Option<(String, Instant)>,
+}
+
+/// Render data for a notice
+pub struct NoticeFrame {
+    pub background: RenderQuad,
+    pub text: TextRender,
+    pub opacity: f32,
+}
+
+impl Notice {
+    pub fn new() -> Self {
+        Self { shown: None }
+    }
+
+    /// Show a message, replacing whatever was up
+    pub fn show(&mut self, message: impl Into<String>) {
+        self.shown = Some((message.into(), Instant::now()));
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.shown.is_some()
+    }
+
+    /// Drop the notice once it's been up long enough
+    pub fn update(&mut self) {
+        if let Some((_, shown_at)) = &self.shown {
+            if shown_at.elapsed().as_millis() as f32 >= NOTICE_MS {
+                self.shown = None;
+            }
+        }
+    }
+
+    /// Generate render data - fades in, then out over the last `fade`
+    pub fn render(&self, screen_width: i32, fade: Duration, theme: &CommandCenterTheme) -> Option<NoticeFrame> {
+        let (message, shown_at) = self.shown.as_ref()?;
+
+        let left = NOTICE_MS - shown_at.elapsed().as_millis() as f32;
+        let fade_out = if fade.is_zero() { 1.0 } else { left / fade.as_millis() as f32 };
+        let opacity = fade_in(*shown_at, fade).min(fade_out).clamp(0.0, 1.0);
+
+        let padding = 16.0;
+        let width = (screen_width as f32 - 2.0 * padding).min(640.0);
+        let height = padding * 2.0 + 20.0;
+        let x = (screen_width as f32 - width) / 2.0;
+        let y = 32.0;
+
+        Some(NoticeFrame {
+            background: RenderQuad {
+                x,
+                y,
+                width,
+                height,
+                color: with_alpha(theme.bg_color, opacity),
+                corner_radius: theme.card_border_radius,
+            },
+            text: TextRender {
+                x: x + padding,
+                y: y + padding + 4.0,
+                text: message.clone(),
+                color: with_alpha(theme.accent_secondary, opacity),
+                size: 14.0,
+                font_weight: FontWeight::Medium,
+            },
+            opacity,
+        })
+    }
+}
+
+/// Translucent flash over the region a window just snapped to
+pub struct SnapPreview {
+    /// Target region and when it was shown
+    pub target: Option<(Rectangle<i32, Logical>, Instant)>,
+
+    /// How long this one stays up
+    pub duration: Duration,
+}
+
+impl SnapPreview {
+    pub fn new() -> Self {
+        Self { target: None, duration: SNAP_PREVIEW }
+    }
+
+    /// Show a preview for `duration`, replacing whatever was up - zero shows nothing
+    pub fn show(&mut self, rect: Rectangle<i32, Logical>, duration: Duration) {
+        self.target = (!duration.is_zero()).then(|| (rect, Instant::now()));
+        self.duration = duration;
+    }
+
+    /// Drop the preview once it has faded out
+    pub fn update(&mut self) {
+        if let Some((_, shown_at)) = self.target {
+            if shown_at.elapsed() >= self.duration {
+                self.target = None;
+            }
+        }
+    }
+
+    /// Generate the preview quad - fades out over its lifetime
+    pub fn render(&self, theme: &CommandCenterTheme) -> Option<RenderQuad> {
+        let (rect, shown_at) = self.target?;
+
+        let t = (shown_at.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0);
+        let opacity = 0.25 * (1.0 - t);
+
+        Some(RenderQuad {
+            x: rect.loc.x as f32,
+            y: rect.loc.y as f32,
+            width: rect.size.w as f32,
+            height: rect.size.h as f32,
+            color: with_alpha(theme.accent_primary, opacity),
+            corner_radius: theme.card_border_radius,
+        })
+    }
+}
+
+/// Render data for spotlight mode - dims everything except the hole
+pub struct SpotlightFrame {
+    /// Full-screen dim (drawn with SPOTLIGHT_SHADER_FRAG)
+    pub dim: RenderQuad,
+
+    /// The spotlighted window, left undimmed
+    pub hole: Rectangle<i32, Logical>,
+    pub hole_radius: f32,
+}
+
+/// How dark everything but the spotlighted window gets
+const SPOTLIGHT_DIM: f32 = 0.75;
+
+/// Generate the spotlight dim for a window's on-screen rect
+pub fn render_spotlight(
+    screen_width: i32,
+    screen_height: i32,
+    hole: Rectangle<i32, Logical>,
+    theme: &CommandCenterTheme,
+) -> SpotlightFrame {
+    SpotlightFrame {
+        dim: RenderQuad {
+            x: 0.0,
+            y: 0.0,
+            width: screen_width as f32,
+            height: screen_height as f32,
+            color: [0.0, 0.0, 0.0, SPOTLIGHT_DIM],
+            corner_radius: 0.0,
+        },
+        hole,
+        hole_radius: theme.card_border_radius,
+    }
+}
+
+/// Generate the focus ring flash for a window that just gained focus
+///
+/// Expands outwards and fades with an ease-out - None once it's done.
+pub fn render_focus_ring(
+    geometry: Rectangle<i32, Logical>,
+    focused_at: Instant,
+    config: &FocusRingConfig,
+    theme: &CommandCenterTheme,
+) -> Option<GlowEffect> {
+    let duration = config.duration_ms.max(1) as f32;
+    let t = focused_at.elapsed().as_millis() as f32 / duration;
+    if t >= 1.0 {
+        return None;
+    }
+
+    let eased = 1.0 - (1.0 - t).powi(3);
+
+    Some(GlowEffect {
+        x: geometry.loc.x as f32,
+        y: geometry.loc.y as f32,
+        width: geometry.size.w as f32,
+        height: geometry.size.h as f32,
+        color: config.color,
+        intensity: 1.0 - eased,
+        spread: FOCUS_RING_SPREAD * eased,
+        corner_radius: theme.card_border_radius,
+    })
+}
+
+/// Recording dot size and distance from the corner (px)
+const RECORDING_DOT_SIZE: f32 = 12.0;
+const RECORDING_DOT_MARGIN: f32 = 10.0;
+
+/// Steady red dot in a corner of an output that's being recorded
+///
+/// Doesn't pulse - it would end up in the recording.
+pub fn render_recording_dot(screen_width: i32, screen_height: i32, corner: ScreenCorner) -> RenderQuad {
+    let far_x = screen_width as f32 - RECORDING_DOT_MARGIN - RECORDING_DOT_SIZE;
+    let far_y = screen_height as f32 - RECORDING_DOT_MARGIN - RECORDING_DOT_SIZE;
+
+    let (x, y) = match corner {
+        ScreenCorner::TopLeft => (RECORDING_DOT_MARGIN, RECORDING_DOT_MARGIN),
+        ScreenCorner::TopRight => (far_x, RECORDING_DOT_MARGIN),
+        ScreenCorner::BottomLeft => (RECORDING_DOT_MARGIN, far_y),
+        ScreenCorner::BottomRight => (far_x, far_y),
+    };
+
+    RenderQuad {
+        x,
+        y,
+        width: RECORDING_DOT_SIZE,
+        height: RECORDING_DOT_SIZE,
+        color: [0.95, 0.2, 0.25, 1.0],
+        corner_radius: RECORDING_DOT_SIZE / 2.0,
+    }
+}
+
+/// Render data for the loop timings card
+pub struct DebugOverlayFrame {
+    pub background: RenderQuad,
+    pub lines: Vec<TextRender>,
+}
+
+/// Loop timings card in the bottom-left corner
+pub fn render_debug_overlay(screen_height: i32, lines: &[String], theme: &CommandCenterTheme) -> DebugOverlayFrame {
+    let padding = 10.0;
+    let line_height = 16.0;
+    let width = 200.0;
+    let height = padding * 2.0 + line_height * lines.len() as f32;
+    let x = padding;
+    let y = screen_height as f32 - height - padding;
+
+    DebugOverlayFrame {
+        background: RenderQuad {
+            x,
+            y,
+            width,
+            height,
+            color: with_alpha(theme.bg_color, 0.85),
+            corner_radius: 6.0,
+        },
+        lines: lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| TextRender {
+                x: x + padding,
+                y: y + padding + i as f32 * line_height,
+                text: line.clone(),
+                color: theme.text_secondary,
+                size: 12.0,
+                font_weight: FontWeight::Regular,
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,161 @@
+//! Frozen client detection - xdg_wm_base ping/pong
+//!
+//! Every client with a toplevel gets pinged on a slow cadence. One that
+//! hasn't answered within the timeout is marked unresponsive until a pong
+//! finally shows up.
+
+use std::time::{Duration, Instant};
+
+use smithay::{
+    desktop::Window,
+    input::SERIAL_COUNTER,
+    reexports::wayland_server::{backend::DisconnectReason, Resource},
+    wayland::shell::xdg::ShellClient,
+};
+
+use crate::state::VibeWM;
+
+/// How often each client gets pinged
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Ping state for one client
+struct ClientPing {
+    client: ShellClient,
+
+    /// When the outstanding ping went out
+    sent_at: Option<Instant>,
+
+    /// When we last pinged it, answered or not
+    last_ping: Option<Instant>,
+
+    unresponsive: bool,
+}
+
+/// Outstanding pings, per client
+pub struct PingTracker {
+    clients: Vec<ClientPing>,
+}
+
+impl PingTracker {
+    pub fn new() -> Self {
+        Self { clients: Vec::new() }
+    }
+
+    /// Ping whoever is due, flag whoever is late - returns clients that just went unresponsive
+    fn update(&mut self, live: Vec<ShellClient>, timeout: Duration) -> usize {
+        // Forget clients that are gone or have no toplevels left
+        self.clients.retain(|c| c.client.alive() && live.contains(&c.client));
+        for client in live {
+            if !self.clients.iter().any(|c| c.client == client) {
+                self.clients.push(ClientPing {
+                    client,
+                    sent_at: None,
+                    last_ping: None,
+                    unresponsive: false,
+                });
+            }
+        }
+
+        let mut newly_unresponsive = 0;
+        for ping in &mut self.clients {
+            match ping.sent_at {
+                Some(sent_at) if !ping.unresponsive && sent_at.elapsed() >= timeout => {
+                    ping.unresponsive = true;
+                    newly_unresponsive += 1;
+                }
+                Some(_) => {}
+                None if ping.last_ping.map_or(true, |t| t.elapsed() >= PING_INTERVAL) => {
+                    // Smithay refuses a second ping while one is pending, which is what we want
+                    if ping.client.send_ping(SERIAL_COUNTER.next_serial()).is_ok() {
+                        ping.sent_at = Some(Instant::now());
+                    }
+                    ping.last_ping = Some(Instant::now());
+                }
+                None => {}
+            }
+        }
+
+        newly_unresponsive
+    }
+
+    /// A pong arrived - returns true if the client had been marked unresponsive
+    fn pong(&mut self, client: &ShellClient) -> bool {
+        let Some(ping) = self.clients.iter_mut().find(|c| &c.client == client) else {
+            return false;
+        };
+
+        ping.sent_at = None;
+        std::mem::replace(&mut ping.unresponsive, false)
+    }
+
+    fn is_unresponsive(&self, client: &ShellClient) -> bool {
+        self.clients.iter().any(|c| &c.client == client && c.unresponsive)
+    }
+}
+
+impl VibeWM {
+    /// Send due pings and time out the late ones (called every frame)
+    pub fn update_pings(&mut self) {
+        let mut live: Vec<ShellClient> = Vec::new();
+        for window in self.windows.all() {
+            if let Some(toplevel) = window.toplevel() {
+                let client = toplevel.client();
+                if !live.contains(&client) {
+                    live.push(client);
+                }
+            }
+        }
+
+        let timeout = Duration::from_millis(self.config.ping_timeout_ms);
+        if self.pings.update(live, timeout) > 0 {
+            tracing::warn!("A client stopped responding to pings");
+        }
+    }
+
+    /// xdg_wm_base pong from a client
+    pub fn client_ponged(&mut self, client: &ShellClient) {
+        if self.pings.pong(client) {
+            tracing::info!("Client is responding again");
+        }
+    }
+
+    /// Is this window's client frozen?
+    pub fn is_unresponsive(&self, window: &Window) -> bool {
+        window
+            .toplevel()
+            .is_some_and(|toplevel| self.pings.is_unresponsive(&toplevel.client()))
+    }
+
+    /// Title for lists - the app_id (or a number) for untitled windows,
+    /// flags frozen ones
+    pub fn display_title(&self, window: &Window) -> String {
+        let title = Some(crate::window::window_title(window))
+            .filter(|title| !title.is_empty())
+            .or_else(|| crate::window::window_app_id(window))
+            .or_else(|| Some(format!("Window {}", self.windows.meta(window)?.id)))
+            .unwrap_or_default();
+        if self.is_unresponsive(window) {
+            format!("{} (Not responding)", title)
+        } else {
+            title
+        }
+    }
+
+    /// Disconnect the focused window's client outright - for when mod+W gets ignored
+    pub fn force_close_focused(&mut self) {
+        let Some(window) = self.windows.focused() else {
+            return;
+        };
+        let Some(client) = window
+            .toplevel()
+            .and_then(|toplevel| self.display_handle.get_client(toplevel.wl_surface().id()).ok())
+        else {
+            return;
+        };
+
+        tracing::info!("Force closing {:?}", crate::window::window_title(window));
+        self.display_handle
+            .backend_handle()
+            .kill_client(client.id(), DisconnectReason::ConnectionClosed);
+    }
+}
@@ -0,0 +1,95 @@
+//! xdg popups - context menus, dropdowns, tooltips
+//!
+//! smithay's PopupManager keeps the popup trees; a Window draws and
+//! hit-tests its popups along with itself, above its own surfaces. What's
+//! left here is placing them - the positioner, flipped or slid to stay on
+//! the window's outputs - and the grab that dismisses a menu when you click
+//! somewhere else.
+
+use smithay::{
+    desktop::{
+        find_popup_root_surface, get_popup_toplevel_coords, PopupKeyboardGrab, PopupKind, PopupPointerGrab,
+        PopupUngrabStrategy, Window,
+    },
+    input::{pointer::Focus, Seat},
+    reexports::wayland_server::protocol::{wl_seat::WlSeat, wl_surface::WlSurface},
+    utils::Serial,
+    wayland::{seat::WaylandFocus, shell::xdg::PopupSurface},
+};
+
+use crate::state::VibeWM;
+
+impl VibeWM {
+    /// The mapped window a surface is the toplevel of
+    fn window_for_surface(&self, surface: &WlSurface) -> Option<Window> {
+        self.windows
+            .all()
+            .iter()
+            .find(|w| w.wl_surface().is_some_and(|s| &*s == surface))
+            .cloned()
+    }
+
+    /// Fit a popup's pending geometry onto the outputs its window is on,
+    /// using whatever flip/slide/resize the client allowed
+    pub fn unconstrain_popup(&self, popup: &PopupSurface) {
+        let kind = PopupKind::Xdg(popup.clone());
+        let root = find_popup_root_surface(&kind).ok();
+        let Some(window) = root.and_then(|root| self.window_for_surface(&root)) else { return };
+        let Some(window_geometry) = self.space.element_geometry(&window) else { return };
+
+        let outputs = self
+            .space
+            .outputs_for_element(&window)
+            .into_iter()
+            .filter_map(|output| self.space.output_geometry(&output))
+            .reduce(|area, geometry| area.merge(geometry));
+        let Some(mut target) = outputs else { return };
+
+        // The positioner works relative to the parent - the window, or the
+        // popup this one hangs off
+        target.loc -= get_popup_toplevel_coords(&kind);
+        target.loc -= window_geometry.loc;
+
+        popup.with_pending_state(|state| {
+            state.geometry = state.positioner.get_unconstrained_geometry(target);
+        });
+    }
+
+    /// A client wants its popup to have the pointer and keyboard until it's
+    /// dismissed - a click outside the popup chain closes the lot
+    pub fn grab_popup(&mut self, surface: PopupSurface, seat: WlSeat, serial: Serial) {
+        let Some(seat) = Seat::<Self>::from_resource(&seat) else { return };
+        let kind = PopupKind::Xdg(surface);
+        let root = find_popup_root_surface(&kind).ok();
+        let Some(root) = root.filter(|root| self.window_for_surface(root).is_some()) else { return };
+
+        let mut grab = match self.popups.grab_popup(root, kind, &seat, serial) {
+            Ok(grab) => grab,
+            Err(e) => {
+                tracing::debug!("Popup grab refused: {:?}", e);
+                return;
+            }
+        };
+
+        // Only for the click or key that opened it - anything else already
+        // holding the seat wins
+        if let Some(keyboard) = seat.get_keyboard() {
+            let previous = grab.previous_serial().unwrap_or(serial);
+            if keyboard.is_grabbed() && !(keyboard.has_grab(serial) || keyboard.has_grab(previous)) {
+                grab.ungrab(PopupUngrabStrategy::All);
+                return;
+            }
+            keyboard.set_focus(self, grab.current_grab(), serial);
+            keyboard.set_grab(self, PopupKeyboardGrab::new(&grab), serial);
+        }
+
+        if let Some(pointer) = seat.get_pointer() {
+            let previous = grab.previous_serial().unwrap_or_else(|| grab.serial());
+            if pointer.is_grabbed() && !(pointer.has_grab(serial) || pointer.has_grab(previous)) {
+                grab.ungrab(PopupUngrabStrategy::All);
+                return;
+            }
+            pointer.set_grab(self, PopupPointerGrab::new(&grab), serial, Focus::Keep);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Per-window CPU/memory usage
+//!
+//! A background thread samples /proc for every client PID we care about and
+//! keeps a cache. The compositor thread only ever peeks at the cache with
+//! try_lock, so a slow /proc read can't hitch a frame.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Slow-poll cadence for process sampling
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Kernel clock ticks per second - USER_HZ is 100 on every Linux we run on
+const CLOCK_TICKS: f64 = 100.0;
+
+/// Cap on processes walked per window (browsers love to fork)
+const MAX_PROCESS_TREE: usize = 64;
+
+/// Resource usage of a window's process tree
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcUsage {
+    /// CPU% over the last sample interval (100 = one full core)
+    pub cpu_percent: f32,
+
+    /// Resident memory (kB)
+    pub rss_kb: u64,
+}
+
+/// State shared with the sampler thread
+#[derive(Default)]
+struct Shared {
+    /// PIDs to sample, set by the compositor
+    watched: HashSet<u32>,
+
+    /// Latest usage per watched PID
+    usage: HashMap<u32, ProcUsage>,
+}
+
+/// Handle to the sampler thread
+pub struct ProcSampler {
+    shared: Arc<Mutex<Shared>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ProcSampler {
+    pub fn spawn() -> Self {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let shared = shared.clone();
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("vibewm-procstat".to_string())
+                .spawn(move || sample_loop(shared, stop))
+                .map_err(|e| tracing::warn!("Process sampler disabled: {}", e))
+                .ok()
+        };
+
+        Self { shared, stop, thread }
+    }
+
+    /// Replace the set of PIDs to sample
+    ///
+    /// Only called when windows come and go - the sampler holds the lock just
+    /// long enough to copy the set, so a plain lock is fine here.
+    pub fn watch(&self, pids: impl IntoIterator<Item = u32>) {
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.watched = pids.into_iter().collect();
+        }
+    }
+
+    /// Cached usage for a PID - None if not sampled yet (or the lock is busy)
+    pub fn usage(&self, pid: u32) -> Option<ProcUsage> {
+        self.shared.try_lock().ok()?.usage.get(&pid).copied()
+    }
+}
+
+impl Drop for ProcSampler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            thread.join().ok();
+        }
+    }
+}
+
+fn sample_loop(shared: Arc<Mutex<Shared>>, stop: Arc<AtomicBool>) {
+    // CPU ticks per root PID at the previous sample
+    let mut previous: HashMap<u32, (u64, Instant)> = HashMap::new();
+
+    while !stop.load(Ordering::Relaxed) {
+        let watched: Vec<u32> = match shared.lock() {
+            Ok(shared) => shared.watched.iter().copied().collect(),
+            Err(_) => return,
+        };
+
+        // All the /proc reading happens without holding the lock
+        let mut usage = HashMap::new();
+        for pid in watched {
+            let Some((ticks, rss_kb)) = sample_tree(pid) else {
+                // Gone mid-sample - skip it
+                continue;
+            };
+
+            let now = Instant::now();
+            let cpu_percent = previous
+                .get(&pid)
+                .map(|(prev_ticks, prev_time)| {
+                    let secs = now.duration_since(*prev_time).as_secs_f64();
+                    let used = ticks.saturating_sub(*prev_ticks) as f64 / CLOCK_TICKS;
+                    if secs > 0.0 { (used / secs * 100.0) as f32 } else { 0.0 }
+                })
+                .unwrap_or(0.0);
+
+            previous.insert(pid, (ticks, now));
+            usage.insert(pid, ProcUsage { cpu_percent, rss_kb });
+        }
+
+        previous.retain(|pid, _| usage.contains_key(pid));
+
+        match shared.lock() {
+            Ok(mut shared) => shared.usage = usage,
+            Err(_) => return,
+        }
+
+        thread::park_timeout(SAMPLE_INTERVAL);
+    }
+}
+
+/// Total CPU ticks and RSS of a process and its children
+fn sample_tree(root: u32) -> Option<(u64, u64)> {
+    // The root has to be there, children are best effort
+    let (mut ticks, mut rss_kb) = (read_cpu_ticks(root)?, read_rss_kb(root).unwrap_or(0));
+
+    let mut seen = HashSet::from([root]);
+    let mut queue = children(root);
+
+    while let Some(pid) = queue.pop() {
+        if seen.len() >= MAX_PROCESS_TREE || !seen.insert(pid) {
+            continue;
+        }
+
+        if let Some(child_ticks) = read_cpu_ticks(pid) {
+            ticks += child_ticks;
+            rss_kb += read_rss_kb(pid).unwrap_or(0);
+            queue.extend(children(pid));
+        }
+    }
+
+    Some((ticks, rss_kb))
+}
+
+/// utime + stime from /proc/<pid>/stat
+fn read_cpu_ticks(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+
+    // comm can contain spaces and parens - the fields start after the last ')'
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+
+    // fields[0] is state (field 3), so utime (14) and stime (15) are 11 and 12
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+/// VmRSS from /proc/<pid>/status
+fn read_rss_kb(pid: u32) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|kb| kb.parse().ok())
+}
+
+/// Direct children, from every thread's children list
+fn children(pid: u32) -> Vec<u32> {
+    let Ok(tasks) = fs::read_dir(format!("/proc/{}/task", pid)) else {
+        return Vec::new();
+    };
+
+    tasks
+        .flatten()
+        .filter_map(|task| fs::read_to_string(task.path().join("children")).ok())
+        .flat_map(|list| {
+            list.split_whitespace()
+                .filter_map(|p| p.parse().ok())
+                .collect::<Vec<u32>>()
+        })
+        .collect()
+}
+
+/// Is `pid` the same process as `ancestor`, or one of its descendants?
+pub fn is_descendant(pid: u32, ancestor: u32) -> bool {
+    let mut current = pid;
+
+    // Launch wrappers are shallow - don't chase the whole tree
+    for _ in 0..8 {
+        if current == ancestor {
+            return true;
+        }
+
+        match read_ppid(current) {
+            Some(ppid) if ppid > 1 => current = ppid,
+            _ => return false,
+        }
+    }
+
+    false
+}
+
+/// Parent PID from /proc/<pid>/stat
+fn read_ppid(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+
+    // Field 4, right after state
+    stat.rsplit_once(')')?.1.split_whitespace().nth(1)?.parse().ok()
+}
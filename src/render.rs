@@ -4,29 +4,430 @@
 //! the full implementation would use glow/OpenGL directly for the
 //! command center effects.
 
+use smithay::{
+    backend::renderer::{
+        element::{
+            surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+            utils::{Relocate, RelocateRenderElement, RescaleRenderElement},
+            AsRenderElements, Kind,
+        },
+        glow::GlowRenderer,
+    },
+    input::pointer::{CursorImageStatus, CursorImageSurfaceData},
+    output::Output,
+    utils::{IsAlive, Physical, Point},
+    wayland::compositor::with_states,
+};
+
 use crate::state::VibeWM;
-use crate::command_center::{CommandCenterLayout, CommandCenterTheme};
+use crate::command_center::{CommandCenter, CommandCenterTheme};
+use crate::overlay::FADE;
+use crate::screenshot::RegionSelect;
+use crate::wallpaper::WallpaperFrame;
+use crate::titlebar::{self, TitlebarFrame};
+use crate::window::window_title;
+use crate::window_menu::WindowMenu;
+
+// Everything a backend draws on an output, topmost first
+smithay::render_elements! {
+    pub OutputRenderElement<=GlowRenderer>;
+    Surface=WaylandSurfaceRenderElement<GlowRenderer>,
+    Zoomed=RelocateRenderElement<RescaleRenderElement<WaylandSurfaceRenderElement<GlowRenderer>>>,
+}
 
 impl VibeWM {
-    /// Called each frame to render
-    pub fn render_frame(&mut self) {
-        // Render command center if visible
-        if self.command_center.visible || self.command_center.animation_t > 0.0 {
-            self.render_command_center();
+    /// Everything to draw on this output this frame, topmost first -
+    /// `cursor` is false where the host draws the cursor for us
+    ///
+    /// With the magnifier on here, all of it is scaled up by the zoom level
+    /// with the viewport's corner pinned to the output's - Zoom::to_screen,
+    /// which input goes through backwards.
+    pub fn output_elements(&self, renderer: &mut GlowRenderer, output: &Output, cursor: bool) -> Vec<OutputRenderElement> {
+        let mut elements = Vec::new();
+        if cursor {
+            elements.extend(self.cursor_elements(renderer, output));
+        }
+        elements.extend(self.window_elements(renderer, output));
+
+        let (Some(viewport), Some(output_geo)) = (self.zoom_view(output), self.space.output_geometry(output)) else {
+            return elements.into_iter().map(OutputRenderElement::from).collect();
+        };
+
+        let scale = output.current_scale().fractional_scale();
+        let level = output_geo.size.w as f64 / viewport.size.w;
+        let origin: Point<i32, Physical> = (viewport.loc - output_geo.loc.to_f64()).to_physical_precise_round(scale);
+
+        elements
+            .into_iter()
+            .map(|element| {
+                let scaled = RescaleRenderElement::from_element(element, origin, level);
+                OutputRenderElement::from(RelocateRenderElement::from_element(scaled, (-origin.x, -origin.y), Relocate::Relative))
+            })
+            .collect()
+    }
+
+    /// Every window on this output, topmost first, popups included
+    ///
+    /// Each window draws at window_opacity() - smithay multiplies that with
+    /// the client's own wp_alpha_modifier factor per surface.
+    ///
+    /// TODO: Windows whose client is_unresponsive() should be dimmed and
+    /// desaturated too - desaturating needs a texture shader.
+    fn window_elements(&self, renderer: &mut GlowRenderer, output: &Output) -> Vec<WaylandSurfaceRenderElement<GlowRenderer>> {
+        let Some(output_geo) = self.space.output_geometry(output) else {
+            return Vec::new();
+        };
+        let scale = output.current_scale().fractional_scale();
+
+        self.space
+            .elements_for_output(output)
+            .rev()
+            .flat_map(|window| {
+                let Some(location) = self.space.element_location(window) else {
+                    return Vec::new();
+                };
+                // Where the buffer starts, which is left/above the window
+                // geometry by however big the client's shadows are
+                let location: Point<i32, Physical> =
+                    (location - window.geometry().loc - output_geo.loc).to_physical_precise_round(scale);
+                window.render_elements::<WaylandSurfaceRenderElement<GlowRenderer>>(
+                    renderer,
+                    location,
+                    scale.into(),
+                    self.window_opacity(window),
+                )
+            })
+            .collect()
+    }
+
+    /// The cursor surface the focused client set, placed at the pointer on
+    /// this output - empty when there's none
+    ///
+    /// These are Kind::Cursor, so DRM puts them on the cursor plane when
+    /// they fit. Named cursors need an xcursor theme we don't load yet.
+    fn cursor_elements(&self, renderer: &mut GlowRenderer, output: &Output) -> Vec<WaylandSurfaceRenderElement<GlowRenderer>> {
+        // Hidden (idle or typing) - with no cursor element DrmCompositor
+        // clears the cursor plane, and sets it again once there's one back
+        if !self.cursor_visible() {
+            return Vec::new();
+        }
+        let CursorImageStatus::Surface(surface) = &self.cursor_status else {
+            return Vec::new();
+        };
+        let Some(output_geo) = self.space.output_geometry(output) else {
+            return Vec::new();
+        };
+        if !surface.alive() {
+            return Vec::new();
+        }
+
+        let hotspot = with_states(surface, |states| {
+            states.data_map
+                .get::<CursorImageSurfaceData>()
+                .map(|data| data.lock().unwrap().hotspot)
+                .unwrap_or_default()
+        });
+
+        let scale = output.current_scale().fractional_scale();
+        let location: Point<i32, Physical> = (self.input.pointer_pos - output_geo.loc.to_f64() - hotspot.to_f64())
+            .to_physical_precise_round(scale);
+        render_elements_from_surface_tree(renderer, surface, location, scale, 1.0, Kind::Cursor)
+    }
+
+    /// Called each frame, once per output
+    pub fn render_frame(&mut self, output: &Output) {
+        // Wallpaper goes under everything, windows included
+        self.render_wallpaper(output);
+
+        // Snap preview sits under everything else
+        if self.snap_preview.target.is_some() && !self.popups_hidden_on(output) {
+            self.render_snap_preview();
+        }
+
+        // Compositor titlebars, for windows that have them
+        if self.config.titlebars.enabled {
+            self.render_titlebars(output);
+        }
+
+        // Focus ring flashes around the window that just got focus
+        if self.config.focus_ring.enabled && self.decorative_animations() {
+            self.render_focus_ring(output);
+        }
+
+        // Spotlight dims every window but one - under the overlays so the
+        // command center stays usable
+        if self.spotlight.is_some() {
+            self.render_spotlight(output);
+        }
+
+        // Command center only on the output it's anchored to
+        if let Some(command_center) = &self.command_center {
+            let anchored_here = command_center.output
+                .as_deref()
+                .map(|name| name == output.name())
+                .unwrap_or(true);
+
+            if anchored_here && (command_center.visible || command_center.animation_t > 0.0) {
+                self.render_command_center(command_center, output);
+            }
+        }
+
+        // Empty-desktop hints
+        if self.help_overlay.visible {
+            self.render_help_overlay();
+        }
+
+        // Cheat sheet above the windows and the other cards - a lock screen
+        // goes above it when there is one
+        if self.cheat_sheet.visible {
+            self.render_cheat_sheet(output);
+        }
+
+        // Notices sit above the windows and overlays
+        if self.notice.is_visible() && !self.popups_hidden_on(output) {
+            self.render_notice(output);
+        }
+
+        // Region screenshot dims the frozen frame around the selection
+        if let Some(select) = self.region_select.as_ref().filter(|s| s.output == output.name()) {
+            self.render_region_select(select, output);
+        }
+
+        // Window menu above the windows and cards
+        if let Some(menu) = &self.window_menu {
+            self.render_window_menu(menu, output);
+        }
+
+        // Quit confirmation goes on top of everything
+        if self.quit_confirm.is_armed() {
+            self.render_quit_confirm();
+        }
+
+        // Loop timings, when asked for
+        if self.config.debug_overlay {
+            self.render_debug_overlay(output);
+        }
+
+        // ...except the recording dot, which is never covered
+        if self.config.recording.indicator && self.captures.is_recording(&output.name()) {
+            self.render_recording_indicator(output);
+        }
+    }
+
+    fn render_wallpaper(&self, output: &Output) {
+        let Some(geometry) = self.space.output_geometry(output) else {
+            return;
+        };
+
+        // Mid-slide both workspaces are on screen, each with its own wallpaper
+        let _frames: Vec<WallpaperFrame> = match &self.workspace_slide {
+            Some(slide) => std::iter::once(slide.from)
+                .chain(slide.to)
+                .filter_map(|workspace| {
+                    let offset = slide.offset_for(workspace, geometry.size.w)?;
+                    self.wallpapers.render(workspace, offset)
+                })
+                .collect(),
+            None => self.wallpapers.render(self.windows.active_workspace(), 0).into_iter().collect(),
+        };
+
+        // TODO: Upload each layer's image as a texture (kept per renderer
+        // while the Arc is the same - a renderer rebuilt after a GPU reset
+        // uploads again) and draw it scaled to cover the output, at
+        // offset_x and the layer's opacity
+    }
+
+    fn render_debug_overlay(&self, output: &Output) {
+        let Some(geometry) = self.space.output_geometry(output) else {
+            return;
+        };
+
+        let theme = CommandCenterTheme::default();
+        let lines = self.frames.timings.lines();
+        let _frame = crate::overlay::render_debug_overlay(geometry.size.h, &lines, &theme);
+
+        // TODO: Draw with the same quad/text pipeline as the command center
+    }
+
+    fn render_recording_indicator(&self, output: &Output) {
+        let Some(geometry) = self.space.output_geometry(output) else {
+            return;
+        };
+
+        let _dot = crate::overlay::render_recording_dot(
+            geometry.size.w,
+            geometry.size.h,
+            self.config.recording.indicator_corner,
+        );
+
+        // TODO: Draw with the same quad pipeline as the command center
+    }
+
+    fn render_snap_preview(&self) {
+        let theme = CommandCenterTheme::default();
+        let _quad = self.snap_preview.render(&theme);
+
+        // TODO: Draw with the same quad pipeline as the command center
+    }
+
+    fn render_titlebars(&self, output: &Output) {
+        let Some(output_geo) = self.space.output_geometry(output) else {
+            return;
+        };
+
+        let theme = CommandCenterTheme::default();
+        let hovered = self.titlebar_under(self.input.pointer_pos);
+        let focused = self.windows.focused();
+
+        // Bottom to top, titles read fresh so renames show up right away
+        let _frames: Vec<TitlebarFrame> = self.space
+            .elements()
+            .filter_map(|window| {
+                let height = self.windows.meta(window)?.titlebar?;
+                let mut bar = titlebar::bar_rect(self.space.element_geometry(window)?, height);
+                if !output_geo.overlaps(bar) {
+                    return None;
+                }
+                bar.loc -= output_geo.loc;
+
+                let hover = hovered.as_ref().filter(|(w, _)| w == window).map(|(_, hit)| *hit);
+                Some(titlebar::render(bar, &window_title(window), focused == Some(window), hover, &theme))
+            })
+            .collect();
+
+        // TODO: Draw each bar with the same quad/text pipeline as the command
+        // center, right after its window's surfaces so stacking holds
+    }
+
+    fn render_focus_ring(&self, output: &Output) {
+        let Some(window) = self.windows.focused() else { return };
+        let Some(focused_at) = self.windows.meta(window).and_then(|m| m.focused_at) else {
+            return;
+        };
+        let (Some(output_geo), Some(mut geometry)) =
+            (self.space.output_geometry(output), self.windows.frame_geometry(&self.space, window))
+        else {
+            return;
+        };
+
+        if !output_geo.overlaps(geometry) {
+            return;
+        }
+        geometry.loc -= output_geo.loc;
+
+        let theme = CommandCenterTheme::default();
+        let _glow = crate::overlay::render_focus_ring(geometry, focused_at, &self.config.focus_ring, &theme);
+
+        // TODO: Draw with GLOW_SHADER_FRAG, same as the command center glow
+    }
+
+    fn render_spotlight(&self, output: &Output) {
+        let Some(window) = &self.spotlight else { return };
+        let (Some(output_geo), Some(window_geo)) =
+            (self.space.output_geometry(output), self.space.element_geometry(window))
+        else {
+            return;
+        };
+
+        // Hole in this output's coordinates
+        let mut hole = window_geo;
+        hole.loc -= output_geo.loc;
+
+        let theme = CommandCenterTheme::default();
+        let _frame = crate::overlay::render_spotlight(output_geo.size.w, output_geo.size.h, hole, &theme);
+
+        // TODO: Draw the dim with SPOTLIGHT_SHADER_FRAG
+    }
+
+    fn render_notice(&self, output: &Output) {
+        let Some(mode) = output.current_mode() else { return };
+
+        let theme = CommandCenterTheme::default();
+        let _frame = self.notice.render(mode.size.w, self.animation_duration(FADE), &theme);
+
+        // TODO: Draw with the same quad/text pipeline as the command center
+    }
+
+    fn render_quit_confirm(&self) {
+        let Some(output_size) = self.output.as_ref().and_then(|o| o.current_mode()).map(|m| m.size) else {
+            return;
+        };
+
+        let theme = CommandCenterTheme::default();
+        let fade = self.animation_duration(FADE);
+        let _frame = self.quit_confirm.render(output_size.w, output_size.h, fade, &theme);
+
+        // TODO: Draw with the same quad/text pipeline as the command center
+    }
+
+    fn render_help_overlay(&self) {
+        let Some(output_size) = self.output.as_ref().and_then(|o| o.current_mode()).map(|m| m.size) else {
+            return;
+        };
+
+        let theme = CommandCenterTheme::default();
+        let fade = self.animation_duration(FADE);
+        let _frame = self.help_overlay.render(output_size.w, output_size.h, fade, &theme);
+
+        // TODO: Draw with the same quad/text pipeline as the command center
+    }
+
+    fn render_window_menu(&self, menu: &WindowMenu, output: &Output) {
+        let Some(geometry) = self.space.output_geometry(output) else {
+            return;
+        };
+        // Just the output it opened on
+        if !geometry.contains(menu.location) {
+            return;
         }
+
+        let (snapped, on_top) = self.windows
+            .meta(&menu.window)
+            .map(|m| (m.snap_state.is_some(), m.always_on_top))
+            .unwrap_or_default();
+        let theme = CommandCenterTheme::default();
+        let _frame = menu.render(geometry.loc, snapped, on_top, &theme);
+
+        // TODO: Draw with the same quad/text pipeline as the command center
+    }
+
+    fn render_region_select(&self, select: &RegionSelect, output: &Output) {
+        let Some(geometry) = self.space.output_geometry(output) else {
+            return;
+        };
+
+        let theme = CommandCenterTheme::default();
+        let _frame = select.render(geometry.size, &theme);
+
+        // TODO: Draw select.frozen as a full-output texture in place of the
+        // live scene, then the dim/border/handle quads and the size label on
+        // top with the same pipeline as the command center
+    }
+
+    fn render_cheat_sheet(&self, output: &Output) {
+        let Some(output_size) = output.current_mode().map(|m| m.size) else {
+            return;
+        };
+
+        // Built fresh each frame, so remaps and resize mode show up right away
+        let groups = self.cheat_sheet_groups();
+        let theme = CommandCenterTheme::default();
+        let fade = self.animation_duration(FADE);
+        let _frame = self.cheat_sheet.render(output_size.w, output_size.h, &groups, fade, &theme);
+
+        // TODO: Draw with the same quad/text pipeline as the command center
     }
 
-    fn render_command_center(&self) {
-        let output_size = self.output.as_ref()
-            .and_then(|o| o.current_mode())
-            .map(|m| m.size)
-            .unwrap_or((1920, 1080).into());
+    fn render_command_center(&self, command_center: &CommandCenter, output: &Output) {
+        let Some(output_size) = output.current_mode().map(|m| m.size) else {
+            return;
+        };
 
-        let layout = CommandCenterLayout::calculate(output_size.w, output_size.h);
+        let layout = self.command_center_layout(output_size.w, output_size.h);
         let theme = CommandCenterTheme::default();
 
         // Get render data
-        let _frame = self.command_center.render(&layout, &theme);
+        let _frame = command_center.render(&layout, &theme);
 
         // TODO: Actually render the frame using glow
         // This would involve:
@@ -34,7 +435,8 @@ impl VibeWM {
         // 2. Drawing gradient overlay
         // 3. Drawing glow border
         // 4. Drawing search bar
-        // 5. Drawing app cards with stagger animation
+        // 5. Drawing app cards with stagger animation - CardIcon::App
+        //    images uploaded as textures, kept while the Arc is the same
         // 6. Drawing system bar
         //
         // The shaders are defined in render_command_center.rs
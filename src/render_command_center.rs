@@ -3,7 +3,16 @@
 //! This is the anti-suckless manifesto in code form.
 //! Every pixel drips with intention.
 
-use crate::command_center::{CommandCenter, CommandCenterLayout, CommandCenterTheme};
+use std::sync::Arc;
+
+use crate::command_center::{
+    AppEntry, CommandCenter, CommandCenterLayout, CommandCenterSection, CommandCenterTheme, PowerAction, CARD_GAP,
+    GLOW_SPREAD,
+};
+use crate::icons::{IconCache, IconImage};
+
+/// App card icon size (px)
+const CARD_ICON_SIZE: f32 = 24.0;
 
 /// Render data for a single frame
 pub struct CommandCenterFrame {
@@ -22,6 +31,12 @@ pub struct CommandCenterFrame {
     /// App cards
     pub app_cards: Vec<AppCardRender>,
 
+    /// "...and N more" row under the cards, past max_results
+    pub more_results: Option<MoreResultsRender>,
+
+    /// Usage stats in place of the cards ("stats" query)
+    pub stats: Option<StatsRender>,
+
     /// System info bar
     pub system_bar: SystemBarRender,
 
@@ -110,22 +125,68 @@ pub enum Icon {
     Cpu,
     Memory,
     App,
+    Action,
+    File,
     Window,
+    Power,
     Close,
+    Maximize,
+}
+
+/// What sits at the left of a card
+pub enum CardIcon {
+    /// Built-in glyph (actions, files)
+    Glyph(IconRender),
+
+    /// The app's own icon, clipped to a rounded square
+    App {
+        /// Decoded from the icon theme - the renderer uploads it as a texture
+        /// (once per Arc) and draws it over the mask
+        image: Arc<IconImage>,
+        mask: RenderQuad,
+    },
+
+    /// No icon to be had - first letter on an accent tile
+    Monogram { tile: RenderQuad, letter: TextRender },
 }
 
 pub struct AppCardRender {
     pub background: RenderQuad,
-    pub icon: Option<IconRender>,
+    pub icon: Option<CardIcon>,
     pub name: TextRender,
     pub selected: bool,
     pub hover_t: f32,  // Animation progress
     pub stagger_delay: f32,  // For staggered entrance
 }
 
+pub struct MoreResultsRender {
+    pub background: RenderQuad,
+    pub text: TextRender,
+}
+
+pub struct StatsRender {
+    pub title: TextRender,
+    pub rows: Vec<StatsRowRender>,
+
+    /// Windows opened and uptime
+    pub footer: TextRender,
+}
+
+/// One app - a card with a bar scaled against the busiest app
+pub struct StatsRowRender {
+    pub background: RenderQuad,
+    pub name: TextRender,
+    pub bar: RenderQuad,
+    pub count: TextRender,
+}
+
 pub struct SystemBarRender {
     pub background: RenderQuad,
     pub clock: TextRender,
+    pub workspace: TextRender,
+
+    /// CPU and memory - None until the sampler has read /proc
+    pub resources: Option<TextRender>,
     pub battery: BatteryRender,
     pub dividers: Vec<RenderQuad>,
 }
@@ -191,12 +252,18 @@ impl CommandCenter {
                 height: scaled_h,
                 color: with_alpha(theme.glow_color, self.current_glow() * eased_t),
                 intensity: theme.glow_intensity,
-                spread: 20.0,
+                spread: GLOW_SPREAD,
                 corner_radius: 16.0,
             },
 
             search_bar: self.render_search_bar(layout, theme, eased_t),
-            app_cards: self.render_app_cards(layout, theme, eased_t),
+            app_cards: if self.showing_stats() {
+                Vec::new()
+            } else {
+                self.render_app_cards(layout, theme, eased_t)
+            },
+            more_results: self.render_more_results(layout, theme, eased_t),
+            stats: self.render_stats(layout, theme, eased_t),
             system_bar: self.render_system_bar(layout, theme, eased_t),
 
             opacity: eased_t,
@@ -236,7 +303,11 @@ impl CommandCenter {
                 x: x + 48.0,
                 y: y + offset_y + h / 2.0,
                 text: if self.search_query.is_empty() {
-                    "Search apps...".to_string()
+                    match self.section {
+                        CommandCenterSection::Files => "Search recent files...".to_string(),
+                        CommandCenterSection::Windows => "Search windows...".to_string(),
+                        _ => "Search apps...".to_string(),
+                    }
                 } else {
                     self.search_query.clone()
                 },
@@ -264,21 +335,60 @@ impl CommandCenter {
         let card_w = layout.app_card_width as f32;
         let card_h = layout.app_card_height as f32;
         let columns = layout.app_columns as usize;
-        let gap = 12.0;
+        let gap = CARD_GAP;
+
+        // Only rows inside the grid viewport get cards
+        let scroll = self.scroll.offset;
+        let view_top = start_y;
+        let view_bottom = start_y + layout.apps_height as f32;
 
-        self.filtered_apps
+        // Matching actions come first, in their own purple cards
+        let actions = self.filtered_actions
+            .iter()
+            .map(|matched| (matched.name.as_str(), true, None));
+        let apps = self.filtered_apps
             .iter()
-            .take(12)  // Max visible
+            .map(|app| (app.name.as_str(), false, Some(app)));
+
+        // The other sections are files, windows or power cards and nothing else
+        let cards: Vec<(&str, bool, Option<&AppEntry>)> = match self.section {
+            CommandCenterSection::Files => {
+                self.filtered_files.iter().map(|file| (file.name.as_str(), false, None)).collect()
+            }
+            CommandCenterSection::Windows => {
+                self.filtered_windows.iter().map(|window| (window.title.as_str(), false, None)).collect()
+            }
+            CommandCenterSection::System => PowerAction::ALL
+                .iter()
+                .map(|&power| {
+                    let armed = self.power_armed == Some(power);
+                    (if armed { power.confirm_label() } else { power.label() }, false, None)
+                })
+                .collect(),
+            _ => actions.chain(apps).collect(),
+        };
+        let glyph = match self.section {
+            CommandCenterSection::Windows => Icon::Window,
+            CommandCenterSection::System => Icon::Power,
+            _ => Icon::File,
+        };
+
+        cards
+            .into_iter()
             .enumerate()
-            .map(|(i, app)| {
+            .filter(|(i, _)| {
+                let y = start_y + (i / columns) as f32 * (card_h + gap) - scroll;
+                y + card_h > view_top && y < view_bottom
+            })
+            .map(|(i, (name, is_action, app))| {
                 let col = i % columns;
                 let row = i / columns;
 
                 let x = start_x + col as f32 * (card_w + gap);
-                let y = start_y + row as f32 * (card_h + gap);
+                let y = start_y + row as f32 * (card_h + gap) - scroll;
 
-                // Stagger animation - each card delayed slightly
-                let delay = 0.1 + i as f32 * 0.03;
+                // Stagger animation - each card delayed slightly (first screenful only)
+                let delay = 0.1 + i.min(11) as f32 * 0.03;
                 let local_t = ((t - delay) * 3.0).clamp(0.0, 1.0);
                 let eased = 1.0 - (1.0 - local_t).powi(3);
 
@@ -287,6 +397,8 @@ impl CommandCenter {
 
                 let selected = i == self.selected_index;
 
+                let accent = if is_action { theme.accent_tertiary } else { theme.accent_primary };
+
                 AppCardRender {
                     background: RenderQuad {
                         x,
@@ -295,25 +407,38 @@ impl CommandCenter {
                         height: card_h,
                         color: if selected {
                             with_alpha(theme.card_selected, card_opacity)
+                        } else if is_action {
+                            with_alpha(theme.card_action, card_opacity)
                         } else {
                             with_alpha(theme.card_bg, card_opacity)
                         },
                         corner_radius: theme.card_border_radius,
                     },
-                    icon: Some(IconRender {
-                        x: x + 16.0,
-                        y: y + offset_y + card_h / 2.0,
-                        size: 24.0,
-                        icon: Icon::App,
-                        color: with_alpha(
-                            if selected { theme.accent_primary } else { theme.text_secondary },
-                            card_opacity
+                    icon: Some(match app {
+                        Some(app) => app_icon(
+                            app,
+                            &self.icons,
+                            x + 16.0,
+                            y + offset_y + card_h / 2.0,
+                            card_h,
+                            theme,
+                            card_opacity,
                         ),
+                        None => CardIcon::Glyph(IconRender {
+                            x: x + 16.0,
+                            y: y + offset_y + card_h / 2.0,
+                            size: CARD_ICON_SIZE,
+                            icon: if is_action { Icon::Action } else { glyph },
+                            color: with_alpha(
+                                if selected || is_action { accent } else { theme.text_secondary },
+                                card_opacity
+                            ),
+                        }),
                     }),
                     name: TextRender {
                         x: x + 52.0,
                         y: y + offset_y + card_h / 2.0,
-                        text: truncate_string(&app.name, 15),
+                        text: truncate_string(name, 15),
                         color: with_alpha(
                             if selected { theme.text_highlight } else { theme.text_primary },
                             card_opacity
@@ -329,6 +454,48 @@ impl CommandCenter {
             .collect()
     }
 
+    /// Full-width row after the last card row, scrolling with the grid
+    fn render_more_results(&self, layout: &CommandCenterLayout, theme: &CommandCenterTheme, t: f32) -> Option<MoreResultsRender> {
+        if !self.shows_more_footer() {
+            return None;
+        }
+
+        let columns = layout.app_columns.max(1) as usize;
+        let card_h = layout.app_card_height as f32;
+        let row = self.result_count().div_ceil(columns);
+        let x = layout.apps_x as f32;
+        let y = layout.apps_y as f32 + row as f32 * (card_h + CARD_GAP) - self.scroll.offset;
+        let width = columns as f32 * (layout.app_card_width as f32 + CARD_GAP) - CARD_GAP;
+
+        // Only while it's inside the grid viewport
+        let view_bottom = layout.apps_y as f32 + layout.apps_height as f32;
+        if y + card_h <= layout.apps_y as f32 || y >= view_bottom {
+            return None;
+        }
+
+        // Comes in with the last of the cards
+        let opacity = ((t - 0.4) * 3.0).clamp(0.0, 1.0);
+
+        Some(MoreResultsRender {
+            background: RenderQuad {
+                x,
+                y,
+                width,
+                height: card_h,
+                color: with_alpha(theme.card_bg, opacity * 0.5),
+                corner_radius: theme.card_border_radius,
+            },
+            text: TextRender {
+                x: x + 16.0,
+                y: y + card_h / 2.0,
+                text: format!("\u{2026}and {} more \u{2014} keep typing", self.more_results),
+                color: with_alpha(theme.text_secondary, opacity),
+                size: 13.0,
+                font_weight: FontWeight::Regular,
+            },
+        })
+    }
+
     fn render_system_bar(&self, layout: &CommandCenterLayout, theme: &CommandCenterTheme, t: f32) -> SystemBarRender {
         let x = layout.system_x as f32;
         let y = layout.system_y as f32;
@@ -361,6 +528,27 @@ impl CommandCenter {
                 size: 16.0,
                 font_weight: FontWeight::Medium,
             },
+            workspace: TextRender {
+                x: x + 90.0,
+                y: y + offset_y + h / 2.0,
+                text: format!("ws {}", self.workspace_name),
+                color: with_alpha(theme.accent_tertiary, eased),
+                size: 14.0,
+                font_weight: FontWeight::Medium,
+            },
+            resources: (sys_info.memory_total_gb > 0.0).then(|| TextRender {
+                x: x + w - 270.0,
+                y: y + offset_y + h / 2.0,
+                text: format!(
+                    "cpu {:.0}%  mem {:.1}/{:.0}G",
+                    sys_info.cpu_usage,
+                    sys_info.memory_used_gb,
+                    sys_info.memory_total_gb,
+                ),
+                color: with_alpha(theme.text_secondary, eased),
+                size: 13.0,
+                font_weight: FontWeight::Regular,
+            }),
             battery: BatteryRender {
                 icon: IconRender {
                     x: x + w - 100.0,
@@ -423,22 +611,217 @@ impl CommandCenter {
             ],
         }
     }
+
+    /// Stats view - top apps this week as bar cards, in the grid's space
+    fn render_stats(&self, layout: &CommandCenterLayout, theme: &CommandCenterTheme, t: f32) -> Option<StatsRender> {
+        if !self.showing_stats() {
+            return None;
+        }
+        let usage = self.usage.as_ref()?;
+
+        let x = layout.apps_x as f32;
+        let y = layout.apps_y as f32;
+        let w = layout.apps_width as f32;
+        let h = layout.apps_height as f32;
+
+        const TITLE_HEIGHT: f32 = 32.0;
+        const FOOTER_HEIGHT: f32 = 28.0;
+        const NAME_WIDTH: f32 = 180.0;
+        const COUNT_WIDTH: f32 = 48.0;
+
+        // Rows share what's left, but never get taller than a normal card
+        let rows_height = (h - TITLE_HEIGHT - FOOTER_HEIGHT).max(0.0);
+        let row_count = usage.top_apps.len().max(1) as f32;
+        let row_h = ((rows_height + CARD_GAP) / row_count - CARD_GAP)
+            .min(layout.app_card_height as f32)
+            .max(0.0);
+
+        let max_count = usage.top_apps.first().map_or(1, |(_, count)| (*count).max(1)) as f32;
+        let bar_max = (w - NAME_WIDTH - COUNT_WIDTH - 32.0).max(0.0);
+
+        let rows = usage.top_apps
+            .iter()
+            .enumerate()
+            .map(|(i, (name, count))| {
+                // Same staggered entrance as the cards
+                let delay = 0.1 + i.min(11) as f32 * 0.03;
+                let local_t = ((t - delay) * 3.0).clamp(0.0, 1.0);
+                let eased = 1.0 - (1.0 - local_t).powi(3);
+
+                let row_y = y + TITLE_HEIGHT + i as f32 * (row_h + CARD_GAP) + 30.0 * (1.0 - eased);
+                let mid_y = row_y + row_h / 2.0;
+
+                StatsRowRender {
+                    background: RenderQuad {
+                        x,
+                        y: row_y,
+                        width: w,
+                        height: row_h,
+                        color: with_alpha(theme.card_bg, eased),
+                        corner_radius: theme.card_border_radius,
+                    },
+                    name: TextRender {
+                        x: x + 16.0,
+                        y: mid_y,
+                        text: name.clone(),
+                        color: with_alpha(theme.text_primary, eased),
+                        size: 14.0,
+                        font_weight: FontWeight::Medium,
+                    },
+                    bar: RenderQuad {
+                        x: x + NAME_WIDTH,
+                        y: mid_y - 5.0,
+                        // Grows in with the entrance
+                        width: bar_max * (*count as f32 / max_count) * eased,
+                        height: 10.0,
+                        color: with_alpha(theme.accent_primary, eased),
+                        corner_radius: 5.0,
+                    },
+                    count: TextRender {
+                        x: x + w - COUNT_WIDTH,
+                        y: mid_y,
+                        text: count.to_string(),
+                        color: with_alpha(theme.text_secondary, eased),
+                        size: 14.0,
+                        font_weight: FontWeight::Regular,
+                    },
+                }
+            })
+            .collect();
+
+        let title = if usage.top_apps.is_empty() {
+            "Nothing launched this week"
+        } else {
+            "Top apps this week"
+        };
+
+        let uptime = usage.uptime().as_secs();
+
+        Some(StatsRender {
+            title: TextRender {
+                x,
+                y: y + TITLE_HEIGHT / 2.0,
+                text: title.to_string(),
+                color: with_alpha(theme.text_primary, t),
+                size: 16.0,
+                font_weight: FontWeight::Bold,
+            },
+            rows,
+            footer: TextRender {
+                x,
+                y: y + h - FOOTER_HEIGHT / 2.0,
+                text: format!(
+                    "{} windows opened this week · up {}h {:02}m",
+                    usage.windows_opened,
+                    uptime / 3600,
+                    uptime % 3600 / 60,
+                ),
+                color: with_alpha(theme.text_secondary, t),
+                size: 13.0,
+                font_weight: FontWeight::Regular,
+            },
+        })
+    }
 }
 
 // Helper functions
 
-fn with_alpha(color: [f32; 4], alpha: f32) -> [f32; 4] {
+/// An app's icon - or its monogram when the desktop file names none we can
+/// find and decode
+///
+/// `x` is the left edge, `center_y` the card's vertical middle.
+fn app_icon(
+    app: &AppEntry,
+    icons: &IconCache,
+    x: f32,
+    center_y: f32,
+    card_h: f32,
+    theme: &CommandCenterTheme,
+    opacity: f32,
+) -> CardIcon {
+    // Same roundness as the card, scaled down to the icon
+    let corner_radius = theme.card_border_radius * CARD_ICON_SIZE / card_h.max(CARD_ICON_SIZE);
+    let square = |color: [f32; 4]| RenderQuad {
+        x,
+        y: center_y - CARD_ICON_SIZE / 2.0,
+        width: CARD_ICON_SIZE,
+        height: CARD_ICON_SIZE,
+        color,
+        corner_radius,
+    };
+
+    if let Some(image) = app.icon.as_deref().and_then(|icon| icons.get(icon)) {
+        return CardIcon::App {
+            image,
+            mask: square(with_alpha([1.0, 1.0, 1.0, 1.0], opacity)),
+        };
+    }
+
+    // Same app, same color - hashed over the accents
+    let accents = [theme.accent_primary, theme.accent_secondary, theme.accent_tertiary];
+    let hash = app.name.bytes().fold(0usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize));
+    let letter = app.name
+        .chars()
+        .find(|c| c.is_alphanumeric())
+        .map(|c| c.to_uppercase().collect::<String>())
+        .unwrap_or_else(|| "?".to_string());
+
+    CardIcon::Monogram {
+        tile: square(with_alpha(accents[hash % accents.len()], opacity)),
+        letter: TextRender {
+            x: x + CARD_ICON_SIZE / 2.0,
+            y: center_y,
+            text: letter,
+            color: with_alpha(theme.bg_color, opacity),
+            size: 14.0,
+            font_weight: FontWeight::Bold,
+        },
+    }
+}
+
+pub fn with_alpha(color: [f32; 4], alpha: f32) -> [f32; 4] {
     [color[0], color[1], color[2], color[3] * alpha]
 }
 
-fn truncate_string(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
+pub fn truncate_string(s: &str, max_len: usize) -> String {
+    // Count chars, not bytes - app names aren't all ASCII
+    if s.chars().count() <= max_len {
         s.to_string()
     } else {
-        format!("{}...", &s[..max_len - 3])
+        let head: String = s.chars().take(max_len.saturating_sub(3)).collect();
+        format!("{}...", head)
     }
 }
 
+/// GLSL shader for the spotlight dim - full-screen, with a rounded hole
+pub const SPOTLIGHT_SHADER_FRAG: &str = r#"
+#version 300 es
+precision highp float;
+
+uniform vec4 u_color;
+uniform vec2 u_screen;
+uniform vec2 u_hole_center;
+uniform vec2 u_hole_size;
+uniform float u_radius;
+
+in vec2 v_uv;
+out vec4 frag_color;
+
+float rounded_box_sdf(vec2 p, vec2 b, float r) {
+    vec2 q = abs(p) - b + r;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - r;
+}
+
+void main() {
+    vec2 p = v_uv * u_screen - u_hole_center;
+    float d = rounded_box_sdf(p, u_hole_size * 0.5, u_radius);
+
+    // Inside the hole stays clear, 1px AA on the edge
+    float mask = smoothstep(-0.5, 0.5, d);
+    frag_color = vec4(u_color.rgb, u_color.a * mask);
+}
+"#;
+
 /// GLSL shader source for the glow effect
 /// This is the good stuff - the actual GPU magic
 pub const GLOW_SHADER_FRAG: &str = r#"
@@ -0,0 +1,218 @@
+//! Window rules - the per-app config entries, and a record of how they went
+//!
+//! `passthrough` and `titlebars.apps` both pick windows by app_id. Matching
+//! goes through here so every open window keeps a trace of each rule checked
+//! against it, whether it matched and what it did. `explain-window` over IPC
+//! prints that trace; `test-rule` tries a matcher against the open windows.
+
+use crate::config::Config;
+#[cfg(feature = "compositor")]
+use crate::state::VibeWM;
+#[cfg(feature = "compositor")]
+use crate::window::window_app_id;
+
+/// One rule checked against one window
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleCheck {
+    /// Config key and pattern, e.g. `passthrough "org.remmina.Remmina"`
+    pub rule: String,
+    pub matched: bool,
+
+    /// Matched and in effect - an earlier rule or a setting that's off can
+    /// keep a match from doing anything
+    pub applied: bool,
+
+    /// What it does, or why it doesn't
+    pub effect: String,
+}
+
+/// Every rule checked for a window, and the app_id they were checked against
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleTrace {
+    pub app_id: Option<String>,
+    pub checks: Vec<RuleCheck>,
+}
+
+/// `passthrough` - exact app_id, first rule wins
+pub fn passthrough_matches(rule: &str, app_id: Option<&str>) -> bool {
+    app_id == Some(rule)
+}
+
+/// `titlebars.apps` - app_id, ignoring case
+pub fn titlebar_matches(rule: &str, app_id: Option<&str>) -> bool {
+    app_id.is_some_and(|app_id| app_id.eq_ignore_ascii_case(rule))
+}
+
+/// The pass-through chords for an app_id
+pub fn passthrough_chords<'a>(config: &'a Config, app_id: Option<&str>) -> &'a [String] {
+    config
+        .passthrough
+        .iter()
+        .find(|(rule, _)| passthrough_matches(rule, app_id))
+        .map(|(_, chords)| chords.as_slice())
+        .unwrap_or_default()
+}
+
+/// Is the app_id in `titlebars.apps`? (Regardless of `titlebars.enabled`)
+pub fn titlebar_forced(config: &Config, app_id: Option<&str>) -> bool {
+    config.titlebars.apps.iter().any(|rule| titlebar_matches(rule, app_id))
+}
+
+/// Check every rule against an app_id, without acting on any of them
+pub fn evaluate(config: &Config, app_id: Option<&str>) -> RuleTrace {
+    let mut checks = Vec::new();
+
+    let mut passthrough_taken = false;
+    for (rule, chords) in &config.passthrough {
+        let matched = passthrough_matches(rule, app_id);
+        let applied = matched && !passthrough_taken;
+        passthrough_taken |= matched;
+
+        checks.push(RuleCheck {
+            rule: format!("passthrough {:?}", rule),
+            matched,
+            applied,
+            effect: if matched && !applied {
+                "an earlier passthrough rule already matched".to_string()
+            } else {
+                format!("pass through {}", chords.join(", "))
+            },
+        });
+    }
+
+    for rule in &config.titlebars.apps {
+        let matched = titlebar_matches(rule, app_id);
+        let applied = matched && config.titlebars.enabled;
+
+        checks.push(RuleCheck {
+            rule: format!("titlebars.apps {:?}", rule),
+            matched,
+            applied,
+            effect: if matched && !applied {
+                "titlebars.enabled is off".to_string()
+            } else {
+                "compositor titlebar".to_string()
+            },
+        });
+    }
+
+    RuleTrace { app_id: app_id.map(str::to_string), checks }
+}
+
+/// A rule pattern typed at `test-rule` - `app_id=<id>` (ignoring case, like
+/// `titlebars.apps`), `title=<text>` (anywhere in the title, ignoring case)
+/// or a bare app_id
+#[derive(Debug, Clone, PartialEq)]
+pub enum Matcher {
+    AppId(String),
+    Title(String),
+}
+
+impl Matcher {
+    pub fn parse(matcher: &str) -> Result<Self, String> {
+        let matcher = matcher.trim().trim_matches(|c| c == '\'' || c == '"');
+        let parsed = match matcher.split_once('=') {
+            Some(("app_id", pattern)) => Self::AppId(pattern.to_string()),
+            Some(("title", pattern)) => Self::Title(pattern.to_string()),
+            Some((field, _)) => return Err(format!("can't match on '{}' - use app_id= or title=", field)),
+            None => Self::AppId(matcher.to_string()),
+        };
+
+        match &parsed {
+            Self::AppId(pattern) | Self::Title(pattern) if pattern.is_empty() => Err("empty matcher".to_string()),
+            _ => Ok(parsed),
+        }
+    }
+
+    pub fn matches(&self, app_id: Option<&str>, title: &str) -> bool {
+        match self {
+            Self::AppId(pattern) => titlebar_matches(pattern, app_id),
+            Self::Title(pattern) => title.to_lowercase().contains(&pattern.to_lowercase()),
+        }
+    }
+}
+
+#[cfg(feature = "compositor")]
+impl VibeWM {
+    /// Re-check rules for windows whose app_id changed (called every frame) -
+    /// most clients only set it after their first commit
+    pub fn update_rule_traces(&mut self) {
+        for window in self.windows.all().to_vec() {
+            let app_id = window_app_id(&window);
+            let Some(meta) = self.windows.meta_mut(&window) else { continue };
+            if meta.rules.as_ref().is_some_and(|trace| trace.app_id == app_id) {
+                continue;
+            }
+
+            let trace = evaluate(&self.config, app_id.as_deref());
+            for check in trace.checks.iter().filter(|check| check.matched) {
+                tracing::debug!("Window {} ({:?}): {} - {}", meta.id, app_id, check.rule, check.effect);
+            }
+            meta.rules = Some(trace);
+        }
+    }
+
+    /// Rules changed - every window gets checked again next frame
+    pub fn forget_rule_traces(&mut self) {
+        for window in self.windows.all().to_vec() {
+            if let Some(meta) = self.windows.meta_mut(&window) {
+                meta.rules = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.passthrough = vec![
+            ("org.remmina.Remmina".to_string(), vec!["super+Tab".to_string()]),
+            ("org.remmina.Remmina".to_string(), vec!["super+q".to_string()]),
+        ];
+        config.titlebars.apps = vec!["MPV".to_string()];
+        config
+    }
+
+    #[test]
+    fn traces_say_which_rules_matched_and_applied() {
+        let trace = evaluate(&config(), Some("org.remmina.Remmina"));
+        let summary: Vec<_> = trace.checks.iter().map(|c| (c.matched, c.applied)).collect();
+        assert_eq!(summary, vec![(true, true), (true, false), (false, false)]);
+        assert_eq!(trace.checks[0].effect, "pass through super+Tab");
+        assert_eq!(trace.checks[1].effect, "an earlier passthrough rule already matched");
+    }
+
+    #[test]
+    fn titlebar_rules_match_without_applying_while_disabled() {
+        let mut config = config();
+        let check = |config: &Config| evaluate(config, Some("mpv")).checks[2].clone();
+
+        assert!(check(&config).matched && !check(&config).applied);
+        assert_eq!(check(&config).effect, "titlebars.enabled is off");
+
+        config.titlebars.enabled = true;
+        assert!(check(&config).applied);
+    }
+
+    #[test]
+    fn no_app_id_matches_nothing() {
+        let trace = evaluate(&config(), None);
+        assert!(trace.checks.iter().all(|c| !c.matched));
+        assert!(passthrough_chords(&config(), None).is_empty());
+    }
+
+    #[test]
+    fn matchers() {
+        assert_eq!(Matcher::parse("firefox"), Ok(Matcher::AppId("firefox".to_string())));
+        assert_eq!(Matcher::parse("'title=nvim'"), Ok(Matcher::Title("nvim".to_string())));
+        assert!(Matcher::parse("class=foo").is_err());
+        assert!(Matcher::parse("title=").is_err());
+
+        assert!(Matcher::AppId("Firefox".to_string()).matches(Some("firefox"), ""));
+        assert!(!Matcher::AppId("fire".to_string()).matches(Some("firefox"), ""));
+        assert!(Matcher::Title("NVIM".to_string()).matches(None, "~/src - nvim"));
+    }
+}
@@ -0,0 +1,227 @@
+//! Frame scheduling - input first, at most one frame in flight
+//!
+//! Each loop iteration drains input, renders only if the last frame made it
+//! to the screen, then hands the rest of the frame budget to client dispatch.
+//! A heavy client committing every frame can slow its own frames down, but
+//! not the pointer.
+//!
+//! "Made it to the screen" is the backend's presentation feedback: the page
+//! flip's vblank on DRM, the host's next redraw under winit.
+
+use std::time::{Duration, Instant};
+
+/// Target frame time
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// A frame that hasn't been presented after this long never will be (lost
+/// vblank, output turned off) - stop waiting for it
+const PRESENT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Weight of the newest sample in the running averages
+const SMOOTHING: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Input,
+    Dispatch,
+    Render,
+}
+
+/// A phase in progress - inside its tracing span until finished
+pub struct PhaseTimer {
+    phase: Phase,
+    start: Instant,
+    _span: tracing::span::EnteredSpan,
+}
+
+impl PhaseTimer {
+    pub fn start(phase: Phase) -> Self {
+        crate::watchdog::phase_started(phase);
+
+        let span = match phase {
+            Phase::Input => tracing::debug_span!("input"),
+            Phase::Dispatch => tracing::debug_span!("dispatch"),
+            Phase::Render => tracing::debug_span!("render"),
+        };
+
+        Self {
+            phase,
+            start: Instant::now(),
+            _span: span.entered(),
+        }
+    }
+}
+
+/// Running average of how long each phase of the loop takes
+#[derive(Debug, Clone, Default)]
+pub struct PhaseTimings {
+    input_ms: f64,
+    dispatch_ms: f64,
+    render_ms: f64,
+
+    /// Submit to presentation
+    present_ms: f64,
+
+    /// Frames skipped because the previous one was still in flight
+    pub skipped_frames: u64,
+}
+
+impl PhaseTimings {
+    pub fn record(&mut self, phase: Phase, elapsed: Duration) {
+        let average = match phase {
+            Phase::Input => &mut self.input_ms,
+            Phase::Dispatch => &mut self.dispatch_ms,
+            Phase::Render => &mut self.render_ms,
+        };
+        smooth(average, elapsed);
+    }
+
+    /// Stop a phase's timer and fold it into the average
+    pub fn finish(&mut self, timer: PhaseTimer) {
+        let elapsed = timer.start.elapsed();
+        self.record(timer.phase, elapsed);
+        crate::watchdog::phase_finished(timer.phase);
+
+        #[cfg(feature = "metrics")]
+        if timer.phase == Phase::Render {
+            crate::metrics::frame_rendered(elapsed);
+        }
+    }
+
+    /// One line per phase, for the debug overlay
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("input     {:6.2} ms", self.input_ms),
+            format!("dispatch  {:6.2} ms", self.dispatch_ms),
+            format!("render    {:6.2} ms", self.render_ms),
+            format!("present   {:6.2} ms", self.present_ms),
+            format!("skipped   {:6}", self.skipped_frames),
+        ]
+    }
+}
+
+fn smooth(average: &mut f64, elapsed: Duration) {
+    let sample = elapsed.as_secs_f64() * 1000.0;
+    *average += (sample - *average) * SMOOTHING;
+}
+
+/// Decides whether this iteration renders
+pub struct FrameScheduler {
+    /// When the frame waiting for presentation was submitted
+    in_flight: Option<Instant>,
+
+    last_render: Instant,
+
+    pub timings: PhaseTimings,
+}
+
+impl FrameScheduler {
+    pub fn new() -> Self {
+        Self {
+            in_flight: None,
+            last_render: Instant::now(),
+            timings: PhaseTimings::default(),
+        }
+    }
+
+    /// Render now? Skips (doesn't queue) while the last frame is still in flight
+    pub fn should_render(&mut self) -> bool {
+        match self.in_flight {
+            Some(submitted) if submitted.elapsed() < PRESENT_TIMEOUT => {
+                self.timings.skipped_frames += 1;
+                #[cfg(feature = "metrics")]
+                crate::metrics::frame_dropped();
+                false
+            }
+            Some(_) => {
+                tracing::debug!("Frame never got presented - rendering anyway");
+                self.in_flight = None;
+                true
+            }
+            None => true,
+        }
+    }
+
+    pub fn submitted(&mut self) {
+        let now = Instant::now();
+        self.in_flight = Some(now);
+        self.last_render = now;
+    }
+
+    /// The frame hit the screen at `at` (vblank / presentation feedback)
+    pub fn presented(&mut self, at: Instant) {
+        if let Some(submitted) = self.in_flight.take() {
+            smooth(&mut self.timings.present_ms, at.saturating_duration_since(submitted));
+        }
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::frame_presented(at);
+    }
+
+    /// How long dispatch can block before the next frame is due
+    pub fn dispatch_timeout(&self) -> Duration {
+        FRAME_INTERVAL.saturating_sub(self.last_render.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phase_timer_feeds_its_phase() {
+        let mut timings = PhaseTimings::default();
+
+        let timer = PhaseTimer::start(Phase::Render);
+        std::thread::sleep(Duration::from_millis(5));
+        timings.finish(timer);
+
+        assert!(timings.render_ms >= 5.0 * SMOOTHING, "{}", timings.render_ms);
+        assert_eq!(timings.input_ms, 0.0);
+        assert_eq!(timings.dispatch_ms, 0.0);
+    }
+
+    #[test]
+    fn averages_move_a_tenth_of_the_way() {
+        let mut timings = PhaseTimings::default();
+        timings.record(Phase::Input, Duration::from_millis(10));
+        assert!((timings.input_ms - 1.0).abs() < 1e-9);
+        timings.record(Phase::Input, Duration::from_millis(10));
+        assert!((timings.input_ms - 1.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn one_frame_in_flight_until_presented() {
+        let mut frames = FrameScheduler::new();
+        assert!(frames.should_render());
+        frames.submitted();
+
+        assert!(!frames.should_render());
+        assert_eq!(frames.timings.skipped_frames, 1);
+
+        frames.presented(Instant::now());
+        assert!(frames.should_render());
+    }
+
+    #[test]
+    fn present_time_comes_from_the_feedback() {
+        let mut frames = FrameScheduler::new();
+        frames.submitted();
+        let submitted = frames.in_flight.unwrap();
+
+        frames.presented(submitted + Duration::from_millis(16));
+        assert!((frames.timings.present_ms - 1.6).abs() < 1e-9, "{}", frames.timings.present_ms);
+
+        // A stray presentation with nothing in flight doesn't count
+        frames.presented(submitted + Duration::from_secs(1));
+        assert!((frames.timings.present_ms - 1.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lost_frames_stop_blocking_after_the_timeout() {
+        let mut frames = FrameScheduler::new();
+        frames.in_flight = Some(Instant::now() - PRESENT_TIMEOUT);
+        assert!(frames.should_render());
+        assert!(frames.in_flight.is_none());
+    }
+}
@@ -0,0 +1,461 @@
+//! Region screenshots - mod+shift+Print
+//!
+//! The backend reads back the last composited frame once, then the output
+//! shows that frozen frame (dimmed) while you drag a rectangle over it. The
+//! save is cropped out of the frozen pixels - nothing re-renders, so an
+//! animation mid-flight can't shift what you picked.
+//!
+//! While a selection is up it's a grab: clients see no pointer or keys.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use smithay::input::keyboard::{Keysym, ModifiersState};
+use smithay::utils::{Logical, Physical, Point, Rectangle, Size};
+
+use crate::command_center::CommandCenterTheme;
+use crate::launch;
+use crate::render_command_center::{with_alpha, FontWeight, RenderQuad, TextRender};
+use crate::state::VibeWM;
+
+/// Selection handle size (px)
+const HANDLE_SIZE: f32 = 8.0;
+
+/// Arrow key nudge, and with shift held (px)
+const NUDGE_STEP: i32 = 1;
+const NUDGE_STEP_FAST: i32 = 10;
+
+/// The frame a selection is made on, as read back from the renderer
+pub struct FrozenFrame {
+    /// Physical size
+    pub size: Size<i32, Physical>,
+    pub scale: f64,
+    /// RGBA8, rows top to bottom, no padding
+    pub pixels: Vec<u8>,
+}
+
+impl FrozenFrame {
+    /// From a framebuffer readback - GL hands the rows over bottom to top
+    /// when the mapping says it's flipped
+    pub fn from_readback(size: Size<i32, Physical>, scale: f64, mut pixels: Vec<u8>, flipped: bool) -> Self {
+        if flipped {
+            flip_rows(&mut pixels, size.w.max(0) as usize * 4);
+        }
+        Self { size, scale, pixels }
+    }
+
+    /// Copy out a logical rect (output-local) as RGBA8 - None if it's empty
+    pub fn crop(&self, rect: Rectangle<i32, Logical>) -> Option<(Size<i32, Physical>, Vec<u8>)> {
+        let bounds = Rectangle::<i32, Physical>::from_size(self.size);
+        let rect = rect.to_f64().to_physical(self.scale).to_i32_round().intersection(bounds)?;
+        if rect.size.w <= 0 || rect.size.h <= 0 {
+            return None;
+        }
+
+        let stride = self.size.w as usize * 4;
+        let mut out = Vec::with_capacity(rect.size.w as usize * rect.size.h as usize * 4);
+        for row in rect.loc.y..rect.loc.y + rect.size.h {
+            let start = row as usize * stride + rect.loc.x as usize * 4;
+            out.extend_from_slice(self.pixels.get(start..start + rect.size.w as usize * 4)?);
+        }
+        Some((rect.size, out))
+    }
+}
+
+/// Turn an image upside down, `stride` bytes per row
+fn flip_rows(pixels: &mut [u8], stride: usize) {
+    if stride == 0 {
+        return;
+    }
+    let rows = pixels.len() / stride;
+    for row in 0..rows / 2 {
+        let (top, bottom) = pixels.split_at_mut((rows - 1 - row) * stride);
+        top[row * stride..(row + 1) * stride].swap_with_slice(&mut bottom[..stride]);
+    }
+}
+
+/// A region screenshot in progress
+pub struct RegionSelect {
+    /// Output it's on
+    pub output: String,
+
+    /// Output origin and scale, for pointer -> output-local
+    pub origin: Point<i32, Logical>,
+    pub scale: f64,
+
+    /// None until the backend has read back a frame
+    pub frozen: Option<FrozenFrame>,
+
+    /// Drag start, output-local
+    anchor: Option<Point<i32, Logical>>,
+
+    /// Current selection, output-local
+    pub selection: Option<Rectangle<i32, Logical>>,
+}
+
+/// Render data for the region selection
+pub struct RegionSelectFrame {
+    /// Dim over everything outside the selection (the whole output before there is one)
+    pub dim: Vec<RenderQuad>,
+    pub border: Vec<RenderQuad>,
+    pub handles: Vec<RenderQuad>,
+    /// "640 x 480" - size of the saved image
+    pub dimensions: Option<TextRender>,
+}
+
+impl RegionSelect {
+    pub fn new(output: String, origin: Point<i32, Logical>, scale: f64) -> Self {
+        Self {
+            output,
+            origin,
+            scale,
+            frozen: None,
+            anchor: None,
+            selection: None,
+        }
+    }
+
+    /// Hand over the read-back frame - only the first one counts
+    pub fn freeze(&mut self, frame: FrozenFrame) {
+        if self.frozen.is_none() {
+            self.frozen = Some(frame);
+        }
+    }
+
+    fn local(&self, pos: Point<f64, Logical>) -> Point<i32, Logical> {
+        (pos - self.origin.to_f64()).to_i32_round()
+    }
+
+    /// Pointer went down - start a fresh selection there
+    pub fn begin_drag(&mut self, pos: Point<f64, Logical>) {
+        let at = self.local(pos);
+        self.anchor = Some(at);
+        self.selection = Some(Rectangle::new(at, (0, 0).into()));
+    }
+
+    /// Pointer moved - stretch the selection from the anchor
+    pub fn drag_to(&mut self, pos: Point<f64, Logical>) {
+        let Some(anchor) = self.anchor else {
+            return;
+        };
+        let at = self.local(pos);
+        self.selection = Some(Rectangle::from_extremities(
+            (anchor.x.min(at.x), anchor.y.min(at.y)),
+            (anchor.x.max(at.x), anchor.y.max(at.y)),
+        ));
+    }
+
+    /// Pointer let go - true if there's something to save
+    pub fn end_drag(&mut self) -> bool {
+        self.anchor = None;
+        self.selection.is_some_and(|rect| rect.size.w > 0 && rect.size.h > 0)
+    }
+
+    pub fn dragging(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    /// Arrows move the right/bottom edge, alt+arrows the left/top one
+    pub fn nudge(&mut self, keysym: Keysym, modifiers: &ModifiersState, at: Point<f64, Logical>) -> bool {
+        let step = if modifiers.shift { NUDGE_STEP_FAST } else { NUDGE_STEP };
+        let (dx, dy) = match keysym {
+            Keysym::Left => (-step, 0),
+            Keysym::Right => (step, 0),
+            Keysym::Up => (0, -step),
+            Keysym::Down => (0, step),
+            _ => return false,
+        };
+
+        let mut rect = self.selection.unwrap_or_else(|| Rectangle::new(self.local(at), (0, 0).into()));
+        if modifiers.alt {
+            rect.loc.x += dx;
+            rect.loc.y += dy;
+            rect.size.w -= dx;
+            rect.size.h -= dy;
+        } else {
+            rect.size.w += dx;
+            rect.size.h += dy;
+        }
+        rect.size.w = rect.size.w.max(0);
+        rect.size.h = rect.size.h.max(0);
+        self.selection = Some(rect);
+        true
+    }
+
+    /// Generate render data for an output of this (logical) size
+    pub fn render(&self, output_size: Size<i32, Logical>, theme: &CommandCenterTheme) -> RegionSelectFrame {
+        let dim_color = [0.0, 0.0, 0.0, 0.45];
+        let quad = |x: f32, y: f32, width: f32, height: f32, color: [f32; 4]| RenderQuad {
+            x,
+            y,
+            width,
+            height,
+            color,
+            corner_radius: 0.0,
+        };
+        let (w, h) = (output_size.w as f32, output_size.h as f32);
+
+        let Some(rect) = self.selection else {
+            return RegionSelectFrame {
+                dim: vec![quad(0.0, 0.0, w, h, dim_color)],
+                border: Vec::new(),
+                handles: Vec::new(),
+                dimensions: None,
+            };
+        };
+
+        let (x, y) = (rect.loc.x as f32, rect.loc.y as f32);
+        let (rw, rh) = (rect.size.w as f32, rect.size.h as f32);
+
+        // Above, below, left, right of the selection
+        let dim = vec![
+            quad(0.0, 0.0, w, y, dim_color),
+            quad(0.0, y + rh, w, h - y - rh, dim_color),
+            quad(0.0, y, x, rh, dim_color),
+            quad(x + rw, y, w - x - rw, rh, dim_color),
+        ];
+
+        let accent = theme.accent_primary;
+        let border = vec![
+            quad(x - 1.0, y - 1.0, rw + 2.0, 1.0, accent),
+            quad(x - 1.0, y + rh, rw + 2.0, 1.0, accent),
+            quad(x - 1.0, y, 1.0, rh, accent),
+            quad(x + rw, y, 1.0, rh, accent),
+        ];
+
+        let half = HANDLE_SIZE / 2.0;
+        let handles = [(x, y), (x + rw, y), (x, y + rh), (x + rw, y + rh)]
+            .into_iter()
+            .map(|(hx, hy)| RenderQuad {
+                corner_radius: 2.0,
+                ..quad(hx - half, hy - half, HANDLE_SIZE, HANDLE_SIZE, accent)
+            })
+            .collect();
+
+        // Saved size is physical - that's the number that matters
+        let physical = rect.to_f64().to_physical(self.scale).to_i32_round::<i32>().size;
+        let label_y = if y + rh + 28.0 < h { y + rh + 8.0 } else { (y - 24.0).max(0.0) };
+        let dimensions = Some(TextRender {
+            x,
+            y: label_y,
+            text: format!("{} x {}", physical.w, physical.h),
+            color: with_alpha(theme.text_primary, 1.0),
+            size: 13.0,
+            font_weight: FontWeight::Medium,
+        });
+
+        RegionSelectFrame { dim, border, handles, dimensions }
+    }
+}
+
+/// Where screenshots go: config dir, else $XDG_PICTURES_DIR/Screenshots, else ~/Pictures/Screenshots
+pub fn screenshot_dir(configured: Option<&Path>) -> Option<PathBuf> {
+    if let Some(dir) = configured {
+        return Some(dir.to_path_buf());
+    }
+
+    let pictures = std::env::var_os("XDG_PICTURES_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Pictures")))?;
+    Some(pictures.join("Screenshots"))
+}
+
+/// Write RGBA8 pixels out as a PNG
+fn write_png(path: &Path, size: Size<i32, Physical>, pixels: &[u8]) -> std::io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, size.w as u32, size.h as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(std::io::Error::other)?;
+    writer.write_image_data(pixels).map_err(std::io::Error::other)?;
+    writer.finish().map_err(std::io::Error::other)
+}
+
+impl VibeWM {
+    /// mod+shift+Print - freeze the output under the pointer and start selecting
+    pub fn start_region_screenshot(&mut self) {
+        if self.region_select.is_some() {
+            return;
+        }
+
+        let output = self.space.output_under(self.input.pointer_pos).next().cloned()
+            .or_else(|| self.output.clone());
+        let Some(output) = output else {
+            return;
+        };
+        let Some(geometry) = self.space.output_geometry(&output) else {
+            return;
+        };
+
+        tracing::info!("Region screenshot on {}", output.name());
+        self.region_select = Some(RegionSelect::new(
+            output.name(),
+            geometry.loc,
+            output.current_scale().fractional_scale(),
+        ));
+    }
+
+    pub fn cancel_region_screenshot(&mut self) {
+        if self.region_select.take().is_some() {
+            tracing::info!("Region screenshot cancelled");
+        }
+    }
+
+    /// Keys while selecting - all of them are ours
+    pub fn region_screenshot_key(&mut self, keysym: Keysym, modifiers: &ModifiersState) {
+        let pointer = self.input.pointer_pos;
+        let Some(select) = self.region_select.as_mut() else {
+            return;
+        };
+
+        match keysym {
+            Keysym::Escape => self.cancel_region_screenshot(),
+            Keysym::Return | Keysym::KP_Enter => self.finish_region_screenshot(),
+            _ => {
+                select.nudge(keysym, modifiers, pointer);
+            }
+        }
+    }
+
+    /// Pointer button while selecting - press starts a drag, release saves it
+    pub fn region_screenshot_button(&mut self, pressed: bool) {
+        let pointer = self.input.pointer_pos;
+        let Some(select) = self.region_select.as_mut() else {
+            return;
+        };
+
+        if pressed {
+            select.begin_drag(pointer);
+        } else if select.dragging() && select.end_drag() {
+            self.finish_region_screenshot();
+        }
+    }
+
+    /// Crop the selection out of the frozen frame, save it, and copy it
+    ///
+    /// Encoding runs off the main thread - a big PNG would drop frames. The
+    /// notice goes up once it's written (update_screenshot_saves).
+    pub fn finish_region_screenshot(&mut self) {
+        let Some(select) = self.region_select.take() else {
+            return;
+        };
+        let Some(frame) = &select.frozen else {
+            tracing::warn!("Region screenshot: no frame to crop (the backend never froze one)");
+            return;
+        };
+        let Some((size, pixels)) = select.selection.and_then(|rect| frame.crop(rect)) else {
+            return;
+        };
+
+        let Some(dir) = screenshot_dir(self.config.screenshot_dir.as_deref()) else {
+            tracing::warn!("Region screenshot: no HOME, nowhere to save");
+            return;
+        };
+        let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let path = dir.join(format!("vibewm-{}.png", stamp));
+        let copy = self.config.screenshot_copy;
+        let (done_tx, done_rx) = mpsc::channel();
+
+        let spawned = std::thread::Builder::new()
+            .name("vibewm-screenshot".into())
+            .spawn(move || {
+                let saved = std::fs::create_dir_all(&dir).and_then(|_| write_png(&path, size, &pixels));
+                if let Err(e) = saved {
+                    tracing::warn!("Failed to save screenshot to {}: {}", path.display(), e);
+                    done_tx.send(Err(e.to_string())).ok();
+                    return;
+                }
+                tracing::info!("Screenshot saved: {} ({}x{})", path.display(), size.w, size.h);
+                done_tx.send(Ok(path.clone())).ok();
+
+                if copy {
+                    let argv = ["wl-copy", "--type", "image/png"].map(String::from);
+                    let piped = File::open(&path).and_then(|image| {
+                        launch::command(&argv, &[])?.stdin(image).spawn()
+                    });
+                    if let Err(e) = piped {
+                        tracing::warn!("Couldn't copy the screenshot (is wl-clipboard installed?): {}", e);
+                    }
+                }
+            });
+        match spawned {
+            Ok(_) => self.screenshot_saves.push(done_rx),
+            Err(e) => {
+                tracing::warn!("Failed to start the screenshot writer: {}", e);
+                self.notice.show("Screenshot not saved");
+            }
+        }
+    }
+
+    /// Say how screenshots that finished writing went
+    pub fn update_screenshot_saves(&mut self) {
+        let notice = &mut self.notice;
+        self.screenshot_saves.retain(|done| match done.try_recv() {
+            Ok(Ok(path)) => {
+                notice.show(format!("Screenshot saved to {}", path.display()));
+                false
+            }
+            Ok(Err(e)) => {
+                notice.show(format!("Screenshot not saved: {}", e));
+                false
+            }
+            Err(mpsc::TryRecvError::Empty) => true,
+            Err(mpsc::TryRecvError::Disconnected) => false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4x3 frame, each pixel's bytes are [x, y, 0, 255]
+    fn frame(scale: f64) -> FrozenFrame {
+        let pixels = (0..3u8).flat_map(|y| (0..4u8).flat_map(move |x| [x, y, 0, 255])).collect();
+        FrozenFrame { size: (4, 3).into(), scale, pixels }
+    }
+
+    fn coords(pixels: &[u8]) -> Vec<(u8, u8)> {
+        pixels.chunks(4).map(|p| (p[0], p[1])).collect()
+    }
+
+    #[test]
+    fn crop_takes_the_rect_and_clips_to_the_frame() {
+        let (size, pixels) = frame(1.0).crop(Rectangle::new((1, 1).into(), (2, 2).into())).unwrap();
+        assert_eq!(size, (2, 2).into());
+        assert_eq!(coords(&pixels), vec![(1, 1), (2, 1), (1, 2), (2, 2)]);
+
+        let (size, pixels) = frame(1.0).crop(Rectangle::new((3, 2).into(), (5, 5).into())).unwrap();
+        assert_eq!(size, (1, 1).into());
+        assert_eq!(coords(&pixels), vec![(3, 2)]);
+
+        assert!(frame(1.0).crop(Rectangle::new((4, 0).into(), (2, 2).into())).is_none());
+    }
+
+    #[test]
+    fn crop_is_in_physical_pixels() {
+        // Logical 1x1 at scale 2 is 2x2 physical
+        let (size, pixels) = frame(2.0).crop(Rectangle::new((1, 0).into(), (1, 1).into())).unwrap();
+        assert_eq!(size, (2, 2).into());
+        assert_eq!(coords(&pixels), vec![(2, 0), (3, 0), (2, 1), (3, 1)]);
+    }
+
+    #[test]
+    fn flipped_readback_comes_out_top_down() {
+        let upright = frame(1.0);
+        let mut upside_down = upright.pixels.clone();
+        flip_rows(&mut upside_down, 16);
+        assert_eq!(coords(&upside_down[..16]), vec![(0, 2), (1, 2), (2, 2), (3, 2)]);
+
+        let frozen = FrozenFrame::from_readback(upright.size, 1.0, upside_down, true);
+        assert_eq!(frozen.pixels, upright.pixels);
+
+        let frozen = FrozenFrame::from_readback(upright.size, 1.0, upright.pixels.clone(), false);
+        assert_eq!(frozen.pixels, upright.pixels);
+    }
+}
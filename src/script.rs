@@ -0,0 +1,405 @@
+//! Scripting - small custom behaviors from ~/.config/vibewm/init.rhai
+//!
+//! The script defines whichever callbacks it cares about:
+//!
+//! ```rhai
+//! fn window_opened(w) {
+//!     if w.app_id == "zoom" && w.title == "zoom_linux_float_video_window" {
+//!         w.snap("bottom-right");
+//!     }
+//! }
+//! ```
+//!
+//! Callbacks: `window_opened`, `window_closed`, `window_focused`,
+//! `title_changed`, each getting a window handle. Handles are snapshots -
+//! `move_to`, `resize`, `snap`, `close`, `focus` and `send_to_workspace` queue
+//! up and run after the callback returns, through the same paths as IPC.
+//!
+//! Every run gets a time budget. A script that blows it (or errors) is
+//! stopped, logged, and flagged on screen - rendering never waits on it.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use smithay::desktop::Window;
+use smithay::utils::{Logical, Rectangle};
+
+use crate::launch;
+use crate::state::VibeWM;
+use crate::window::{window_app_id, window_title};
+
+/// Window handle as scripts see it
+#[derive(Debug, Clone)]
+pub struct ScriptWindow {
+    pub id: u64,
+    pub title: String,
+    pub app_id: String,
+    /// 1-based, like the keybinds
+    pub workspace: usize,
+    pub geometry: Rectangle<i32, Logical>,
+}
+
+/// Something a script asked for, run once the callback returns
+#[derive(Debug, Clone)]
+enum ScriptCommand {
+    /// A window command, spelled the way IPC takes it
+    Ipc(String),
+    SendToWorkspace { id: u64, workspace: usize },
+    Spawn(Vec<String>),
+    RenameWorkspace { workspace: usize, name: String },
+}
+
+/// Read-only compositor info, refreshed before every run
+#[derive(Debug, Clone)]
+struct ScriptWorld {
+    active_workspace: usize,
+    workspace_names: Vec<String>,
+    outputs: Vec<(String, Rectangle<i32, Logical>)>,
+}
+
+/// The loaded script and what it has seen so far
+pub struct ScriptHost {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+
+    /// Callbacks the script actually defines
+    callbacks: HashSet<String>,
+
+    commands: Rc<RefCell<Vec<ScriptCommand>>>,
+    world: Rc<RefCell<ScriptWorld>>,
+
+    /// The current run gets stopped past this
+    deadline: Rc<Cell<Instant>>,
+    budget: Duration,
+
+    /// For spotting focus and title changes between frames
+    last_focused: Option<u64>,
+    titles: HashMap<u64, String>,
+}
+
+/// ~/.config/vibewm/init.rhai (or under $XDG_CONFIG_HOME)
+pub fn script_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_dir.join("vibewm").join("init.rhai"))
+}
+
+impl ScriptHost {
+    /// Compile the script and run its top level
+    fn load(path: &Path, budget: Duration, world: ScriptWorld) -> Result<Self, String> {
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let world = Rc::new(RefCell::new(world));
+        let deadline = Rc::new(Cell::new(Instant::now() + budget));
+
+        let engine = build_engine(&commands, &world, &deadline);
+        let ast = engine.compile_file(path.to_path_buf()).map_err(|e| e.to_string())?;
+        let callbacks = ast.iter_functions().map(|f| f.name.to_string()).collect();
+
+        let mut host = Self {
+            engine,
+            ast,
+            scope: Scope::new(),
+            callbacks,
+            commands,
+            world,
+            deadline,
+            budget,
+            last_focused: None,
+            titles: HashMap::new(),
+        };
+
+        host.deadline.set(Instant::now() + budget);
+        host.engine
+            .run_ast_with_scope(&mut host.scope, &host.ast)
+            .map_err(|e| e.to_string())?;
+
+        Ok(host)
+    }
+
+    /// Run a callback if the script defines it
+    fn call(&mut self, callback: &str, window: ScriptWindow) -> Result<(), String> {
+        if !self.callbacks.contains(callback) {
+            return Ok(());
+        }
+
+        self.deadline.set(Instant::now() + self.budget);
+
+        // Top level already ran at load - don't run it again per event
+        let options = CallFnOptions::new().eval_ast(false);
+        self.engine
+            .call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, callback, (window,))
+            .map(|_| ())
+            .map_err(|e| format!("{}: {}", callback, e))
+    }
+
+    fn take_commands(&self) -> Vec<ScriptCommand> {
+        std::mem::take(&mut *self.commands.borrow_mut())
+    }
+}
+
+/// Engine with the vibeWM API registered and the safety limits on
+fn build_engine(
+    commands: &Rc<RefCell<Vec<ScriptCommand>>>,
+    world: &Rc<RefCell<ScriptWorld>>,
+    deadline: &Rc<Cell<Instant>>,
+) -> Engine {
+    let mut engine = Engine::new();
+
+    // A runaway script gets cut off instead of freezing the compositor
+    let run_deadline = deadline.clone();
+    engine.on_progress(move |_| (Instant::now() > run_deadline.get()).then(|| "out of time".into()));
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(64 * 1024);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+
+    engine.on_print(|text| tracing::info!("init.rhai: {}", text));
+    engine.on_debug(|text, _, pos| tracing::debug!("init.rhai {}: {}", pos, text));
+
+    engine
+        .register_type_with_name::<ScriptWindow>("Window")
+        .register_get("id", |w: &mut ScriptWindow| w.id as i64)
+        .register_get("title", |w: &mut ScriptWindow| w.title.clone())
+        .register_get("app_id", |w: &mut ScriptWindow| w.app_id.clone())
+        .register_get("workspace", |w: &mut ScriptWindow| w.workspace as i64)
+        .register_get("x", |w: &mut ScriptWindow| w.geometry.loc.x as i64)
+        .register_get("y", |w: &mut ScriptWindow| w.geometry.loc.y as i64)
+        .register_get("width", |w: &mut ScriptWindow| w.geometry.size.w as i64)
+        .register_get("height", |w: &mut ScriptWindow| w.geometry.size.h as i64);
+
+    let queue = commands.clone();
+    engine.register_fn("move_to", move |w: &mut ScriptWindow, x: i64, y: i64| {
+        queue.borrow_mut().push(ScriptCommand::Ipc(format!("move {} {} {}", w.id, x, y)));
+    });
+    let queue = commands.clone();
+    engine.register_fn("resize", move |w: &mut ScriptWindow, width: i64, height: i64| {
+        queue.borrow_mut().push(ScriptCommand::Ipc(format!("resize {} {} {}", w.id, width, height)));
+    });
+    let queue = commands.clone();
+    engine.register_fn("snap", move |w: &mut ScriptWindow, position: &str| {
+        queue.borrow_mut().push(ScriptCommand::Ipc(format!("snap {} {}", w.id, position)));
+    });
+    let queue = commands.clone();
+    engine.register_fn("close", move |w: &mut ScriptWindow| {
+        queue.borrow_mut().push(ScriptCommand::Ipc(format!("close {}", w.id)));
+    });
+    let queue = commands.clone();
+    engine.register_fn("focus", move |w: &mut ScriptWindow| {
+        queue.borrow_mut().push(ScriptCommand::Ipc(format!("focus {}", w.id)));
+    });
+    let queue = commands.clone();
+    engine.register_fn("send_to_workspace", move |w: &mut ScriptWindow, workspace: i64| {
+        queue.borrow_mut().push(ScriptCommand::SendToWorkspace {
+            id: w.id,
+            workspace: workspace.max(1) as usize,
+        });
+    });
+
+    // spawn("foot -e htop") or spawn(["foot", "-e", "htop"]) - no shell either way
+    let queue = commands.clone();
+    engine.register_fn("spawn", move |command: &str| {
+        let argv: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+        queue.borrow_mut().push(ScriptCommand::Spawn(argv));
+    });
+    let queue = commands.clone();
+    engine.register_fn("spawn", move |argv: Array| {
+        let argv: Vec<String> = argv.into_iter().map(|arg| arg.to_string()).collect();
+        queue.borrow_mut().push(ScriptCommand::Spawn(argv));
+    });
+
+    let queue = commands.clone();
+    engine.register_fn("rename_workspace", move |workspace: i64, name: &str| {
+        queue.borrow_mut().push(ScriptCommand::RenameWorkspace {
+            workspace: workspace.max(1) as usize,
+            name: name.to_string(),
+        });
+    });
+
+    let info = world.clone();
+    engine.register_fn("workspace", move || info.borrow().active_workspace as i64 + 1);
+    let info = world.clone();
+    engine.register_fn("workspace_name", move |workspace: i64| {
+        let index = workspace.max(1) as usize - 1;
+        info.borrow()
+            .workspace_names
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| (index + 1).to_string())
+    });
+    let info = world.clone();
+    engine.register_fn("outputs", move || {
+        info.borrow()
+            .outputs
+            .iter()
+            .map(|(name, geo)| {
+                let mut output = Map::new();
+                output.insert("name".into(), name.clone().into());
+                output.insert("x".into(), (geo.loc.x as i64).into());
+                output.insert("y".into(), (geo.loc.y as i64).into());
+                output.insert("width".into(), (geo.size.w as i64).into());
+                output.insert("height".into(), (geo.size.h as i64).into());
+                Dynamic::from_map(output)
+            })
+            .collect::<Array>()
+    });
+
+    engine
+}
+
+impl VibeWM {
+    /// Load init.rhai if there is one - a broken script just means no script
+    pub fn load_script(&mut self) {
+        self.scripts = None;
+
+        let Some(path) = script_path().filter(|p| p.exists()) else {
+            return;
+        };
+
+        let budget = Duration::from_millis(self.config.script_budget_ms);
+        match ScriptHost::load(&path, budget, self.script_world()) {
+            Ok(host) => {
+                tracing::info!("Loaded {} ({} callbacks)", path.display(), host.callbacks.len());
+                self.scripts = Some(host);
+                self.apply_script_commands();
+            }
+            Err(e) => self.script_error(&e),
+        }
+    }
+
+    /// A window event for the script
+    pub fn script_event(&mut self, callback: &str, window: &Window) {
+        if self.scripts.is_none() {
+            return;
+        }
+        let Some(handle) = self.script_window(window) else {
+            return;
+        };
+
+        self.refresh_script_world();
+        let result = match self.scripts.as_mut() {
+            Some(host) => {
+                // Don't fire title_changed for whatever the window opened with
+                host.titles.insert(handle.id, handle.title.clone());
+                host.call(callback, handle)
+            }
+            None => return,
+        };
+
+        if let Err(e) = result {
+            self.script_error(&e);
+        }
+        self.apply_script_commands();
+    }
+
+    /// Fire focus and title callbacks for whatever changed (called every frame)
+    pub fn update_scripts(&mut self) {
+        let Some(host) = self.scripts.as_mut() else {
+            return;
+        };
+
+        let focused = self.windows.focused().cloned();
+        let focused_id = focused.as_ref().and_then(|w| self.windows.meta(w)).map(|m| m.id);
+        let focus_changed = focused_id.is_some() && focused_id != host.last_focused;
+        host.last_focused = focused_id;
+
+        // Titles that changed since last frame
+        let mut retitled = Vec::new();
+        let mut live = HashSet::new();
+        for window in self.windows.all() {
+            let Some(meta) = self.windows.meta(window) else { continue };
+            live.insert(meta.id);
+
+            let title = window_title(window);
+            match host.titles.get(&meta.id) {
+                Some(seen) if *seen == title => {}
+                Some(_) => {
+                    host.titles.insert(meta.id, title);
+                    retitled.push(window.clone());
+                }
+                None => {
+                    host.titles.insert(meta.id, title);
+                }
+            }
+        }
+        host.titles.retain(|id, _| live.contains(id));
+
+        if focus_changed {
+            if let Some(window) = focused {
+                self.script_event("window_focused", &window);
+            }
+        }
+        for window in retitled {
+            self.script_event("title_changed", &window);
+        }
+    }
+
+    fn script_window(&self, window: &Window) -> Option<ScriptWindow> {
+        let meta = self.windows.meta(window)?;
+        Some(ScriptWindow {
+            id: meta.id,
+            title: window_title(window),
+            app_id: window_app_id(window).unwrap_or_default(),
+            workspace: meta.workspace + 1,
+            geometry: self.space.element_geometry(window).unwrap_or_default(),
+        })
+    }
+
+    fn refresh_script_world(&mut self) {
+        let Some(host) = &self.scripts else { return };
+        *host.world.borrow_mut() = self.script_world();
+    }
+
+    fn script_world(&self) -> ScriptWorld {
+        let active_workspace = self.windows.active_workspace();
+        ScriptWorld {
+            active_workspace,
+            workspace_names: (0..=active_workspace).map(|i| self.workspaces.name(i)).collect(),
+            outputs: self.space
+                .outputs()
+                .filter_map(|o| Some((o.name(), self.space.output_geometry(o)?)))
+                .collect(),
+        }
+    }
+
+    fn apply_script_commands(&mut self) {
+        let Some(host) = &self.scripts else { return };
+
+        for command in host.take_commands() {
+            let result = match command {
+                ScriptCommand::Ipc(line) => self.handle_ipc_command(&line).map(|_| ()),
+                ScriptCommand::SendToWorkspace { id, workspace } => match self.windows.by_id(id).cloned() {
+                    Some(window) => self
+                        .workspaces
+                        .check(workspace - 1)
+                        .map(|()| self.send_to_workspace(&window, workspace - 1)),
+                    None => Err(format!("no window with id {}", id)),
+                },
+                ScriptCommand::Spawn(argv) => launch::spawn(&argv)
+                    .map(|_| ())
+                    .map_err(|e| format!("spawn {:?}: {}", argv, e)),
+                ScriptCommand::RenameWorkspace { workspace, name } => {
+                    self.workspaces.get_or_create(workspace - 1).map(|ws| ws.name = name)
+                }
+            };
+
+            if let Err(e) = result {
+                self.script_error(&e);
+            }
+        }
+    }
+
+    /// Log it and flag it on screen
+    fn script_error(&mut self, error: &str) {
+        tracing::warn!("init.rhai: {}", error);
+        let first_line = error.lines().next().unwrap_or(error);
+        self.notice.show(format!("init.rhai: {}", first_line));
+    }
+}
@@ -0,0 +1,134 @@
+//! systemd readiness notification
+//!
+//! Hand-rolled sd_notify - one datagram per message to $NOTIFY_SOCKET, no
+//! libsystemd needed. Only active with the `systemd` feature and when
+//! systemd actually started us; everything else is a silent no-op.
+
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use smithay::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    EventLoop,
+};
+
+use crate::state::VibeWM;
+
+/// $NOTIFY_SOCKET and the watchdog interval, as take_env() found them
+static NOTIFY_ENV: OnceLock<Option<(String, Option<Duration>)>> = OnceLock::new();
+
+/// Read the notify variables and clear them so apps we launch don't report
+/// as us - from init_env, while ours is still the only thread
+pub fn take_env() {
+    if !cfg!(feature = "systemd") {
+        return;
+    }
+
+    let path = std::env::var("NOTIFY_SOCKET").ok();
+    let watchdog = watchdog_from_env();
+    for var in ["NOTIFY_SOCKET", "WATCHDOG_USEC", "WATCHDOG_PID"] {
+        std::env::remove_var(var);
+    }
+
+    NOTIFY_ENV.set(path.map(|path| (path, watchdog))).ok();
+}
+
+/// Connection to the service manager
+pub struct SdNotify {
+    socket: UnixDatagram,
+    addr: SocketAddr,
+
+    /// How often systemd wants a WATCHDOG=1, if at all
+    watchdog: Option<Duration>,
+}
+
+impl SdNotify {
+    /// Connect to the $NOTIFY_SOCKET take_env() found - None when not
+    /// running under systemd
+    pub fn from_env() -> Option<Self> {
+        let (path, watchdog) = NOTIFY_ENV.get()?.clone()?;
+
+        let addr = match socket_addr(&path) {
+            Ok(addr) => addr,
+            Err(e) => {
+                tracing::debug!("NOTIFY_SOCKET {:?} unusable: {}", path, e);
+                return None;
+            }
+        };
+
+        let socket = UnixDatagram::unbound()
+            .map_err(|e| tracing::debug!("sd_notify socket failed: {}", e))
+            .ok()?;
+
+        Some(Self { socket, addr, watchdog })
+    }
+
+    pub fn ready(&self) {
+        self.send("READY=1\nSTATUS=Running");
+    }
+
+    pub fn status(&self, status: &str) {
+        self.send(&format!("STATUS={}", status));
+    }
+
+    pub fn watchdog(&self) {
+        self.send("WATCHDOG=1");
+    }
+
+    pub fn stopping(&self) {
+        self.send("STOPPING=1\nSTATUS=Shutting down");
+    }
+
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog
+    }
+
+    fn send(&self, message: &str) {
+        if let Err(e) = self.socket.send_to_addr(message.as_bytes(), &self.addr) {
+            tracing::debug!("sd_notify {:?} failed: {}", message, e);
+        }
+    }
+}
+
+/// Path or abstract ("@name") socket address
+fn socket_addr(path: &str) -> std::io::Result<SocketAddr> {
+    match path.strip_prefix('@') {
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            SocketAddr::from_abstract_name(name)
+        }
+        None => SocketAddr::from_pathname(path),
+    }
+}
+
+/// $WATCHDOG_USEC, if it's meant for this process
+fn watchdog_from_env() -> Option<Duration> {
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+/// Ping the watchdog from the event loop at half the requested interval
+///
+/// Pings come from the loop itself, so a wedged compositor stops pinging
+/// and systemd restarts it.
+pub fn init_watchdog(event_loop: &mut EventLoop<'static, VibeWM>, interval: Duration) {
+    let period = interval / 2;
+
+    let result = event_loop.handle().insert_source(Timer::from_duration(period), move |_, _, state| {
+        if let Some(notify) = &state.sd_notify {
+            notify.watchdog();
+        }
+        TimeoutAction::ToDuration(period)
+    });
+
+    if let Err(e) = result {
+        tracing::warn!("systemd watchdog disabled: {:?}", e);
+    }
+}
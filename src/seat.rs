@@ -0,0 +1,232 @@
+//! Extra seats - independent keyboard/pointer pairs (kiosks, shared desks)
+//!
+//! The default seat is `VibeWM::seat` and keeps all the normal input
+//! handling. Seats declared in config claim devices by name; events from a
+//! claimed device drive that seat's own pointer and keyboard focus instead.
+//! With no seats declared nothing here runs.
+
+use anyhow::Result;
+use smithay::{
+    backend::input::{
+        AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, Event, InputBackend, InputEvent,
+        KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionEvent,
+    },
+    input::{
+        keyboard::{FilterResult, XkbConfig},
+        pointer::{AxisFrame, ButtonEvent, MotionEvent},
+        Seat,
+    },
+    utils::{Logical, Point, SERIAL_COUNTER},
+    wayland::seat::WaylandFocus,
+};
+
+use crate::config::SeatConfig;
+use crate::input::HeldKeys;
+use crate::state::VibeWM;
+
+/// A seat from config, with its own pointer
+pub struct ExtraSeat {
+    pub seat: Seat<VibeWM>,
+
+    /// Device name substrings this seat claims
+    pub devices: Vec<String>,
+
+    pub pointer_pos: Point<f64, Logical>,
+
+    /// Keys held down on this seat's keyboards
+    held_keys: HeldKeys,
+}
+
+impl VibeWM {
+    /// Create the seats declared in config
+    pub fn create_configured_seats(&mut self, seats: &[SeatConfig]) -> Result<()> {
+        for config in seats {
+            let mut seat = self.seat_state.new_wl_seat(&self.display_handle, config.name.clone());
+            seat.add_keyboard(XkbConfig::default(), 200, 25)?;
+            seat.add_pointer();
+
+            tracing::info!("Seat {} claims devices matching {:?}", config.name, config.devices);
+
+            self.extra_seats.push(ExtraSeat {
+                seat,
+                devices: config.devices.clone(),
+                pointer_pos: Point::from((0.0, 0.0)),
+                held_keys: HeldKeys::default(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Which extra seat a device belongs to - None = the default seat
+    pub fn extra_seat_for(&self, device_name: &str) -> Option<usize> {
+        self.extra_seats
+            .iter()
+            .position(|s| s.devices.iter().any(|pattern| device_name.contains(pattern.as_str())))
+    }
+
+    /// Take an event if it comes from a device an extra seat claimed
+    ///
+    /// Returns the event back when it's the default seat's to handle.
+    pub fn route_to_extra_seat<I: InputBackend>(&mut self, event: InputEvent<I>) -> Option<InputEvent<I>> {
+        if self.extra_seats.is_empty() {
+            return Some(event);
+        }
+
+        let device = match &event {
+            InputEvent::Keyboard { event } => event.device(),
+            InputEvent::PointerMotion { event } => event.device(),
+            InputEvent::PointerMotionAbsolute { event } => event.device(),
+            InputEvent::PointerButton { event } => event.device(),
+            InputEvent::PointerAxis { event } => event.device(),
+            InputEvent::DeviceAdded { device } => device.clone(),
+            InputEvent::DeviceRemoved { device } => device.clone(),
+            _ => return Some(event),
+        };
+
+        let Some(index) = self.extra_seat_for(&device.name()) else {
+            return Some(event);
+        };
+
+        match event {
+            InputEvent::Keyboard { event } => self.extra_seat_key(index, event),
+            InputEvent::PointerMotion { event } => {
+                let pos = self.extra_seats[index].pointer_pos + event.delta();
+                self.extra_seat_motion(index, pos, event.time_msec());
+            }
+            InputEvent::PointerMotionAbsolute { event } => {
+                if let Some(target) = self.absolute_target(&event.device().name()) {
+                    let normalized = (event.x_transformed(1), event.y_transformed(1));
+                    let pos = crate::input::absolute_position(normalized, target);
+                    self.extra_seat_motion(index, pos, event.time_msec());
+                }
+            }
+            InputEvent::PointerButton { event } => self.extra_seat_button(index, event),
+            InputEvent::PointerAxis { event } => self.extra_seat_axis(index, event),
+            InputEvent::DeviceRemoved { device } => self.extra_seat_device_removed(index, &device.id()),
+            _ => {}
+        }
+
+        None
+    }
+
+    fn extra_seat_key<I: InputBackend>(&mut self, index: usize, event: impl KeyboardKeyEvent<I>) {
+        let keycode = event.key_code();
+        let device = event.device().id();
+
+        // Only the first press / last release of a key reaches xkb
+        if !self.extra_seats[index].held_keys.track(keycode, device, event.state()) {
+            return;
+        }
+
+        // Compositor keybinds are the default seat's - this one just types
+        let Some(keyboard) = self.extra_seats[index].seat.get_keyboard() else {
+            return;
+        };
+        keyboard.input::<(), _>(
+            self,
+            keycode,
+            event.state(),
+            SERIAL_COUNTER.next_serial(),
+            event.time_msec(),
+            |_, _, _| FilterResult::Forward,
+        );
+    }
+
+    /// Unplugged keyboard - release whatever only it was holding
+    fn extra_seat_device_removed(&mut self, index: usize, device: &str) {
+        let released = self.extra_seats[index].held_keys.release_device(device);
+
+        let Some(keyboard) = self.extra_seats[index].seat.get_keyboard() else {
+            return;
+        };
+        let time = self.start_time.elapsed().as_millis() as u32;
+        for keycode in released {
+            keyboard.input::<(), _>(
+                self,
+                keycode,
+                KeyState::Released,
+                SERIAL_COUNTER.next_serial(),
+                time,
+                |_, _, _| FilterResult::Forward,
+            );
+        }
+    }
+
+    fn extra_seat_motion(&mut self, index: usize, pos: Point<f64, Logical>, time: u32) {
+        // Keep the pointer on the outputs
+        let pos = crate::input::constrain_pointer(
+            &self.output_rects(),
+            self.extra_seats[index].pointer_pos,
+            pos,
+            self.config.pointer_wrap,
+        );
+        self.extra_seats[index].pointer_pos = pos;
+
+        let under = self.surface_under(pos);
+        let Some(pointer) = self.extra_seats[index].seat.get_pointer() else {
+            return;
+        };
+        pointer.motion(
+            self,
+            under,
+            &MotionEvent {
+                location: pos,
+                serial: SERIAL_COUNTER.next_serial(),
+                time,
+            },
+        );
+    }
+
+    fn extra_seat_button<I: InputBackend>(&mut self, index: usize, event: impl PointerButtonEvent<I>) {
+        let seat = self.extra_seats[index].seat.clone();
+        let serial = SERIAL_COUNTER.next_serial();
+
+        // Click to focus - on this seat's keyboard only, and only the
+        // spotlighted window in spotlight mode
+        if event.state() == ButtonState::Pressed {
+            let pos = self.extra_seats[index].pointer_pos;
+            let surface = self.window_under(pos).and_then(|(window, _)| window.wl_surface().map(|s| s.into_owned()));
+            if let (Some(keyboard), Some(surface)) = (seat.get_keyboard(), surface) {
+                keyboard.set_focus(self, Some(surface), serial);
+            }
+        }
+
+        if let Some(pointer) = seat.get_pointer() {
+            pointer.button(
+                self,
+                &ButtonEvent {
+                    button: event.button_code(),
+                    state: event.state(),
+                    serial,
+                    time: event.time_msec(),
+                },
+            );
+        }
+    }
+
+    fn extra_seat_axis<I: InputBackend>(&mut self, index: usize, event: impl PointerAxisEvent<I>) {
+        let Some(pointer) = self.extra_seats[index].seat.get_pointer() else {
+            return;
+        };
+
+        let mut frame = AxisFrame::new(event.time_msec());
+        for axis in [Axis::Horizontal, Axis::Vertical] {
+            if let Some(amount) = event.amount(axis) {
+                frame = frame.value(axis, amount);
+            }
+        }
+        if event.source() == AxisSource::Finger {
+            frame = frame.source(AxisSource::Finger);
+        }
+
+        pointer.axis(self, frame);
+    }
+
+    /// Every seat's pointer, default seat first - one cursor each
+    pub fn cursor_positions(&self) -> Vec<Point<f64, Logical>> {
+        std::iter::once(self.input.pointer_pos)
+            .chain(self.extra_seats.iter().map(|s| s.pointer_pos))
+            .collect()
+    }
+}
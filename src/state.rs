@@ -1,21 +1,26 @@
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use smithay::{
-    desktop::{Space, Window},
-    input::{keyboard::XkbConfig, Seat, SeatHandler, SeatState},
+    backend::renderer::utils::on_commit_buffer_handler,
+    desktop::{PopupKind, PopupManager, Space, Window},
+    input::{keyboard::XkbConfig, pointer::CursorImageStatus, Seat, SeatHandler, SeatState},
     output::Output,
     reexports::{
         calloop::{generic::Generic, EventLoop, Interest, Mode, PostAction},
+        wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode,
         wayland_server::{
             backend::{ClientData, ClientId, DisconnectReason},
             protocol::wl_surface::WlSurface,
             Display, DisplayHandle, Resource,
         },
     },
-    utils::Serial,
+    utils::{Logical, Point, Rectangle, Serial, Size},
     wayland::{
+        alpha_modifier::AlphaModifierState,
         buffer::BufferHandler,
         compositor::{CompositorClientState, CompositorHandler, CompositorState},
         selection::{
@@ -27,16 +32,45 @@ use smithay::{
         },
         output::{OutputHandler, OutputManagerState},
         seat::WaylandFocus,
-        shell::xdg::{XdgShellHandler, XdgShellState, ToplevelSurface, PopupSurface, PositionerState},
+        shell::xdg::{
+            decoration::{XdgDecorationHandler, XdgDecorationState},
+            Configure, XdgShellHandler, XdgShellState, ShellClient, ToplevelSurface, PopupSurface, PositionerState,
+        },
         shm::{ShmHandler, ShmState},
+        single_pixel_buffer::SinglePixelBufferState,
         socket::ListeningSocketSource,
     },
 };
 
-use crate::config::Config;
-use crate::window::WindowManager;
+use crate::config::{default_launch_template, Config};
+use crate::launch;
+use crate::layout::clamp_to_limits;
+use crate::window::{
+    has_buffer, initial_configure_sent, size_limits, window_app_id, WindowManager,
+};
+use crate::workspace::{PendingPlacement, WorkspaceSlide, Workspaces};
 use crate::input::InputState;
-use crate::command_center::CommandCenter;
+use crate::keybind::Keybinds;
+use crate::command_center::{CommandCenter, CommandCenterLayout, WindowInfo};
+use crate::config::{CommandCenterPlacement, EmptyDesktopAction};
+use crate::overlay::{CheatSheet, HelpOverlay, Notice, QuitConfirm, SnapPreview};
+use crate::ping::PingTracker;
+use crate::procstat::ProcSampler;
+use crate::sysinfo::SysSampler;
+use crate::sd_notify::SdNotify;
+use crate::script::ScriptHost;
+use crate::screenshot::RegionSelect;
+use crate::seat::ExtraSeat;
+use crate::osk::Osk;
+use crate::usage::UsageStats;
+use crate::window_menu::WindowMenu;
+use crate::grab::{MoveGrab, ResizeGrab};
+use crate::titlebar::{decoration_mode, set_requested_mode};
+use crate::capture::Captures;
+use crate::foreign_toplevel::ForeignToplevels;
+use crate::schedule::FrameScheduler;
+use crate::wallpaper::Wallpapers;
+use crate::zoom::Zoom;
 
 /// Main compositor state
 pub struct VibeWM {
@@ -50,23 +84,132 @@ pub struct VibeWM {
     pub shm_state: ShmState,
     pub output_manager_state: OutputManagerState,
     pub data_device_state: DataDeviceState,
+    pub alpha_modifier_state: AlphaModifierState,
+    pub single_pixel_buffer_state: SinglePixelBufferState,
+    pub xdg_decoration_state: XdgDecorationState,
+
+    /// Window lists for taskbars (ext and wlr foreign toplevel)
+    pub foreign_toplevels: ForeignToplevels,
+
     pub seat_state: SeatState<Self>,
     pub seat: Seat<Self>,
 
+    /// What the focused client wants the default seat's cursor to look like
+    pub cursor_status: CursorImageStatus,
+
+    /// Seats declared in config, on top of the default one
+    pub extra_seats: Vec<ExtraSeat>,
+
     // Desktop
     pub space: Space<Window>,
+
+    /// Primary output - None before the backend has one and after the last
+    /// one is unplugged (see output.rs)
     pub output: Option<Output>,
 
+    /// New toplevels not mapped yet - waiting on their first buffer, or on
+    /// an output to go on
+    pub pending_toplevels: Vec<Window>,
+
+    /// xdg popups (menus, dropdowns) - their windows draw and hit-test them
+    pub popups: PopupManager,
+
     // vibeWM specific
     pub windows: WindowManager,
+    pub workspaces: Workspaces,
+
+    /// Workspace slide in progress (swipe or animated switch)
+    pub workspace_slide: Option<WorkspaceSlide>,
+
+    /// Background images, per workspace
+    pub wallpapers: Wallpapers,
+
     pub input: InputState,
+    pub keybinds: Keybinds,
 
     // Command center - the anti-suckless control panel
-    pub command_center: CommandCenter,
+    // (None when disabled in config - fuzzel/rofi people)
+    pub command_center: Option<CommandCenter>,
+
+    /// Hints card for an empty desktop
+    pub help_overlay: HelpOverlay,
+
+    /// mod+/ keybinding list
+    pub cheat_sheet: CheatSheet,
+
+    /// "Press mod+Q again" card
+    pub quit_confirm: QuitConfirm,
+
+    /// Flash of the region a keyboard snap is heading to
+    pub snap_preview: SnapPreview,
+
+    /// Apps launched onto a specific workspace, waiting for their window
+    pub pending_placements: Vec<PendingPlacement>,
+
+    /// Magnifier level
+    pub zoom: Zoom,
+
+    /// Spotlighted window - everything else is dimmed and can't be clicked
+    pub spotlight: Option<Window>,
+
+    /// Per-window CPU/memory, sampled off-thread
+    pub proc_sampler: ProcSampler,
+
+    /// Battery/CPU/memory/network/volume for the system bar, off-thread
+    pub sys_sampler: SysSampler,
+
+    /// Outstanding pings, for spotting frozen clients
+    pub pings: PingTracker,
+
+    /// Set when the GPU context is lost (or a reset is simulated for debugging)
+    pub gpu_reset_pending: bool,
+
+    /// systemd notify socket - None unless a systemd unit started us
+    pub sd_notify: Option<SdNotify>,
+
+    /// init.rhai, if there is one and it loaded
+    pub scripts: Option<ScriptHost>,
+
+    /// One-line OSD notice (script errors)
+    pub notice: Notice,
+
+    /// mod+shift+Print selection in progress - a grab while Some
+    pub region_select: Option<RegionSelect>,
+
+    /// Screenshots still being written - where each went, or why it didn't
+    pub screenshot_saves: Vec<Receiver<Result<PathBuf, String>>>,
+
+    /// Window menu (titlebar right-click, mod+Menu) - a grab while Some
+    pub window_menu: Option<WindowMenu>,
+
+    /// mod+drag or titlebar drag in progress - a grab while Some
+    pub move_grab: Option<MoveGrab>,
+
+    /// mod+right-drag in progress - a grab while Some and not released
+    pub resize_grab: Option<ResizeGrab>,
+
+    /// Screencopy sessions, for the recording indicator
+    pub captures: Captures,
+
+    /// Whether to render this iteration, and how long each loop phase takes
+    pub frames: FrameScheduler,
+
+    /// On-screen keyboard for the command center on touch-only setups
+    pub osk: Osk,
+
+    /// Launch/window history behind the command center's stats view
+    pub usage: UsageStats,
 }
 
 impl VibeWM {
-    pub fn new(event_loop: &mut EventLoop<'static, Self>, config: Config) -> Result<Self> {
+    pub fn new(event_loop: &mut EventLoop<'static, Self>, mut config: Config) -> Result<Self> {
+        // A broken launch wrapper would break every launch - fall back to none
+        if let Err(e) = launch::validate_template(&config.launch_command_template) {
+            tracing::warn!("{} - launching apps without a wrapper", e);
+            config.launch_command_template = default_launch_template();
+        }
+        launch::set_launch_env(&config.env);
+
         let display = Display::<Self>::new()?;
         let display_handle = display.handle();
         let loop_handle = event_loop.handle();
@@ -78,6 +221,20 @@ impl VibeWM {
         let output_manager_state = OutputManagerState::new_with_xdg_output::<Self>(&display_handle);
         let data_device_state = DataDeviceState::new::<Self>(&display_handle);
 
+        // wp_alpha_modifier_v1 - smithay's surface render elements pick up the
+        // multiplier themselves (and drop the opaque region when it's < 1.0)
+        let alpha_modifier_state = AlphaModifierState::new::<Self>(&display_handle);
+
+        // wp_single_pixel_buffer_manager_v1 - bars and lock screens want it.
+        // The renderer imports these as solid colors, no texture upload
+        let single_pixel_buffer_state = SinglePixelBufferState::new::<Self>(&display_handle);
+
+        // xdg-decoration - client-side unless titlebars are on and the client asks
+        let xdg_decoration_state = XdgDecorationState::new::<Self>(&display_handle);
+
+        // Taskbars (waybar, sfwbar) list and activate windows through these
+        let foreign_toplevels = ForeignToplevels::new(&display_handle);
+
         // Create seat
         let mut seat_state = SeatState::new();
         let mut seat = seat_state.new_wl_seat(&display_handle, "vibeWM");
@@ -116,7 +273,12 @@ impl VibeWM {
             },
         )?;
 
-        Ok(Self {
+        let workspaces = Workspaces::from_config(&config.workspaces, config.max_workspaces);
+        let wallpapers = Wallpapers::from_config(&config);
+        let usage_stats = config.command_center.usage_stats;
+        let keybinds = Keybinds::from_config(&config.keybinds);
+
+        let mut state = Self {
             config,
             start_time: Instant::now(),
             display_handle,
@@ -125,30 +287,502 @@ impl VibeWM {
             shm_state,
             output_manager_state,
             data_device_state,
+            alpha_modifier_state,
+            single_pixel_buffer_state,
+            xdg_decoration_state,
+            foreign_toplevels,
             seat_state,
             seat,
+            cursor_status: CursorImageStatus::default_named(),
+            extra_seats: Vec::new(),
             space: Space::default(),
             output: None,
+            pending_toplevels: Vec::new(),
+            popups: PopupManager::default(),
             windows: WindowManager::new(),
+            workspaces,
+            workspace_slide: None,
+            wallpapers,
             input: InputState::new(),
-            command_center: CommandCenter::new(),
-        })
+            keybinds,
+            command_center: None,
+            help_overlay: HelpOverlay::new(),
+            cheat_sheet: CheatSheet::new(),
+            quit_confirm: QuitConfirm::new(),
+            snap_preview: SnapPreview::new(),
+            pending_placements: Vec::new(),
+            zoom: Zoom::new(),
+            spotlight: None,
+            proc_sampler: ProcSampler::spawn(),
+            sys_sampler: SysSampler::spawn(),
+            pings: PingTracker::new(),
+            gpu_reset_pending: false,
+            sd_notify: SdNotify::from_env(),
+            scripts: None,
+            notice: Notice::new(),
+            region_select: None,
+            screenshot_saves: Vec::new(),
+            window_menu: None,
+            move_grab: None,
+            resize_grab: None,
+            captures: Captures::new(),
+            frames: FrameScheduler::new(),
+            osk: Osk::new(),
+            usage: UsageStats::load(usage_stats),
+        };
+
+        state.sync_command_center();
+
+        let seats = state.config.seats.clone();
+        state.create_configured_seats(&seats)?;
+
+        state.load_script();
+
+        Ok(state)
+    }
+
+    /// Build or drop the command center to match config
+    ///
+    /// Apps get scanned on a worker thread, and only when enabled - call
+    /// this again after a config reload.
+    pub fn sync_command_center(&mut self) {
+        match (self.config.command_center.enabled, self.command_center.is_some()) {
+            (true, false) => {
+                let mut command_center = CommandCenter::new();
+                command_center.extra_app_dirs = self.config.command_center.app_dirs.clone();
+                command_center.system_info = self.sys_sampler.info();
+                command_center.load_apps_in_background();
+                self.command_center = Some(command_center);
+            }
+            (false, true) => self.command_center = None,
+            _ => {}
+        }
+    }
+
+    /// Re-read config.toml and apply it without touching clients
+    ///
+    /// A file that doesn't load - gone, bad TOML, one typo'd key - leaves
+    /// the running config alone, with a warning and a notice saying why.
+    pub fn reload_config(&mut self) {
+        let Some(path) = Config::path() else {
+            tracing::warn!("Config not reloaded: no $XDG_CONFIG_HOME or $HOME");
+            return;
+        };
+
+        let old = self.config.clone();
+        if let Err(e) = self.config.reload_from(&path) {
+            tracing::warn!("Config not reloaded, keeping the old one: {:#}", e);
+            self.notice.show(format!("Config not reloaded: {}", e.root_cause()));
+            return;
+        }
+
+        self.apply_config(&old);
+        tracing::info!("Config reloaded from {}", path.display());
+        self.notice.show("Config reloaded");
+    }
+
+    /// Redo whatever was built from the config - `old` is what ran before.
+    /// Colors and the clear color are read every frame, so they need nothing
+    fn apply_config(&mut self, old: &Config) {
+        // Same check as at startup
+        if let Err(e) = launch::validate_template(&self.config.launch_command_template) {
+            tracing::warn!("{} - launching apps without a wrapper", e);
+            self.config.launch_command_template = default_launch_template();
+        }
+
+        crate::watchdog::configure(&self.config);
+        launch::set_launch_env(&self.config.env);
+        if self.config.titlebars.enabled != old.titlebars.enabled {
+            self.renegotiate_decorations();
+        }
+        self.forget_rule_traces();
+        self.workspaces.set_max(self.config.max_workspaces);
+        self.reload_keybinds();
+        self.sync_command_center();
+        if let Some(command_center) = self.command_center.as_mut() {
+            command_center.set_app_dirs(&self.config.command_center.app_dirs);
+        }
+        self.wallpapers = Wallpapers::from_config(&self.config);
+
+        if self.config.command_center.usage_stats != old.command_center.usage_stats {
+            self.usage.flush();
+            self.usage = UsageStats::load(self.config.command_center.usage_stats);
+        }
+
+        // Snapped windows pick up the new gaps and snap ratio, floating ones
+        // stay put. Hidden workspaces re-snap when they're switched to
+        for window in self.windows.on_workspace(self.windows.active_workspace()) {
+            if let Some(position) = self.windows.meta(&window).and_then(|m| m.snap_state) {
+                self.snap_window(&window, position);
+            }
+        }
     }
 
     pub fn handle_pending(&mut self) {
+        // Pointer motion is coalesced - dispatch this frame's worth
+        self.flush_pointer_motion();
+
         // Handle any pending compositor work
         self.space.refresh();
+        self.popups.cleanup();
         self.windows.cleanup_closed();
 
-        // Update command center animations
-        self.command_center.update();
+        // Update command center animations - the glow holds still on camera
+        // and with reduced motion
+        let glow = self.decorative_animations();
+        let animations = self.config.animations;
+        if let Some(command_center) = &mut self.command_center {
+            command_center.glow_paused = !glow;
+            command_center.animations = animations;
+            command_center.update();
+        }
+
+        // System bar data only needs to be fresh while someone can see it
+        self.sys_sampler.set_active(self.command_center.as_ref().is_some_and(|cc| cc.visible));
+
+        // On-screen keyboard follows the command center
+        self.update_osk();
+
+        // Workspace slides ease to rest
+        self.update_workspace_slide();
+
+        // Slideshows rotate, wallpapers for nearby workspaces get decoded
+        self.update_wallpapers();
+
+        // Focused/unfocused opacity fades
+        self.update_window_opacity();
+
+        // Titlebars come and go with decoration modes and fullscreen
+        self.update_titlebars();
+
+        // Menu goes when its window does, always-on-top windows stay on top
+        self.update_window_menu();
+        self.windows.raise_always_on_top(&mut self.space);
+
+        // Quit confirmation times out on its own, snap previews fade out,
+        // an idle cursor hides
+        self.quit_confirm.update();
+        self.snap_preview.update();
+        self.update_screenshot_saves();
+        self.usage.save_if_due();
+        self.notice.update();
+        self.update_cursor_idle();
+
+        // Focus moved some other way (close, workspace switch, IPC) - the
+        // keyboard follows
+        self.sync_keyboard_focus();
+
+        // New app_ids get their rules checked, focus may have moved to an
+        // app with pass-through chords
+        self.update_rule_traces();
+        self.update_passthrough();
+
+        // Script callbacks for focus and title changes
+        self.update_scripts();
+
+        // Keep pinging clients so a frozen one gets noticed
+        self.update_pings();
+
+        // Taskbars hear about new, changed and closed windows
+        self.update_foreign_toplevels();
 
         // Flush client events
         self.display_handle.flush_clients().ok();
     }
 
+    /// Decorative animations are off because something is being recorded
+    pub fn calm_animations(&self) -> bool {
+        self.config.recording.calm_animations && self.captures.any_recording()
+    }
+
+    /// How long an animation that takes `full` runs right now - everything
+    /// that moves asks here, so `animations` applies everywhere. Zero means
+    /// jump straight to the end.
+    pub fn animation_duration(&self, full: Duration) -> Duration {
+        self.config.animations.duration(full)
+    }
+
+    /// Purely decorative effects (glow pulse, focus ring flash) - full
+    /// animations only, and not while recording with calm_animations
+    pub fn decorative_animations(&self) -> bool {
+        self.config.animations.decorative() && !self.calm_animations()
+    }
+
+    /// Notices and snap previews stay off this output while it's recorded
+    pub fn popups_hidden_on(&self, output: &Output) -> bool {
+        self.config.recording.hide_popups && self.captures.is_recording(&output.name())
+    }
+
+    /// Point every window's opacity fade at focused or unfocused
+    ///
+    /// Only retargets when focus changed - a fade in progress keeps going.
+    pub fn update_window_opacity(&mut self) {
+        let fade = &self.config.focus_fade;
+        let calm = self.calm_animations();
+        let focused = self.windows.focused().cloned();
+
+        for window in self.windows.all().to_vec() {
+            let is_focused = focused.as_ref() == Some(&window);
+            let (target, ms) = match (fade.enabled, is_focused) {
+                (false, _) | (true, true) => (1.0, fade.focus_ms),
+                (true, false) => (fade.unfocused_dim.clamp(0.0, 1.0), fade.blur_ms),
+            };
+            let duration = if calm {
+                Duration::ZERO
+            } else {
+                self.animation_duration(Duration::from_millis(ms))
+            };
+
+            if let Some(meta) = self.windows.meta_mut(&window) {
+                if meta.opacity.target() != target {
+                    meta.opacity.fade_to(target, duration);
+                }
+            }
+        }
+    }
+
+    /// Compositor-side alpha for a window this frame - the renderer multiplies
+    /// it with the client's own wp_alpha_modifier factor
+    pub fn window_opacity(&self, window: &Window) -> f32 {
+        self.windows.meta(window).map(|meta| meta.opacity.value()).unwrap_or(1.0)
+    }
+
+    /// Usable area of the output - its geometry minus the outer gap
+    pub fn work_area(&self) -> Option<Rectangle<i32, Logical>> {
+        let output = self.output.as_ref()?;
+        let geo = self.space.output_geometry(output)?;
+
+        let gap = self.config.outer_gap;
+        Some(Rectangle::new(
+            (geo.loc.x + gap, geo.loc.y + gap).into(),
+            (geo.size.w - gap * 2, geo.size.h - gap * 2).into(),
+        ))
+    }
+
+    /// Layout the command center opened with, or a fresh one from config
+    pub fn command_center_layout(&self, screen_width: i32, screen_height: i32) -> CommandCenterLayout {
+        self.command_center
+            .as_ref()
+            .and_then(|cc| cc.layout.clone())
+            .unwrap_or_else(|| {
+                CommandCenterLayout::calculate(screen_width, screen_height, &self.config.command_center.layout)
+            })
+    }
+
+    /// Focus a window and bring it to the front, keyboard included
+    ///
+    /// Everything that hands focus to a window goes through here - focusing
+    /// anything but the spotlighted window ends spotlight, so the dimmed
+    /// windows never get the keyboard behind its back.
+    pub fn focus_and_raise(&mut self, window: &Window) {
+        if self.spotlight.as_ref().is_some_and(|spotlight| spotlight != window) {
+            self.toggle_spotlight();
+        }
+
+        self.windows.focus(window);
+        self.windows.raise_focused();
+        self.space.raise_element(window, true);
+        self.sync_keyboard_focus();
+    }
+
+    /// Spotlight the focused window, or end spotlight mode
+    pub fn toggle_spotlight(&mut self) {
+        self.spotlight = match self.spotlight.take() {
+            Some(_) => None,
+            None => self.windows.focused().cloned(),
+        };
+
+        tracing::info!("Spotlight {}", if self.spotlight.is_some() { "on" } else { "off" });
+    }
+
+    /// Quit for real - no confirmation
+    pub fn quit(&mut self) {
+        tracing::info!("Quit requested");
+        self.quit_confirm.cancel();
+        self.input.quit_requested = true;
+    }
+
+    /// Ask every client to close, on the way out
+    pub fn close_all_windows(&mut self) {
+        for window in self.windows.all() {
+            if let Some(toplevel) = window.toplevel() {
+                toplevel.send_close();
+            }
+        }
+        self.display_handle.flush_clients().ok();
+    }
+
+    /// Point the process sampler at the current set of client PIDs
+    fn watch_window_pids(&self) {
+        let pids = self.windows
+            .all()
+            .iter()
+            .filter_map(|w| self.windows.meta(w)?.pid);
+        self.proc_sampler.watch(pids);
+    }
+
+    /// The last window on screen just closed
+    fn on_desktop_empty(&mut self) {
+        match self.config.on_last_window_closed {
+            EmptyDesktopAction::CommandCenter => match self.command_center.as_ref().map(|cc| cc.visible) {
+                Some(false) => self.toggle_command_center(),
+                Some(true) => {}
+                // Disabled - popping an external launcher unasked would be rude
+                None => self.help_overlay.show(),
+            },
+            EmptyDesktopAction::HelpOverlay => self.help_overlay.show(),
+            EmptyDesktopAction::Nothing => {}
+        }
+    }
+
+    /// Output the command center should open on, per `command_center.placement`
+    fn command_center_target(&mut self) -> Option<Output> {
+        let focused = self.windows
+            .focused()
+            .and_then(|window| self.space.outputs_for_element(window).into_iter().next());
+
+        let active = self.windows.active_workspace();
+        let workspace_output = self.workspaces
+            .get(active)
+            .and_then(|ws| ws.output.clone())
+            .and_then(|name| self.space.outputs().find(|o| o.name() == name).cloned());
+
+        let target = match self.config.command_center.placement {
+            CommandCenterPlacement::Pointer => self.space.output_under(self.input.pointer_pos).next().cloned(),
+            CommandCenterPlacement::Focused => focused.or(workspace_output),
+            CommandCenterPlacement::Primary => None,
+        };
+        target.or_else(|| self.output.clone())
+    }
+
+    /// Open windows for the command center's windows section, most recently
+    /// raised first, every workspace
+    fn command_center_windows(&self) -> Vec<WindowInfo> {
+        self.windows
+            .all()
+            .iter()
+            .rev()
+            .filter_map(|window| {
+                let id = self.windows.meta(window)?.id;
+                let title = self.display_title(window);
+                Some(WindowInfo { id, title, app_id: window_app_id(window), score: 0 })
+            })
+            .collect()
+    }
+
     pub fn toggle_command_center(&mut self) {
-        self.command_center.toggle();
+        let active = self.windows.active_workspace();
+        let workspace_name = self.workspaces.name(active);
+
+        let anchor = self.command_center_target();
+        let windows = self.command_center_windows();
+
+        let Some(command_center) = self.command_center.as_mut() else {
+            return;
+        };
+        command_center.workspace_name = workspace_name;
+        command_center.remember_query = self.config.command_center.remember_query
+            .then(|| Duration::from_millis(self.config.command_center.remember_query_ms));
+        command_center.files_enabled = self.config.command_center.recent_files;
+        command_center.max_results = self.config.command_center.max_results;
+
+        if !command_center.visible {
+            // Nowhere to show it - closing still works
+            let Some(output_size) = anchor.as_ref().and_then(|o| o.current_mode()).map(|m| m.size) else {
+                tracing::warn!("No output - not opening the command center");
+                return;
+            };
+            command_center.output = anchor.as_ref().map(|o| o.name());
+
+            let layout = CommandCenterLayout::calculate(
+                output_size.w,
+                output_size.h,
+                &self.config.command_center.layout,
+            );
+            command_center.set_grid_metrics(&layout);
+            command_center.layout = Some(layout);
+            command_center.set_windows(windows);
+
+            // Snapshot for the stats view - it doesn't move while the center is open
+            command_center.usage = self.usage
+                .enabled()
+                .then(|| self.usage.summary(self.start_time));
+            // With no history the center's own counts are all there is -
+            // they last until vibeWM exits
+            if self.usage.enabled() {
+                command_center.launch_counts = self.usage.launch_counts();
+            }
+        }
+
+        command_center.toggle();
+        self.help_overlay.hide();
+    }
+
+    /// default_window_size on the primary output, if set
+    fn default_window_size(&self) -> Option<Size<i32, Logical>> {
+        let (width, height) = self.config.default_window_size?;
+        let output_size = self.output.as_ref()?.current_mode()?.size;
+
+        Some((width.resolve(output_size.w).max(1), height.resolve(output_size.h).max(1)).into())
+    }
+
+    /// Put a pending toplevel on screen, centered on the primary output - with
+    /// no output it stays pending (output.rs maps it once one shows up)
+    pub fn map_toplevel(&mut self, window: &Window) {
+        let Some(area) = self.primary_output_rect() else {
+            tracing::warn!("No output yet - holding a new window until one shows up");
+            return;
+        };
+        self.pending_toplevels.retain(|pending| pending != window);
+
+        let pid = window
+            .wl_surface()
+            .and_then(|surface| self.display_handle.get_client(surface.id()).ok())
+            .and_then(|client| client.get_credentials(&self.display_handle).ok())
+            .map(|credentials| credentials.pid as u32);
+
+        // Center new windows - it has drawn its first buffer, so this is its real size
+        let window_size = window.geometry().size;
+        let x = area.loc.x + (area.size.w - window_size.w) / 2;
+        let y = area.loc.y + (area.size.h - window_size.h) / 2;
+
+        // Launched onto another workspace from the command center?
+        let app_id = window_app_id(window);
+        let placement = self.take_pending_placement(pid, app_id.as_deref());
+
+        self.space.map_element(window.clone(), (x, y), false);
+        self.windows.add(window.clone(), pid);
+
+        // The keyboard and Activated follow right away
+        self.focus_and_raise(window);
+        self.watch_window_pids();
+        self.usage.record_window();
+
+        if let Some(workspace) = placement {
+            self.send_to_workspace(window, workspace);
+        }
+
+        // Something's open now, hints not needed
+        self.help_overlay.hide();
+
+        self.script_event("window_opened", window);
+
+        tracing::info!("New window mapped");
+    }
+
+    /// First configure for a new toplevel - with the default size, inside the client's limits
+    fn send_initial_configure(&mut self, window: &Window) {
+        let Some(toplevel) = window.toplevel() else { return };
+
+        let (min, max) = size_limits(window);
+        let fixed = min.w > 0 && min == max;
+
+        // A fixed-size client gets no size - it knows best
+        if let Some(size) = self.default_window_size().filter(|_| !fixed) {
+            let size = clamp_to_limits(size, min, max);
+            toplevel.with_pending_state(|state| state.size = Some(size));
+        }
+        toplevel.send_configure();
     }
 }
 
@@ -159,8 +793,17 @@ pub struct ClientState {
 }
 
 impl ClientData for ClientState {
-    fn initialized(&self, _client_id: ClientId) {}
-    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    fn initialized(&self, _client_id: ClientId) {
+        crate::watchdog::client_connected();
+        #[cfg(feature = "metrics")]
+        crate::metrics::client_connected();
+    }
+
+    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {
+        crate::watchdog::client_disconnected();
+        #[cfg(feature = "metrics")]
+        crate::metrics::client_disconnected();
+    }
 }
 
 // SeatHandler implementation
@@ -178,8 +821,11 @@ impl SeatHandler for VibeWM {
         set_data_device_focus(&self.display_handle, seat, client);
     }
 
-    fn cursor_image(&mut self, _seat: &Seat<Self>, _image: smithay::input::pointer::CursorImageStatus) {
-        // Handle cursor changes
+    fn cursor_image(&mut self, seat: &Seat<Self>, image: CursorImageStatus) {
+        // Extra seats don't draw a cursor image of their own
+        if seat == &self.seat {
+            self.cursor_status = image;
+        }
     }
 }
 
@@ -197,13 +843,46 @@ impl CompositorHandler for VibeWM {
     }
 
     fn commit(&mut self, surface: &WlSurface) {
-        // Handle surface commit - find window with this surface
-        let window = self.space.elements()
-            .find(|w| w.wl_surface().map(|s| &*s == surface).unwrap_or(false))
-            .cloned();
+        on_commit_buffer_handler::<Self>(surface);
 
-        if let Some(window) = window {
+        // Popups get their configure on their first commit, with the
+        // geometry new_popup worked out
+        self.popups.commit(surface);
+        if let Some(PopupKind::Xdg(popup)) = self.popups.find_popup(surface) {
+            if !popup.is_initial_configure_sent() {
+                if let Err(e) = popup.send_configure() {
+                    tracing::warn!("Couldn't configure a popup: {:?}", e);
+                }
+            }
+            return;
+        }
+
+        // Handle surface commit - find window with this surface
+        // (hidden workspaces included, they still commit)
+        let is_window = |w: &Window| w.wl_surface().is_some_and(|s| &*s == surface);
+        if let Some(window) = self.windows.all().iter().find(|w| is_window(w)).cloned() {
             window.on_commit();
+            self.resize_grab_commit(&window);
+            return;
+        }
+
+        // Not mapped yet - xdg-shell goes: empty commit, configure, ack,
+        // buffer. Only then does it go on screen
+        let Some(window) = self.pending_toplevels.iter().find(|w| is_window(w)).cloned() else {
+            return;
+        };
+        window.on_commit();
+
+        // min/max are only known once the first commit lands
+        if !initial_configure_sent(&window) {
+            self.send_initial_configure(&window);
+            return;
+        }
+
+        // smithay's xdg-shell already refuses a buffer before the configure
+        // is acked, so a buffer here is one drawn for it
+        if has_buffer(&window) {
+            self.map_toplevel(&window);
         }
     }
 }
@@ -223,42 +902,129 @@ impl XdgShellHandler for VibeWM {
         &mut self.xdg_shell_state
     }
 
-    fn new_toplevel(&mut self, surface: ToplevelSurface) {
-        let window = Window::new_wayland_window(surface);
-
-        // Center new windows
-        let size = self.output.as_ref()
-            .map(|o| o.current_mode().map(|m| m.size).unwrap_or((1920, 1080).into()))
-            .unwrap_or((1920, 1080).into());
+    fn client_pong(&mut self, client: ShellClient) {
+        self.client_ponged(&client);
+    }
 
-        let window_size = window.geometry().size;
-        let x = (size.w - window_size.w) / 2;
-        let y = (size.h - window_size.h) / 2;
+    fn new_toplevel(&mut self, surface: ToplevelSurface) {
+        // Mapped once it has been configured and drawn something - see commit()
+        self.pending_toplevels.push(Window::new_wayland_window(surface));
+    }
 
-        self.space.map_element(window.clone(), (x, y), false);
-        self.windows.add(window);
+    fn show_window_menu(
+        &mut self,
+        surface: ToplevelSurface,
+        _seat: smithay::reexports::wayland_server::protocol::wl_seat::WlSeat,
+        _serial: Serial,
+        location: Point<i32, Logical>,
+    ) {
+        let window = self.windows.all()
+            .iter()
+            .find(|w| w.toplevel().is_some_and(|t| t == &surface))
+            .cloned();
+        let Some(window) = window else { return };
 
-        tracing::info!("New window mapped");
+        // Surface-local from the client, global for us
+        let Some(origin) = self.space.element_location(&window) else { return };
+        self.open_window_menu(&window, origin + location);
     }
 
-    fn new_popup(&mut self, _surface: PopupSurface, _positioner: PositionerState) {
-        // Handle popups
+    fn new_popup(&mut self, surface: PopupSurface, _positioner: PositionerState) {
+        self.unconstrain_popup(&surface);
+        if let Err(e) = self.popups.track_popup(PopupKind::Xdg(surface)) {
+            tracing::warn!("Couldn't track a popup: {:?}", e);
+        }
     }
 
     fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
-        // Find and remove the window
-        let window = self.space.elements()
+        // Closed before it was ever mapped
+        self.pending_toplevels.retain(|pending| pending.toplevel() != Some(&surface));
+
+        // Find and remove the window - it may live on a hidden workspace
+        let window = self.windows.all()
+            .iter()
             .find(|w| w.toplevel().map(|t| t == &surface).unwrap_or(false))
             .cloned();
 
         if let Some(window) = window {
+            // Last chance for the script to see it
+            self.script_event("window_closed", &window);
+
+            // Emptying a hidden workspace doesn't empty the screen
+            let on_screen = self.windows
+                .meta(&window)
+                .is_some_and(|m| m.workspace == self.windows.active_workspace());
+
             self.space.unmap_elem(&window);
             self.windows.remove(&window);
+            self.watch_window_pids();
+
+            if self.spotlight.as_ref() == Some(&window) {
+                self.spotlight = None;
+            }
+            if self.move_grab.as_ref().is_some_and(|grab| grab.window == window) {
+                self.move_grab = None;
+            }
+            if self.resize_grab.as_ref().is_some_and(|grab| grab.window == window) {
+                self.resize_grab = None;
+            }
+
+            if on_screen && self.windows.on_workspace(self.windows.active_workspace()).is_empty() {
+                self.on_desktop_empty();
+            }
+        }
+    }
+
+    fn ack_configure(&mut self, surface: WlSurface, configure: Configure) {
+        let window = self.windows.all().iter().find(|w| w.wl_surface().is_some_and(|s| *s == surface)).cloned();
+        if let Some(window) = window {
+            self.resize_grab_ack(&window, &configure);
         }
     }
 
-    fn grab(&mut self, _surface: PopupSurface, _seat: smithay::reexports::wayland_server::protocol::wl_seat::WlSeat, _serial: Serial) {}
-    fn reposition_request(&mut self, _surface: PopupSurface, _positioner: PositionerState, _token: u32) {}
+    fn grab(&mut self, surface: PopupSurface, seat: smithay::reexports::wayland_server::protocol::wl_seat::WlSeat, serial: Serial) {
+        self.grab_popup(surface, seat, serial);
+    }
+
+    fn reposition_request(&mut self, surface: PopupSurface, positioner: PositionerState, token: u32) {
+        surface.with_pending_state(|state| {
+            state.geometry = positioner.get_geometry();
+            state.positioner = positioner;
+        });
+        self.unconstrain_popup(&surface);
+        surface.send_repositioned(token);
+    }
+}
+
+impl XdgDecorationHandler for VibeWM {
+    fn new_decoration(&mut self, toplevel: ToplevelSurface) {
+        set_requested_mode(&toplevel, None);
+        toplevel.with_pending_state(|state| {
+            state.decoration_mode = Some(DecorationMode::ClientSide);
+        });
+    }
+
+    fn request_mode(&mut self, toplevel: ToplevelSurface, mode: DecorationMode) {
+        // Server-side means a titlebar from us - only if they're turned on
+        set_requested_mode(&toplevel, Some(mode));
+        let mode = decoration_mode(self.config.titlebars.enabled, Some(mode));
+        toplevel.with_pending_state(|state| {
+            state.decoration_mode = Some(mode);
+        });
+        if toplevel.is_initial_configure_sent() {
+            toplevel.send_pending_configure();
+        }
+    }
+
+    fn unset_mode(&mut self, toplevel: ToplevelSurface) {
+        set_requested_mode(&toplevel, None);
+        toplevel.with_pending_state(|state| {
+            state.decoration_mode = Some(DecorationMode::ClientSide);
+        });
+        if toplevel.is_initial_configure_sent() {
+            toplevel.send_pending_configure();
+        }
+    }
 }
 
 impl SelectionHandler for VibeWM {
@@ -277,6 +1043,10 @@ impl ServerDndGrabHandler for VibeWM {}
 smithay::delegate_compositor!(VibeWM);
 smithay::delegate_shm!(VibeWM);
 smithay::delegate_xdg_shell!(VibeWM);
+smithay::delegate_xdg_decoration!(VibeWM);
 smithay::delegate_data_device!(VibeWM);
 smithay::delegate_output!(VibeWM);
 smithay::delegate_seat!(VibeWM);
+smithay::delegate_alpha_modifier!(VibeWM);
+smithay::delegate_single_pixel_buffer!(VibeWM);
+smithay::delegate_foreign_toplevel_list!(VibeWM);
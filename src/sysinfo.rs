@@ -0,0 +1,342 @@
+//! System info for the command center's system bar - battery, CPU, memory,
+//! network and volume
+//!
+//! Sampled on a background thread: sysfs battery reads can stall on flaky
+//! embedded controllers and wpctl is a whole process spawn, neither of which
+//! belongs on the frame path. The thread fills an `Arc<RwLock<SystemInfo>>`
+//! and the compositor only reads the snapshot. It samples often while the
+//! command center is open and slows right down while it's closed.
+
+use std::fs;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Sample cadence while the command center is open
+const ACTIVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// ...and while nobody's looking
+const IDLE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long shutting down waits for a sample in flight - a stuck sysfs read
+/// or wpctl mustn't hold up quitting
+const STOP_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// What the system bar shows
+#[derive(Debug, Clone)]
+pub struct SystemInfo {
+    pub battery_percent: u8,
+    pub battery_charging: bool,
+
+    /// All cores, percent of total (0..100)
+    pub cpu_usage: f32,
+    pub memory_used_gb: f32,
+    pub memory_total_gb: f32,
+
+    /// Default route's interface, None when offline
+    pub network: Option<NetworkInfo>,
+
+    /// Default sink, None without wpctl
+    pub volume: Option<VolumeInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkInfo {
+    pub interface: String,
+    pub wireless: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumeInfo {
+    pub percent: u8,
+    pub muted: bool,
+}
+
+impl Default for SystemInfo {
+    fn default() -> Self {
+        Self {
+            // No battery (desktops) reads as full
+            battery_percent: 100,
+            battery_charging: false,
+            cpu_usage: 0.0,
+            memory_used_gb: 0.0,
+            memory_total_gb: 0.0,
+            network: None,
+            volume: None,
+        }
+    }
+}
+
+/// Handle to the sampler thread - stops it on drop
+pub struct SysSampler {
+    info: Arc<RwLock<SystemInfo>>,
+    active: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SysSampler {
+    pub fn spawn() -> Self {
+        let info = Arc::new(RwLock::new(SystemInfo::default()));
+        let active = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let (info, active, stop) = (info.clone(), active.clone(), stop.clone());
+            thread::Builder::new()
+                .name("vibewm-sysinfo".to_string())
+                .spawn(move || sample_loop(info, active, stop))
+                .map_err(|e| tracing::warn!("System info sampler disabled: {}", e))
+                .ok()
+        };
+
+        Self { info, active, stop, thread }
+    }
+
+    /// The snapshot the thread keeps up to date
+    pub fn info(&self) -> Arc<RwLock<SystemInfo>> {
+        self.info.clone()
+    }
+
+    /// Sample fast while the command center is open - opening it wakes the
+    /// thread for a fresh sample right away
+    pub fn set_active(&self, active: bool) {
+        let was = self.active.swap(active, Ordering::Relaxed);
+        if active && !was {
+            if let Some(thread) = &self.thread {
+                thread.thread().unpark();
+            }
+        }
+    }
+}
+
+impl Drop for SysSampler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let Some(thread) = self.thread.take() else { return };
+        thread.thread().unpark();
+
+        // Joined if it's quick about it, otherwise left to exit on its own
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while !thread.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        if thread.is_finished() {
+            thread.join().ok();
+        } else {
+            tracing::debug!("System info sampler is mid-sample - not waiting for it");
+        }
+    }
+}
+
+fn sample_loop(info: Arc<RwLock<SystemInfo>>, active: Arc<AtomicBool>, stop: Arc<AtomicBool>) {
+    let mut previous_cpu = read_cpu_times();
+
+    while !stop.load(Ordering::Relaxed) {
+        // Every read happens before the lock is taken
+        let cpu = read_cpu_times();
+        let cpu_usage = match (previous_cpu, cpu) {
+            (Some(before), Some(now)) => cpu_percent(before, now),
+            _ => 0.0,
+        };
+        previous_cpu = cpu.or(previous_cpu);
+
+        let (memory_used_gb, memory_total_gb) = read_memory_gb().unwrap_or((0.0, 0.0));
+        let sample = SystemInfo {
+            battery_percent: read_battery_percent().unwrap_or(100),
+            battery_charging: read_battery_charging().unwrap_or(false),
+            cpu_usage,
+            memory_used_gb,
+            memory_total_gb,
+            network: read_network(),
+            volume: read_volume(),
+        };
+
+        match info.write() {
+            Ok(mut info) => *info = sample,
+            Err(_) => return,
+        }
+
+        let interval = if active.load(Ordering::Relaxed) { ACTIVE_INTERVAL } else { IDLE_INTERVAL };
+        thread::park_timeout(interval);
+    }
+}
+
+/// (busy, total) jiffies across all CPUs, from the first line of /proc/stat
+fn read_cpu_times() -> Option<(u64, u64)> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    parse_cpu_times(stat.lines().next()?)
+}
+
+fn parse_cpu_times(line: &str) -> Option<(u64, u64)> {
+    let mut fields = line.split_whitespace();
+    if fields.next()? != "cpu" {
+        return None;
+    }
+
+    let values: Vec<u64> = fields.filter_map(|v| v.parse().ok()).collect();
+    if values.len() < 4 {
+        return None;
+    }
+
+    // idle + iowait are the not-busy part
+    let idle = values[3] + values.get(4).copied().unwrap_or(0);
+    let total: u64 = values.iter().sum();
+    Some((total - idle, total))
+}
+
+fn cpu_percent(before: (u64, u64), now: (u64, u64)) -> f32 {
+    let busy = now.0.saturating_sub(before.0) as f32;
+    let total = now.1.saturating_sub(before.1) as f32;
+    if total > 0.0 { (busy / total * 100.0).clamp(0.0, 100.0) } else { 0.0 }
+}
+
+/// (used, total) from /proc/meminfo - used is total minus available
+fn read_memory_gb() -> Option<(f32, f32)> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let kb = |key: &str| -> Option<f32> {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(key))
+            .and_then(|value| value.split_whitespace().next())
+            .and_then(|kb| kb.parse().ok())
+    };
+
+    let total = kb("MemTotal:")?;
+    let available = kb("MemAvailable:")?;
+    let gb = 1024.0 * 1024.0;
+    Some(((total - available) / gb, total / gb))
+}
+
+/// Read battery percentage from sysfs
+fn read_battery_percent() -> Option<u8> {
+    let paths = [
+        "/sys/class/power_supply/BAT0/capacity",
+        "/sys/class/power_supply/BAT1/capacity",
+    ];
+
+    for path in &paths {
+        if let Ok(content) = fs::read_to_string(path) {
+            if let Ok(percent) = content.trim().parse() {
+                return Some(percent);
+            }
+        }
+    }
+
+    None
+}
+
+/// Check if battery is charging
+fn read_battery_charging() -> Option<bool> {
+    let paths = [
+        "/sys/class/power_supply/BAT0/status",
+        "/sys/class/power_supply/BAT1/status",
+    ];
+
+    for path in &paths {
+        if let Ok(content) = fs::read_to_string(path) {
+            return Some(content.trim() == "Charging");
+        }
+    }
+
+    None
+}
+
+/// Interface carrying the default route (/proc/net/route), None when offline
+fn read_network() -> Option<NetworkInfo> {
+    let routes = fs::read_to_string("/proc/net/route").ok()?;
+    let interface = default_route_interface(&routes)?;
+    let wireless = fs::metadata(format!("/sys/class/net/{}/wireless", interface)).is_ok();
+    Some(NetworkInfo { interface, wireless })
+}
+
+fn default_route_interface(routes: &str) -> Option<String> {
+    // Iface Destination Gateway ... - the default route has destination 0
+    routes.lines().skip(1).find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let interface = fields.next()?;
+        (fields.next()? == "00000000").then(|| interface.to_string())
+    })
+}
+
+/// Default sink volume through wpctl (PipeWire)
+fn read_volume() -> Option<VolumeInfo> {
+    let output = Command::new("wpctl")
+        .args(["get-volume", "@DEFAULT_AUDIO_SINK@"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    parse_wpctl_volume(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// "Volume: 0.45" or "Volume: 0.45 [MUTED]"
+fn parse_wpctl_volume(output: &str) -> Option<VolumeInfo> {
+    let mut fields = output.trim().strip_prefix("Volume:")?.split_whitespace();
+    let level: f32 = fields.next()?.parse().ok()?;
+
+    Some(VolumeInfo {
+        percent: (level * 100.0).round().clamp(0.0, 255.0) as u8,
+        muted: fields.any(|field| field == "[MUTED]"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_times() {
+        let cases = [
+            // user nice system idle iowait irq softirq steal
+            ("cpu  100 0 50 800 50 0 0 0", Some((150, 1000))),
+            ("cpu  10 20 30 40", Some((60, 100))),
+            ("cpu  1 2 3", None),
+            ("cpu0 100 0 50 800 50 0 0 0", None),
+            ("intr 12345", None),
+            ("", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(parse_cpu_times(line), expected, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn wpctl_volume() {
+        let volume = |percent, muted| Some(VolumeInfo { percent, muted });
+        let cases = [
+            ("Volume: 0.45\n", volume(45, false)),
+            ("Volume: 0.45 [MUTED]\n", volume(45, true)),
+            ("Volume: 1.50", volume(150, false)),
+            ("Volume: 0.00 [MUTED]", volume(0, true)),
+            ("Volume: loud", None),
+            ("Error: no such node", None),
+            ("", None),
+        ];
+        for (output, expected) in cases {
+            assert_eq!(parse_wpctl_volume(output), expected, "{:?}", output);
+        }
+    }
+
+    #[test]
+    fn default_route() {
+        let header = "Iface\tDestination\tGateway\tFlags\tRefCnt\tUse\tMetric\tMask\n";
+        let cases = [
+            ("wlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\n", Some("wlan0")),
+            (
+                "docker0\t000011AC\t00000000\t0001\t0\t0\t0\t0000FFFF\neth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\n",
+                Some("eth0"),
+            ),
+            ("docker0\t000011AC\t00000000\t0001\t0\t0\t0\t0000FFFF\n", None),
+            ("", None),
+        ];
+        for (routes, expected) in cases {
+            let routes = format!("{}{}", header, routes);
+            assert_eq!(default_route_interface(&routes).as_deref(), expected, "{:?}", routes);
+        }
+
+        // The first line is the header, whatever it says
+        assert_eq!(default_route_interface("eth0\t00000000\n"), None);
+    }
+}
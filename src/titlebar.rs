@@ -0,0 +1,357 @@
+//! Compositor-side titlebars - for apps that draw no decorations of their own
+//!
+//! Off unless `titlebars.enabled`. A window gets one when its client
+//! negotiates server-side decorations (xdg-decoration), or when its app_id is
+//! in `titlebars.apps`. Fullscreen windows never do. The bar sits right above
+//! the window geometry: title on the left, maximize and close on the right.
+//! Dragging the bar moves the window - the same grab as mod+drag.
+//!
+//! The mode a client asked for is remembered, so turning titlebars on or off
+//! in a reload hands already-open windows the right one.
+
+use std::sync::Mutex;
+
+use smithay::{
+    desktop::Window,
+    reexports::{
+        wayland_protocols::xdg::{
+            decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode,
+            shell::server::xdg_toplevel,
+        },
+    },
+    utils::{Logical, Point, Rectangle},
+    wayland::{compositor::with_states, shell::xdg::ToplevelSurface},
+};
+
+use crate::command_center::CommandCenterTheme;
+use crate::config::SnapPosition;
+use crate::render_command_center::{
+    truncate_string, with_alpha, FontWeight, Icon, IconRender, RenderQuad, TextRender,
+};
+use crate::rules::titlebar_forced;
+use crate::state::VibeWM;
+use crate::window::window_app_id;
+
+/// Rough title glyph width (px), for truncating
+const TITLE_CHAR_WIDTH: f32 = 7.0;
+
+/// linux/input-event-codes.h
+const BTN_RIGHT: u32 = 0x111;
+
+/// Where a point landed on a titlebar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TitlebarHit {
+    Bar,
+    Maximize,
+    Close,
+}
+
+/// The decoration mode a client last asked for, None once it unset it
+struct RequestedDecoration(Mutex<Option<DecorationMode>>);
+
+/// Render data for one titlebar
+pub struct TitlebarFrame {
+    pub bar: RenderQuad,
+    pub title: TextRender,
+    /// Highlight under the hovered button
+    pub hover: Option<RenderQuad>,
+    pub maximize: IconRender,
+    pub close: IconRender,
+}
+
+/// The bar above a window's geometry
+pub fn bar_rect(geometry: Rectangle<i32, Logical>, height: i32) -> Rectangle<i32, Logical> {
+    Rectangle::new((geometry.loc.x, geometry.loc.y - height).into(), (geometry.size.w, height).into())
+}
+
+/// Mode to hand a client - server-side only if titlebars are on and it asked
+pub fn decoration_mode(titlebars: bool, requested: Option<DecorationMode>) -> DecorationMode {
+    match requested {
+        Some(DecorationMode::ServerSide) if titlebars => DecorationMode::ServerSide,
+        _ => DecorationMode::ClientSide,
+    }
+}
+
+/// Remember what a client asked for (None: no preference)
+pub fn set_requested_mode(toplevel: &ToplevelSurface, mode: Option<DecorationMode>) {
+    with_states(toplevel.wl_surface(), |states| {
+        states.data_map.insert_if_missing_threadsafe(|| RequestedDecoration(Mutex::new(None)));
+        if let Some(requested) = states.data_map.get::<RequestedDecoration>() {
+            *requested.0.lock().unwrap() = mode;
+        }
+    });
+}
+
+/// What a client asked for - Err if it never bound xdg-decoration at all
+fn requested_mode(toplevel: &ToplevelSurface) -> Result<Option<DecorationMode>, ()> {
+    with_states(toplevel.wl_surface(), |states| {
+        let requested = states.data_map.get::<RequestedDecoration>().ok_or(())?;
+        Ok(*requested.0.lock().unwrap())
+    })
+}
+
+/// Square buttons at the right end of the bar - close is last
+fn button_rect(bar: Rectangle<i32, Logical>, hit: TitlebarHit) -> Rectangle<i32, Logical> {
+    let from_right = match hit {
+        TitlebarHit::Close => 1,
+        TitlebarHit::Maximize => 2,
+        TitlebarHit::Bar => 0,
+    };
+    let size = bar.size.h;
+    Rectangle::new((bar.loc.x + bar.size.w - size * from_right, bar.loc.y).into(), (size, size).into())
+}
+
+/// What's under a point on the bar, if anything
+pub fn hit(bar: Rectangle<i32, Logical>, point: Point<f64, Logical>) -> Option<TitlebarHit> {
+    let point = point.to_i32_floor();
+    if !bar.contains(point) {
+        return None;
+    }
+
+    [TitlebarHit::Close, TitlebarHit::Maximize]
+        .into_iter()
+        .find(|&button| button_rect(bar, button).contains(point))
+        .or(Some(TitlebarHit::Bar))
+}
+
+/// Titlebar render data - `bar` in output-local coordinates
+pub fn render(
+    bar: Rectangle<i32, Logical>,
+    title: &str,
+    focused: bool,
+    hovered: Option<TitlebarHit>,
+    theme: &CommandCenterTheme,
+) -> TitlebarFrame {
+    let height = bar.size.h as f32;
+    let text_color = if focused { theme.text_primary } else { theme.text_secondary };
+
+    // Title gets whatever the buttons leave
+    let title_room = bar.size.w as f32 - height * 2.0 - 16.0;
+    let max_chars = (title_room / TITLE_CHAR_WIDTH).max(0.0) as usize;
+
+    let icon = |button: TitlebarHit, icon: Icon| {
+        let rect = button_rect(bar, button);
+        IconRender {
+            x: rect.loc.x as f32 + height / 4.0,
+            y: rect.loc.y as f32 + height / 2.0,
+            size: height / 2.0,
+            icon,
+            color: if hovered == Some(button) && button == TitlebarHit::Close {
+                theme.accent_secondary
+            } else {
+                text_color
+            },
+        }
+    };
+
+    let hover = hovered.filter(|&h| h != TitlebarHit::Bar).map(|button| {
+        let rect = button_rect(bar, button);
+        RenderQuad {
+            x: rect.loc.x as f32,
+            y: rect.loc.y as f32,
+            width: rect.size.w as f32,
+            height: rect.size.h as f32,
+            color: theme.card_hover,
+            corner_radius: 0.0,
+        }
+    });
+
+    TitlebarFrame {
+        bar: RenderQuad {
+            x: bar.loc.x as f32,
+            y: bar.loc.y as f32,
+            width: bar.size.w as f32,
+            height,
+            color: if focused { theme.card_selected } else { with_alpha(theme.bg_color, 0.95) },
+            corner_radius: 0.0,
+        },
+        title: TextRender {
+            x: bar.loc.x as f32 + 10.0,
+            y: bar.loc.y as f32 + height / 2.0,
+            text: truncate_string(title, max_chars),
+            color: text_color,
+            size: 13.0,
+            font_weight: if focused { FontWeight::Medium } else { FontWeight::Regular },
+        },
+        hover,
+        maximize: icon(TitlebarHit::Maximize, Icon::Maximize),
+        close: icon(TitlebarHit::Close, Icon::Close),
+    }
+}
+
+impl VibeWM {
+    /// Should this window have a compositor titlebar right now?
+    fn wants_titlebar(&self, window: &Window) -> bool {
+        let config = &self.config.titlebars;
+        if !config.enabled {
+            return false;
+        }
+        let Some(toplevel) = window.toplevel() else {
+            return false;
+        };
+
+        let state = toplevel.current_state();
+        if state.states.contains(xdg_toplevel::State::Fullscreen) {
+            return false;
+        }
+
+        let by_rule = titlebar_forced(&self.config, window_app_id(window).as_deref());
+        by_rule || state.decoration_mode == Some(DecorationMode::ServerSide)
+    }
+
+    /// Give windows titlebars (or take them away) as decorations get
+    /// negotiated, fullscreen toggles and config changes
+    pub fn update_titlebars(&mut self) {
+        let height = self.config.titlebars.height.max(1);
+
+        for window in self.windows.all().to_vec() {
+            let titlebar = self.wants_titlebar(&window).then_some(height);
+            let Some(meta) = self.windows.meta_mut(&window) else { continue };
+            if meta.titlebar == titlebar {
+                continue;
+            }
+            let gained = titlebar.unwrap_or(0) - meta.titlebar.unwrap_or(0);
+            let snap_state = meta.snap_state;
+            meta.titlebar = titlebar;
+
+            // Snapped windows re-snap so bar and window share the slot,
+            // floating ones shift so the bar isn't pushed off the top
+            if let Some(position) = snap_state {
+                self.snap_window(&window, position);
+            } else if let Some(location) = self.space.element_location(&window) {
+                let top = self.space
+                    .output_under(location.to_f64())
+                    .next()
+                    .and_then(|o| self.space.output_geometry(o))
+                    .map(|rect| rect.loc.y)
+                    .unwrap_or(0);
+                if gained > 0 && location.y - gained < top {
+                    self.space.map_element(window.clone(), (location.x, top + gained), false);
+                }
+            }
+        }
+    }
+
+    /// Titlebars were turned on or off - give every client that negotiated
+    /// decorations the mode it would get now
+    pub fn renegotiate_decorations(&mut self) {
+        let titlebars = self.config.titlebars.enabled;
+
+        for toplevel in self.xdg_shell_state.toplevel_surfaces().to_vec() {
+            let Ok(requested) = requested_mode(&toplevel) else { continue };
+            let mode = decoration_mode(titlebars, requested);
+            if toplevel.with_pending_state(|state| state.decoration_mode.replace(mode)) == Some(mode) {
+                continue;
+            }
+            if toplevel.is_initial_configure_sent() {
+                toplevel.send_pending_configure();
+            }
+        }
+    }
+
+    /// Titlebar under a point - topmost window first (only the spotlighted
+    /// one's in spotlight mode)
+    pub fn titlebar_under(&self, point: Point<f64, Logical>) -> Option<(Window, TitlebarHit)> {
+        let spotlit = |window: &Window| self.spotlight.as_ref().map_or(true, |s| s == window);
+
+        self.space.elements().rev().filter(|w| spotlit(w)).find_map(|window| {
+            let height = self.windows.meta(window)?.titlebar?;
+            let geometry = self.space.element_geometry(window)?;
+            hit(bar_rect(geometry, height), point).map(|hit| (window.clone(), hit))
+        })
+    }
+
+    /// Pointer press on a titlebar - returns whether it was one (and was
+    /// handled). A drag's release ends the move grab like mod+drag's does
+    pub fn titlebar_button(&mut self, button: u32, pressed: bool) -> bool {
+        if !pressed {
+            return false;
+        }
+
+        let pointer = self.input.pointer_pos;
+        let Some((window, hit)) = self.titlebar_under(pointer) else {
+            return false;
+        };
+
+        self.focus_and_raise(&window);
+
+        // Right-click anywhere on the bar is the window menu
+        if button == BTN_RIGHT {
+            self.open_window_menu(&window, pointer.to_i32_round());
+            return true;
+        }
+
+        match hit {
+            TitlebarHit::Close => {
+                if let Some(toplevel) = window.toplevel() {
+                    toplevel.send_close();
+                }
+            }
+            TitlebarHit::Maximize => {
+                let maximized = self.windows
+                    .meta(&window)
+                    .is_some_and(|m| m.snap_state == Some(SnapPosition::Maximize));
+                let restore = self.windows.meta(&window).and_then(|m| m.pre_snap_geometry);
+                match (maximized, restore) {
+                    (true, Some(rect)) => {
+                        self.move_window(&window, rect.loc);
+                        self.resize_window(&window, rect.size);
+                    }
+                    _ => {
+                        self.snap_window(&window, SnapPosition::Maximize);
+                    }
+                }
+            }
+            TitlebarHit::Bar => self.start_move_grab(window),
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar() -> Rectangle<i32, Logical> {
+        bar_rect(Rectangle::new((100, 200).into(), (400, 300).into()), 30)
+    }
+
+    #[test]
+    fn bar_sits_right_above_the_window() {
+        assert_eq!(bar(), Rectangle::new((100, 170).into(), (400, 30).into()));
+    }
+
+    #[test]
+    fn buttons_are_squares_at_the_right_end() {
+        assert_eq!(button_rect(bar(), TitlebarHit::Close), Rectangle::new((470, 170).into(), (30, 30).into()));
+        assert_eq!(button_rect(bar(), TitlebarHit::Maximize), Rectangle::new((440, 170).into(), (30, 30).into()));
+    }
+
+    #[test]
+    fn hits() {
+        let at = |x: f64, y: f64| hit(bar(), (x, y).into());
+
+        assert_eq!(at(110.0, 180.0), Some(TitlebarHit::Bar));
+        assert_eq!(at(439.9, 180.0), Some(TitlebarHit::Bar));
+        assert_eq!(at(440.0, 180.0), Some(TitlebarHit::Maximize));
+        assert_eq!(at(469.5, 199.5), Some(TitlebarHit::Maximize));
+        assert_eq!(at(470.0, 170.0), Some(TitlebarHit::Close));
+        assert_eq!(at(499.9, 199.9), Some(TitlebarHit::Close));
+
+        // Window below, nothing above or past either end
+        assert_eq!(at(110.0, 200.0), None);
+        assert_eq!(at(110.0, 169.9), None);
+        assert_eq!(at(99.9, 180.0), None);
+        assert_eq!(at(500.0, 180.0), None);
+    }
+
+    #[test]
+    fn server_side_only_when_on_and_asked_for() {
+        use DecorationMode::*;
+
+        assert_eq!(decoration_mode(true, Some(ServerSide)), ServerSide);
+        assert_eq!(decoration_mode(true, Some(ClientSide)), ClientSide);
+        assert_eq!(decoration_mode(true, None), ClientSide);
+        assert_eq!(decoration_mode(false, Some(ServerSide)), ClientSide);
+    }
+}
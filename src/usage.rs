@@ -0,0 +1,317 @@
+//! App usage history - launches and opened windows, per day
+//!
+//! Kept in $XDG_DATA_HOME/vibewm/usage, one `day<TAB>kind<TAB>count<TAB>name`
+//! line per entry (day = days since the Unix epoch, UTC). Small enough to
+//! rewrite whole - at most every SAVE_INTERVAL, and on the way out - through
+//! a temp file, so a crash mid-write can't leave half a history. Turning
+//! tracking off deletes the file.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Days of history kept
+const RETENTION_DAYS: u64 = 90;
+
+/// Changes wait this long to be written, so a burst of windows is one write
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The stats view covers the last week
+const SUMMARY_DAYS: u64 = 7;
+
+/// Apps listed in the stats view
+const SUMMARY_TOP_APPS: usize = 8;
+
+/// One day's counts
+#[derive(Debug, Clone, Default, PartialEq)]
+struct DayUsage {
+    /// Launches by app name
+    launches: HashMap<String, u32>,
+    windows: u32,
+}
+
+/// What the stats card shows
+#[derive(Debug, Clone)]
+pub struct UsageSummary {
+    /// Most launched apps over the last week, busiest first
+    pub top_apps: Vec<(String, u32)>,
+
+    /// Windows opened over the last week
+    pub windows_opened: u32,
+
+    /// When the compositor started, for the uptime line
+    pub started: Instant,
+}
+
+impl UsageSummary {
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+/// Launch/window history, persisted per day
+pub struct UsageStats {
+    enabled: bool,
+    path: Option<PathBuf>,
+    days: BTreeMap<u64, DayUsage>,
+
+    /// Changed since it was last written
+    dirty: bool,
+    last_save: Instant,
+}
+
+impl UsageStats {
+    /// Read the history - or, with tracking off, delete it
+    pub fn load(enabled: bool) -> Self {
+        Self::load_from(usage_path(), enabled)
+    }
+
+    fn load_from(path: Option<PathBuf>, enabled: bool) -> Self {
+        if !enabled {
+            if let Some(path) = path.as_ref().filter(|p| p.exists()) {
+                match std::fs::remove_file(path) {
+                    Ok(()) => tracing::info!("Usage tracking off - deleted {}", path.display()),
+                    Err(e) => tracing::warn!("Couldn't delete {}: {}", path.display(), e),
+                }
+            }
+            return Self { enabled, path: None, days: BTreeMap::new(), dirty: false, last_save: Instant::now() };
+        }
+
+        let mut days = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .map(|content| parse(&content))
+            .unwrap_or_default();
+        prune(&mut days, today());
+
+        Self { enabled, path, days, dirty: false, last_save: Instant::now() }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn record_launch(&mut self, name: &str) {
+        if !self.enabled || name.is_empty() {
+            return;
+        }
+        *self.today().launches.entry(name.to_string()).or_default() += 1;
+        self.dirty = true;
+    }
+
+    pub fn record_window(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        self.today().windows += 1;
+        self.dirty = true;
+    }
+
+    /// Launches per app over all the history kept - for the command center's
+    /// most-used order
+    pub fn launch_counts(&self) -> HashMap<String, u32> {
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        for usage in self.days.values() {
+            for (name, count) in &usage.launches {
+                *counts.entry(name.clone()).or_default() += count;
+            }
+        }
+        counts
+    }
+
+    /// Top launched apps and windows opened this week (today and the 6 days before)
+    pub fn summary(&self, started: Instant) -> UsageSummary {
+        let since = today().saturating_sub(SUMMARY_DAYS - 1);
+
+        let mut launches: HashMap<&str, u32> = HashMap::new();
+        let mut windows_opened = 0;
+        for (_, usage) in self.days.range(since..) {
+            for (name, count) in &usage.launches {
+                *launches.entry(name.as_str()).or_default() += count;
+            }
+            windows_opened += usage.windows;
+        }
+
+        let mut top_apps: Vec<(String, u32)> = launches
+            .into_iter()
+            .map(|(name, count)| (name.to_string(), count))
+            .collect();
+        top_apps.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_apps.truncate(SUMMARY_TOP_APPS);
+
+        UsageSummary { top_apps, windows_opened, started }
+    }
+
+    fn today(&mut self) -> &mut DayUsage {
+        let today = today();
+        prune(&mut self.days, today);
+        self.days.entry(today).or_default()
+    }
+
+    /// Write out changes once they've waited SAVE_INTERVAL - called every loop
+    pub fn save_if_due(&mut self) {
+        if self.dirty && self.last_save.elapsed() >= SAVE_INTERVAL {
+            self.flush();
+        }
+    }
+
+    /// Write out changes now - before quitting or swapping in a new one
+    pub fn flush(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        self.last_save = Instant::now();
+
+        let Some(path) = &self.path else { return };
+        if let Err(e) = write_atomically(path, &serialize(&self.days)) {
+            tracing::warn!("Couldn't save usage history to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Write next to the file, then rename over it - readers see the old
+/// history or the new one, never a torn write
+fn write_atomically(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Drop days older than RETENTION_DAYS
+fn prune(days: &mut BTreeMap<u64, DayUsage>, today: u64) {
+    days.retain(|&day, _| day + RETENTION_DAYS > today);
+}
+
+/// $XDG_DATA_HOME/vibewm/usage
+fn usage_path() -> Option<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+    Some(data_home.join("vibewm").join("usage"))
+}
+
+/// Days since the Unix epoch, UTC
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0)
+}
+
+/// Read the history file - lines that don't parse are dropped
+fn parse(content: &str) -> BTreeMap<u64, DayUsage> {
+    let mut days: BTreeMap<u64, DayUsage> = BTreeMap::new();
+
+    for line in content.lines() {
+        let mut fields = line.splitn(4, '\t');
+        let (Some(day), Some(kind), Some(count)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let (Ok(day), Ok(count)) = (day.parse::<u64>(), count.parse::<u32>()) else {
+            continue;
+        };
+
+        let usage = days.entry(day).or_default();
+        match (kind, fields.next()) {
+            ("launch", Some(name)) if !name.is_empty() => {
+                *usage.launches.entry(name.to_string()).or_default() += count;
+            }
+            ("windows", _) => usage.windows += count,
+            _ => {}
+        }
+    }
+
+    days
+}
+
+fn serialize(days: &BTreeMap<u64, DayUsage>) -> String {
+    let mut out = String::new();
+
+    for (day, usage) in days {
+        if usage.windows > 0 {
+            out.push_str(&format!("{}\twindows\t{}\n", day, usage.windows));
+        }
+
+        let mut launches: Vec<_> = usage.launches.iter().collect();
+        launches.sort();
+        for (name, count) in launches {
+            // Names are one line in the file
+            let name = name.replace(['\t', '\n'], " ");
+            out.push_str(&format!("{}\tlaunch\t{}\t{}\n", day, count, name));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("vibewm-{}-usage-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = temp_path("round-trip");
+        let mut usage = UsageStats::load_from(Some(path.clone()), true);
+        usage.record_launch("Firefox");
+        usage.record_launch("Firefox");
+        usage.record_launch("Files");
+        usage.record_window();
+
+        // Nothing written until it's due
+        usage.save_if_due();
+        assert!(!path.exists());
+
+        usage.flush();
+        let loaded = UsageStats::load_from(Some(path.clone()), true);
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.days, usage.days);
+        assert_eq!(loaded.launch_counts().get("Firefox"), Some(&2));
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn days_past_retention_are_pruned() {
+        let today = today();
+        let path = temp_path("prune");
+        let file = format!(
+            "{}\twindows\t3\n{}\tlaunch\t5\tOld\n{}\tlaunch\t2\tRecent\n",
+            today - RETENTION_DAYS,
+            today - RETENTION_DAYS,
+            today - (RETENTION_DAYS - 1),
+        );
+        std::fs::write(&path, file).unwrap();
+
+        let mut usage = UsageStats::load_from(Some(path.clone()), true);
+        assert_eq!(usage.days.keys().copied().collect::<Vec<_>>(), vec![today - (RETENTION_DAYS - 1)]);
+        assert_eq!(usage.launch_counts().get("Old"), None);
+
+        usage.record_window();
+        usage.flush();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(!content.contains("Old"), "{}", content);
+        assert!(content.contains("Recent"), "{}", content);
+    }
+
+    #[test]
+    fn turning_tracking_off_deletes_the_history() {
+        let path = temp_path("disabled");
+        std::fs::write(&path, "1\twindows\t1\n").unwrap();
+
+        let mut usage = UsageStats::load_from(Some(path.clone()), false);
+        usage.record_launch("Firefox");
+        usage.flush();
+
+        assert!(!path.exists());
+        assert!(usage.launch_counts().is_empty());
+    }
+}
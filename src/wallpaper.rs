@@ -0,0 +1,341 @@
+//! Wallpapers - a single image, or a directory slideshow
+//!
+//! Every workspace shows the global wallpaper unless it has its own. Images
+//! are decoded on a worker thread and only kept in memory for the workspaces
+//! on screen and their neighbors (the ones a switch can slide in); everything
+//! else is dropped and decoded again when it's needed.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::state::VibeWM;
+
+/// How long a slideshow crossfades between two images at full animations
+pub const CROSSFADE: Duration = Duration::from_millis(500);
+
+/// Extensions we try to decode in a wallpaper directory
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+/// A decoded wallpaper, RGBA8
+pub struct WallpaperImage {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// One image to draw, bottom to top
+pub struct WallpaperLayer {
+    pub image: Arc<WallpaperImage>,
+    pub opacity: f32,
+}
+
+/// Render data for one workspace's background
+pub struct WallpaperFrame {
+    pub layers: Vec<WallpaperLayer>,
+
+    /// Horizontal offset (px) while a workspace slide is moving it
+    pub offset_x: i32,
+}
+
+/// A configured wallpaper path and where its slideshow is at
+struct Slideshow {
+    /// Files to cycle through - just the one for a plain image
+    files: Vec<PathBuf>,
+    is_dir: bool,
+    index: usize,
+
+    current: Option<Arc<WallpaperImage>>,
+
+    /// Previous image and when the fade away from it started
+    fading_from: Option<(Arc<WallpaperImage>, Instant)>,
+
+    next_change: Instant,
+
+    /// File being decoded right now
+    pending: Option<PathBuf>,
+
+    /// Decodes that failed in a row, so a directory of junk doesn't spin
+    failures: usize,
+}
+
+impl Slideshow {
+    fn new(path: &Path) -> Self {
+        let is_dir = path.is_dir();
+        let files = if is_dir { list_images(path) } else { vec![path.to_path_buf()] };
+        if is_dir && files.is_empty() {
+            tracing::warn!("Wallpaper directory {} has no images", path.display());
+        }
+
+        Self {
+            files,
+            is_dir,
+            index: 0,
+            current: None,
+            fading_from: None,
+            next_change: Instant::now(),
+            pending: None,
+            failures: 0,
+        }
+    }
+
+    /// Move on to the next file - picks up files added to the directory on wrap-around
+    fn advance(&mut self, source: &Path) {
+        self.index += 1;
+        if self.index >= self.files.len() {
+            self.index = 0;
+            if self.is_dir {
+                self.files = list_images(source);
+            }
+        }
+    }
+
+    /// Drop the decoded images - a later update decodes the current file again
+    fn release(&mut self) {
+        self.current = None;
+        self.fading_from = None;
+    }
+}
+
+/// Background decoder thread
+struct Decoder {
+    requests: Sender<PathBuf>,
+    results: Receiver<(PathBuf, Result<WallpaperImage, String>)>,
+}
+
+impl Decoder {
+    fn spawn() -> Option<Self> {
+        let (request_tx, request_rx) = mpsc::channel::<PathBuf>();
+        let (result_tx, result_rx) = mpsc::channel();
+
+        // Runs until the compositor drops its end of the request channel
+        thread::Builder::new()
+            .name("vibewm-wallpaper".to_string())
+            .spawn(move || {
+                for path in request_rx {
+                    let decoded = decode(&path);
+                    if result_tx.send((path, decoded)).is_err() {
+                        break;
+                    }
+                }
+            })
+            .map_err(|e| tracing::warn!("Wallpapers disabled - couldn't start the decoder: {}", e))
+            .ok()?;
+
+        Some(Self { requests: request_tx, results: result_rx })
+    }
+}
+
+/// Wallpapers for every workspace
+pub struct Wallpapers {
+    global: Option<PathBuf>,
+
+    /// Per-workspace overrides, by workspace index
+    overrides: HashMap<usize, PathBuf>,
+
+    interval: Duration,
+
+    /// Crossfade length, scaled by `animations` - zero cuts straight over
+    crossfade: Duration,
+
+    /// Keyed by configured path - workspaces sharing a wallpaper share its slideshow
+    slideshows: HashMap<PathBuf, Slideshow>,
+
+    /// Started the first time something needs decoding
+    decoder: Option<Decoder>,
+}
+
+impl Wallpapers {
+    pub fn from_config(config: &Config) -> Self {
+        let overrides = config
+            .workspaces
+            .iter()
+            .enumerate()
+            .filter_map(|(index, workspace)| Some((index, workspace.wallpaper.clone()?)))
+            .collect();
+
+        Self {
+            global: config.wallpaper.clone(),
+            overrides,
+            interval: Duration::from_secs(config.wallpaper_interval_secs.max(1)),
+            crossfade: CROSSFADE,
+            slideshows: HashMap::new(),
+            decoder: None,
+        }
+    }
+
+    /// Wallpaper path for a workspace, None = just the background color
+    pub fn source_for(&self, workspace: usize) -> Option<&Path> {
+        self.overrides.get(&workspace).or(self.global.as_ref()).map(PathBuf::as_path)
+    }
+
+    /// Take finished decodes, rotate slideshows and load/release images
+    ///
+    /// `needed` is every workspace that's on screen or one switch away.
+    pub fn update(&mut self, needed: &HashSet<usize>, crossfade: Duration) {
+        let now = Instant::now();
+        self.crossfade = crossfade;
+
+        let wanted: HashSet<PathBuf> = needed
+            .iter()
+            .filter_map(|&workspace| self.source_for(workspace))
+            .map(Path::to_path_buf)
+            .collect();
+
+        if let Some(decoder) = &self.decoder {
+            for (file, result) in decoder.results.try_iter() {
+                let result = result.map(Arc::new);
+                for (source, show) in &mut self.slideshows {
+                    if show.pending.as_ref() != Some(&file) {
+                        continue;
+                    }
+                    show.pending = None;
+
+                    match &result {
+                        Ok(image) => {
+                            show.failures = 0;
+                            show.next_change = now + self.interval;
+                            // Fade from whatever was up, unless it's the first image
+                            show.fading_from = show.current
+                                .take()
+                                .filter(|_| !crossfade.is_zero())
+                                .map(|old| (old, now));
+                            show.current = Some(image.clone());
+                        }
+                        Err(e) => {
+                            // Skip it - the next update asks for the following file
+                            tracing::warn!("Skipping wallpaper {}: {}", file.display(), e);
+                            show.failures += 1;
+                            show.advance(source);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Slideshows nothing can see any more give their memory back
+        self.slideshows.retain(|source, show| {
+            if wanted.contains(source) {
+                return true;
+            }
+            show.release();
+            show.is_dir
+        });
+
+        let mut requests = Vec::new();
+        for source in &wanted {
+            let show = self.slideshows.entry(source.clone()).or_insert_with(|| Slideshow::new(source));
+
+            if show.fading_from.as_ref().is_some_and(|(_, since)| now.duration_since(*since) >= crossfade) {
+                show.fading_from = None;
+            }
+
+            if show.pending.is_some() || show.files.is_empty() || show.failures >= show.files.len() {
+                continue;
+            }
+
+            let rotate = show.is_dir && show.files.len() > 1 && show.current.is_some() && now >= show.next_change;
+            if rotate {
+                show.advance(source);
+            }
+
+            if show.current.is_none() || rotate {
+                let file = show.files[show.index].clone();
+                show.pending = Some(file.clone());
+                requests.push(file);
+            }
+        }
+
+        if requests.is_empty() {
+            return;
+        }
+        if self.decoder.is_none() {
+            self.decoder = Decoder::spawn();
+        }
+        let Some(decoder) = &self.decoder else { return };
+
+        for file in requests {
+            if decoder.requests.send(file).is_err() {
+                tracing::warn!("Wallpaper decoder went away - wallpapers stop updating");
+                self.decoder = None;
+                return;
+            }
+        }
+    }
+
+    /// Render data for a workspace's background, None = just the color
+    pub fn render(&self, workspace: usize, offset_x: i32) -> Option<WallpaperFrame> {
+        let show = self.slideshows.get(self.source_for(workspace)?)?;
+        let current = show.current.clone()?;
+
+        let mut layers = Vec::new();
+        let mut opacity = 1.0;
+        if let Some((old, since)) = &show.fading_from {
+            let t = (since.elapsed().as_secs_f32() / self.crossfade.as_secs_f32()).min(1.0);
+            layers.push(WallpaperLayer { image: old.clone(), opacity: 1.0 });
+            opacity = t;
+        }
+        layers.push(WallpaperLayer { image: current, opacity });
+
+        Some(WallpaperFrame { layers, offset_x })
+    }
+}
+
+impl VibeWM {
+    /// Keep the wallpapers on screen (and one switch away) loaded
+    pub fn update_wallpapers(&mut self) {
+        let mut needed = HashSet::new();
+
+        let active = self.windows.active_workspace();
+        needed.insert(active);
+        needed.insert(active + 1);
+        if active > 0 {
+            needed.insert(active - 1);
+        }
+
+        if let Some(slide) = &self.workspace_slide {
+            needed.insert(slide.from);
+            needed.extend(slide.to);
+        }
+
+        let crossfade = self.animation_duration(CROSSFADE);
+        self.wallpapers.update(&needed, crossfade);
+    }
+}
+
+/// Image files in a directory, sorted by name
+fn list_images(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        tracing::warn!("Can't read wallpaper directory {}", dir.display());
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Decode an image file to RGBA8 (runs on the decoder thread)
+fn decode(path: &Path) -> Result<WallpaperImage, String> {
+    let image = image::open(path).map_err(|e| e.to_string())?.into_rgba8();
+    let (width, height) = image.dimensions();
+
+    Ok(WallpaperImage {
+        path: path.to_path_buf(),
+        width,
+        height,
+        pixels: image.into_raw(),
+    })
+}
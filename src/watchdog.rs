@@ -0,0 +1,201 @@
+//! Stall watchdog - notices when the event loop stops coming round
+//!
+//! The main loop pings every iteration. A thread checks on it, and when no
+//! ping has come for `watchdog_timeout_ms` it writes what it knows to
+//! $XDG_STATE_HOME/vibewm/stall-<unix time>.txt: the last loop phase, the
+//! connected client count and when input last came in. With `watchdog_abort`
+//! it then aborts, so a wedged session leaves a core dump (every thread's
+//! backtrace) and systemd restarts it instead of sitting frozen.
+//!
+//! Like metrics.rs it's all plain atomics - pinging costs a relaxed store.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+use crate::schedule::Phase;
+
+/// How often the watchdog thread looks at the last ping
+const CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Last ping / input, ms since EPOCH + 1 (0 = never)
+static LAST_PING: AtomicU64 = AtomicU64::new(0);
+static LAST_INPUT: AtomicU64 = AtomicU64::new(0);
+
+/// Last phase started, and whether it finished - see phase_code
+static PHASE: AtomicU8 = AtomicU8::new(0);
+
+static CLIENTS: AtomicU64 = AtomicU64::new(0);
+
+/// Nesting depth of disarm() guards
+static DISARMED: AtomicU32 = AtomicU32::new(0);
+
+/// Timeout in ms, 0 = off
+static TIMEOUT_MS: AtomicU64 = AtomicU64::new(0);
+static ABORT: AtomicBool = AtomicBool::new(false);
+
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+static THREAD: OnceLock<()> = OnceLock::new();
+
+fn now_ms() -> u64 {
+    EPOCH.get_or_init(Instant::now).elapsed().as_millis() as u64 + 1
+}
+
+/// Pick up the watchdog settings, starting the thread the first time it's on
+pub fn configure(config: &Config) {
+    TIMEOUT_MS.store(config.watchdog_timeout_ms.unwrap_or(0), Ordering::Relaxed);
+    ABORT.store(config.watchdog_abort, Ordering::Relaxed);
+
+    if config.watchdog_timeout_ms.is_some_and(|ms| ms > 0) {
+        THREAD.get_or_init(|| {
+            let spawned = std::thread::Builder::new().name("watchdog".to_string()).spawn(watch);
+            if let Err(e) = spawned {
+                tracing::warn!("Stall watchdog disabled: {}", e);
+            }
+        });
+    }
+}
+
+/// How long the loop may stall before the watchdog reports it - None when it's off
+pub fn timeout() -> Option<Duration> {
+    let ms = TIMEOUT_MS.load(Ordering::Relaxed);
+    (ms > 0).then(|| Duration::from_millis(ms))
+}
+
+/// The loop came round - called once per iteration
+pub fn ping() {
+    LAST_PING.store(now_ms(), Ordering::Relaxed);
+}
+
+pub fn phase_started(phase: Phase) {
+    PHASE.store(phase_code(phase, false), Ordering::Relaxed);
+}
+
+pub fn phase_finished(phase: Phase) {
+    PHASE.store(phase_code(phase, true), Ordering::Relaxed);
+}
+
+pub fn input_seen() {
+    LAST_INPUT.store(now_ms(), Ordering::Relaxed);
+}
+
+pub fn client_connected() {
+    CLIENTS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn client_disconnected() {
+    CLIENTS.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Keeps the watchdog quiet while it's alive - for things that are meant to
+/// block the loop for a while, like mode-setting
+pub struct Disarmed(());
+
+pub fn disarm() -> Disarmed {
+    DISARMED.fetch_add(1, Ordering::Relaxed);
+    Disarmed(())
+}
+
+impl Drop for Disarmed {
+    fn drop(&mut self) {
+        // The clock starts over - the long operation doesn't count against the loop
+        ping();
+        DISARMED.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 0 = no phase yet, then two codes per phase: started, finished
+fn phase_code(phase: Phase, finished: bool) -> u8 {
+    let index = match phase {
+        Phase::Input => 0,
+        Phase::Dispatch => 1,
+        Phase::Render => 2,
+    };
+    1 + index * 2 + finished as u8
+}
+
+fn describe_phase(code: u8) -> String {
+    let Some(code) = code.checked_sub(1) else {
+        return "none yet".to_string();
+    };
+    let phase = ["input", "dispatch", "render"].get(code as usize / 2).unwrap_or(&"?");
+    let state = if code % 2 == 1 { "finished" } else { "in progress" };
+    format!("{} ({})", phase, state)
+}
+
+/// The watchdog thread - one dump per stall, then quiet until the loop pings again
+fn watch() {
+    let mut reported = 0;
+
+    loop {
+        std::thread::sleep(CHECK_INTERVAL);
+
+        let timeout = TIMEOUT_MS.load(Ordering::Relaxed);
+        let last_ping = LAST_PING.load(Ordering::Relaxed);
+
+        // Not started yet, turned off, or deliberately busy
+        if timeout == 0 || last_ping == 0 || DISARMED.load(Ordering::Relaxed) > 0 {
+            continue;
+        }
+
+        let stalled_ms = now_ms().saturating_sub(last_ping);
+        if stalled_ms < timeout || last_ping == reported {
+            continue;
+        }
+        reported = last_ping;
+
+        let dump = diagnostics(stalled_ms);
+        let phase = describe_phase(PHASE.load(Ordering::Relaxed));
+        tracing::error!("Event loop stalled for {}ms - last phase {}", stalled_ms, phase);
+        match write_dump(&dump) {
+            Ok(path) => tracing::error!("Stall diagnostics written to {}", path.display()),
+            Err(e) => tracing::error!("Couldn't write stall diagnostics: {}\n{}", e, dump),
+        }
+
+        if ABORT.load(Ordering::Relaxed) {
+            tracing::error!("watchdog_abort is on - aborting");
+            std::process::abort();
+        }
+    }
+}
+
+fn diagnostics(stalled_ms: u64) -> String {
+    let last_input = match LAST_INPUT.load(Ordering::Relaxed) {
+        0 => "none yet".to_string(),
+        at => format!("{}ms ago", now_ms().saturating_sub(at)),
+    };
+
+    format!(
+        "vibeWM event loop stall\n\
+         pid: {}\n\
+         stalled for: {}ms\n\
+         last phase: {}\n\
+         clients: {}\n\
+         last input: {}\n",
+        std::process::id(),
+        stalled_ms,
+        describe_phase(PHASE.load(Ordering::Relaxed)),
+        CLIENTS.load(Ordering::Relaxed),
+        last_input,
+    )
+}
+
+fn write_dump(dump: &str) -> std::io::Result<PathBuf> {
+    let dir = state_dir().ok_or_else(|| std::io::Error::other("no $XDG_STATE_HOME or $HOME"))?;
+    std::fs::create_dir_all(&dir)?;
+
+    let unix = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    let path = dir.join(format!("stall-{}.txt", unix));
+    std::fs::write(&path, dump)?;
+    Ok(path)
+}
+
+/// $XDG_STATE_HOME/vibewm
+fn state_dir() -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state_home.join("vibewm"))
+}
@@ -1,11 +1,20 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use smithay::{
-    desktop::Window,
-    utils::{IsAlive, Logical, Point, Rectangle},
+    backend::renderer::utils::with_renderer_surface_state,
+    desktop::{Space, Window},
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    utils::{IsAlive, Logical, Point, Rectangle, Size},
+    wayland::{
+        compositor::with_states,
+        shell::xdg::{SurfaceCachedState, XdgToplevelSurfaceData},
+    },
 };
 
 use crate::config::SnapPosition;
+use crate::layout::fit_to_limits;
+use crate::rules::RuleTrace;
 
 /// Manages window state and operations
 pub struct WindowManager {
@@ -20,8 +29,14 @@ pub struct WindowManager {
 
     /// Counter for window IDs
     next_id: u64,
+
+    /// Workspace currently on screen
+    active_workspace: usize,
 }
 
+/// Window ID, stashed in the window's user data so metadata can find it
+struct WindowId(u64);
+
 /// Metadata for each window
 #[derive(Debug, Clone)]
 pub struct WindowMeta {
@@ -30,8 +45,89 @@ pub struct WindowMeta {
     /// Position before snapping (for restore)
     pub pre_snap_geometry: Option<Rectangle<i32, Logical>>,
 
+    /// What we last laid it out at, fitted to its size limits - ahead of
+    /// the client's own geometry until it has drawn the new size
+    pub applied_geometry: Option<Rectangle<i32, Logical>>,
+
     /// Current snap state
     pub snap_state: Option<SnapPosition>,
+
+    /// Workspace this window lives on
+    pub workspace: usize,
+
+    /// Where the window sat when its workspace was hidden
+    pub saved_location: Option<Point<i32, Logical>>,
+
+    /// Client PID, from the socket credentials
+    pub pid: Option<u32>,
+
+    /// When the window last gained focus, for the focus ring flash
+    pub focused_at: Option<Instant>,
+
+    /// Compositor-side opacity, fading between focused and unfocused
+    pub opacity: OpacityFade,
+
+    /// Shown on every workspace - `workspace` follows the active one
+    pub sticky: bool,
+
+    /// Stacked above everything else (window menu)
+    pub always_on_top: bool,
+
+    /// Unmapped until something (a taskbar) restores it
+    pub minimized: bool,
+
+    /// Height of the compositor titlebar above the window, if it has one
+    pub titlebar: Option<i32>,
+
+    /// Rules checked against its current app_id - None until the first check
+    pub rules: Option<RuleTrace>,
+}
+
+/// An opacity easing from one value to another
+#[derive(Debug, Clone, Copy)]
+pub struct OpacityFade {
+    from: f32,
+    to: f32,
+    started: Instant,
+    duration: Duration,
+}
+
+impl OpacityFade {
+    /// Sitting still at `value`
+    pub fn at(value: f32) -> Self {
+        Self {
+            from: value,
+            to: value,
+            started: Instant::now(),
+            duration: Duration::ZERO,
+        }
+    }
+
+    /// Where it's heading
+    pub fn target(&self) -> f32 {
+        self.to
+    }
+
+    /// Start fading to `target` from wherever it is right now
+    pub fn fade_to(&mut self, target: f32, duration: Duration) {
+        *self = Self {
+            from: self.value(),
+            to: target,
+            started: Instant::now(),
+            duration,
+        };
+    }
+
+    /// Interpolated opacity for this frame (ease-out)
+    pub fn value(&self) -> f32 {
+        if self.duration.is_zero() {
+            return self.to;
+        }
+
+        let t = (self.started.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0);
+        let eased = 1.0 - (1.0 - t).powi(2);
+        self.from + (self.to - self.from) * eased
+    }
 }
 
 impl WindowManager {
@@ -41,44 +137,72 @@ impl WindowManager {
             focused: None,
             metadata: HashMap::new(),
             next_id: 0,
+            active_workspace: 0,
         }
     }
 
-    pub fn add(&mut self, window: Window) {
+    pub fn add(&mut self, window: Window, pid: Option<u32>) {
         let id = self.next_id;
         self.next_id += 1;
 
         self.metadata.insert(id, WindowMeta {
             id,
             pre_snap_geometry: None,
+            applied_geometry: None,
             snap_state: None,
+            workspace: self.active_workspace,
+            saved_location: None,
+            pid,
+            focused_at: None,
+            opacity: OpacityFade::at(1.0),
+            sticky: false,
+            always_on_top: false,
+            minimized: false,
+            titlebar: None,
+            rules: None,
         });
 
+        window.user_data().insert_if_missing(|| WindowId(id));
         self.windows.push(window);
+        #[cfg(feature = "metrics")]
+        crate::metrics::windows_changed(self.windows.len());
 
         // Focus the new window
-        self.focused = Some(self.windows.len() - 1);
+        self.set_focused(Some(self.windows.len() - 1));
     }
 
     pub fn remove(&mut self, window: &Window) {
         if let Some(pos) = self.windows.iter().position(|w| w == window) {
             self.windows.remove(pos);
+            #[cfg(feature = "metrics")]
+            crate::metrics::windows_changed(self.windows.len());
 
-            // Adjust focus
-            if let Some(focused) = self.focused {
-                if focused >= self.windows.len() {
-                    self.focused = if self.windows.is_empty() {
-                        None
-                    } else {
-                        Some(self.windows.len() - 1)
-                    };
-                } else if focused > pos {
-                    self.focused = Some(focused - 1);
+            if let Some(id) = window_id(window) {
+                self.metadata.remove(&id);
+            }
+
+            // Adjust focus - losing the focused one hands it to the last
+            // used window on screen, never one on a hidden workspace
+            match self.focused {
+                Some(focused) if focused == pos => {
+                    self.focused = None;
+                    self.focus_recent();
                 }
+                Some(focused) if focused > pos => self.focused = Some(focused - 1),
+                _ => {}
             }
         }
     }
 
+    /// Metadata for a managed window
+    pub fn meta(&self, window: &Window) -> Option<&WindowMeta> {
+        window_id(window).and_then(|id| self.metadata.get(&id))
+    }
+
+    pub fn meta_mut(&mut self, window: &Window) -> Option<&mut WindowMeta> {
+        window_id(window).and_then(|id| self.metadata.get_mut(&id))
+    }
+
     pub fn focused(&self) -> Option<&Window> {
         self.focused.and_then(|i| self.windows.get(i))
     }
@@ -88,31 +212,181 @@ impl WindowManager {
     }
 
     pub fn focus_next(&mut self) {
-        if self.windows.is_empty() {
+        let visible = self.visible_indices();
+        if visible.is_empty() {
             return;
         }
 
-        self.focused = Some(match self.focused {
-            Some(i) => (i + 1) % self.windows.len(),
-            None => 0,
-        });
+        let current = self.focused.and_then(|f| visible.iter().position(|&i| i == f));
+        self.set_focused(Some(match current {
+            Some(pos) => visible[(pos + 1) % visible.len()],
+            None => visible[0],
+        }));
     }
 
     pub fn focus_prev(&mut self) {
-        if self.windows.is_empty() {
+        let visible = self.visible_indices();
+        if visible.is_empty() {
             return;
         }
 
-        self.focused = Some(match self.focused {
-            Some(i) => {
-                if i == 0 {
-                    self.windows.len() - 1
-                } else {
-                    i - 1
-                }
+        let current = self.focused.and_then(|f| visible.iter().position(|&i| i == f));
+        self.set_focused(Some(match current {
+            Some(0) | None => visible[visible.len() - 1],
+            Some(pos) => visible[pos - 1],
+        }));
+    }
+
+    /// Focus a specific window
+    pub fn focus(&mut self, window: &Window) {
+        if let Some(index) = self.windows.iter().position(|w| w == window) {
+            self.set_focused(Some(index));
+        }
+    }
+
+    /// Nothing focused (click on empty space)
+    pub fn unfocus(&mut self) {
+        self.set_focused(None);
+    }
+
+    /// Look a window up by its id
+    pub fn by_id(&self, id: u64) -> Option<&Window> {
+        self.windows
+            .iter()
+            .find(|w| window_id(w) == Some(id))
+    }
+
+    /// Focus whichever window on the active workspace had focus last, so
+    /// switching back lands where you left off (topmost if none ever had it)
+    pub fn focus_recent(&mut self) {
+        let recent = self.visible_indices()
+            .into_iter()
+            .max_by_key(|&i| self.meta(&self.windows[i]).and_then(|m| m.focused_at));
+        self.set_focused(recent);
+    }
+
+    /// Move focus, stamping the newly focused window so its focus ring flashes
+    fn set_focused(&mut self, index: Option<usize>) {
+        if index == self.focused {
+            return;
+        }
+
+        self.focused = index;
+
+        if let Some(window) = index.and_then(|i| self.windows.get(i)).cloned() {
+            if let Some(meta) = self.meta_mut(&window) {
+                meta.focused_at = Some(Instant::now());
             }
-            None => 0,
-        });
+        }
+    }
+
+    pub fn active_workspace(&self) -> usize {
+        self.active_workspace
+    }
+
+    pub fn set_active_workspace(&mut self, index: usize) {
+        self.active_workspace = index;
+    }
+
+    /// Flip sticky on the focused window
+    ///
+    /// Sticky windows already live on the active workspace, so turning it
+    /// off just leaves the window where it is.
+    pub fn toggle_sticky_focused(&mut self) {
+        let Some(window) = self.focused().cloned() else {
+            return;
+        };
+        let active = self.active_workspace;
+        if let Some(meta) = self.meta_mut(&window) {
+            meta.sticky = !meta.sticky;
+            meta.workspace = active;
+            tracing::info!("Window {}", if meta.sticky { "sticky" } else { "unstuck" });
+        }
+    }
+
+    /// Move sticky windows onto a workspace (it's about to become active)
+    pub fn carry_sticky(&mut self, workspace: usize) {
+        for meta in self.metadata.values_mut().filter(|m| m.sticky) {
+            meta.workspace = workspace;
+        }
+    }
+
+    /// Put always-on-top windows back above the rest if something got raised over them
+    pub fn raise_always_on_top(&self, space: &mut Space<Window>) {
+        let on_top = |w: &Window| self.meta(w).is_some_and(|m| m.always_on_top);
+
+        let covered = space.elements().last().is_some_and(|top| !on_top(top));
+        if !covered {
+            return;
+        }
+
+        let raise: Vec<Window> = space.elements().filter(|w| on_top(w)).cloned().collect();
+        for window in raise {
+            space.raise_element(&window, false);
+        }
+    }
+
+    /// Hide a window until it's restored - it keeps its workspace and spot
+    pub fn minimize(&mut self, window: &Window, space: &mut Space<Window>) {
+        let location = space.element_location(window);
+        let Some(meta) = self.meta_mut(window).filter(|m| !m.minimized) else {
+            return;
+        };
+        meta.minimized = true;
+        meta.saved_location = location.or(meta.saved_location);
+        space.unmap_elem(window);
+
+        if self.focused() == Some(window) {
+            self.focus_recent();
+        }
+    }
+
+    /// Bring a minimized window back where it was (if its workspace is up)
+    pub fn unminimize(&mut self, window: &Window, space: &mut Space<Window>) {
+        let active = self.active_workspace;
+        let Some(meta) = self.meta_mut(window).filter(|m| m.minimized) else {
+            return;
+        };
+        meta.minimized = false;
+
+        if meta.workspace == active {
+            space.map_element(window.clone(), meta.saved_location.unwrap_or_default(), false);
+        }
+    }
+
+    /// Height of the window's compositor titlebar, 0 without one
+    pub fn titlebar_height(&self, window: &Window) -> i32 {
+        self.meta(window).and_then(|m| m.titlebar).unwrap_or(0)
+    }
+
+    /// Where the window sits on screen, titlebar included
+    pub fn frame_geometry(&self, space: &Space<Window>, window: &Window) -> Option<Rectangle<i32, Logical>> {
+        let mut geometry = space.element_geometry(window)?;
+        let titlebar = self.titlebar_height(window);
+        geometry.loc.y -= titlebar;
+        geometry.size.h += titlebar;
+        Some(geometry)
+    }
+
+    /// Windows on a workspace, in stacking order
+    pub fn on_workspace(&self, workspace: usize) -> Vec<Window> {
+        self.windows
+            .iter()
+            .filter(|w| self.meta(w).map(|m| m.workspace) == Some(workspace))
+            .cloned()
+            .collect()
+    }
+
+    /// Indices of windows on the active workspace
+    fn visible_indices(&self) -> Vec<usize> {
+        self.windows
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| {
+                self.meta(w).is_some_and(|m| m.workspace == self.active_workspace && !m.minimized)
+            })
+            .map(|(i, _)| i)
+            .collect()
     }
 
     pub fn raise_focused(&mut self) {
@@ -127,16 +401,114 @@ impl WindowManager {
 
     pub fn cleanup_closed(&mut self) {
         // Remove any windows that are no longer alive
-        self.windows.retain(|w| w.alive());
-
-        // Adjust focus if needed
-        if let Some(focused) = self.focused {
-            if focused >= self.windows.len() {
-                self.focused = if self.windows.is_empty() {
-                    None
-                } else {
-                    Some(self.windows.len() - 1)
-                };
+        let focused = self.focused().cloned();
+        let metadata = &mut self.metadata;
+        self.windows.retain(|w| {
+            let alive = w.alive();
+            if !alive {
+                if let Some(id) = window_id(w) {
+                    metadata.remove(&id);
+                }
+            }
+            alive
+        });
+        #[cfg(feature = "metrics")]
+        crate::metrics::windows_changed(self.windows.len());
+
+        // Indices shifted - find the focused window again, or pick the
+        // last used one on screen if it's gone
+        match refocus_after_cleanup(focused.as_ref(), &self.windows) {
+            Refocus::Keep(index) => self.focused = index,
+            Refocus::Recent => self.focus_recent(),
+        }
+    }
+
+    /// Swap the focused window with the largest one on screen
+    ///
+    /// The focused window takes over the biggest spot, the old "master" gets
+    /// the focused window's old geometry.
+    pub fn promote_focused(&mut self, space: &mut Space<Window>) {
+        let Some(focused) = self.focused().cloned() else {
+            return;
+        };
+
+        let largest = self.windows
+            .iter()
+            .filter_map(|w| self.frame_geometry(space, w).map(|geo| (w.clone(), geo)))
+            .max_by_key(|(_, geo)| geo.size.w * geo.size.h);
+
+        let Some((largest, largest_geo)) = largest else {
+            return;
+        };
+
+        if largest == focused {
+            return;
+        }
+
+        let Some(focused_geo) = self.frame_geometry(space, &focused) else {
+            return;
+        };
+
+        // Swapped windows float, so they get their decorations back
+        for window in [&focused, &largest] {
+            set_layout_state(window, TiledEdges::NONE, false, None);
+        }
+
+        let applied = [
+            place_window(space, &focused, largest_geo, self.titlebar_height(&focused)),
+            place_window(space, &largest, focused_geo, self.titlebar_height(&largest)),
+        ];
+
+        // Swapped windows are floating now, whatever they were snapped to
+        for (window, applied) in [&focused, &largest].into_iter().zip(applied) {
+            if let Some(meta) = self.meta_mut(window) {
+                meta.snap_state = None;
+                meta.applied_geometry = Some(applied);
+            }
+        }
+    }
+
+    /// Tile every window on the active workspace into an even grid inside the work area
+    ///
+    /// One-shot - the windows float freely again afterwards. Rows are filled
+    /// left to right, and a short last row stretches to use the full width.
+    pub fn balance(&mut self, space: &mut Space<Window>, work_area: Rectangle<i32, Logical>, gaps: i32) {
+        let visible: Vec<Window> = self.on_workspace(self.active_workspace);
+        let count = visible.len() as i32;
+        if count == 0 {
+            return;
+        }
+
+        // As square as possible: columns first, rows to fit
+        let columns = (count as f32).sqrt().ceil() as i32;
+        let rows = (count + columns - 1) / columns;
+
+        let cell_h = (work_area.size.h - gaps * (rows - 1)) / rows;
+
+        for (i, window) in visible.iter().enumerate() {
+            let i = i as i32;
+            let row = i / columns;
+            let col = i % columns;
+
+            // Windows in this row - the last one may be short
+            let in_row = (count - row * columns).min(columns);
+            let cell_w = (work_area.size.w - gaps * (in_row - 1)) / in_row;
+
+            let rect = Rectangle::new(
+                (
+                    work_area.loc.x + col * (cell_w + gaps),
+                    work_area.loc.y + row * (cell_h + gaps),
+                ).into(),
+                (cell_w, cell_h).into(),
+            );
+
+            // Every cell butts up against a neighbor or the work area edge
+            set_layout_state(window, TiledEdges::ALL, false, Some(work_area.size));
+            let applied = place_window(space, window, rect, self.titlebar_height(window));
+
+            if let Some(meta) = self.meta_mut(window) {
+                meta.snap_state = None;
+                meta.applied_geometry = Some(applied);
             }
         }
     }
@@ -154,6 +526,194 @@ impl WindowManager {
     }
 }
 
+/// What cleanup_closed does with focus
+#[derive(Debug, PartialEq)]
+enum Refocus {
+    /// Same as before, at its new index - or still nothing
+    Keep(Option<usize>),
+
+    /// The focused window closed - hand focus on
+    Recent,
+}
+
+/// Focus only moves on if the focused window is the one that went away -
+/// a click on empty space unfocused on purpose, closing something in the
+/// background mustn't undo that
+fn refocus_after_cleanup<W: PartialEq>(focused: Option<&W>, remaining: &[W]) -> Refocus {
+    match focused {
+        None => Refocus::Keep(None),
+        Some(focused) => match remaining.iter().position(|w| w == focused) {
+            Some(index) => Refocus::Keep(Some(index)),
+            None => Refocus::Recent,
+        },
+    }
+}
+
+/// Move a window to a rectangle and ask the client to resize to match
+///
+/// A compositor titlebar takes `titlebar` px off the top of the rectangle.
+/// A client whose min/max size can't fill the rest is centered on it at the
+/// nearest size it takes. Returns where the window itself ended up.
+pub fn place_window(
+    space: &mut Space<Window>,
+    window: &Window,
+    mut rect: Rectangle<i32, Logical>,
+    titlebar: i32,
+) -> Rectangle<i32, Logical> {
+    rect.loc.y += titlebar;
+    rect.size.h -= titlebar;
+
+    let (min, max) = size_limits(window);
+    let rect = fit_to_limits(rect, min, max);
+
+    space.map_element(window.clone(), rect.loc, false);
+
+    if let Some(toplevel) = window.toplevel() {
+        toplevel.with_pending_state(|state| {
+            state.size = Some(rect.size);
+        });
+        toplevel.send_pending_configure();
+    }
+    rect
+}
+
+/// The title the client set, empty if none
+pub fn window_title(window: &Window) -> String {
+    let Some(toplevel) = window.toplevel() else {
+        return String::new();
+    };
+
+    with_states(toplevel.wl_surface(), |states| {
+        states
+            .data_map
+            .get::<XdgToplevelSurfaceData>()
+            .and_then(|data| data.lock().ok()?.title.clone())
+            .unwrap_or_default()
+    })
+}
+
+/// The app_id the client set, if it has yet
+pub fn window_app_id(window: &Window) -> Option<String> {
+    let toplevel = window.toplevel()?;
+
+    with_states(toplevel.wl_surface(), |states| {
+        states
+            .data_map
+            .get::<XdgToplevelSurfaceData>()
+            .and_then(|data| data.lock().ok()?.app_id.clone())
+    })
+}
+
+/// The client's (min, max) size - 0 on an axis means no limit
+pub fn size_limits(window: &Window) -> (Size<i32, Logical>, Size<i32, Logical>) {
+    let Some(toplevel) = window.toplevel() else {
+        return (Size::default(), Size::default());
+    };
+
+    with_states(toplevel.wl_surface(), |states| {
+        let mut cached = states.cached_state.get::<SurfaceCachedState>();
+        let current = cached.current();
+        (current.min_size, current.max_size)
+    })
+}
+
+/// Has this toplevel had its first configure yet?
+pub fn initial_configure_sent(window: &Window) -> bool {
+    let Some(toplevel) = window.toplevel() else {
+        return true;
+    };
+
+    with_states(toplevel.wl_surface(), |states| {
+        states
+            .data_map
+            .get::<XdgToplevelSurfaceData>()
+            .and_then(|data| Some(data.lock().ok()?.initial_configure_sent))
+            .unwrap_or(true)
+    })
+}
+
+/// Has this toplevel committed a buffer - drawn anything at all?
+pub fn has_buffer(window: &Window) -> bool {
+    let Some(toplevel) = window.toplevel() else {
+        return false;
+    };
+    with_renderer_surface_state(toplevel.wl_surface(), |state| state.buffer().is_some()).unwrap_or(false)
+}
+
+/// Which edges of a window sit against a neighbor or the screen edge
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TiledEdges {
+    pub left: bool,
+    pub right: bool,
+    pub top: bool,
+    pub bottom: bool,
+}
+
+impl TiledEdges {
+    pub const NONE: Self = Self { left: false, right: false, top: false, bottom: false };
+    pub const ALL: Self = Self { left: true, right: true, top: true, bottom: true };
+
+    /// Edges a snap position pins down
+    pub fn for_snap(position: SnapPosition) -> Self {
+        let (left, right, top, bottom) = match position {
+            SnapPosition::Left => (true, false, true, true),
+            SnapPosition::Right => (false, true, true, true),
+            SnapPosition::Top => (true, true, true, false),
+            SnapPosition::Bottom => (true, true, false, true),
+            SnapPosition::TopLeft => (true, false, true, false),
+            SnapPosition::TopRight => (false, true, true, false),
+            SnapPosition::BottomLeft => (true, false, false, true),
+            SnapPosition::BottomRight => (false, true, false, true),
+            SnapPosition::Maximize => (true, true, true, true),
+            SnapPosition::Center => (false, false, false, false),
+        };
+
+        Self { left, right, top, bottom }
+    }
+}
+
+/// Tell the client how it's laid out - tiled edges, maximized, and the bounds to fit in
+///
+/// Tiled clients drop their CSD shadows and rounded corners, which would
+/// otherwise eat into our gaps. Only sets pending state - place_window (or
+/// send_pending_configure) sends it.
+pub fn set_layout_state(
+    window: &Window,
+    edges: TiledEdges,
+    maximized: bool,
+    bounds: Option<Size<i32, Logical>>,
+) {
+    let Some(toplevel) = window.toplevel() else {
+        return;
+    };
+
+    toplevel.with_pending_state(|state| {
+        for (on, flag) in [
+            (edges.left, xdg_toplevel::State::TiledLeft),
+            (edges.right, xdg_toplevel::State::TiledRight),
+            (edges.top, xdg_toplevel::State::TiledTop),
+            (edges.bottom, xdg_toplevel::State::TiledBottom),
+            (maximized, xdg_toplevel::State::Maximized),
+        ] {
+            if on {
+                state.states.set(flag);
+            } else {
+                state.states.unset(flag);
+            }
+        }
+
+        // None leaves the last bounds alone
+        if bounds.is_some() {
+            state.bounds = bounds;
+        }
+    });
+}
+
+/// Look up the ID vibeWM assigned to a window
+fn window_id(window: &Window) -> Option<u64> {
+    window.user_data().get::<WindowId>().map(|id| id.0)
+}
+
 /// Direction for window operations
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Direction {
@@ -184,3 +744,27 @@ impl Direction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Window needs a live client, so these run the same rule over stand-ins
+
+    #[test]
+    fn click_on_empty_space_survives_cleanup() {
+        // Unfocused by the click, then a background window closes
+        let remaining = ["editor", "terminal"];
+        assert_eq!(refocus_after_cleanup(None, &remaining), Refocus::Keep(None));
+
+        // ...or nothing closed at all
+        assert_eq!(refocus_after_cleanup::<&str>(None, &[]), Refocus::Keep(None));
+    }
+
+    #[test]
+    fn focus_follows_its_window_and_moves_on_when_it_closes() {
+        let remaining = ["editor", "terminal"];
+        assert_eq!(refocus_after_cleanup(Some(&"terminal"), &remaining), Refocus::Keep(Some(1)));
+        assert_eq!(refocus_after_cleanup(Some(&"browser"), &remaining), Refocus::Recent);
+    }
+}
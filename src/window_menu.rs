@@ -0,0 +1,473 @@
+//! Window menu - right-click a titlebar, or mod+Menu
+//!
+//! GTK headerbars ask for it with xdg_toplevel.show_window_menu and expect
+//! the compositor to draw it. While it's up it's a grab, like the region
+//! screenshot: clients see no keys or clicks. Move and Resize hand the
+//! pointer to the window until the next click.
+
+use smithay::desktop::Window;
+use smithay::input::keyboard::Keysym;
+use smithay::utils::{IsAlive, Logical, Point, Rectangle, Size};
+
+use crate::command_center::CommandCenterTheme;
+use crate::config::SnapPosition;
+use crate::render_command_center::{with_alpha, FontWeight, RenderQuad, TextRender};
+use crate::state::VibeWM;
+
+const ITEM_WIDTH: i32 = 200;
+const ITEM_HEIGHT: i32 = 32;
+const PADDING: i32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuItem {
+    Move,
+    Resize,
+    /// Opens the snap submenu
+    Snap,
+    /// Float a snapped window, snap a floating one
+    ToggleFloat,
+    AlwaysOnTop,
+    Close,
+}
+
+const ITEMS: [MenuItem; 6] = [
+    MenuItem::Move,
+    MenuItem::Resize,
+    MenuItem::Snap,
+    MenuItem::ToggleFloat,
+    MenuItem::AlwaysOnTop,
+    MenuItem::Close,
+];
+
+const SNAP_ITEMS: [(SnapPosition, &str); 10] = [
+    (SnapPosition::Left, "Left half"),
+    (SnapPosition::Right, "Right half"),
+    (SnapPosition::Top, "Top half"),
+    (SnapPosition::Bottom, "Bottom half"),
+    (SnapPosition::TopLeft, "Top left"),
+    (SnapPosition::TopRight, "Top right"),
+    (SnapPosition::BottomLeft, "Bottom left"),
+    (SnapPosition::BottomRight, "Bottom right"),
+    (SnapPosition::Maximize, "Maximize"),
+    (SnapPosition::Center, "Center"),
+];
+
+/// What the menu grab is doing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuMode {
+    /// Picking an entry - `submenu` is the highlighted snap position while it's open
+    Open { selected: usize, submenu: Option<usize> },
+
+    /// The window follows the pointer until a click
+    Moving { grab_offset: Point<i32, Logical> },
+
+    /// The bottom-right corner follows the pointer until a click
+    Resizing { start_pointer: Point<f64, Logical>, start_size: Size<i32, Logical> },
+}
+
+/// Where a point landed on the menu
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuHit {
+    Item(usize),
+    SnapItem(usize),
+}
+
+/// An open window menu
+pub struct WindowMenu {
+    pub window: Window,
+
+    /// Top-left corner, global
+    pub location: Point<i32, Logical>,
+
+    pub mode: MenuMode,
+}
+
+/// Render data for the menu
+pub struct WindowMenuFrame {
+    pub background: RenderQuad,
+    pub rows: Vec<MenuRowRender>,
+
+    /// Snap positions, beside the Snap entry
+    pub submenu: Option<(RenderQuad, Vec<MenuRowRender>)>,
+}
+
+pub struct MenuRowRender {
+    /// Highlight behind the selected row
+    pub highlight: Option<RenderQuad>,
+    pub label: TextRender,
+
+    /// ▸ on Snap, ✓ on Always on Top when it's on
+    pub hint: Option<TextRender>,
+}
+
+impl WindowMenu {
+    fn new(window: Window, location: Point<i32, Logical>) -> Self {
+        Self {
+            window,
+            location,
+            mode: MenuMode::Open { selected: 0, submenu: None },
+        }
+    }
+
+    pub fn size() -> Size<i32, Logical> {
+        (ITEM_WIDTH + PADDING * 2, ITEMS.len() as i32 * ITEM_HEIGHT + PADDING * 2).into()
+    }
+
+    fn submenu_size() -> Size<i32, Logical> {
+        (ITEM_WIDTH + PADDING * 2, SNAP_ITEMS.len() as i32 * ITEM_HEIGHT + PADDING * 2).into()
+    }
+
+    fn item_rect(&self, index: usize) -> Rectangle<i32, Logical> {
+        Rectangle::new(
+            (self.location.x + PADDING, self.location.y + PADDING + index as i32 * ITEM_HEIGHT).into(),
+            (ITEM_WIDTH, ITEM_HEIGHT).into(),
+        )
+    }
+
+    /// Top-left of the snap submenu - level with the Snap entry, to the right
+    fn submenu_location(&self) -> Point<i32, Logical> {
+        let snap = ITEMS.iter().position(|&item| item == MenuItem::Snap).unwrap_or(0);
+        let row = self.item_rect(snap);
+        (self.location.x + Self::size().w, row.loc.y - PADDING).into()
+    }
+
+    fn snap_item_rect(&self, index: usize) -> Rectangle<i32, Logical> {
+        let origin = self.submenu_location();
+        Rectangle::new(
+            (origin.x + PADDING, origin.y + PADDING + index as i32 * ITEM_HEIGHT).into(),
+            (ITEM_WIDTH, ITEM_HEIGHT).into(),
+        )
+    }
+
+    fn submenu_open(&self) -> bool {
+        matches!(self.mode, MenuMode::Open { submenu: Some(_), .. })
+    }
+
+    /// Which row is under a point (submenu rows only count while it's open)
+    pub fn hit(&self, pos: Point<f64, Logical>) -> Option<MenuHit> {
+        let pos = pos.to_i32_floor();
+
+        if self.submenu_open() {
+            if let Some(index) = (0..SNAP_ITEMS.len()).find(|&i| self.snap_item_rect(i).contains(pos)) {
+                return Some(MenuHit::SnapItem(index));
+            }
+        }
+        (0..ITEMS.len())
+            .find(|&i| self.item_rect(i).contains(pos))
+            .map(MenuHit::Item)
+    }
+
+    /// Is a point anywhere on the menu (padding included)?
+    pub fn contains(&self, pos: Point<f64, Logical>) -> bool {
+        let pos = pos.to_i32_floor();
+        let menu = Rectangle::new(self.location, Self::size());
+        let submenu = Rectangle::new(self.submenu_location(), Self::submenu_size());
+        menu.contains(pos) || (self.submenu_open() && submenu.contains(pos))
+    }
+
+    /// Render data, in output-local coordinates
+    pub fn render(
+        &self,
+        output_origin: Point<i32, Logical>,
+        snapped: bool,
+        on_top: bool,
+        theme: &CommandCenterTheme,
+    ) -> Option<WindowMenuFrame> {
+        let MenuMode::Open { selected, submenu } = self.mode else {
+            return None;
+        };
+
+        let local = |rect: Rectangle<i32, Logical>| {
+            let loc = rect.loc - output_origin;
+            (loc.x as f32, loc.y as f32, rect.size.w as f32, rect.size.h as f32)
+        };
+        let panel = |rect: Rectangle<i32, Logical>| {
+            let (x, y, width, height) = local(rect);
+            RenderQuad { x, y, width, height, color: theme.bg_color, corner_radius: 10.0 }
+        };
+        let row = |rect: Rectangle<i32, Logical>, label: &str, hint: Option<&str>, highlighted: bool| {
+            let (x, y, width, height) = local(rect);
+            MenuRowRender {
+                highlight: highlighted.then(|| RenderQuad {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color: theme.card_selected,
+                    corner_radius: 6.0,
+                }),
+                label: TextRender {
+                    x: x + 12.0,
+                    y: y + height / 2.0,
+                    text: label.to_string(),
+                    color: theme.text_primary,
+                    size: 14.0,
+                    font_weight: FontWeight::Regular,
+                },
+                hint: hint.map(|hint| TextRender {
+                    x: x + width - 24.0,
+                    y: y + height / 2.0,
+                    text: hint.to_string(),
+                    color: with_alpha(theme.text_secondary, 0.8),
+                    size: 14.0,
+                    font_weight: FontWeight::Regular,
+                }),
+            }
+        };
+
+        let rows = ITEMS
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let (label, hint) = match item {
+                    MenuItem::Move => ("Move", None),
+                    MenuItem::Resize => ("Resize", None),
+                    MenuItem::Snap => ("Snap", Some("▸")),
+                    MenuItem::ToggleFloat if snapped => ("Float", None),
+                    MenuItem::ToggleFloat => ("Tile", None),
+                    MenuItem::AlwaysOnTop => ("Always on Top", on_top.then_some("✓")),
+                    MenuItem::Close => ("Close", None),
+                };
+                row(self.item_rect(i), label, hint, i == selected)
+            })
+            .collect();
+
+        let submenu = submenu.map(|highlighted| {
+            let background = panel(Rectangle::new(self.submenu_location(), Self::submenu_size()));
+            let rows = SNAP_ITEMS
+                .iter()
+                .enumerate()
+                .map(|(i, (_, label))| row(self.snap_item_rect(i), label, None, i == highlighted))
+                .collect();
+            (background, rows)
+        });
+
+        Some(WindowMenuFrame {
+            background: panel(Rectangle::new(self.location, Self::size())),
+            rows,
+            submenu,
+        })
+    }
+}
+
+impl VibeWM {
+    /// Open the window menu at a global position, kept on the output it's on
+    pub fn open_window_menu(&mut self, window: &Window, location: Point<i32, Logical>) {
+        let output_rect = self.space
+            .output_under(location.to_f64())
+            .next()
+            .or(self.output.as_ref())
+            .and_then(|o| self.space.output_geometry(o));
+
+        let size = WindowMenu::size();
+        let location = match output_rect {
+            Some(rect) => Point::from((
+                location.x.clamp(rect.loc.x, (rect.loc.x + rect.size.w - size.w).max(rect.loc.x)),
+                location.y.clamp(rect.loc.y, (rect.loc.y + rect.size.h - size.h).max(rect.loc.y)),
+            )),
+            None => location,
+        };
+
+        self.windows.focus(window);
+        self.window_menu = Some(WindowMenu::new(window.clone(), location));
+    }
+
+    /// mod+Menu - the focused window's menu at its top-left corner
+    pub fn open_focused_window_menu(&mut self) {
+        let Some(window) = self.windows.focused().cloned() else {
+            return;
+        };
+        let Some(location) = self.space.element_location(&window) else {
+            return;
+        };
+        self.open_window_menu(&window, location + Point::from((PADDING * 4, PADDING * 4)));
+    }
+
+    pub fn close_window_menu(&mut self) {
+        self.window_menu = None;
+    }
+
+    /// Drop the menu if its window went away
+    pub fn update_window_menu(&mut self) {
+        if self.window_menu.as_ref().is_some_and(|menu| !menu.window.alive()) {
+            self.window_menu = None;
+        }
+    }
+
+    /// Keys while the menu is up - all of them are ours
+    pub fn window_menu_key(&mut self, keysym: Keysym) {
+        let Some(menu) = self.window_menu.as_mut() else {
+            return;
+        };
+
+        let MenuMode::Open { selected, submenu } = menu.mode else {
+            // Moving/resizing: Enter or Escape puts it down where it is
+            if matches!(keysym, Keysym::Escape | Keysym::Return | Keysym::KP_Enter) {
+                self.close_window_menu();
+            }
+            return;
+        };
+
+        match (keysym, submenu) {
+            (Keysym::Escape | Keysym::Left, Some(_)) => {
+                menu.mode = MenuMode::Open { selected, submenu: None };
+            }
+            (Keysym::Escape, None) => self.close_window_menu(),
+            (Keysym::Up, Some(index)) => {
+                let index = (index + SNAP_ITEMS.len() - 1) % SNAP_ITEMS.len();
+                menu.mode = MenuMode::Open { selected, submenu: Some(index) };
+            }
+            (Keysym::Down, Some(index)) => {
+                let index = (index + 1) % SNAP_ITEMS.len();
+                menu.mode = MenuMode::Open { selected, submenu: Some(index) };
+            }
+            (Keysym::Up, None) => {
+                let selected = (selected + ITEMS.len() - 1) % ITEMS.len();
+                menu.mode = MenuMode::Open { selected, submenu: None };
+            }
+            (Keysym::Down, None) => {
+                let selected = (selected + 1) % ITEMS.len();
+                menu.mode = MenuMode::Open { selected, submenu: None };
+            }
+            (Keysym::Return | Keysym::KP_Enter | Keysym::Right, Some(index)) => self.activate_snap_item(index),
+            (Keysym::Right, None) if ITEMS[selected] == MenuItem::Snap => self.activate_menu_item(selected),
+            (Keysym::Return | Keysym::KP_Enter, None) => self.activate_menu_item(selected),
+            _ => {}
+        }
+    }
+
+    /// Pointer moved - hover highlights, or drag the window along
+    pub fn window_menu_motion(&mut self) {
+        let pointer = self.input.pointer_pos;
+        let Some(menu) = self.window_menu.as_mut() else {
+            return;
+        };
+        let window = menu.window.clone();
+
+        match menu.mode {
+            MenuMode::Open { selected, submenu } => {
+                menu.mode = match menu.hit(pointer) {
+                    // Hovering Snap opens its submenu
+                    Some(MenuHit::Item(index)) if ITEMS[index] == MenuItem::Snap => MenuMode::Open {
+                        selected: index,
+                        submenu: Some(submenu.unwrap_or(0)),
+                    },
+                    Some(MenuHit::Item(index)) => MenuMode::Open { selected: index, submenu: None },
+                    Some(MenuHit::SnapItem(index)) => MenuMode::Open { selected, submenu: Some(index) },
+                    None => MenuMode::Open { selected, submenu },
+                };
+            }
+            MenuMode::Moving { grab_offset } => {
+                self.move_window(&window, pointer.to_i32_round() - grab_offset);
+            }
+            MenuMode::Resizing { start_pointer, start_size } => {
+                let delta = (pointer - start_pointer).to_i32_round();
+                self.resize_window(&window, (start_size.w + delta.x, start_size.h + delta.y).into());
+            }
+        }
+    }
+
+    /// Pointer button while the menu is up - releases don't do anything
+    pub fn window_menu_button(&mut self, pressed: bool) {
+        if !pressed {
+            return;
+        }
+        let Some(menu) = self.window_menu.as_ref() else {
+            return;
+        };
+
+        if !matches!(menu.mode, MenuMode::Open { .. }) {
+            // Click puts a moving/resizing window down
+            self.close_window_menu();
+            return;
+        }
+
+        match menu.hit(self.input.pointer_pos) {
+            Some(MenuHit::Item(index)) => self.activate_menu_item(index),
+            Some(MenuHit::SnapItem(index)) => self.activate_snap_item(index),
+            // Padding isn't outside
+            None if menu.contains(self.input.pointer_pos) => {}
+            None => self.close_window_menu(),
+        }
+    }
+
+    fn activate_menu_item(&mut self, index: usize) {
+        let pointer = self.input.pointer_pos;
+        let Some(menu) = self.window_menu.as_mut() else {
+            return;
+        };
+        let window = menu.window.clone();
+
+        match ITEMS[index] {
+            MenuItem::Move => {
+                let location = self.space.element_location(&window).unwrap_or_default();
+                menu.mode = MenuMode::Moving { grab_offset: pointer.to_i32_round() - location };
+                return;
+            }
+            MenuItem::Resize => {
+                menu.mode = MenuMode::Resizing {
+                    start_pointer: pointer,
+                    start_size: window.geometry().size,
+                };
+                return;
+            }
+            MenuItem::Snap => {
+                menu.mode = MenuMode::Open { selected: index, submenu: Some(0) };
+                return;
+            }
+            MenuItem::ToggleFloat => self.toggle_float(&window),
+            MenuItem::AlwaysOnTop => {
+                if let Some(meta) = self.windows.meta_mut(&window) {
+                    meta.always_on_top = !meta.always_on_top;
+                }
+            }
+            MenuItem::Close => {
+                if let Some(toplevel) = window.toplevel() {
+                    toplevel.send_close();
+                }
+            }
+        }
+
+        self.close_window_menu();
+    }
+
+    fn activate_snap_item(&mut self, index: usize) {
+        let Some(menu) = self.window_menu.take() else {
+            return;
+        };
+        self.snap_window(&menu.window, SNAP_ITEMS[index].0);
+    }
+
+    /// Snapped windows go back to where they were, floating ones snap to
+    /// the half their center is in
+    pub fn toggle_float(&mut self, window: &Window) {
+        let (snapped, restore) = self.windows
+            .meta(window)
+            .map(|m| (m.snap_state.is_some(), m.pre_snap_geometry))
+            .unwrap_or_default();
+
+        if snapped {
+            if let Some(rect) = restore {
+                self.move_window(window, rect.loc);
+                self.resize_window(window, rect.size);
+            } else if let Some(meta) = self.windows.meta_mut(window) {
+                meta.snap_state = None;
+            }
+            return;
+        }
+
+        let Some(geometry) = self.space.element_geometry(window) else {
+            return;
+        };
+        let center = geometry.loc.x + geometry.size.w / 2;
+        let output_center = self.space
+            .output_under(geometry.loc.to_f64())
+            .next()
+            .and_then(|o| self.space.output_geometry(o))
+            .map(|rect| rect.loc.x + rect.size.w / 2)
+            .unwrap_or(center);
+
+        let position = if center < output_center { SnapPosition::Left } else { SnapPosition::Right };
+        self.snap_window(window, position);
+    }
+}
@@ -0,0 +1,497 @@
+//! Workspaces for vibeWM
+//!
+//! Windows belong to a workspace (tracked in `WindowMeta`), this module owns
+//! the names and output assignments. Declared names come from config, anything
+//! past the end of the list is created on demand with a numeric name.
+
+use std::time::{Duration, Instant};
+
+use smithay::desktop::Window;
+use smithay::output::Output;
+
+use crate::config::WorkspaceConfig;
+use crate::launch;
+use crate::procstat;
+use crate::state::VibeWM;
+use crate::window::Direction;
+
+/// A single workspace
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub name: String,
+
+    /// Output (connector name) this workspace lives on, None = wherever is active
+    pub output: Option<String>,
+}
+
+/// How long a slide takes to settle (and a keyboard switch takes overall)
+const SLIDE_DURATION: Duration = Duration::from_millis(250);
+
+/// Past this much of the output width, letting go commits the switch
+const SWIPE_COMMIT_PROGRESS: f64 = 0.3;
+
+/// A flick faster than this (output widths/s) commits regardless of distance
+const SWIPE_COMMIT_VELOCITY: f64 = 1.5;
+
+/// How long a launched app has to open its window before we stop waiting for it
+const PLACEMENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An app launched onto a workspace whose window hasn't shown up yet
+#[derive(Debug, Clone)]
+pub struct PendingPlacement {
+    /// PID we spawned (may be a wrapper around the real client)
+    pub pid: u32,
+
+    /// Expected app_id - the desktop file name, best effort
+    pub app_id: Option<String>,
+
+    pub workspace: usize,
+    pub launched_at: Instant,
+}
+
+/// Horizontal slide between two workspaces - follows the fingers during a
+/// swipe, then eases to rest. Keyboard switches play the same slide.
+///
+/// Scene offsets: `from` draws at `-direction * progress * width`, `to` at
+/// `direction * (1 - progress) * width`.
+#[derive(Debug, Clone)]
+pub struct WorkspaceSlide {
+    pub from: usize,
+
+    /// Neighbor being revealed (None while the fingers haven't picked a side)
+    pub to: Option<usize>,
+
+    /// +1 = `to` comes in from the right, -1 = from the left
+    pub direction: i32,
+
+    /// 0 = `from` fully shown, 1 = `to` fully shown
+    pub progress: f64,
+
+    /// Fingers on the touchpad
+    pub tracking: bool,
+
+    /// Total finger travel (px), and how fast it's going (widths/s)
+    swipe_dx: f64,
+    velocity: f64,
+    last_update: Instant,
+
+    /// Easing toward 0 (spring back) or 1 (commit), from (progress, when)
+    settle: Option<(f64, f64, Instant)>,
+
+    /// Keyboard switches swap workspaces up front, swipes only on commit
+    switched: bool,
+}
+
+impl WorkspaceSlide {
+    /// Offset (px) to draw a workspace at, None if it's not part of the slide
+    pub fn offset_for(&self, workspace: usize, width: i32) -> Option<i32> {
+        let width = width as f64;
+        let direction = self.direction as f64;
+
+        if workspace == self.from {
+            Some((-direction * self.progress * width) as i32)
+        } else if Some(workspace) == self.to {
+            Some((direction * (1.0 - self.progress) * width) as i32)
+        } else {
+            None
+        }
+    }
+
+    fn settle_to(&mut self, target: f64) {
+        self.tracking = false;
+        self.settle = Some((self.progress, target, Instant::now()));
+    }
+}
+
+/// All known workspaces, by index
+pub struct Workspaces {
+    workspaces: Vec<Workspace>,
+
+    /// How many there can be - numbers past it are typos or runaway scripts
+    max: usize,
+}
+
+impl Workspaces {
+    pub fn from_config(declared: &[WorkspaceConfig], max: usize) -> Self {
+        Self {
+            workspaces: declared
+                .iter()
+                .map(|ws| Workspace {
+                    name: ws.name.clone(),
+                    output: ws.output.clone(),
+                })
+                .collect(),
+            max: max.max(declared.len()).max(1),
+        }
+    }
+
+    /// Pick up a new max_workspaces - ones that already exist stay
+    pub fn set_max(&mut self, max: usize) {
+        self.max = max.max(1);
+    }
+
+    /// Can `index` be switched or sent to?
+    pub fn check(&self, index: usize) -> Result<(), String> {
+        if index < self.workspaces.len() || index < self.max {
+            Ok(())
+        } else {
+            Err(format!("no workspace {} - max_workspaces is {}", index + 1, self.max))
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Workspace> {
+        self.workspaces.get(index)
+    }
+
+    /// Get a workspace, creating numbered ones up to `index` if needed
+    pub fn get_or_create(&mut self, index: usize) -> Result<&mut Workspace, String> {
+        self.check(index)?;
+        while self.workspaces.len() <= index {
+            let n = self.workspaces.len() + 1;
+            self.workspaces.push(Workspace {
+                name: n.to_string(),
+                output: None,
+            });
+        }
+
+        Ok(&mut self.workspaces[index])
+    }
+
+    /// Display name - declared name, or the 1-based number
+    pub fn name(&self, index: usize) -> String {
+        self.workspaces
+            .get(index)
+            .map(|ws| ws.name.clone())
+            .unwrap_or_else(|| (index + 1).to_string())
+    }
+
+    /// Index of the workspace assigned to an output, if any
+    pub fn on_output(&self, output_name: &str) -> Option<usize> {
+        self.workspaces
+            .iter()
+            .position(|ws| ws.output.as_deref() == Some(output_name))
+    }
+}
+
+impl VibeWM {
+    /// Switch workspaces from a keybind or the palette, sliding if animations are on
+    pub fn switch_workspace_animated(&mut self, index: usize) {
+        let from = self.windows.active_workspace();
+        if index == from {
+            return;
+        }
+
+        self.switch_workspace(index);
+        if self.windows.active_workspace() == from {
+            // Out of range - nothing to slide to
+            return;
+        }
+
+        if !self.animation_duration(SLIDE_DURATION).is_zero() {
+            let mut slide = WorkspaceSlide {
+                from,
+                to: Some(index),
+                direction: if index > from { 1 } else { -1 },
+                progress: 0.0,
+                tracking: false,
+                swipe_dx: 0.0,
+                velocity: 0.0,
+                last_update: Instant::now(),
+                settle: None,
+                switched: true,
+            };
+            slide.settle_to(1.0);
+            self.workspace_slide = Some(slide);
+        }
+    }
+
+    /// Three fingers down - start dragging workspaces around
+    pub fn begin_workspace_swipe(&mut self) {
+        // A committed swipe still settling has to land first
+        if let Some(slide) = self.workspace_slide.take() {
+            let committed = slide.settle.is_some_and(|(_, target, _)| target == 1.0);
+            if let Some(index) = slide.to.filter(|_| committed && !slide.switched) {
+                self.switch_workspace(index);
+            }
+        }
+
+        self.workspace_slide = Some(WorkspaceSlide {
+            from: self.windows.active_workspace(),
+            to: None,
+            direction: 1,
+            progress: 0.0,
+            tracking: true,
+            swipe_dx: 0.0,
+            velocity: 0.0,
+            last_update: Instant::now(),
+            settle: None,
+            switched: false,
+        });
+    }
+
+    /// Fingers moved - the slide follows them 1:1
+    pub fn update_workspace_swipe(&mut self, dx: f64) {
+        let Some(width) = self.output_width() else { return };
+        let Some(slide) = self.workspace_slide.as_mut().filter(|s| s.tracking) else {
+            return;
+        };
+
+        slide.swipe_dx += dx;
+
+        // Fingers left reveal the next workspace, right the previous one
+        slide.direction = if slide.swipe_dx < 0.0 { 1 } else { -1 };
+        slide.to = (slide.from as i64 + slide.direction as i64)
+            .try_into()
+            .ok();
+
+        slide.progress = match slide.to {
+            Some(_) => (slide.swipe_dx.abs() / width).min(1.0),
+            // Nothing left of workspace 1
+            None => 0.0,
+        };
+
+        // Smoothed velocity toward the neighbor
+        let dt = slide.last_update.elapsed().as_secs_f64().max(0.001);
+        let instant = -dx * slide.direction as f64 / width / dt;
+        slide.velocity = slide.velocity * 0.5 + instant * 0.5;
+        slide.last_update = Instant::now();
+    }
+
+    /// Fingers up - commit to the neighbor or spring back
+    pub fn end_workspace_swipe(&mut self, cancelled: bool) {
+        let instant = self.animation_duration(SLIDE_DURATION).is_zero();
+        let Some(slide) = self.workspace_slide.as_mut().filter(|s| s.tracking) else {
+            return;
+        };
+
+        let commit = !cancelled
+            && slide.to.is_some()
+            && (slide.progress > SWIPE_COMMIT_PROGRESS || slide.velocity > SWIPE_COMMIT_VELOCITY);
+
+        let target = if commit { 1.0 } else { 0.0 };
+        slide.settle_to(target);
+
+        // No animations - jump straight to the end, next frame finishes it
+        if instant {
+            slide.settle = Some((target, target, Instant::now()));
+        }
+    }
+
+    /// Advance the settle animation - switches workspace when a swipe commits
+    pub fn update_workspace_slide(&mut self) {
+        let duration = self.animation_duration(SLIDE_DURATION);
+        let Some(slide) = self.workspace_slide.as_mut() else {
+            return;
+        };
+
+        let Some((start, target, since)) = slide.settle else {
+            return;
+        };
+
+        let t = if duration.is_zero() {
+            1.0
+        } else {
+            (since.elapsed().as_secs_f64() / duration.as_secs_f64()).min(1.0)
+        };
+        let eased = 1.0 - (1.0 - t).powi(3);
+        slide.progress = start + (target - start) * eased;
+
+        let done = t >= 1.0 || slide.progress == target;
+        if !done {
+            return;
+        }
+
+        let commit_to = slide.to.filter(|_| target == 1.0 && !slide.switched);
+        self.workspace_slide = None;
+
+        if let Some(index) = commit_to {
+            self.switch_workspace(index);
+        }
+    }
+
+    /// Is a swipe in progress? (clients don't get pointer input meanwhile)
+    pub fn swipe_tracking(&self) -> bool {
+        self.workspace_slide.as_ref().is_some_and(|s| s.tracking)
+    }
+
+    fn output_width(&self) -> Option<f64> {
+        Some(self.output.as_ref()?.current_mode()?.size.w as f64)
+    }
+
+    /// Switch to a workspace - hides the current windows, shows the new ones
+    pub fn switch_workspace(&mut self, index: usize) {
+        let current = self.windows.active_workspace();
+        if index == current {
+            return;
+        }
+
+        if let Err(e) = self.workspaces.get_or_create(index) {
+            tracing::warn!("{}", e);
+            return;
+        }
+
+        // The spotlighted window is about to be hidden
+        self.spotlight = None;
+
+        // Park the current workspace's windows, remembering where they were
+        for window in self.windows.on_workspace(current) {
+            // Minimized ones are already unmapped, their spot saved
+            if self.windows.meta(&window).is_some_and(|m| m.minimized) {
+                continue;
+            }
+            let location = self.space.element_location(&window);
+            // Sticky ones stay mapped and come along
+            if self.windows.meta(&window).is_some_and(|m| m.sticky) {
+                if let Some(meta) = self.windows.meta_mut(&window) {
+                    meta.saved_location = location;
+                }
+                continue;
+            }
+            if let Some(meta) = self.windows.meta_mut(&window) {
+                meta.saved_location = location;
+            }
+            self.space.unmap_elem(&window);
+        }
+
+        self.windows.set_active_workspace(index);
+        self.windows.carry_sticky(index);
+
+        // Bring the new workspace's windows back, bottom to top (sticky ones
+        // are remapped where they are, to keep the stacking in order)
+        for window in self.windows.on_workspace(index) {
+            if self.windows.meta(&window).is_some_and(|m| m.minimized) {
+                continue;
+            }
+            let (location, snap_state) = self.windows
+                .meta(&window)
+                .map(|m| (m.saved_location.unwrap_or_default(), m.snap_state))
+                .unwrap_or_default();
+            self.space.map_element(window.clone(), location, false);
+
+            // The output may have changed size while we were away
+            if let Some(position) = snap_state {
+                self.snap_window(&window, position);
+            }
+        }
+
+        // Back to whatever had focus last time we were here
+        self.windows.focus_recent();
+
+        tracing::info!("Workspace: {}", self.workspaces.name(index));
+    }
+
+    /// Move the active workspace to the neighboring output, swapping assignments
+    pub fn move_workspace_to_output(&mut self, direction: Direction) {
+        // Outputs left to right
+        let mut outputs: Vec<Output> = self.space.outputs().cloned().collect();
+        outputs.sort_by_key(|o| self.space.output_geometry(o).map(|g| g.loc.x).unwrap_or(0));
+
+        let active = self.windows.active_workspace();
+        let current_output = self.workspaces
+            .get(active)
+            .and_then(|ws| ws.output.clone())
+            .or_else(|| self.output.as_ref().map(|o| o.name()));
+
+        let Some(current_pos) = current_output
+            .and_then(|name| outputs.iter().position(|o| o.name() == name))
+        else {
+            return;
+        };
+
+        let target_pos = match direction {
+            Direction::Left => current_pos.checked_sub(1),
+            Direction::Right => Some(current_pos + 1).filter(|&p| p < outputs.len()),
+            _ => None,
+        };
+
+        let Some(target) = target_pos.map(|p| outputs[p].name()) else {
+            tracing::info!("No output to the {:?} of workspace {}", direction, self.workspaces.name(active));
+            return;
+        };
+
+        // Whatever lived on the target output takes our old spot
+        let old_output = outputs[current_pos].name();
+        let other = self.workspaces.on_output(&target);
+        if let Some(other) = other.and_then(|other| self.workspaces.get_or_create(other).ok()) {
+            other.output = Some(old_output);
+        }
+        if let Ok(workspace) = self.workspaces.get_or_create(active) {
+            workspace.output = Some(target.clone());
+        }
+
+        tracing::info!("Workspace {} moved to {}", self.workspaces.name(active), target);
+    }
+
+    /// Launch an app and send its first window to a workspace
+    pub fn launch_on_workspace(&mut self, argv: &[String], app_id: Option<String>, name: &str, workspace: usize) {
+        let env = self.config.env_for_app(app_id.as_deref(), Some(name));
+        match launch::launch_app(&self.config.launch_command_template, argv, &env) {
+            Ok(child) => {
+                self.usage.record_launch(name);
+                self.pending_placements.push(PendingPlacement {
+                    pid: child.id(),
+                    app_id,
+                    workspace,
+                    launched_at: Instant::now(),
+                });
+            }
+            Err(e) => tracing::warn!("Failed to launch {:?}: {}", argv, e),
+        }
+    }
+
+    /// Workspace a new window was launched onto, if it matches a pending launch
+    ///
+    /// Matches the client PID (or one of its parents) first, then the app_id -
+    /// most clients haven't set one yet when the toplevel is created.
+    pub fn take_pending_placement(&mut self, pid: Option<u32>, app_id: Option<&str>) -> Option<usize> {
+        self.pending_placements.retain(|p| p.launched_at.elapsed() < PLACEMENT_TIMEOUT);
+
+        let index = pid
+            .and_then(|pid| {
+                self.pending_placements
+                    .iter()
+                    .position(|p| procstat::is_descendant(pid, p.pid))
+            })
+            .or_else(|| {
+                let app_id = app_id?;
+                self.pending_placements
+                    .iter()
+                    .position(|p| p.app_id.as_deref().is_some_and(|id| id.eq_ignore_ascii_case(app_id)))
+            })?;
+
+        Some(self.pending_placements.remove(index).workspace)
+    }
+
+    /// Move a window to another workspace, hiding it if that one isn't on screen
+    pub fn send_to_workspace(&mut self, window: &Window, index: usize) {
+        let Some(current) = self.windows.meta(window).map(|m| m.workspace) else {
+            return;
+        };
+        if current == index {
+            return;
+        }
+
+        if let Err(e) = self.workspaces.get_or_create(index) {
+            tracing::warn!("{}", e);
+            return;
+        }
+
+        let location = self.space.element_location(window);
+        if let Some(meta) = self.windows.meta_mut(window) {
+            meta.workspace = index;
+            meta.saved_location = location;
+            // Sent somewhere on purpose - it lives there now
+            meta.sticky = false;
+        }
+
+        if index != self.windows.active_workspace() {
+            self.space.unmap_elem(window);
+            if self.spotlight.as_ref() == Some(window) {
+                self.spotlight = None;
+            }
+            self.windows.focus_recent();
+        }
+
+        tracing::info!("Window sent to workspace {}", self.workspaces.name(index));
+    }
+}
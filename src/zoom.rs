@@ -0,0 +1,134 @@
+//! Zoom - a magnifier for the whole output
+//!
+//! The zoomed view is a viewport of `output / level` that follows the cursor
+//! so the cursor sits at the same relative spot in the viewport as on the
+//! output. That pins the point under the cursor in place: what you see under
+//! the cursor is exactly what the cursor's logical position hits, so input
+//! keeps working untransformed.
+
+use std::time::{Duration, Instant};
+
+use smithay::output::Output;
+use smithay::utils::{Logical, Point, Rectangle};
+
+use crate::state::VibeWM;
+
+/// How long a zoom step takes to ease in
+const ZOOM_DURATION: Duration = Duration::from_millis(150);
+
+/// Zoom level, easing toward a target
+#[derive(Debug, Clone)]
+pub struct Zoom {
+    from: f64,
+    target: f64,
+    started: Instant,
+    duration: Duration,
+}
+
+impl Zoom {
+    pub fn new() -> Self {
+        Self {
+            from: 1.0,
+            target: 1.0,
+            started: Instant::now(),
+            duration: ZOOM_DURATION,
+        }
+    }
+
+    /// Current level - 1.0 = not zoomed
+    pub fn level(&self) -> f64 {
+        if self.duration.is_zero() {
+            return self.target;
+        }
+        let t = (self.started.elapsed().as_secs_f64() / self.duration.as_secs_f64()).min(1.0);
+        let eased = 1.0 - (1.0 - t).powi(3);
+        self.from + (self.target - self.from) * eased
+    }
+
+    pub fn target(&self) -> f64 {
+        self.target
+    }
+
+    /// Head for a new level over `duration`, from wherever we are now
+    pub fn set(&mut self, target: f64, duration: Duration) {
+        self.from = self.level();
+        self.target = target;
+        self.started = Instant::now();
+        self.duration = duration;
+    }
+
+    pub fn is_zoomed(&self) -> bool {
+        self.target > 1.0 || self.level() > 1.0
+    }
+
+    /// Part of the output that's on screen, in logical coordinates
+    pub fn viewport(
+        &self,
+        output_geo: Rectangle<i32, Logical>,
+        cursor: Point<f64, Logical>,
+    ) -> Rectangle<f64, Logical> {
+        let level = self.level().max(1.0);
+        let geo = output_geo.to_f64();
+
+        // Cursor position relative to the output, clamped onto it
+        let rel_x = (cursor.x - geo.loc.x).clamp(0.0, geo.size.w);
+        let rel_y = (cursor.y - geo.loc.y).clamp(0.0, geo.size.h);
+
+        let shrink = 1.0 - 1.0 / level;
+        Rectangle::new(
+            (geo.loc.x + rel_x * shrink, geo.loc.y + rel_y * shrink).into(),
+            (geo.size.w / level, geo.size.h / level).into(),
+        )
+    }
+
+    /// Logical position -> where it lands on screen (output-relative)
+    pub fn to_screen(
+        &self,
+        viewport: Rectangle<f64, Logical>,
+        output_geo: Rectangle<i32, Logical>,
+        pos: Point<f64, Logical>,
+    ) -> Point<f64, Logical> {
+        let level = output_geo.size.w as f64 / viewport.size.w;
+        (pos - viewport.loc).upscale(level)
+    }
+
+    /// Output-relative screen position -> logical position
+    pub fn to_logical(
+        &self,
+        viewport: Rectangle<f64, Logical>,
+        output_geo: Rectangle<i32, Logical>,
+        screen: Point<f64, Logical>,
+    ) -> Point<f64, Logical> {
+        let level = output_geo.size.w as f64 / viewport.size.w;
+        viewport.loc + screen.downscale(level)
+    }
+}
+
+impl VibeWM {
+    /// Multiply the zoom level, clamped to the configured range
+    pub fn zoom_by(&mut self, factor: f64) {
+        let target = (self.zoom.target() * factor).clamp(self.config.zoom_min, self.config.zoom_max);
+        self.zoom.set(target, self.animation_duration(ZOOM_DURATION));
+        tracing::debug!("Zoom: {:.2}x", target);
+    }
+
+    pub fn zoom_reset(&mut self) {
+        self.zoom.set(1.0, self.animation_duration(ZOOM_DURATION));
+    }
+
+    /// Viewport to render this output with, None if it isn't zoomed
+    ///
+    /// Only the output under the cursor zooms.
+    pub fn zoom_view(&self, output: &Output) -> Option<Rectangle<f64, Logical>> {
+        if !self.zoom.is_zoomed() {
+            return None;
+        }
+
+        let output_geo = self.space.output_geometry(output)?;
+        if !output_geo.to_f64().contains(self.input.pointer_pos) {
+            return None;
+        }
+
+        Some(self.zoom.viewport(output_geo, self.input.pointer_pos))
+    }
+}